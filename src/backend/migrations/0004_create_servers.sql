@@ -0,0 +1,15 @@
+CREATE TABLE servers (
+  uuid UUID PRIMARY KEY,
+  name VARCHAR NOT NULL UNIQUE,
+  working_dir VARCHAR NOT NULL,
+  jar_file VARCHAR NOT NULL,
+  launch_command VARCHAR,
+  java_path VARCHAR NOT NULL,
+  min_memory_mb INTEGER NOT NULL,
+  max_memory_mb INTEGER NOT NULL,
+  server_port INTEGER NOT NULL,
+  rcon_port INTEGER,
+  query_port INTEGER,
+  auto_start BOOL NOT NULL DEFAULT false,
+  owner UUID NOT NULL REFERENCES users(uuid)
+);
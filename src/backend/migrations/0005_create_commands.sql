@@ -0,0 +1,21 @@
+CREATE TABLE command_rules (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID REFERENCES servers(uuid) ON DELETE CASCADE,
+  user_uuid UUID REFERENCES users(uuid) ON DELETE CASCADE,
+  action JSON,
+  verb VARCHAR NOT NULL,
+  allow BOOL NOT NULL,
+  CHECK (user_uuid IS NULL OR action IS NULL)
+);
+
+CREATE TABLE command_history (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  user_uuid UUID NOT NULL REFERENCES users(uuid),
+  command VARCHAR NOT NULL,
+  source VARCHAR NOT NULL,
+  allowed BOOL NOT NULL,
+  executed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX command_history_server_idx ON command_history (server_uuid, executed_at DESC);
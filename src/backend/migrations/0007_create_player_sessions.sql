@@ -0,0 +1,12 @@
+CREATE TABLE player_sessions (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  player_uuid UUID,
+  player_name VARCHAR NOT NULL,
+  ip VARCHAR,
+  joined_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  left_at TIMESTAMPTZ
+);
+
+CREATE INDEX player_sessions_player_idx ON player_sessions (server_uuid, LOWER(player_name), joined_at DESC);
+CREATE INDEX player_sessions_open_idx ON player_sessions (server_uuid) WHERE left_at IS NULL;
@@ -0,0 +1,15 @@
+CREATE TABLE crashes (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  kind VARCHAR NOT NULL,
+  exit_code INT,
+  signal INT,
+  report_file VARCHAR,
+  report TEXT,
+  console TEXT NOT NULL,
+  restart_scheduled BOOL NOT NULL,
+  started_at TIMESTAMPTZ NOT NULL,
+  crashed_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX crashes_server_idx ON crashes (server_uuid, crashed_at DESC);
@@ -0,0 +1,28 @@
+CREATE TABLE scheduled_tasks (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  cron VARCHAR NOT NULL,
+  timezone VARCHAR NOT NULL,
+  action VARCHAR NOT NULL,
+  command VARCHAR,
+  catch_up VARCHAR NOT NULL,
+  enabled BOOL NOT NULL,
+  created_by UUID REFERENCES users(uuid) ON DELETE SET NULL,
+  last_run_at TIMESTAMPTZ,
+  next_run_at TIMESTAMPTZ
+);
+
+CREATE INDEX scheduled_tasks_server_idx ON scheduled_tasks (server_uuid);
+
+CREATE TABLE task_runs (
+  uuid UUID PRIMARY KEY,
+  task_uuid UUID NOT NULL REFERENCES scheduled_tasks(uuid) ON DELETE CASCADE,
+  scheduled_for TIMESTAMPTZ NOT NULL,
+  started_at TIMESTAMPTZ NOT NULL,
+  finished_at TIMESTAMPTZ NOT NULL,
+  outcome VARCHAR NOT NULL,
+  message TEXT
+);
+
+CREATE INDEX task_runs_task_idx ON task_runs (task_uuid, scheduled_for DESC);
@@ -0,0 +1,23 @@
+CREATE TABLE backups (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  scope VARCHAR NOT NULL,
+  status VARCHAR NOT NULL,
+  file_name VARCHAR NOT NULL,
+  size_bytes BIGINT,
+  sha256 VARCHAR,
+  error TEXT,
+  created_by UUID REFERENCES users(uuid) ON DELETE SET NULL,
+  started_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  completed_at TIMESTAMPTZ
+);
+
+CREATE INDEX backups_server_idx ON backups (server_uuid, started_at DESC);
+
+CREATE TABLE backup_retention (
+  server_uuid UUID PRIMARY KEY REFERENCES servers(uuid) ON DELETE CASCADE,
+  keep_last INT NOT NULL,
+  keep_daily INT NOT NULL,
+  keep_weekly INT NOT NULL,
+  keep_monthly INT NOT NULL
+);
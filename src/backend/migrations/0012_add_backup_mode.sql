@@ -0,0 +1 @@
+ALTER TABLE backups ADD COLUMN mode VARCHAR NOT NULL DEFAULT 'archive';
@@ -0,0 +1,23 @@
+CREATE TABLE backup_destinations (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  name VARCHAR NOT NULL,
+  config JSONB NOT NULL,
+  secret BYTEA,
+  encrypt BOOLEAN NOT NULL DEFAULT FALSE,
+  enabled BOOLEAN NOT NULL DEFAULT TRUE,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  UNIQUE (server_uuid, name)
+);
+
+CREATE TABLE backup_copies (
+  backup_uuid UUID NOT NULL REFERENCES backups(uuid) ON DELETE CASCADE,
+  destination_uuid UUID NOT NULL REFERENCES backup_destinations(uuid) ON DELETE CASCADE,
+  status VARCHAR NOT NULL,
+  remote_name VARCHAR NOT NULL,
+  encrypted BOOLEAN NOT NULL,
+  attempts INT NOT NULL DEFAULT 0,
+  error TEXT,
+  updated_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  PRIMARY KEY (backup_uuid, destination_uuid)
+);
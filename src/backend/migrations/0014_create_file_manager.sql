@@ -0,0 +1,21 @@
+CREATE TABLE file_rules (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  user_uuid UUID REFERENCES users(uuid) ON DELETE CASCADE,
+  action JSON,
+  path VARCHAR NOT NULL,
+  access VARCHAR NOT NULL,
+  CHECK (user_uuid IS NULL OR action IS NULL)
+);
+
+CREATE INDEX file_rules_server_idx ON file_rules (server_uuid);
+
+CREATE TABLE file_uploads (
+  uuid UUID PRIMARY KEY,
+  server_uuid UUID NOT NULL REFERENCES servers(uuid) ON DELETE CASCADE,
+  user_uuid UUID NOT NULL REFERENCES users(uuid) ON DELETE CASCADE,
+  path VARCHAR NOT NULL,
+  size BIGINT NOT NULL,
+  overwrite BOOLEAN NOT NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now()
+);
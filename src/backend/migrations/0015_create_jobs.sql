@@ -0,0 +1,21 @@
+CREATE TABLE jobs (
+  uuid UUID PRIMARY KEY,
+  kind VARCHAR NOT NULL,
+  server_uuid UUID REFERENCES servers(uuid) ON DELETE CASCADE,
+  status VARCHAR NOT NULL,
+  description TEXT NOT NULL,
+  step TEXT,
+  total BIGINT,
+  processed BIGINT NOT NULL DEFAULT 0,
+  log TEXT[] NOT NULL DEFAULT '{}',
+  result JSONB,
+  error TEXT,
+  cancellable BOOLEAN NOT NULL,
+  created_by UUID REFERENCES users(uuid) ON DELETE SET NULL,
+  created_at TIMESTAMPTZ NOT NULL DEFAULT now(),
+  started_at TIMESTAMPTZ,
+  finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX jobs_created_idx ON jobs (created_at DESC);
+CREATE INDEX jobs_server_idx ON jobs (server_uuid, created_at DESC);
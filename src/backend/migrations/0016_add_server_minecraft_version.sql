@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN minecraft_version VARCHAR(64);
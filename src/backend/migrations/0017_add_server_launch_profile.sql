@@ -0,0 +1 @@
+ALTER TABLE servers ADD COLUMN launch_profile JSONB NOT NULL DEFAULT '{}';
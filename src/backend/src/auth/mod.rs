@@ -3,7 +3,10 @@ use crate::prelude::*;
 use anyhow::Result;
 use argon2::{
     Argon2, PasswordHash, PasswordVerifier,
-    password_hash::{PasswordHasher, SaltString, rand_core::OsRng},
+    password_hash::{
+        PasswordHasher, SaltString,
+        rand_core::{OsRng, RngCore},
+    },
 };
 use axum::http::StatusCode;
 use chrono::{Duration, Utc};
@@ -11,6 +14,10 @@ use jsonwebtoken::{DecodingKey, EncodingKey, Header, TokenData, Validation, deco
 
 use crate::domain::api::AuthClaims;
 
+pub const AUTH_COOKIE: &str = "auth_token";
+pub const CSRF_COOKIE: &str = "csrf_token";
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
 pub fn hash_password(password: &str) -> Result<String, password_hash::Error> {
     let salt = SaltString::generate(&mut OsRng);
     let argon2 = Argon2::default();
@@ -31,14 +38,19 @@ pub fn verify_password(password: &str, password_hash: &str) -> Result<bool, pass
     }
 }
 
-pub fn gen_jwt(username: String) -> Result<String, StatusCode> {
+pub fn gen_jwt(username: String, csrf: String) -> Result<String, StatusCode> {
     // TODO: Update secret to .env file
     let secret: String = "verysafestring".to_string();
     let now = Utc::now();
     let expire: chrono::TimeDelta = Duration::hours(24);
     let exp = (now + expire).timestamp();
     let iat = now.timestamp();
-    let claim = AuthClaims { iat, exp, username };
+    let claim = AuthClaims {
+        iat,
+        exp,
+        username,
+        csrf,
+    };
 
     encode(
         &Header::default(),
@@ -64,3 +76,21 @@ pub fn verify_jwt(token: String) -> Result<TokenData<AuthClaims>, StatusCode> {
     });
     result
 }
+
+pub fn gen_csrf_token() -> String {
+    let mut bytes = [0u8; 32];
+    OsRng.fill_bytes(&mut bytes);
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn verify_csrf(provided: &str, expected: &str) -> bool {
+    // Constant time comparison so the token cannot be guessed byte by byte
+    if provided.len() != expected.len() {
+        return false;
+    }
+    provided
+        .bytes()
+        .zip(expected.bytes())
+        .fold(0u8, |acc, (a, b)| acc | (a ^ b))
+        == 0
+}
@@ -0,0 +1,598 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+pub mod replicate;
+pub mod restore;
+
+use anyhow::Result;
+use tokio::{fs, task::spawn_blocking};
+use uuid::Uuid;
+
+use crate::{
+    core::command_routines,
+    domain::{
+        backup::{
+            Backup, BackupMode, BackupScope, BackupStatus, BackupVerification, RetentionPolicy,
+        },
+        server::Server,
+    },
+    infra::{
+        archive::{self, ArchiveInfo},
+        db,
+        dedup::{self, ChunkStore, Manifest},
+        minecraft::properties,
+    },
+    jobs::JobHandle,
+    prelude::*,
+    state::AppState,
+};
+
+pub const ARCHIVE_EXTENSION: &str = "tar.gz";
+const MANIFEST_EXTENSION: &str = "manifest.json";
+const CHUNK_DIR: &str = "chunks";
+const PARTIAL_SUFFIX: &str = "part";
+/// Archives fetched back from a destination, before they are opened.
+const DOWNLOAD_SUFFIX: &str = "download";
+/// Held open by the server for every loaded world, worthless in a backup.
+const SKIPPED_FILES: &[&str] = &["session.lock"];
+const DEFAULT_LEVEL_NAME: &str = "world";
+
+/// Where a backup is read back from.
+#[derive(Debug, Clone)]
+pub enum Source {
+    Archive(PathBuf),
+    Snapshot {
+        manifest: PathBuf,
+        store: ChunkStore,
+    },
+}
+
+/// Servers with a backup or in-place restore in progress, at most one each.
+#[derive(Default)]
+pub struct BackupEngine {
+    running: Arc<Mutex<HashSet<Uuid>>>,
+}
+
+/// A server reserved for a backup or restore, released when dropped.
+pub struct BackupClaim {
+    running: Arc<Mutex<HashSet<Uuid>>>,
+    server_uuid: Uuid,
+}
+
+impl BackupEngine {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the server, `None` if a backup or restore already holds it.
+    pub fn claim(&self, server_uuid: Uuid) -> Option<BackupClaim> {
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(server_uuid)
+            .then(|| BackupClaim {
+                running: self.running.clone(),
+                server_uuid,
+            })
+    }
+}
+
+impl Drop for BackupClaim {
+    fn drop(&mut self) {
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.server_uuid);
+    }
+}
+
+/// Directory holding the archives of one server.
+pub fn server_dir(state: &AppState, server_uuid: Uuid) -> PathBuf {
+    state.config.backup.dir.join(server_uuid.to_string())
+}
+
+/// The archive, or for dedup backups the manifest.
+pub fn archive_path(state: &AppState, backup: &Backup) -> PathBuf {
+    server_dir(state, backup.server_uuid).join(&backup.file_name)
+}
+
+pub fn file_name(backup_uuid: Uuid, mode: BackupMode) -> String {
+    match mode {
+        BackupMode::Archive => format!("{backup_uuid}.{ARCHIVE_EXTENSION}"),
+        BackupMode::Dedup => format!("{backup_uuid}.{MANIFEST_EXTENSION}"),
+    }
+}
+
+/// Chunk store shared by every dedup backup of the server.
+fn chunk_store(state: &AppState, server_uuid: Uuid) -> ChunkStore {
+    ChunkStore::new(
+        server_dir(state, server_uuid).join(CHUNK_DIR),
+        state.config.backup.compression_level,
+    )
+}
+
+pub fn source(state: &AppState, backup: &Backup) -> Source {
+    let path = archive_path(state, backup);
+    match backup.mode {
+        BackupMode::Archive => Source::Archive(path),
+        BackupMode::Dedup => Source::Snapshot {
+            manifest: path,
+            store: chunk_store(state, backup.server_uuid),
+        },
+    }
+}
+
+impl Source {
+    /// File the recorded checksum covers.
+    pub fn path(&self) -> &Path {
+        match self {
+            Source::Archive(path) => path,
+            Source::Snapshot { manifest, .. } => manifest,
+        }
+    }
+
+    /// First path component of every entry. Blocking.
+    pub fn top_level_entries(&self) -> io::Result<BTreeSet<PathBuf>> {
+        match self {
+            Source::Archive(path) => archive::top_level_entries(path),
+            Source::Snapshot { manifest, .. } => {
+                Ok(dedup::top_level_entries(&dedup::read_manifest(manifest)?))
+            }
+        }
+    }
+
+    /// Writes the backup into `dest`, limited to `only` when it is not empty.
+    /// Returns how many entries were written. Blocking.
+    pub fn extract(&self, dest: &Path, only: &[PathBuf]) -> io::Result<u64> {
+        match self {
+            Source::Archive(path) => archive::extract_tar_gz(path, dest, only),
+            Source::Snapshot { manifest, store } => {
+                dedup::restore_snapshot(&dedup::read_manifest(manifest)?, store, dest, only)
+            }
+        }
+    }
+}
+
+/// Reconciles what a previous run of the daemon left unfinished: running
+/// backups are failed, partial archives and downloads removed, chunks of
+/// interrupted dedup backups collected, in-place restores rolled back and
+/// uploads failed.
+pub async fn init(state: &AppState) {
+    match db::backup::fail_running(&state.db_pool, "daemon stopped during backup").await {
+        Ok(stale) => {
+            let mut snapshots = HashSet::new();
+            for backup in stale {
+                warn!(backup_uuid = %backup.uuid, "backup interrupted by daemon shutdown");
+                if backup.mode == BackupMode::Dedup {
+                    snapshots.insert(backup.server_uuid);
+                }
+            }
+            remove_partials(state).await;
+            for server_uuid in snapshots {
+                collect_garbage(state, server_uuid).await;
+            }
+        }
+        Err(e) => error!(error = %e, "fail stale backups failed"),
+    }
+    restore::init(state).await;
+    replicate::init(state).await;
+}
+
+/// Removes the partial archives, manifests, sealed copies and downloads in
+/// every server backup directory, nothing writes them while the daemon starts.
+async fn remove_partials(state: &AppState) {
+    let Ok(mut servers) = fs::read_dir(&state.config.backup.dir).await else {
+        return;
+    };
+    while let Ok(Some(server)) = servers.next_entry().await {
+        let Ok(mut files) = fs::read_dir(server.path()).await else {
+            continue;
+        };
+        while let Ok(Some(file)) = files.next_entry().await {
+            let path = file.path();
+            let partial = path
+                .extension()
+                .is_some_and(|ext| ext == PARTIAL_SUFFIX || ext == DOWNLOAD_SUFFIX);
+            if partial && file.file_type().await.is_ok_and(|t| t.is_file()) {
+                debug!(path = %path.display(), "partial backup file removed");
+                remove_file(&path).await;
+            }
+        }
+    }
+}
+
+/// Archives the server for an already recorded backup and applies retention,
+/// reporting each step through `job`. Releases the claim on the server once
+/// the archive is written.
+pub async fn run(
+    state: Arc<AppState>,
+    server: Server,
+    backup: Backup,
+    claim: BackupClaim,
+    job: &JobHandle,
+) -> Backup {
+    let uuid = server.uuid;
+
+    job.step("archiving");
+    let backup = match archive(&state, &server, &backup).await {
+        Ok(info) => {
+            info!(
+                backup_uuid = %backup.uuid,
+                server_uuid = %uuid,
+                size_bytes = info.size_bytes,
+                "backup completed"
+            );
+            db::backup::complete(
+                &state.db_pool,
+                backup.uuid,
+                info.size_bytes as i64,
+                &info.sha256,
+            )
+            .await
+            .unwrap_or_else(|e| {
+                error!(error = %e, backup_uuid = %backup.uuid, "record completed backup failed");
+                backup
+            })
+        }
+        Err(reason) => {
+            warn!(backup_uuid = %backup.uuid, server_uuid = %uuid, reason, "backup failed");
+            remove_file(&partial_path(&archive_path(&state, &backup))).await;
+            db::backup::fail(&state.db_pool, backup.uuid, &reason)
+                .await
+                .unwrap_or_else(|e| {
+                    error!(error = %e, backup_uuid = %backup.uuid, "record failed backup failed");
+                    backup
+                })
+        }
+    };
+
+    drop(claim);
+    let mut pruned_snapshots = false;
+    if backup.status == BackupStatus::Completed {
+        job.step("copying to destinations");
+        replicate::replicate(&state, &backup).await;
+        job.step("applying retention");
+        pruned_snapshots = apply_retention(&state, uuid).await;
+    }
+    // A failed dedup backup may leave chunks no manifest refers to
+    if backup.mode == BackupMode::Dedup || pruned_snapshots {
+        job.step("collecting unused chunks");
+        collect_garbage(&state, uuid).await;
+    }
+    backup
+}
+
+async fn archive(
+    state: &AppState,
+    server: &Server,
+    backup: &Backup,
+) -> Result<ArchiveInfo, String> {
+    let cfg = &state.config.backup;
+    let root = PathBuf::from(&server.working_dir);
+
+    let dest = archive_path(state, backup);
+    let partial = partial_path(&dest);
+    fs::create_dir_all(server_dir(state, server.uuid))
+        .await
+        .map_err(|e| format!("create backup directory: {e}"))?;
+
+    let entries = match backup.scope {
+        BackupScope::Full => vec![PathBuf::new()],
+        BackupScope::Worlds => world_dirs(&root).await?,
+    };
+    let exclude: Vec<PathBuf> = nested_dir(&root, &cfg.dir).await.into_iter().collect();
+
+    // Autosaves would change region files halfway through the archive
+    let alive = state.supervisor.state(server.uuid).await.is_alive();
+    if alive {
+        if command_routines::dispatch(state, server, "save-off")
+            .await
+            .is_err()
+        {
+            warn!(server_uuid = %server.uuid, "disable autosave failed, archiving anyway");
+        }
+        command_routines::save_world(state, server, "save-all flush", cfg.save_timeout).await;
+    }
+
+    let level = cfg.compression_level;
+    let target = partial.clone();
+    let written = match backup.mode {
+        BackupMode::Archive => {
+            spawn_blocking(move || {
+                archive::write_tar_gz(&root, &entries, &target, level, &exclude, SKIPPED_FILES)
+            })
+            .await
+        }
+        BackupMode::Dedup => {
+            let previous = previous_manifest(state, server.uuid).await;
+            let store = chunk_store(state, server.uuid);
+            let backup_uuid = backup.uuid;
+            spawn_blocking(move || {
+                let (manifest, stats) = dedup::write_snapshot(
+                    &root,
+                    &entries,
+                    &store,
+                    previous.as_ref(),
+                    &exclude,
+                    SKIPPED_FILES,
+                )?;
+                let info = dedup::write_manifest(&manifest, &target)?;
+                debug!(
+                    backup_uuid = %backup_uuid,
+                    files = stats.files,
+                    unchanged_files = stats.unchanged_files,
+                    new_chunks = stats.new_chunks,
+                    "snapshot written"
+                );
+                Ok(ArchiveInfo {
+                    size_bytes: info.size_bytes + stats.new_bytes,
+                    sha256: info.sha256,
+                })
+            })
+            .await
+        }
+    };
+
+    if alive
+        && command_routines::dispatch(state, server, "save-on")
+            .await
+            .is_err()
+    {
+        warn!(server_uuid = %server.uuid, "enable autosave failed");
+    }
+
+    let info = match written {
+        Ok(Ok(info)) => info,
+        Ok(Err(e)) => return Err(format!("write archive: {e}")),
+        Err(e) => return Err(format!("archive task failed: {e}")),
+    };
+    fs::rename(&partial, &dest)
+        .await
+        .map_err(|e| format!("move archive in place: {e}"))?;
+    Ok(info)
+}
+
+/// Manifest of the latest completed dedup backup, files unchanged since are
+/// not read again.
+async fn previous_manifest(state: &AppState, server_uuid: Uuid) -> Option<Manifest> {
+    let backups = db::backup::get_for_server(&state.db_pool, server_uuid)
+        .await
+        .inspect_err(|e| warn!(error = %e, "fetch previous backups failed"))
+        .ok()?;
+    let previous = backups.into_iter().find(|backup| {
+        backup.mode == BackupMode::Dedup && backup.status == BackupStatus::Completed
+    })?;
+
+    let path = archive_path(state, &previous);
+    match spawn_blocking(move || dedup::read_manifest(&path)).await {
+        Ok(Ok(manifest)) => Some(manifest),
+        Ok(Err(e)) => {
+            warn!(error = %e, backup_uuid = %previous.uuid, "read previous manifest failed");
+            None
+        }
+        Err(_) => None,
+    }
+}
+
+/// Folders of the configured level, the nether and end only exist as separate
+/// folders on Bukkit based servers.
+async fn world_dirs(root: &Path) -> Result<Vec<PathBuf>, String> {
+    let properties = properties::load(root)
+        .await
+        .map_err(|e| format!("read server properties: {e}"))?;
+    let level = properties
+        .get("level-name")
+        .filter(|name| !name.is_empty())
+        .unwrap_or(DEFAULT_LEVEL_NAME)
+        .to_string();
+
+    let mut dirs = Vec::new();
+    for name in [
+        level.clone(),
+        format!("{level}_nether"),
+        format!("{level}_the_end"),
+    ] {
+        if fs::metadata(root.join(&name))
+            .await
+            .is_ok_and(|m| m.is_dir())
+        {
+            dirs.push(PathBuf::from(name));
+        }
+    }
+
+    if dirs.is_empty() {
+        return Err(format!("no world folder found for level {level}"));
+    }
+    Ok(dirs)
+}
+
+/// `dir` expressed under `root` when it lies inside it, so a backup directory
+/// within the server directory is not archived into itself.
+async fn nested_dir(root: &Path, dir: &Path) -> Option<PathBuf> {
+    let canonical_root = fs::canonicalize(root).await.ok()?;
+    let canonical_dir = fs::canonicalize(dir).await.ok()?;
+    let relative = canonical_dir.strip_prefix(&canonical_root).ok()?;
+    Some(root.join(relative))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{PARTIAL_SUFFIX}"));
+    path.with_file_name(name)
+}
+
+async fn remove_file(path: &Path) {
+    match fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => warn!(error = %e, path = %path.display(), "remove backup file failed"),
+    }
+}
+
+/// The server's own retention policy, or the configured default.
+pub async fn retention(state: &AppState, server_uuid: Uuid) -> Result<RetentionPolicy> {
+    Ok(db::backup::get_retention(&state.db_pool, server_uuid)
+        .await?
+        .unwrap_or_else(|| state.config.backup.retention.clone()))
+}
+
+/// Deletes expired backups, returns whether any of them was a dedup backup.
+async fn apply_retention(state: &AppState, server_uuid: Uuid) -> bool {
+    let mut pruned_snapshots = false;
+    let pruned = async {
+        let policy = retention(state, server_uuid).await?;
+        let backups = db::backup::get_for_server(&state.db_pool, server_uuid).await?;
+        for backup in policy.expired(&backups) {
+            delete(state, backup).await?;
+            pruned_snapshots |= backup.mode == BackupMode::Dedup;
+            info!(backup_uuid = %backup.uuid, server_uuid = %server_uuid, "backup expired");
+        }
+        anyhow::Ok(())
+    }
+    .await;
+
+    if let Err(e) = pruned {
+        error!(error = %e, server_uuid = %server_uuid, "apply backup retention failed");
+    }
+    pruned_snapshots
+}
+
+/// Removes chunks no completed dedup backup of the server refers to. Skipped
+/// while a backup or restore holds the server, the next run catches up.
+pub async fn collect_garbage(state: &AppState, server_uuid: Uuid) {
+    let Some(claim) = state.backups.claim(server_uuid) else {
+        debug!(server_uuid = %server_uuid, "server busy, chunk collection skipped");
+        return;
+    };
+
+    let collected = async {
+        let manifests: Vec<PathBuf> = db::backup::get_for_server(&state.db_pool, server_uuid)
+            .await?
+            .iter()
+            .filter(|b| b.mode == BackupMode::Dedup && b.status == BackupStatus::Completed)
+            .map(|b| archive_path(state, b))
+            .collect();
+        let store = chunk_store(state, server_uuid);
+
+        let removed = spawn_blocking(move || {
+            let manifests = manifests
+                .iter()
+                .map(|path| dedup::read_manifest(path))
+                .collect::<io::Result<Vec<_>>>()?;
+            dedup::collect_garbage(&store, &manifests)
+        })
+        .await??;
+        anyhow::Ok(removed)
+    }
+    .await;
+    drop(claim);
+
+    match collected {
+        Ok((0, _)) => {}
+        Ok((chunks, bytes)) => {
+            info!(server_uuid = %server_uuid, chunks, bytes, "unused backup chunks removed")
+        }
+        Err(e) => error!(error = %e, server_uuid = %server_uuid, "collect backup chunks failed"),
+    }
+}
+
+/// Checks a completed backup against its recorded checksum, and for dedup
+/// backups every chunk against its hash.
+pub async fn verify(state: &AppState, backup: &Backup) -> Result<BackupVerification> {
+    let source = source(state, backup);
+    let expected = backup.sha256.clone().unwrap_or_default();
+
+    let (checked_chunks, problems) = spawn_blocking(move || -> io::Result<_> {
+        let checksum = match archive::sha256_file(source.path()) {
+            Ok(checksum) => checksum,
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                return Ok((0, vec![format!("{} is missing", source.path().display())]));
+            }
+            Err(e) => return Err(e),
+        };
+        if !checksum.eq_ignore_ascii_case(&expected) {
+            return Ok((
+                0,
+                vec![format!(
+                    "{} does not match its checksum",
+                    source.path().display()
+                )],
+            ));
+        }
+
+        match &source {
+            Source::Archive(_) => Ok((0, Vec::new())),
+            Source::Snapshot { manifest, store } => {
+                let check = dedup::verify_snapshot(&dedup::read_manifest(manifest)?, store);
+                Ok((check.checked_chunks, check.problems))
+            }
+        }
+    })
+    .await??;
+
+    Ok(BackupVerification {
+        backup_uuid: backup.uuid,
+        ok: problems.is_empty(),
+        checked_chunks,
+        problems,
+    })
+}
+
+/// Removes a backup archive or manifest, its copies and its record, chunks are
+/// left to [`collect_garbage`].
+pub async fn delete(state: &AppState, backup: &Backup) -> Result<()> {
+    replicate::delete_copies(state, backup).await;
+    remove_file(&archive_path(state, backup)).await;
+    db::backup::delete(&state.db_pool, backup.uuid).await?;
+    Ok(())
+}
+
+/// Removes the archives of a deleted server, the database cascades the records.
+pub async fn forget(state: &AppState, server_uuid: Uuid) {
+    let dir = server_dir(state, server_uuid);
+    match fs::remove_dir_all(&dir).await {
+        Ok(()) => info!(server_uuid = %server_uuid, "server backups removed"),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => warn!(error = %e, dir = %dir.display(), "remove server backups failed"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn claims_are_released_when_dropped() {
+        let engine = BackupEngine::new();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+
+        let claim = engine.claim(first).unwrap();
+        assert!(engine.claim(first).is_none());
+        let other = engine.claim(second).unwrap();
+
+        drop(claim);
+        let again = engine.claim(first);
+        assert!(again.is_some());
+        assert!(engine.claim(second).is_none());
+        drop(other);
+        assert!(engine.claim(second).is_some());
+    }
+
+    #[test]
+    fn claims_moved_into_a_dropped_task_are_released() {
+        let engine = BackupEngine::new();
+        let server = Uuid::new_v4();
+        let claim = engine.claim(server).unwrap();
+
+        // A queued job whose work never runs still gives the server back
+        let work = async move {
+            let _claim = claim;
+        };
+        drop(work);
+        assert!(engine.claim(server).is_some());
+    }
+}
@@ -0,0 +1,561 @@
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+
+use tokio::{fs, task::spawn_blocking, time::sleep};
+
+use crate::{
+    domain::{
+        backup::{Backup, BackupMode},
+        destination::{BackupCopy, BackupDestination, CopyStatus, remote_name},
+    },
+    infra::{
+        crypto::SecretBox,
+        db, dedup,
+        destination::{Destination, DestinationError, Remote},
+    },
+    prelude::*,
+    state::AppState,
+};
+
+use super::{DOWNLOAD_SUFFIX, Source, archive_path, file_name, partial_path, remove_file, source};
+
+/// Builds the destination with its secret unsealed.
+pub fn remote(state: &AppState, destination: &BackupDestination) -> Result<Remote, String> {
+    let secret = destination
+        .secret
+        .as_deref()
+        .map(|sealed| state.secrets.decrypt_str(sealed))
+        .transpose()
+        .map_err(|e| format!("unseal destination secret: {e}"))?;
+    Remote::new(&destination.config, secret, state.http.clone()).map_err(|e| e.to_string())
+}
+
+/// Marks copies an earlier run of the daemon was uploading as failed.
+pub async fn init(state: &AppState) {
+    match db::destination::fail_uploading(&state.db_pool, "daemon stopped during upload").await {
+        Ok(0) => {}
+        Ok(count) => warn!(count, "backup uploads interrupted by daemon shutdown"),
+        Err(e) => error!(error = %e, "fail stale backup uploads failed"),
+    }
+}
+
+/// Copies a completed archive to every enabled destination of its server,
+/// sealing it first for destinations that ask for encryption. Dedup backups
+/// are exported to an archive first, a copy has to do without the chunk store.
+pub async fn replicate(state: &Arc<AppState>, backup: &Backup) {
+    let destinations =
+        match db::destination::get_for_server(&state.db_pool, backup.server_uuid).await {
+            Ok(destinations) => destinations,
+            Err(e) => {
+                error!(error = %e, backup_uuid = %backup.uuid, "fetch backup destinations failed");
+                return;
+            }
+        };
+    let destinations: Vec<_> = destinations.into_iter().filter(|d| d.enabled).collect();
+    if destinations.is_empty() {
+        return;
+    }
+
+    let archive = match backup.mode {
+        BackupMode::Archive => archive_path(state, backup),
+        BackupMode::Dedup => match export(state, backup).await {
+            Ok(path) => path,
+            Err(reason) => {
+                for destination in &destinations {
+                    record_failure(state, backup, destination, &reason).await;
+                }
+                return;
+            }
+        },
+    };
+    let sealed = if destinations.iter().any(|d| d.encrypt) {
+        Some(seal(state, &archive).await)
+    } else {
+        None
+    };
+
+    for destination in &destinations {
+        let file = match (&sealed, destination.encrypt) {
+            (Some(Ok(path)), true) => path.clone(),
+            (Some(Err(reason)), true) => {
+                record_failure(state, backup, destination, reason).await;
+                continue;
+            }
+            _ => archive.clone(),
+        };
+        upload(state, backup, destination, &file).await;
+    }
+
+    if let Some(Ok(path)) = sealed {
+        remove_file(&path).await;
+    }
+    if backup.mode == BackupMode::Dedup {
+        remove_file(&archive).await;
+    }
+}
+
+/// Name of the copies of a backup, an archive in either mode.
+fn copy_name(backup: &Backup, encrypted: bool) -> String {
+    remote_name(&file_name(backup.uuid, BackupMode::Archive), encrypted)
+}
+
+/// Writes a dedup backup as an archive next to its manifest.
+async fn export(state: &AppState, backup: &Backup) -> Result<PathBuf, String> {
+    let Source::Snapshot { manifest, store } = source(state, backup) else {
+        return Err("not a dedup backup".to_string());
+    };
+    let dest = partial_path(&manifest.with_file_name(file_name(backup.uuid, BackupMode::Archive)));
+    let level = state.config.backup.compression_level;
+
+    let target = dest.clone();
+    let exported = spawn_blocking(move || {
+        dedup::export_snapshot(&dedup::read_manifest(&manifest)?, &store, &target, level)
+    })
+    .await;
+    match exported {
+        Ok(Ok(())) => Ok(dest),
+        Ok(Err(e)) => {
+            remove_file(&dest).await;
+            Err(format!("export snapshot: {e}"))
+        }
+        Err(e) => Err(format!("export task failed: {e}")),
+    }
+}
+
+/// Seals the archive next to itself with the configured backup key.
+async fn seal(state: &AppState, archive: &Path) -> Result<PathBuf, String> {
+    let Some(key) = state.config.backup.encryption_key.clone() else {
+        return Err("no backup encryption key configured".to_string());
+    };
+
+    let mut name = archive.file_name().unwrap_or_default().to_os_string();
+    name.push(".enc");
+    let dest = partial_path(&archive.with_file_name(name));
+
+    let (src, target) = (archive.to_path_buf(), dest.clone());
+    let sealed =
+        spawn_blocking(move || SecretBox::from_passphrase(&key).encrypt_file(&src, &target)).await;
+    match sealed {
+        Ok(Ok(())) => Ok(dest),
+        Ok(Err(e)) => {
+            remove_file(&dest).await;
+            Err(format!("encrypt archive: {e}"))
+        }
+        Err(e) => Err(format!("encrypt task failed: {e}")),
+    }
+}
+
+async fn upload(state: &AppState, backup: &Backup, destination: &BackupDestination, file: &Path) {
+    let cfg = &state.config.backup;
+    let mut copy = BackupCopy {
+        backup_uuid: backup.uuid,
+        destination_uuid: destination.uuid,
+        status: CopyStatus::Uploading,
+        remote_name: copy_name(backup, destination.encrypt),
+        encrypted: destination.encrypt,
+        attempts: 0,
+        error: None,
+        updated_at: chrono::Utc::now(),
+    };
+
+    let remote = match remote(state, destination) {
+        Ok(remote) => remote,
+        Err(reason) => return record_failure(state, backup, destination, &reason).await,
+    };
+
+    let uploaded = upload_retrying(
+        &remote,
+        file,
+        &mut copy,
+        cfg.upload_attempts,
+        cfg.upload_retry_delay,
+        |copy| async move { save(state, &copy).await },
+    )
+    .await;
+    match uploaded {
+        Ok(()) => info!(
+            backup_uuid = %backup.uuid,
+            destination = destination.name,
+            attempts = copy.attempts,
+            "backup copy uploaded"
+        ),
+        Err(e) => error!(
+            error = %e,
+            backup_uuid = %backup.uuid,
+            destination = destination.name,
+            "backup upload failed"
+        ),
+    }
+}
+
+/// Uploads `file` as the copy, trying up to `attempts` times and doubling the
+/// delay after every failure. `record` gets the copy whenever it changes.
+async fn upload_retrying<F, Fut>(
+    remote: &impl Destination,
+    file: &Path,
+    copy: &mut BackupCopy,
+    attempts: u32,
+    mut delay: Duration,
+    mut record: F,
+) -> Result<(), DestinationError>
+where
+    F: FnMut(BackupCopy) -> Fut,
+    Fut: Future<Output = ()>,
+{
+    loop {
+        copy.attempts += 1;
+        copy.status = CopyStatus::Uploading;
+        record(copy.clone()).await;
+
+        match remote.upload(file, &copy.remote_name).await {
+            Ok(()) => {
+                copy.status = CopyStatus::Uploaded;
+                copy.error = None;
+                record(copy.clone()).await;
+                return Ok(());
+            }
+            Err(e) if copy.attempts < attempts.max(1) as i32 => {
+                warn!(
+                    error = %e,
+                    backup_uuid = %copy.backup_uuid,
+                    destination_uuid = %copy.destination_uuid,
+                    attempt = copy.attempts,
+                    "backup upload failed, retrying"
+                );
+                sleep(delay).await;
+                delay *= 2;
+            }
+            Err(e) => {
+                copy.status = CopyStatus::Failed;
+                copy.error = Some(e.to_string());
+                record(copy.clone()).await;
+                return Err(e);
+            }
+        }
+    }
+}
+
+async fn record_failure(
+    state: &AppState,
+    backup: &Backup,
+    destination: &BackupDestination,
+    reason: &str,
+) {
+    warn!(backup_uuid = %backup.uuid, destination = destination.name, reason, "backup copy skipped");
+    let copy = BackupCopy {
+        backup_uuid: backup.uuid,
+        destination_uuid: destination.uuid,
+        status: CopyStatus::Failed,
+        remote_name: copy_name(backup, destination.encrypt),
+        encrypted: destination.encrypt,
+        attempts: 0,
+        error: Some(reason.to_string()),
+        updated_at: chrono::Utc::now(),
+    };
+    save(state, &copy).await;
+}
+
+async fn save(state: &AppState, copy: &BackupCopy) {
+    if let Err(e) = db::destination::set_copy(&state.db_pool, copy).await {
+        error!(error = %e, backup_uuid = %copy.backup_uuid, "record backup copy failed");
+    }
+}
+
+/// Removes the copies of a backup from its destinations, best effort.
+pub async fn delete_copies(state: &AppState, backup: &Backup) {
+    let copies = match db::destination::get_copies(&state.db_pool, backup.uuid).await {
+        Ok(copies) => copies,
+        Err(e) => {
+            error!(error = %e, backup_uuid = %backup.uuid, "fetch backup copies failed");
+            return;
+        }
+    };
+
+    for copy in copies {
+        let destination = match db::destination::get_by_uuid(
+            &state.db_pool,
+            backup.server_uuid,
+            copy.destination_uuid,
+        )
+        .await
+        {
+            Ok(Some(destination)) => destination,
+            Ok(None) => continue,
+            Err(e) => {
+                error!(error = %e, "fetch backup destination failed");
+                continue;
+            }
+        };
+
+        let deleted = match remote(state, &destination) {
+            Ok(remote) => remote
+                .delete(&copy.remote_name)
+                .await
+                .map_err(|e| e.to_string()),
+            Err(reason) => Err(reason),
+        };
+        if let Err(reason) = deleted {
+            warn!(
+                backup_uuid = %backup.uuid,
+                destination = destination.name,
+                reason,
+                "delete backup copy failed"
+            );
+        }
+    }
+}
+
+/// Downloads a missing archive back from the first destination holding a
+/// copy, opening sealed copies with the configured key.
+pub async fn fetch(state: &AppState, backup: &Backup) -> Result<(), String> {
+    let archive = archive_path(state, backup);
+    match fs::metadata(&archive).await {
+        Ok(_) => return Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => return Err(format!("check archive: {e}")),
+    }
+    if backup.mode != BackupMode::Archive {
+        return Err("dedup backups restore from their chunk store".to_string());
+    }
+
+    let copies = db::destination::get_copies(&state.db_pool, backup.uuid)
+        .await
+        .map_err(|e| format!("fetch backup copies: {e}"))?;
+    let mut last_error = "no uploaded copy".to_string();
+
+    for copy in copies.iter().filter(|c| c.status == CopyStatus::Uploaded) {
+        match fetch_copy(state, backup, copy, &archive).await {
+            Ok(()) => {
+                info!(backup_uuid = %backup.uuid, "backup archive fetched from destination");
+                return Ok(());
+            }
+            Err(reason) => {
+                warn!(backup_uuid = %backup.uuid, reason, "fetch backup copy failed");
+                last_error = reason;
+            }
+        }
+    }
+    Err(last_error)
+}
+
+async fn fetch_copy(
+    state: &AppState,
+    backup: &Backup,
+    copy: &BackupCopy,
+    archive: &Path,
+) -> Result<(), String> {
+    let destination =
+        db::destination::get_by_uuid(&state.db_pool, backup.server_uuid, copy.destination_uuid)
+            .await
+            .map_err(|e| format!("fetch destination: {e}"))?
+            .ok_or("destination removed")?;
+    let remote = remote(state, &destination)?;
+
+    let partial = partial_path(archive);
+    let downloaded = partial.with_extension(DOWNLOAD_SUFFIX);
+    if let Some(parent) = archive.parent() {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| format!("create backup directory: {e}"))?;
+    }
+
+    let result = async {
+        remote
+            .download(&copy.remote_name, &downloaded)
+            .await
+            .map_err(|e| format!("download: {e}"))?;
+
+        if copy.encrypted {
+            let key = state
+                .config
+                .backup
+                .encryption_key
+                .clone()
+                .ok_or("no backup encryption key configured")?;
+            let (src, dest) = (downloaded.clone(), partial.clone());
+            spawn_blocking(move || SecretBox::from_passphrase(&key).decrypt_file(&src, &dest))
+                .await
+                .map_err(|e| format!("decrypt task failed: {e}"))?
+                .map_err(|e| format!("decrypt: {e}"))?;
+        } else {
+            fs::rename(&downloaded, &partial)
+                .await
+                .map_err(|e| format!("move download: {e}"))?;
+        }
+        fs::rename(&partial, archive)
+            .await
+            .map_err(|e| format!("move archive in place: {e}"))
+    }
+    .await;
+
+    remove_file(&downloaded).await;
+    if result.is_err() {
+        remove_file(&partial).await;
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicU32, Ordering},
+    };
+
+    use tokio::time::Instant;
+    use uuid::Uuid;
+
+    use super::*;
+
+    /// Fails the first `failures` uploads and notes when each one came in.
+    struct Flaky {
+        failures: AtomicU32,
+        calls: Mutex<Vec<Instant>>,
+    }
+
+    impl Flaky {
+        fn new(failures: u32) -> Self {
+            Self {
+                failures: AtomicU32::new(failures),
+                calls: Mutex::new(Vec::new()),
+            }
+        }
+
+        fn calls(&self) -> Vec<Instant> {
+            self.calls.lock().unwrap().clone()
+        }
+    }
+
+    impl Destination for Flaky {
+        async fn upload(&self, _file: &Path, _name: &str) -> Result<(), DestinationError> {
+            self.calls.lock().unwrap().push(Instant::now());
+            let left = self.failures.load(Ordering::SeqCst);
+            if left == 0 {
+                return Ok(());
+            }
+            self.failures.store(left - 1, Ordering::SeqCst);
+            Err(DestinationError::S3 {
+                status: 503,
+                message: format!("{left} failures left"),
+            })
+        }
+
+        async fn download(&self, _name: &str, _file: &Path) -> Result<(), DestinationError> {
+            unreachable!("retry test never downloads")
+        }
+
+        async fn delete(&self, _name: &str) -> Result<(), DestinationError> {
+            unreachable!("retry test never deletes")
+        }
+    }
+
+    /// Runs the retry loop and hands back the result, the final copy and
+    /// every recorded state as `(status, attempts)`.
+    async fn run(
+        remote: &Flaky,
+        attempts: u32,
+        delay: Duration,
+    ) -> (
+        Result<(), DestinationError>,
+        BackupCopy,
+        Vec<(CopyStatus, i32)>,
+    ) {
+        let mut copy = BackupCopy {
+            backup_uuid: Uuid::new_v4(),
+            destination_uuid: Uuid::new_v4(),
+            status: CopyStatus::Uploading,
+            remote_name: "backup.tar.zst".to_string(),
+            encrypted: false,
+            attempts: 0,
+            error: None,
+            updated_at: chrono::Utc::now(),
+        };
+        let mut recorded = Vec::new();
+        let result = upload_retrying(
+            remote,
+            Path::new("backup.tar.zst"),
+            &mut copy,
+            attempts,
+            delay,
+            |copy| {
+                recorded.push((copy.status, copy.attempts));
+                async {}
+            },
+        )
+        .await;
+        (result, copy, recorded)
+    }
+
+    #[tokio::test]
+    async fn first_success_needs_one_attempt() {
+        let remote = Flaky::new(0);
+        let (result, copy, recorded) = run(&remote, 3, Duration::from_millis(1)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(copy.attempts, 1);
+        assert_eq!(
+            recorded,
+            vec![(CopyStatus::Uploading, 1), (CopyStatus::Uploaded, 1)]
+        );
+    }
+
+    #[tokio::test]
+    async fn retries_until_the_upload_goes_through() {
+        let remote = Flaky::new(2);
+        let (result, copy, recorded) = run(&remote, 3, Duration::from_millis(1)).await;
+
+        assert!(result.is_ok());
+        assert_eq!(remote.calls().len(), 3);
+        assert_eq!(copy.status, CopyStatus::Uploaded);
+        assert_eq!(copy.error, None);
+        assert_eq!(
+            recorded,
+            vec![
+                (CopyStatus::Uploading, 1),
+                (CopyStatus::Uploading, 2),
+                (CopyStatus::Uploading, 3),
+                (CopyStatus::Uploaded, 3),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn gives_up_with_the_last_error() {
+        let remote = Flaky::new(5);
+        let (result, copy, recorded) = run(&remote, 3, Duration::from_millis(1)).await;
+
+        assert_eq!(remote.calls().len(), 3);
+        let error = result.unwrap_err().to_string();
+        assert!(error.contains("3 failures left"), "{error}");
+        assert_eq!(copy.status, CopyStatus::Failed);
+        assert_eq!(copy.error.as_deref(), Some(error.as_str()));
+        assert_eq!(recorded.last(), Some(&(CopyStatus::Failed, 3)));
+    }
+
+    #[tokio::test]
+    async fn zero_attempts_still_tries_once() {
+        let remote = Flaky::new(1);
+        let (result, copy, _) = run(&remote, 0, Duration::from_millis(1)).await;
+
+        assert!(result.is_err());
+        assert_eq!(copy.attempts, 1);
+        assert_eq!(remote.calls().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn delay_doubles_between_attempts() {
+        let remote = Flaky::new(3);
+        let (result, _, _) = run(&remote, 4, Duration::from_millis(40)).await;
+        assert!(result.is_ok());
+
+        let calls = remote.calls();
+        let gaps: Vec<_> = calls.windows(2).map(|w| w[1] - w[0]).collect();
+        assert!(gaps[0] >= Duration::from_millis(40), "{gaps:?}");
+        assert!(gaps[1] >= Duration::from_millis(80), "{gaps:?}");
+        assert!(gaps[2] >= Duration::from_millis(160), "{gaps:?}");
+    }
+}
@@ -0,0 +1,429 @@
+use std::{
+    fs as std_fs, io,
+    path::{Path, PathBuf},
+};
+
+use chrono::Utc;
+use thiserror::Error;
+use tokio::{
+    fs,
+    task::{JoinError, spawn_blocking},
+};
+
+use crate::{
+    domain::backup::BackupScope,
+    infra::{archive, db},
+    prelude::*,
+    state::AppState,
+};
+
+use super::{Source, nested_dir};
+
+const SNAPSHOT_INFIX: &str = ".pre-restore-";
+/// Appended to the snapshot directory for the file listing what a restore
+/// moved into it, kept until the restore finished or was rolled back.
+const JOURNAL_SUFFIX: &str = ".journal";
+
+#[derive(Debug, Error)]
+pub enum RestoreError {
+    #[error("backup does not match its recorded checksum")]
+    ChecksumMismatch,
+    #[error("target directory is not empty")]
+    TargetNotEmpty,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+    #[error("restore task failed: {0}")]
+    Task(#[from] JoinError),
+}
+
+/// Checks the archive or manifest still matches the checksum recorded when it
+/// was written. Chunks are checked as they are read.
+pub async fn verify(source: &Source, expected: Option<&str>) -> Result<(), RestoreError> {
+    let path = source.path().to_path_buf();
+    let actual = spawn_blocking(move || archive::sha256_file(&path)).await??;
+    match expected {
+        Some(expected) if !expected.eq_ignore_ascii_case(&actual) => {
+            Err(RestoreError::ChecksumMismatch)
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Top level paths of `root` a restore replaces: the selected paths, every
+/// entry for a full backup, or the folders the backup holds otherwise.
+pub async fn replaced_paths(
+    state: &AppState,
+    root: &Path,
+    source: &Source,
+    scope: BackupScope,
+    only: &[PathBuf],
+) -> Result<Vec<PathBuf>, RestoreError> {
+    if !only.is_empty() {
+        return Ok(only.to_vec());
+    }
+
+    match scope {
+        BackupScope::Full => {
+            let backups = nested_dir(root, &state.config.backup.dir).await;
+            let mut replaced = Vec::new();
+            let mut entries = fs::read_dir(root).await?;
+            while let Some(entry) = entries.next_entry().await? {
+                if backups.as_deref() != Some(entry.path().as_path()) {
+                    replaced.push(PathBuf::from(entry.file_name()));
+                }
+            }
+            Ok(replaced)
+        }
+        BackupScope::Worlds => {
+            let source = source.clone();
+            let top = spawn_blocking(move || source.top_level_entries()).await??;
+            Ok(top.into_iter().collect())
+        }
+    }
+}
+
+/// Moves `replaced` into a snapshot directory next to `root`, then extracts
+/// the backup. On failure the extracted files are dropped and the snapshot
+/// moved back. Returns the snapshot directory, if anything was moved, and the
+/// number of restored entries.
+pub async fn replace(
+    root: PathBuf,
+    source: Source,
+    replaced: Vec<PathBuf>,
+    only: Vec<PathBuf>,
+) -> Result<(Option<PathBuf>, u64), RestoreError> {
+    Ok(spawn_blocking(move || replace_blocking(&root, &source, &replaced, &only)).await??)
+}
+
+fn replace_blocking(
+    root: &Path,
+    source: &Source,
+    replaced: &[PathBuf],
+    only: &[PathBuf],
+) -> io::Result<(Option<PathBuf>, u64)> {
+    let snapshot = snapshot_dir(root);
+    let journal = journal_path(&snapshot);
+    // Written before anything moves, so a restore cut short by the daemon
+    // stopping can be rolled back on the next start
+    write_journal(&journal, replaced)?;
+    let mut moved = Vec::new();
+
+    let result = (|| {
+        for relative in replaced {
+            let from = root.join(relative);
+            if std_fs::symlink_metadata(&from).is_err() {
+                continue;
+            }
+            let to = snapshot.join(relative);
+            if let Some(parent) = to.parent() {
+                std_fs::create_dir_all(parent)?;
+            }
+            // Same filesystem as the server directory, so this is a cheap rename
+            std_fs::rename(&from, &to)?;
+            moved.push(relative.clone());
+        }
+        source.extract(root, only)
+    })();
+
+    let result = match result {
+        Ok(extracted) if moved.is_empty() => {
+            let _ = std_fs::remove_dir_all(&snapshot);
+            Ok((None, extracted))
+        }
+        Ok(extracted) => Ok((Some(snapshot), extracted)),
+        Err(e) => {
+            roll_back(root, &snapshot, &moved);
+            Err(e)
+        }
+    };
+    let _ = std_fs::remove_file(&journal);
+    result
+}
+
+/// Moves `moved` back from the snapshot. The snapshot is only removed once
+/// everything is back in place.
+fn roll_back(root: &Path, snapshot: &Path, moved: &[PathBuf]) -> bool {
+    let mut complete = true;
+    for relative in moved.iter().rev() {
+        let restored = root.join(relative);
+        let _ = std_fs::remove_dir_all(&restored).or_else(|_| std_fs::remove_file(&restored));
+        if let Err(e) = std_fs::rename(snapshot.join(relative), &restored) {
+            error!(error = %e, path = %restored.display(), "roll back restore failed");
+            complete = false;
+        }
+    }
+    if complete {
+        let _ = std_fs::remove_dir_all(snapshot);
+    }
+    complete
+}
+
+fn journal_path(snapshot: &Path) -> PathBuf {
+    let mut name = snapshot.file_name().unwrap_or_default().to_os_string();
+    name.push(JOURNAL_SUFFIX);
+    snapshot.with_file_name(name)
+}
+
+fn write_journal(journal: &Path, replaced: &[PathBuf]) -> io::Result<()> {
+    let mut file = std_fs::File::create(journal)?;
+    serde_json::to_writer(&mut file, replaced)?;
+    file.sync_all()
+}
+
+/// Rolls back the in-place restores of `root` a previous run of the daemon
+/// left unfinished, returns the snapshots that were moved back. Only entries
+/// that made it into the snapshot are touched, the rest was never replaced.
+/// Blocking.
+fn recover(root: &Path) -> io::Result<Vec<PathBuf>> {
+    let (Some(parent), Some(name)) = (root.parent(), root.file_name()) else {
+        return Ok(Vec::new());
+    };
+    let mut prefix = name.to_os_string();
+    prefix.push(SNAPSHOT_INFIX);
+    let prefix = prefix.to_string_lossy().into_owned();
+
+    let mut recovered = Vec::new();
+    for entry in std_fs::read_dir(parent)? {
+        let file_name = entry?.file_name().to_string_lossy().into_owned();
+        let Some(snapshot) = file_name
+            .strip_prefix(&prefix)
+            .and_then(|rest| rest.strip_suffix(JOURNAL_SUFFIX))
+            .map(|stamp| parent.join(format!("{prefix}{stamp}")))
+        else {
+            continue;
+        };
+        let journal = parent.join(&file_name);
+
+        // An unreadable journal was cut off before anything moved
+        let replaced: Vec<PathBuf> = std_fs::read(&journal)
+            .ok()
+            .and_then(|data| serde_json::from_slice(&data).ok())
+            .unwrap_or_default();
+        let moved: Vec<PathBuf> = replaced
+            .into_iter()
+            .filter(|relative| std_fs::symlink_metadata(snapshot.join(relative)).is_ok())
+            .collect();
+        if moved.is_empty() {
+            let _ = std_fs::remove_dir_all(&snapshot);
+        } else if !roll_back(root, &snapshot, &moved) {
+            // Keep the journal so the next start tries again
+            continue;
+        } else {
+            recovered.push(snapshot);
+        }
+        std_fs::remove_file(&journal)?;
+    }
+    Ok(recovered)
+}
+
+/// Puts back the files of every server whose in-place restore was cut short
+/// by the daemon stopping, as a failed restore would have.
+pub async fn init(state: &AppState) {
+    let servers = match db::server::get_all(&state.db_pool).await {
+        Ok(servers) => servers,
+        Err(e) => {
+            error!(error = %e, "fetch servers for restore recovery failed");
+            return;
+        }
+    };
+
+    for server in servers {
+        let root = PathBuf::from(&server.working_dir);
+        match spawn_blocking(move || recover(&root)).await {
+            Ok(Ok(recovered)) => {
+                for snapshot in recovered {
+                    warn!(
+                        server_uuid = %server.uuid,
+                        snapshot = %snapshot.display(),
+                        "interrupted restore rolled back"
+                    );
+                }
+            }
+            Ok(Err(e)) => {
+                error!(error = %e, server_uuid = %server.uuid, "recover interrupted restore failed")
+            }
+            Err(e) => error!(error = %e, "restore recovery task failed"),
+        }
+    }
+}
+
+/// `/srv/mc/survival` snapshots to `/srv/mc/survival.pre-restore-20250101-030000`,
+/// with a counter appended when that already exists.
+fn snapshot_dir(root: &Path) -> PathBuf {
+    let mut base = root.file_name().unwrap_or_default().to_os_string();
+    base.push(format!(
+        "{SNAPSHOT_INFIX}{}",
+        Utc::now().format("%Y%m%d-%H%M%S")
+    ));
+
+    let mut candidate = root.with_file_name(&base);
+    let mut counter = 1;
+    while std_fs::symlink_metadata(&candidate).is_ok() {
+        counter += 1;
+        let mut name = base.clone();
+        name.push(format!("-{counter}"));
+        candidate = root.with_file_name(name);
+    }
+    candidate
+}
+
+/// Extracts the backup into a directory that must be missing or empty. The
+/// directory is removed again if extracting fails.
+pub async fn extract_new(
+    root: PathBuf,
+    source: Source,
+    only: Vec<PathBuf>,
+) -> Result<u64, RestoreError> {
+    if let Ok(mut entries) = fs::read_dir(&root).await
+        && entries.next_entry().await?.is_some()
+    {
+        return Err(RestoreError::TargetNotEmpty);
+    }
+    fs::create_dir_all(&root).await?;
+
+    let target = root.clone();
+    let extracted = spawn_blocking(move || source.extract(&target, &only)).await?;
+    if extracted.is_err() {
+        let _ = fs::remove_dir_all(&root).await;
+    }
+    Ok(extracted?)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// A parent directory holding a server directory `survival`.
+    fn scratch() -> TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        std_fs::create_dir(dir.path().join("survival")).unwrap();
+        dir
+    }
+
+    fn names(dir: &TempDir) -> Vec<String> {
+        let mut names: Vec<_> = std_fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        names.sort();
+        names
+    }
+
+    fn server_files(root: &Path, marker: &str) {
+        std_fs::create_dir_all(root.join("world/region")).unwrap();
+        std_fs::write(root.join("world/level.dat"), marker).unwrap();
+        std_fs::write(root.join("world/region/r.0.0.mca"), marker).unwrap();
+        std_fs::write(root.join("server.properties"), marker).unwrap();
+    }
+
+    fn archive_of(scratch: &TempDir, marker: &str) -> Source {
+        let tree = scratch.path().join(format!("tree-{marker}"));
+        server_files(&tree, marker);
+        let path = scratch.path().join(format!("{marker}.tar.gz"));
+        archive::write_tar_gz(&tree, &[PathBuf::new()], &path, 1, &[], &[]).unwrap();
+        std_fs::remove_dir_all(&tree).unwrap();
+        Source::Archive(path)
+    }
+
+    fn read(path: PathBuf) -> String {
+        std_fs::read_to_string(path).unwrap()
+    }
+
+    #[test]
+    fn replace_keeps_a_snapshot_and_no_journal() {
+        let scratch = scratch();
+        let root = scratch.path().join("survival");
+        server_files(&root, "old");
+        let source = archive_of(&scratch, "new");
+
+        let replaced = vec![PathBuf::from("world")];
+        let (snapshot, _) = replace_blocking(&root, &source, &replaced, &[]).unwrap();
+        let snapshot = snapshot.unwrap();
+
+        assert_eq!(read(root.join("world/level.dat")), "new");
+        assert_eq!(read(snapshot.join("world/level.dat")), "old");
+        assert!(!journal_path(&snapshot).exists());
+    }
+
+    #[test]
+    fn failed_extraction_rolls_back() {
+        let scratch = scratch();
+        let root = scratch.path().join("survival");
+        server_files(&root, "old");
+        let broken = scratch.path().join("broken.tar.gz");
+        std_fs::write(&broken, b"not an archive").unwrap();
+
+        let replaced = vec![PathBuf::from("world"), PathBuf::from("server.properties")];
+        let result = replace_blocking(&root, &Source::Archive(broken), &replaced, &[]);
+
+        assert!(result.is_err());
+        assert_eq!(read(root.join("world/level.dat")), "old");
+        assert_eq!(read(root.join("server.properties")), "old");
+        assert_eq!(names(&scratch), vec!["broken.tar.gz", "survival"]);
+    }
+
+    #[test]
+    fn recover_rolls_back_an_interrupted_restore() {
+        let scratch = scratch();
+        let root = scratch.path().join("survival");
+        server_files(&root, "old");
+
+        // Stopped after moving the world and extracting part of the backup,
+        // before the properties moved
+        let snapshot = scratch.path().join("survival.pre-restore-20260101-030000");
+        let replaced = vec![PathBuf::from("world"), PathBuf::from("server.properties")];
+        write_journal(&journal_path(&snapshot), &replaced).unwrap();
+        std_fs::create_dir_all(&snapshot).unwrap();
+        std_fs::rename(root.join("world"), snapshot.join("world")).unwrap();
+        std_fs::create_dir_all(root.join("world")).unwrap();
+        std_fs::write(root.join("world/level.dat"), "half").unwrap();
+
+        let recovered = recover(&root).unwrap();
+
+        assert_eq!(recovered, vec![snapshot]);
+        assert_eq!(read(root.join("world/level.dat")), "old");
+        assert_eq!(read(root.join("world/region/r.0.0.mca")), "old");
+        assert_eq!(read(root.join("server.properties")), "old");
+        assert_eq!(names(&scratch), vec!["survival"]);
+    }
+
+    #[test]
+    fn recover_leaves_finished_restores_and_other_servers_alone() {
+        let scratch = scratch();
+        let root = scratch.path().join("survival");
+        server_files(&root, "new");
+        // A snapshot kept by a finished restore has no journal
+        let kept = scratch.path().join("survival.pre-restore-20260101-030000");
+        server_files(&kept, "old");
+        // Another server's interrupted restore
+        let other = scratch.path().join("creative.pre-restore-20260101-030000");
+        write_journal(&journal_path(&other), &[PathBuf::from("world")]).unwrap();
+
+        assert!(recover(&root).unwrap().is_empty());
+        assert_eq!(read(root.join("world/level.dat")), "new");
+        assert_eq!(
+            names(&scratch),
+            vec![
+                "creative.pre-restore-20260101-030000.journal",
+                "survival",
+                "survival.pre-restore-20260101-030000",
+            ]
+        );
+    }
+
+    #[test]
+    fn recover_drops_journals_of_restores_that_moved_nothing() {
+        let scratch = scratch();
+        let root = scratch.path().join("survival");
+        server_files(&root, "old");
+        let snapshot = scratch.path().join("survival.pre-restore-20260101-030000");
+        // Cut off while writing the journal
+        std_fs::write(journal_path(&snapshot), b"[\"wor").unwrap();
+
+        assert!(recover(&root).unwrap().is_empty());
+        assert_eq!(read(root.join("world/level.dat")), "old");
+        assert_eq!(names(&scratch), vec!["survival"]);
+    }
+}
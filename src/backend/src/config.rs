@@ -1,11 +1,12 @@
-use axum::{
-    Json, RequestExt,
-    extract::{MatchedPath, Request, State},
-    http::{Method, StatusCode},
-};
-use std::collections::HashMap;
+use axum::http::{Method, StatusCode};
+use std::{collections::HashMap, path::PathBuf, time::Duration};
 
-use crate::domain::user_prems::{UserActions, UserPermissions};
+use crate::domain::{
+    backup::{BackupMode, RetentionPolicy},
+    countdown::AnnounceStyle,
+    job::JobKind,
+    user_prems::{UserActions, UserPermissions},
+};
 
 #[derive(Debug, Hash, Clone, PartialEq, Eq)]
 pub struct RouteKey {
@@ -13,17 +14,301 @@ pub struct RouteKey {
     pub path: String,
 }
 
+/// Where the daemon loads the web UI from, if it serves it at all.
+#[derive(Debug, Clone, Default)]
+pub enum FrontendSource {
+    #[default]
+    Disabled,
+    /// A `react-router build` output directory (`build/client`) read at runtime.
+    Directory(PathBuf),
+    /// Assets compiled into the binary with the `embed-frontend` feature.
+    #[cfg(feature = "embed-frontend")]
+    Embedded,
+}
+
+#[derive(Debug, Clone)]
+pub struct SupervisorCfg {
+    /// How long a server gets to exit after `stop` before it is sent SIGTERM.
+    pub stop_timeout: Duration,
+    /// How long a server gets to exit after SIGTERM before it is sent SIGKILL.
+    pub term_timeout: Duration,
+    /// Console lines kept per server and replayed to new console clients.
+    pub scrollback_lines: usize,
+}
+
+impl Default for SupervisorCfg {
+    fn default() -> Self {
+        Self {
+            stop_timeout: Duration::from_secs(60),
+            term_timeout: Duration::from_secs(15),
+            scrollback_lines: 1000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CrashCfg {
+    /// Start crashed servers again on their own.
+    pub auto_restart: bool,
+    /// Delay before the first restart after a crash, doubled for each further one.
+    pub backoff_initial: Duration,
+    pub backoff_max: Duration,
+    /// Restarts allowed within `restart_window` before the daemon gives up.
+    pub max_restarts: usize,
+    pub restart_window: Duration,
+    /// Console lines stored with each crash record.
+    pub console_lines: usize,
+    /// Crash reports larger than this are truncated in the record.
+    pub max_report_bytes: usize,
+}
+
+impl Default for CrashCfg {
+    fn default() -> Self {
+        Self {
+            auto_restart: true,
+            backoff_initial: Duration::from_secs(5),
+            backoff_max: Duration::from_secs(300),
+            max_restarts: 5,
+            restart_window: Duration::from_secs(600),
+            console_lines: 200,
+            max_report_bytes: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct CountdownCfg {
+    /// Seconds before a stop or restart at which players are warned, unless
+    /// the request brings its own.
+    pub announce_at: Vec<u32>,
+    /// Default announcement, `{action}` and `{time}` are filled in.
+    pub message: String,
+    pub cancelled_message: String,
+    pub style: AnnounceStyle,
+    /// How long to wait for `save-all` to finish before stopping anyway.
+    pub save_timeout: Duration,
+}
+
+impl Default for CountdownCfg {
+    fn default() -> Self {
+        Self {
+            announce_at: vec![300, 60, 30, 10],
+            message: "Server {action} in {time}".to_string(),
+            cancelled_message: "Server shutdown cancelled".to_string(),
+            style: AnnounceStyle::Say,
+            save_timeout: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BackupCfg {
+    /// Directory archives are written to, one subdirectory per server.
+    pub dir: PathBuf,
+    /// Mode for backups that do not ask for one.
+    pub mode: BackupMode,
+    /// gzip and zlib level from 0 (store) to 9.
+    pub compression_level: u32,
+    /// How long to wait for `save-all flush` before archiving anyway.
+    pub save_timeout: Duration,
+    /// Retention for servers without a policy of their own.
+    pub retention: RetentionPolicy,
+    /// Key copies for encrypting destinations are sealed with, such
+    /// destinations fail their copies without one.
+    pub encryption_key: Option<String>,
+    /// Tries per destination before a copy is marked failed.
+    pub upload_attempts: u32,
+    /// Wait before the first retry, doubled for every further one.
+    pub upload_retry_delay: Duration,
+}
+
+impl Default for BackupCfg {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("backups"),
+            mode: BackupMode::Archive,
+            compression_level: 6,
+            save_timeout: Duration::from_secs(60),
+            retention: RetentionPolicy {
+                keep_last: 5,
+                keep_daily: 7,
+                keep_weekly: 4,
+                keep_monthly: 3,
+            },
+            encryption_key: None,
+            upload_attempts: 3,
+            upload_retry_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FilesCfg {
+    /// Staging directory for resumable uploads. Finished uploads are moved
+    /// into place, or copied when it is on another filesystem.
+    pub upload_dir: PathBuf,
+    /// Largest file the editor reads or writes.
+    pub max_edit_bytes: u64,
+    /// Largest single upload, for multipart requests all files together.
+    pub max_upload_bytes: u64,
+    /// How long an unfinished resumable upload is kept.
+    pub upload_ttl: Duration,
+    /// Most bytes a single archive may extract to.
+    pub max_extract_bytes: u64,
+    /// Most entries a single archive may hold.
+    pub max_extract_entries: u64,
+}
+
+impl Default for FilesCfg {
+    fn default() -> Self {
+        Self {
+            upload_dir: PathBuf::from("uploads"),
+            max_edit_bytes: 2 * 1024 * 1024,
+            max_upload_bytes: 4 * 1024 * 1024 * 1024,
+            upload_ttl: Duration::from_secs(24 * 60 * 60),
+            max_extract_bytes: 16 * 1024 * 1024 * 1024,
+            max_extract_entries: 100_000,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct JobsCfg {
+    /// Jobs of one kind running at once, further ones wait in the queue.
+    pub limits: HashMap<JobKind, usize>,
+    /// Limit for kinds not listed in `limits`.
+    pub default_limit: usize,
+    /// How often the progress of a running job is saved and streamed.
+    pub progress_interval: Duration,
+    /// Log lines kept per job.
+    pub log_lines: usize,
+    /// How long finished jobs are kept.
+    pub retention: Duration,
+}
+
+impl Default for JobsCfg {
+    fn default() -> Self {
+        Self {
+            limits: HashMap::from([
+                (JobKind::Backup, 2),
+                (JobKind::Restore, 1),
+                (JobKind::Compress, 2),
+                (JobKind::Extract, 2),
+                (JobKind::Install, 2),
+            ]),
+            default_limit: 2,
+            progress_interval: Duration::from_secs(1),
+            log_lines: 200,
+            retention: Duration::from_secs(7 * 24 * 60 * 60),
+        }
+    }
+}
+
+impl JobsCfg {
+    pub fn limit(&self, kind: JobKind) -> usize {
+        self.limits
+            .get(&kind)
+            .copied()
+            .unwrap_or(self.default_limit)
+            .max(1)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct InstallerCfg {
+    /// Downloaded server jars and installers, reused by later installs.
+    pub cache_dir: PathBuf,
+    /// Mojang's version manifest, the download URLs come from it.
+    pub vanilla_manifest_url: String,
+    /// Base URLs of the metadata APIs and maven repositories, point them at
+    /// a mirror to keep installs off the public hosts.
+    pub paper_url: String,
+    pub purpur_url: String,
+    pub fabric_url: String,
+    pub forge_maven_url: String,
+    pub neoforge_maven_url: String,
+    /// How long a Forge or NeoForge installer may run.
+    pub installer_timeout: Duration,
+}
+
+impl Default for InstallerCfg {
+    fn default() -> Self {
+        Self {
+            cache_dir: PathBuf::from("cache"),
+            vanilla_manifest_url: "https://piston-meta.mojang.com/mc/game/version_manifest_v2.json"
+                .to_string(),
+            paper_url: "https://api.papermc.io".to_string(),
+            purpur_url: "https://api.purpurmc.org".to_string(),
+            fabric_url: "https://meta.fabricmc.net".to_string(),
+            forge_maven_url: "https://maven.minecraftforge.net".to_string(),
+            neoforge_maven_url: "https://maven.neoforged.net/releases".to_string(),
+            installer_timeout: Duration::from_secs(10 * 60),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct JavaCfg {
+    /// Searched for Java installations on top of `JAVA_HOME`, the `PATH` and
+    /// the usual install locations. Either an installation itself or a
+    /// directory holding several, like `/usr/lib/jvm`.
+    pub search_dirs: Vec<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StatusCfg {
+    /// How often every server is pinged.
+    pub poll_interval: Duration,
+    /// How long a single ping may take before the server counts as unreachable.
+    pub ping_timeout: Duration,
+    /// How long a query (full stat) answer is served from cache.
+    pub query_ttl: Duration,
+}
+
+impl Default for StatusCfg {
+    fn default() -> Self {
+        Self {
+            poll_interval: Duration::from_secs(15),
+            ping_timeout: Duration::from_secs(3),
+            query_ttl: Duration::from_secs(30),
+        }
+    }
+}
+
 #[derive(Debug)]
 pub struct AppCfg {
     pub db_path: String,
+    /// Passphrase the key sealing secrets at rest (RCON passwords) is derived from.
+    pub secret_key: String,
     pub route_perms: HashMap<RouteKey, UserPermissions>,
+    pub frontend: FrontendSource,
+    pub supervisor: SupervisorCfg,
+    pub status: StatusCfg,
+    pub crash: CrashCfg,
+    pub countdown: CountdownCfg,
+    pub backup: BackupCfg,
+    pub files: FilesCfg,
+    pub jobs: JobsCfg,
+    pub installer: InstallerCfg,
+    pub java: JavaCfg,
 }
 
 impl AppCfg {
-    pub fn new(db_path: String) -> Self {
+    pub fn new(db_path: String, secret_key: String) -> Self {
         Self {
             db_path,
+            secret_key,
             route_perms: HashMap::new(),
+            frontend: FrontendSource::default(),
+            supervisor: SupervisorCfg::default(),
+            status: StatusCfg::default(),
+            crash: CrashCfg::default(),
+            countdown: CountdownCfg::default(),
+            backup: BackupCfg::default(),
+            files: FilesCfg::default(),
+            jobs: JobsCfg::default(),
+            installer: InstallerCfg::default(),
+            java: JavaCfg::default(),
         }
     }
 
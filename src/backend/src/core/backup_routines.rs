@@ -0,0 +1,521 @@
+use crate::{
+    backup::{self, ARCHIVE_EXTENSION, Source, replicate, restore},
+    core::server_routines,
+    countdown,
+    domain::{
+        backup::{
+            Backup, BackupMode, BackupStatus, BackupVerification, NewBackup, RestoreOutcome,
+            RestoreRequest, RestoreServer, RetentionPolicy, restore_path,
+        },
+        job::{Job, JobKind, NewJob},
+        server::{NewServer, Server},
+        user::InternalUser,
+        user_prems::UserActions,
+    },
+    infra::{db, minecraft::properties},
+    jobs::{self, JobHandle},
+    prelude::*,
+    supervisor::{MaintenanceGuard, SupervisorError},
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use tokio::{fs, task::JoinHandle};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+pub async fn list(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<Backup>, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch backups started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    db::backup::get_for_server(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch backups failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    backup_uuid: Uuid,
+) -> Result<Backup, StatusCode> {
+    debug!(server_uuid = %uuid, backup_uuid = %backup_uuid, "fetch backup started");
+    db::backup::get_by_uuid(&state.db_pool, uuid, backup_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, backup_uuid = %backup_uuid, "fetch backup failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Records a backup and queues a job archiving it. The handle resolves to the
+/// finished job, callers that do not care may drop it.
+pub async fn create(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    created_by: Option<Uuid>,
+    request: NewBackup,
+) -> Result<(Backup, JoinHandle<Job>), StatusCode> {
+    debug!(server_uuid = %uuid, scope = request.scope.as_str(), "create backup started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let Some(claim) = state.backups.claim(uuid) else {
+        warn!(server_uuid = %uuid, "backup already running");
+        return Err(StatusCode::CONFLICT);
+    };
+
+    let backup_uuid = Uuid::new_v4();
+    let mode = request.mode.unwrap_or(state.config.backup.mode);
+    let backup = Backup {
+        uuid: backup_uuid,
+        server_uuid: uuid,
+        scope: request.scope,
+        mode,
+        status: BackupStatus::Running,
+        file_name: backup::file_name(backup_uuid, mode),
+        size_bytes: None,
+        sha256: None,
+        error: None,
+        created_by,
+        started_at: Utc::now(),
+        completed_at: None,
+    };
+    let backup = match db::backup::create(&state.db_pool, &backup).await {
+        Ok(backup) => backup,
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, "create backup failed");
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    // The job holds the claim from queued until the archive is written
+    let new_job = NewJob {
+        kind: JobKind::Backup,
+        server_uuid: Some(uuid),
+        description: format!("{} backup of {}", backup.scope.as_str(), server.name),
+        created_by,
+        cancellable: false,
+    };
+    let task_state = state.clone();
+    let record = backup.clone();
+    let started = jobs::start(&state, new_job, move |job| async move {
+        let backup = backup::run(task_state, server, record, claim, &job).await;
+        match backup.status {
+            BackupStatus::Completed => Ok(serde_json::to_value(backup).ok()),
+            _ => Err(backup.error.unwrap_or_else(|| "backup failed".to_string())),
+        }
+    })
+    .await;
+    let (_, handle) = match started {
+        Ok(started) => started,
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, "queue backup job failed");
+            if let Err(e) = db::backup::fail(&state.db_pool, backup.uuid, &e.to_string()).await {
+                error!(error = %e, backup_uuid = %backup.uuid, "record failed backup failed");
+            }
+            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        }
+    };
+
+    info!(backup_uuid = %backup.uuid, server_uuid = %uuid, "backup queued");
+    Ok((backup, handle))
+}
+
+pub async fn delete(state: Arc<AppState>, uuid: Uuid, backup_uuid: Uuid) -> Result<(), StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "delete backup started");
+
+    let backup = get(state.clone(), uuid, backup_uuid).await?;
+    if backup.status == BackupStatus::Running {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    backup::delete(&state, &backup).await.map_err(|e| {
+        error!(error = %e, backup_uuid = %backup_uuid, "delete backup failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if backup.mode == BackupMode::Dedup {
+        let task_state = state.clone();
+        tokio::spawn(async move { backup::collect_garbage(&task_state, uuid).await });
+    }
+
+    info!(backup_uuid = %backup_uuid, "backup deleted");
+    Ok(())
+}
+
+/// Archive path of a completed backup and the file name to offer it under.
+pub async fn download(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    backup_uuid: Uuid,
+) -> Result<(PathBuf, String), StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "download backup started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let backup = get(state.clone(), uuid, backup_uuid).await?;
+    // A dedup backup has no single file to hand out, restore it instead
+    if backup.status != BackupStatus::Completed || backup.mode != BackupMode::Archive {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    fetch(&state, &backup).await?;
+
+    let file_name = format!(
+        "{}-{}-{}.{ARCHIVE_EXTENSION}",
+        server.name,
+        backup.scope.as_str(),
+        backup.started_at.format("%Y%m%d-%H%M%S")
+    );
+    Ok((backup::archive_path(&state, &backup), file_name))
+}
+
+/// Checks a completed backup against its checksums, reading every chunk of a
+/// dedup backup.
+pub async fn verify(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    backup_uuid: Uuid,
+) -> Result<BackupVerification, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "verify backup started");
+    let backup = get(state.clone(), uuid, backup_uuid).await?;
+    if backup.status != BackupStatus::Completed {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let verification = backup::verify(&state, &backup).await.map_err(|e| {
+        error!(error = %e, backup_uuid = %backup_uuid, "verify backup failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    if verification.ok {
+        info!(backup_uuid = %backup_uuid, "backup verified");
+    } else {
+        warn!(
+            backup_uuid = %backup_uuid,
+            problems = verification.problems.len(),
+            "backup failed verification"
+        );
+    }
+    Ok(verification)
+}
+
+pub async fn get_retention(
+    state: Arc<AppState>,
+    uuid: Uuid,
+) -> Result<RetentionPolicy, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch backup retention started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    backup::retention(&state, uuid).await.map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "fetch backup retention failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn set_retention(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    policy: RetentionPolicy,
+) -> Result<RetentionPolicy, StatusCode> {
+    debug!(server_uuid = %uuid, "set backup retention started");
+
+    policy.validate().map_err(|e| {
+        error!(error = %e, "backup retention validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let policy = db::backup::set_retention(&state.db_pool, uuid, &policy)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "set backup retention failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(server_uuid = %uuid, "backup retention updated");
+    Ok(policy)
+}
+
+/// Queues a job restoring a completed backup over its server, or into a new
+/// server when the request names one. The archive checksum is verified before
+/// anything moves. The job's result is the [`RestoreOutcome`].
+pub async fn restore(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    backup_uuid: Uuid,
+    request: RestoreRequest,
+) -> Result<Job, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "restore backup started");
+
+    request.validate().map_err(|e| {
+        error!(error = %e, "restore validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    // A new server needs the same permission as creating one directly
+    if request.new_server.is_some()
+        && !user.permissions.root
+        && !user
+            .permissions
+            .permissions
+            .contains(&UserActions::ManageServers)
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let backup = get(state.clone(), uuid, backup_uuid).await?;
+    if backup.status != BackupStatus::Completed {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let only: Vec<PathBuf> = request
+        .paths
+        .iter()
+        .flatten()
+        .filter_map(|path| restore_path(path))
+        .collect();
+
+    let (description, claim) = match &request.new_server {
+        Some(target) => {
+            // Fail before queueing anything, create checks again
+            let name_taken = db::server::exists_by_name(&state.db_pool, &target.name)
+                .await
+                .map_err(|e| {
+                    error!(error = %e, "check server name failed");
+                    StatusCode::INTERNAL_SERVER_ERROR
+                })?;
+            if name_taken {
+                warn!(server_name = target.name, "server name already in use");
+                return Err(StatusCode::CONFLICT);
+            }
+            if let Ok(mut entries) = fs::read_dir(&target.working_dir).await
+                && let Ok(Some(_)) = entries.next_entry().await
+            {
+                warn!(
+                    dir = target.working_dir,
+                    "restore target directory not empty"
+                );
+                return Err(StatusCode::CONFLICT);
+            }
+            let description = format!(
+                "restore backup of {} into new server {}",
+                server.name, target.name
+            );
+            (description, None)
+        }
+        None => {
+            let Some(claim) = state.backups.claim(uuid) else {
+                warn!(server_uuid = %uuid, "backup or restore already running");
+                return Err(StatusCode::CONFLICT);
+            };
+            (format!("restore backup of {}", server.name), Some(claim))
+        }
+    };
+    // Nothing may start the server from here until its files are back
+    let maintenance = match &request.new_server {
+        Some(_) => None,
+        None => match state.supervisor.maintenance(uuid).await {
+            Some(guard) => Some(guard),
+            None => {
+                warn!(server_uuid = %uuid, "server under maintenance");
+                return Err(StatusCode::CONFLICT);
+            }
+        },
+    };
+
+    // An in-place restore holds the claim and the maintenance guard from
+    // queued until its work ends, dropping the job releases both
+    let new_job = NewJob {
+        kind: JobKind::Restore,
+        server_uuid: Some(uuid),
+        description,
+        created_by: Some(user.uuid),
+        cancellable: false,
+    };
+    let task_state = state.clone();
+    let creator = user.clone();
+    let target = request.new_server;
+    let started = jobs::start(&state, new_job, move |job| async move {
+        let state = task_state;
+        let outcome = async {
+            job.step("fetching archive");
+            replicate::fetch(&state, &backup)
+                .await
+                .map_err(|reason| format!("backup archive unavailable: {reason}"))?;
+            job.step("verifying checksum");
+            let source = backup::source(&state, &backup);
+            restore::verify(&source, backup.sha256.as_deref())
+                .await
+                .map_err(|e| e.to_string())?;
+
+            match target {
+                Some(target) => {
+                    restore_new(&state, &creator, &server, source, only, target, &job).await
+                }
+                None => {
+                    restore_in_place(&state, &server, &backup, source, only, maintenance, &job)
+                        .await
+                }
+            }
+        }
+        .await;
+        drop(claim);
+
+        let outcome = outcome.inspect_err(|e| {
+            error!(error = e, backup_uuid = %backup.uuid, "restore backup failed");
+        })?;
+        info!(
+            backup_uuid = %backup.uuid,
+            server_uuid = %outcome.server_uuid,
+            entries = outcome.restored_entries,
+            "backup restored"
+        );
+        Ok(serde_json::to_value(outcome).ok())
+    })
+    .await;
+
+    match started {
+        Ok((job, _)) => {
+            info!(job_uuid = %job.uuid, backup_uuid = %backup_uuid, "restore backup queued");
+            Ok(job)
+        }
+        Err(e) => {
+            error!(error = %e, backup_uuid = %backup_uuid, "queue restore job failed");
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+async fn restore_in_place(
+    state: &Arc<AppState>,
+    server: &Server,
+    backup: &Backup,
+    source: Source,
+    only: Vec<PathBuf>,
+    maintenance: Option<MaintenanceGuard>,
+    job: &JobHandle,
+) -> Result<RestoreOutcome, String> {
+    let was_running = state.supervisor.state(server.uuid).await.is_alive();
+    if was_running {
+        job.step("stopping server");
+        countdown::cancel(state, server.uuid);
+        match state.supervisor.stop(server.uuid).await {
+            Ok(()) | Err(SupervisorError::NotRunning) => {}
+            Err(e) => return Err(format!("stop server: {e}")),
+        }
+        if !state.supervisor.wait_stopped(server.uuid).await {
+            return Err("server did not stop".to_string());
+        }
+    }
+
+    job.step("replacing files");
+    let root = PathBuf::from(&server.working_dir);
+    let replaced = restore::replaced_paths(state, &root, &source, backup.scope, &only)
+        .await
+        .map_err(|e| e.to_string())?;
+    let (snapshot, restored_entries) = restore::replace(root, source, replaced, only)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    drop(maintenance);
+    let mut restarted = false;
+    if was_running {
+        job.step("starting server");
+        // Start from the stored record, the restored files may name a different jar
+        match server_routines::get_by_uuid(state.clone(), server.uuid).await {
+            Ok(server) => match state.supervisor.start(&server).await {
+                Ok(()) => restarted = true,
+                Err(e) => {
+                    job.log(format!("restart failed: {e}"));
+                    error!(error = %e, server_uuid = %server.uuid, "restart after restore failed")
+                }
+            },
+            Err(_) => warn!(server_uuid = %server.uuid, "server gone before restart"),
+        }
+    }
+
+    Ok(RestoreOutcome {
+        server_uuid: server.uuid,
+        snapshot_dir: snapshot.map(|dir| dir.display().to_string()),
+        restored_entries,
+        restarted,
+    })
+}
+
+async fn restore_new(
+    state: &Arc<AppState>,
+    user: &InternalUser,
+    server: &Server,
+    source: Source,
+    only: Vec<PathBuf>,
+    target: RestoreServer,
+    job: &JobHandle,
+) -> Result<RestoreOutcome, String> {
+    job.step("extracting files");
+    let root = PathBuf::from(&target.working_dir);
+    let restored_entries = restore::extract_new(root.clone(), source, only)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(port) = target.server_port
+        && let Err(e) = set_server_port(&root, port).await
+    {
+        warn!(error = %e, dir = %root.display(), "write restored server port failed");
+    }
+
+    job.step("creating server");
+    let new_server = NewServer {
+        name: target.name,
+        working_dir: target.working_dir,
+        jar_file: server.jar_file.clone(),
+        launch_command: server.launch_command.clone(),
+        java_path: server.java_path.clone(),
+        min_memory_mb: server.min_memory_mb,
+        max_memory_mb: server.max_memory_mb,
+        server_port: target.server_port.unwrap_or(server.server_port),
+        rcon_port: None,
+        query_port: None,
+        auto_start: false,
+        rcon_password: None,
+        minecraft_version: server.minecraft_version.clone(),
+        launch_profile: server.launch_profile.0.clone(),
+    };
+    let server = match server_routines::create(state.clone(), user.uuid, new_server).await {
+        Ok(server) => server,
+        Err(status) => {
+            let _ = fs::remove_dir_all(&root).await;
+            return Err(format!("create server failed with {status}"));
+        }
+    };
+
+    Ok(RestoreOutcome {
+        server_uuid: server.uuid,
+        snapshot_dir: None,
+        restored_entries,
+        restarted: false,
+    })
+}
+
+async fn set_server_port(root: &Path, port: i32) -> std::io::Result<()> {
+    let mut properties = properties::load(root).await?;
+    properties.set("server-port", &port.to_string());
+    properties::save(root, &properties).await
+}
+
+/// Brings back an archive that only survives at a destination.
+async fn fetch(state: &AppState, backup: &Backup) -> Result<(), StatusCode> {
+    replicate::fetch(state, backup).await.map_err(|reason| {
+        error!(backup_uuid = %backup.uuid, reason, "backup archive unavailable");
+        StatusCode::BAD_GATEWAY
+    })
+}
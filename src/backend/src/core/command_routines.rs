@@ -0,0 +1,307 @@
+use crate::{
+    core::server_routines::{self, supervisor_status},
+    domain::{
+        command::{
+            CommandEntry, CommandOutcome, CommandRequest, CommandRule, CommandSource,
+            NewCommandEntry, NewCommandRule, command_verb, denied_verb,
+        },
+        server::Server,
+        user::InternalUser,
+        user_prems::UserActions,
+    },
+    infra::{
+        db,
+        minecraft::{
+            log,
+            rcon::{RconClient, RconError},
+        },
+    },
+    prelude::*,
+    supervisor::SupervisorError,
+};
+use std::{sync::Arc, time::Duration};
+
+use axum::http::StatusCode;
+use tokio::{sync::broadcast::error::RecvError, time::timeout};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+const DEFAULT_HISTORY_LIMIT: i64 = 100;
+const MAX_HISTORY_LIMIT: i64 = 1000;
+const RCON_TIMEOUT: Duration = Duration::from_secs(5);
+
+pub async fn execute(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: CommandRequest,
+    source: CommandSource,
+) -> Result<CommandOutcome, StatusCode> {
+    debug!(server_uuid = %uuid, username = user.username, "execute command started");
+
+    request.validate().map_err(|e| {
+        error!(error = %e, "command validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    // Console sockets only require ReadConsole, sending needs its own permission
+    if !user.permissions.root
+        && !user
+            .permissions
+            .permissions
+            .contains(&UserActions::SendCommands)
+    {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    if !state.supervisor.state(uuid).await.is_alive() && !server.rcon_available() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let command = request.command.trim().trim_start_matches('/').to_string();
+    let verb = command_verb(&command);
+    let response = run_checked(&state, user, &server, &command, source).await?;
+
+    info!(server_uuid = %uuid, username = user.username, verb, "command executed");
+    Ok(CommandOutcome {
+        command,
+        verb,
+        allowed: true,
+        response,
+    })
+}
+
+/// Checks a command against the command rules, records it in the history
+/// as sent by `user` and dispatches it when allowed.
+pub async fn run_checked(
+    state: &AppState,
+    user: &InternalUser,
+    server: &Server,
+    command: &str,
+    source: CommandSource,
+) -> Result<Option<String>, StatusCode> {
+    let uuid = server.uuid;
+    let rules = server_rules(state, uuid).await?;
+    let denied = denied_verb(&rules, user, uuid, command);
+
+    let entry = NewCommandEntry {
+        uuid: Uuid::new_v4(),
+        server_uuid: uuid,
+        user_uuid: user.uuid,
+        command: command.to_string(),
+        source,
+        allowed: denied.is_none(),
+    };
+    db::command::create_entry(&state.db_pool, entry)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "record command failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if let Some(denied) = denied {
+        warn!(server_uuid = %uuid, username = user.username, verb = denied, "command denied by rules");
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    dispatch(state, server, command).await
+}
+
+/// Checks a command against the command rules without sending it, for
+/// changes made in the server files that the command would make otherwise.
+pub async fn authorize(
+    state: &AppState,
+    user: &InternalUser,
+    server: &Server,
+    command: &str,
+) -> Result<(), StatusCode> {
+    let rules = server_rules(state, server.uuid).await?;
+    if let Some(denied) = denied_verb(&rules, user, server.uuid, command) {
+        warn!(server_uuid = %server.uuid, username = user.username, verb = denied, "change denied by command rules");
+        return Err(StatusCode::FORBIDDEN);
+    }
+    Ok(())
+}
+
+async fn server_rules(state: &AppState, uuid: Uuid) -> Result<Vec<CommandRule>, StatusCode> {
+    db::command::get_rules_for_server(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch command rules failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Sends a command through stdin when the daemon owns the process, falling back
+/// to RCON for servers it did not launch or whose stdin was lost. Only RCON
+/// returns the command output.
+pub async fn dispatch(
+    state: &AppState,
+    server: &Server,
+    command: &str,
+) -> Result<Option<String>, StatusCode> {
+    match state.supervisor.send_command(server.uuid, command).await {
+        Ok(()) => return Ok(None),
+        Err(SupervisorError::NotRunning | SupervisorError::Stdin(_)) if server.rcon_available() => {
+            debug!(server_uuid = %server.uuid, "stdin unavailable, using rcon");
+        }
+        Err(e) => {
+            error!(error = %e, server_uuid = %server.uuid, "send command failed");
+            return Err(supervisor_status(&e));
+        }
+    }
+
+    rcon(state, server, command).await.map(Some)
+}
+
+/// Runs a save command (`save-all`, optionally with `flush`) and waits up to
+/// `limit` for the server to report the save finished. Returns whether it did.
+pub async fn save_world(state: &AppState, server: &Server, command: &str, limit: Duration) -> bool {
+    let (_, mut lines) = state.supervisor.subscribe(server.uuid).await;
+
+    match dispatch(state, server, command).await {
+        Ok(None) => {}
+        // RCON only answers once the save is done
+        Ok(Some(_)) => return true,
+        Err(_) => return false,
+    }
+
+    let saved = timeout(limit, async {
+        loop {
+            match lines.recv().await {
+                Ok(line) if log::message(&line.line).starts_with("Saved the game") => return true,
+                Err(RecvError::Closed) => return false,
+                _ => {}
+            }
+        }
+    })
+    .await;
+
+    match saved {
+        Ok(saved) => saved,
+        Err(_) => {
+            warn!(server_uuid = %server.uuid, "world save did not finish in time");
+            false
+        }
+    }
+}
+
+async fn rcon(state: &AppState, server: &Server, command: &str) -> Result<String, StatusCode> {
+    let (Some(port), Some(sealed)) = (server.rcon_port, server.rcon_password.as_deref()) else {
+        return Err(StatusCode::CONFLICT);
+    };
+
+    let password = state.secrets.decrypt_str(sealed).map_err(|e| {
+        error!(error = %e, server_uuid = %server.uuid, "unseal rcon password failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    let result = async {
+        let mut client =
+            RconClient::connect(("127.0.0.1", port as u16), &password, RCON_TIMEOUT).await?;
+        client.cmd(command).await
+    }
+    .await;
+
+    result.map_err(|e| {
+        error!(error = %e, server_uuid = %server.uuid, "rcon command failed");
+        match e {
+            RconError::Timeout => StatusCode::GATEWAY_TIMEOUT,
+            RconError::CommandTooLong => StatusCode::BAD_REQUEST,
+            _ => StatusCode::BAD_GATEWAY,
+        }
+    })
+}
+
+pub async fn history(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    limit: Option<i64>,
+) -> Result<Vec<CommandEntry>, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch command history started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_HISTORY_LIMIT)
+        .clamp(1, MAX_HISTORY_LIMIT);
+    db::command::get_history(&state.db_pool, uuid, limit)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch command history failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get_rules(state: Arc<AppState>) -> Result<Vec<CommandRule>, StatusCode> {
+    debug!("fetch command rules started");
+    db::command::get_all_rules(&state.db_pool)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "fetch command rules failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn create_rule(
+    state: Arc<AppState>,
+    new_rule: NewCommandRule,
+) -> Result<CommandRule, StatusCode> {
+    debug!("create command rule started");
+
+    new_rule.validate().map_err(|e| {
+        error!(error = %e, "command rule validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    if let Some(server_uuid) = new_rule.server_uuid {
+        server_routines::get_by_uuid(state.clone(), server_uuid)
+            .await
+            .map_err(|e| match e {
+                StatusCode::NOT_FOUND => StatusCode::BAD_REQUEST,
+                e => e,
+            })?;
+    }
+
+    if let Some(user_uuid) = new_rule.user_uuid {
+        let exists = db::user::exists_by_uuid(&state.db_pool, user_uuid)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "check rule user failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if !exists {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let rule = db::command::create_rule(&state.db_pool, new_rule.into_rule())
+        .await
+        .map_err(|e| {
+            error!(error = %e, "create command rule failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(rule_uuid = %rule.uuid, verb = rule.verb, allow = rule.allow, "command rule created");
+    Ok(rule)
+}
+
+pub async fn delete_rule(state: Arc<AppState>, uuid: Uuid) -> Result<(), StatusCode> {
+    debug!(rule_uuid = %uuid, "delete command rule started");
+
+    let deleted = db::command::delete_rule(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, rule_uuid = %uuid, "delete command rule failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    info!(rule_uuid = %uuid, "command rule deleted");
+    Ok(())
+}
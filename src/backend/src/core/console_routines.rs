@@ -0,0 +1,17 @@
+use crate::{core::server_routines, domain::console::ConsoleLine, prelude::*};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+pub async fn subscribe(
+    state: Arc<AppState>,
+    uuid: Uuid,
+) -> Result<(Vec<ConsoleLine>, broadcast::Receiver<ConsoleLine>), StatusCode> {
+    debug!(server_uuid = %uuid, "subscribe to console started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+    Ok(state.supervisor.subscribe(uuid).await)
+}
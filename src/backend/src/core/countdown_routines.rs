@@ -0,0 +1,69 @@
+use crate::{
+    core::server_routines,
+    countdown,
+    domain::countdown::{Countdown, CountdownEnd, CountdownRequest},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use tokio::task::JoinHandle;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+/// Starts a countdown to stop or restart a running server. The handle resolves
+/// once the countdown ended, callers that do not care may drop it.
+pub async fn begin(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    request: CountdownRequest,
+) -> Result<(Countdown, JoinHandle<CountdownEnd>), StatusCode> {
+    debug!(server_uuid = %uuid, mode = request.mode.as_str(), "begin countdown started");
+
+    request.validate().map_err(|e| {
+        error!(error = %e, "countdown validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    if !state.supervisor.state(uuid).await.is_alive() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let cfg = &state.config.countdown;
+    let countdown = Countdown::new(
+        uuid,
+        request.mode,
+        request.delay_secs,
+        request.announce_at.as_deref().unwrap_or(&cfg.announce_at),
+        request.message.unwrap_or_else(|| cfg.message.clone()),
+        request.style.unwrap_or(cfg.style),
+    );
+
+    let handle = countdown::begin(state.clone(), server, countdown.clone()).ok_or_else(|| {
+        warn!(server_uuid = %uuid, "countdown already running");
+        StatusCode::CONFLICT
+    })?;
+
+    info!(
+        server_uuid = %uuid,
+        mode = countdown.mode.as_str(),
+        delay_secs = request.delay_secs,
+        "countdown started"
+    );
+    Ok((countdown, handle))
+}
+
+pub async fn get(state: Arc<AppState>, uuid: Uuid) -> Result<Countdown, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch countdown started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+    state.countdowns.get(uuid).ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn cancel(state: Arc<AppState>, uuid: Uuid) -> Result<Countdown, StatusCode> {
+    debug!(server_uuid = %uuid, "cancel countdown started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+    countdown::cancel(&state, uuid).ok_or(StatusCode::NOT_FOUND)
+}
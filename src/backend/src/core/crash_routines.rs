@@ -0,0 +1,49 @@
+use crate::{
+    core::server_routines,
+    domain::crash::{CrashRecord, CrashSummary},
+    infra::db,
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+const DEFAULT_CRASH_LIMIT: i64 = 50;
+const MAX_CRASH_LIMIT: i64 = 500;
+
+pub async fn list(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    limit: Option<i64>,
+) -> Result<Vec<CrashSummary>, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch crashes started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_CRASH_LIMIT)
+        .clamp(1, MAX_CRASH_LIMIT);
+    db::crash::get_for_server(&state.db_pool, uuid, limit)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch crashes failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    crash_uuid: Uuid,
+) -> Result<CrashRecord, StatusCode> {
+    debug!(server_uuid = %uuid, crash_uuid = %crash_uuid, "fetch crash started");
+    db::crash::get_by_uuid(&state.db_pool, uuid, crash_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, crash_uuid = %crash_uuid, "fetch crash failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
@@ -0,0 +1,199 @@
+use crate::{
+    core::{backup_routines, server_routines},
+    domain::destination::{BackupCopy, BackupDestination, NewDestination, UpdateDestination},
+    infra::db,
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+pub async fn list(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<BackupDestination>, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch backup destinations started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    db::destination::get_for_server(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch backup destinations failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    destination_uuid: Uuid,
+) -> Result<BackupDestination, StatusCode> {
+    debug!(destination_uuid = %destination_uuid, "fetch backup destination started");
+    db::destination::get_by_uuid(&state.db_pool, uuid, destination_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, destination_uuid = %destination_uuid, "fetch backup destination failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn create(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    new_destination: NewDestination,
+) -> Result<BackupDestination, StatusCode> {
+    debug!(server_uuid = %uuid, "create backup destination started");
+
+    new_destination.validate().map_err(|e| {
+        error!(error = %e, "backup destination validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    check_encryption(&state, new_destination.encrypt)?;
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let name_taken = db::destination::exists_by_name(&state.db_pool, uuid, &new_destination.name)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "check backup destination name failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if name_taken {
+        warn!(
+            destination = new_destination.name,
+            "backup destination name already in use"
+        );
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let destination = BackupDestination {
+        uuid: Uuid::new_v4(),
+        server_uuid: uuid,
+        name: new_destination.name,
+        config: new_destination.config,
+        secret: seal_secret(&state, new_destination.secret.as_deref())?,
+        encrypt: new_destination.encrypt,
+        enabled: new_destination.enabled,
+        created_at: Utc::now(),
+    };
+    let destination = db::destination::create(&state.db_pool, &destination)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "create backup destination failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        destination_uuid = %destination.uuid,
+        server_uuid = %uuid,
+        kind = destination.config.kind(),
+        "backup destination created"
+    );
+    Ok(destination)
+}
+
+pub async fn update(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    destination_uuid: Uuid,
+    update: UpdateDestination,
+) -> Result<BackupDestination, StatusCode> {
+    debug!(destination_uuid = %destination_uuid, "update backup destination started");
+
+    update.validate().map_err(|e| {
+        error!(error = %e, "backup destination validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    check_encryption(&state, update.encrypt.unwrap_or_default())?;
+
+    let mut destination = get(state.clone(), uuid, destination_uuid).await?;
+    if let Some(name) = update.name.as_deref()
+        && name != destination.name
+    {
+        let name_taken = db::destination::exists_by_name(&state.db_pool, uuid, name)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "check backup destination name failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if name_taken {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    if let Some(secret) = update.secret.as_deref() {
+        destination.secret = seal_secret(&state, Some(secret))?;
+    }
+    destination.apply(update);
+
+    let destination = db::destination::update(&state.db_pool, &destination)
+        .await
+        .map_err(|e| {
+            error!(error = %e, destination_uuid = %destination_uuid, "update backup destination failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(destination_uuid = %destination_uuid, "backup destination updated");
+    Ok(destination)
+}
+
+/// Removes the destination, copies already uploaded stay where they are.
+pub async fn delete(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    destination_uuid: Uuid,
+) -> Result<(), StatusCode> {
+    debug!(destination_uuid = %destination_uuid, "delete backup destination started");
+
+    let deleted = db::destination::delete(&state.db_pool, uuid, destination_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, destination_uuid = %destination_uuid, "delete backup destination failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    info!(destination_uuid = %destination_uuid, "backup destination deleted");
+    Ok(())
+}
+
+pub async fn copies(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    backup_uuid: Uuid,
+) -> Result<Vec<BackupCopy>, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "fetch backup copies started");
+    backup_routines::get(state.clone(), uuid, backup_uuid).await?;
+
+    db::destination::get_copies(&state.db_pool, backup_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, backup_uuid = %backup_uuid, "fetch backup copies failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+/// Encrypting destinations need the backup key, refuse them up front rather
+/// than failing every copy later.
+fn check_encryption(state: &AppState, encrypt: bool) -> Result<(), StatusCode> {
+    if encrypt && state.config.backup.encryption_key.is_none() {
+        warn!("encryption requested without a backup encryption key");
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    Ok(())
+}
+
+fn seal_secret(state: &AppState, secret: Option<&str>) -> Result<Option<Vec<u8>>, StatusCode> {
+    secret
+        .map(|secret| state.secrets.encrypt_str(secret))
+        .transpose()
+        .map_err(|e| {
+            error!(error = %e, "seal destination secret failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
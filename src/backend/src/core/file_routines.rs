@@ -0,0 +1,1094 @@
+use crate::{
+    core::server_routines,
+    domain::{
+        file::{
+            ArchiveFormat, ArchiveSummary, CompressRequest, DeleteQuery, DirListing,
+            ExtractRequest, FileAccess, FileContent, FileEntry, FileKind, FileRule, FileUpload,
+            NewDirectory, NewFileRule, NewUpload, RenameRequest, UploadQuery, UploadStatus,
+            UploadedFile, WriteFile, display_path, file_access, file_path, subtree_access,
+        },
+        job::{Job, JobKind, NewJob},
+        user::InternalUser,
+    },
+    files::{
+        self, UploadError,
+        archive::{self, ArchiveError, ExtractLimits},
+    },
+    infra::{
+        db,
+        sandbox::{self, Sandbox, SandboxError},
+    },
+    jobs,
+    prelude::*,
+};
+use std::{
+    fs::Metadata,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use axum::{body::Body, extract::Multipart, http::StatusCode};
+use chrono::{DateTime, Utc};
+use tokio::{
+    fs::{self, File, OpenOptions},
+    task::spawn_blocking,
+};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+/// What an operation needs on a path.
+#[derive(Debug, Clone, Copy)]
+enum Need {
+    Read,
+    Write,
+    /// Write access to the path and everything below it, for operations on
+    /// whole directories.
+    Subtree,
+}
+
+/// A server directory opened for one request, with the file rules of the server.
+struct Scope {
+    server_uuid: Uuid,
+    sandbox: Sandbox,
+    rules: Vec<FileRule>,
+}
+
+pub async fn list(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    path: &str,
+) -> Result<DirListing, StatusCode> {
+    debug!(server_uuid = %uuid, path, "list files started");
+    let relative = parse(path)?;
+    let scope = open(&state, uuid).await?;
+    let dir = scope.resolve(user, &relative, Need::Read).await?;
+
+    let resolved = scope.sandbox.relative(&dir);
+    let mut entries = Vec::new();
+    let mut read = fs::read_dir(&dir)
+        .await
+        .map_err(|e| scope.io_error(&e, "read directory failed"))?;
+    while let Some(entry) = read
+        .next_entry()
+        .await
+        .map_err(|e| scope.io_error(&e, "read directory failed"))?
+    {
+        if scope.sandbox.is_excluded(&entry.path()) {
+            continue;
+        }
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let entry_path = relative.join(&name);
+        let access = file_access(&scope.rules, user, &entry_path).min(file_access(
+            &scope.rules,
+            user,
+            &resolved.join(&name),
+        ));
+        if access == FileAccess::None {
+            continue;
+        }
+        // Entries can vanish while the server runs
+        let Ok(meta) = entry.metadata().await else {
+            continue;
+        };
+        entries.push(FileEntry {
+            name,
+            path: display_path(&entry_path),
+            kind: kind(&meta),
+            size: meta.len(),
+            modified: modified(&meta),
+            access,
+        });
+    }
+    entries.sort_by(|a, b| {
+        (a.kind != FileKind::Directory, &a.name).cmp(&(b.kind != FileKind::Directory, &b.name))
+    });
+
+    Ok(DirListing {
+        path: display_path(&relative),
+        entries,
+    })
+}
+
+pub async fn read(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    path: &str,
+) -> Result<FileContent, StatusCode> {
+    debug!(server_uuid = %uuid, path, "read file started");
+    let relative = parse(path)?;
+    let scope = open(&state, uuid).await?;
+    let file = scope.resolve(user, &relative, Need::Read).await?;
+
+    let meta = fs::metadata(&file)
+        .await
+        .map_err(|e| scope.io_error(&e, "read file failed"))?;
+    if !meta.is_file() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    if meta.len() > state.config.files.max_edit_bytes {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let bytes = fs::read(&file)
+        .await
+        .map_err(|e| scope.io_error(&e, "read file failed"))?;
+    let binary = !sandbox::is_text(&bytes, false);
+    Ok(FileContent {
+        path: display_path(&relative),
+        size: bytes.len() as u64,
+        modified: modified(&meta),
+        binary,
+        content: (!binary).then(|| String::from_utf8_lossy(&bytes).into_owned()),
+    })
+}
+
+pub async fn write(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: WriteFile,
+) -> Result<FileContent, StatusCode> {
+    debug!(server_uuid = %uuid, path = request.path, "write file started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "write file validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    if request.content.len() as u64 > state.config.files.max_edit_bytes {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let relative = parse(&request.path)?;
+    let scope = open(&state, uuid).await?;
+    let file = scope.resolve(user, &relative, Need::Write).await?;
+
+    match fs::metadata(&file).await {
+        Ok(meta) if meta.is_dir() => return Err(StatusCode::BAD_REQUEST),
+        Ok(meta) => {
+            if request
+                .expected_modified
+                .is_some_and(|expected| modified(&meta) != Some(expected))
+            {
+                warn!(server_uuid = %uuid, path = request.path, "file changed since it was read");
+                return Err(StatusCode::PRECONDITION_FAILED);
+            }
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            if request.expected_modified.is_some() {
+                return Err(StatusCode::PRECONDITION_FAILED);
+            }
+        }
+        Err(e) => return Err(scope.io_error(&e, "read file metadata failed")),
+    }
+
+    sandbox::write_atomic(&file, request.content.as_bytes())
+        .await
+        .map_err(|e| scope.io_error(&e, "write file failed"))?;
+    let meta = fs::metadata(&file)
+        .await
+        .map_err(|e| scope.io_error(&e, "read file metadata failed"))?;
+
+    info!(server_uuid = %uuid, path = request.path, username = user.username, "file written");
+    Ok(FileContent {
+        path: display_path(&relative),
+        size: meta.len(),
+        modified: modified(&meta),
+        binary: false,
+        content: Some(request.content),
+    })
+}
+
+pub async fn create_dir(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: NewDirectory,
+) -> Result<(), StatusCode> {
+    debug!(server_uuid = %uuid, path = request.path, "create directory started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "create directory validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let relative = parse(&request.path)?;
+    let scope = open(&state, uuid).await?;
+    let dir = scope.locate(user, &relative, Need::Write).await?;
+
+    if fs::symlink_metadata(&dir).await.is_ok() {
+        return Err(StatusCode::CONFLICT);
+    }
+    fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| scope.io_error(&e, "create directory failed"))?;
+
+    info!(server_uuid = %uuid, path = request.path, username = user.username, "directory created");
+    Ok(())
+}
+
+pub async fn rename(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: RenameRequest,
+) -> Result<(), StatusCode> {
+    debug!(server_uuid = %uuid, from = request.from, to = request.to, "rename file started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "rename file validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let from = parse(&request.from)?;
+    let to = parse(&request.to)?;
+    // Also catches moving a directory into itself
+    if to.starts_with(&from) {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let scope = open(&state, uuid).await?;
+    let source = scope.locate(user, &from, Need::Subtree).await?;
+    let target = scope.locate(user, &to, Need::Subtree).await?;
+
+    fs::symlink_metadata(&source)
+        .await
+        .map_err(|e| scope.io_error(&e, "read file metadata failed"))?;
+    if !request.overwrite && fs::symlink_metadata(&target).await.is_ok() {
+        return Err(StatusCode::CONFLICT);
+    }
+    fs::rename(&source, &target)
+        .await
+        .map_err(|e| scope.io_error(&e, "rename file failed"))?;
+
+    info!(
+        server_uuid = %uuid,
+        from = request.from,
+        to = request.to,
+        username = user.username,
+        "file renamed"
+    );
+    Ok(())
+}
+
+pub async fn delete(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    query: DeleteQuery,
+) -> Result<(), StatusCode> {
+    debug!(server_uuid = %uuid, path = query.path, "delete file started");
+    let relative = parse(&query.path)?;
+    if relative.as_os_str().is_empty() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let scope = open(&state, uuid).await?;
+    let located = scope.locate(user, &relative, Need::Subtree).await?;
+    sandbox::remove(&located, query.recursive)
+        .await
+        .map_err(|e| scope.io_error(&e, "delete file failed"))?;
+
+    info!(server_uuid = %uuid, path = query.path, username = user.username, "file deleted");
+    Ok(())
+}
+
+/// Resolves a file for download, returns its location and name.
+pub async fn download(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    path: &str,
+) -> Result<(PathBuf, String), StatusCode> {
+    debug!(server_uuid = %uuid, path, "download file started");
+    let relative = parse(path)?;
+    let scope = open(&state, uuid).await?;
+    let file = scope.resolve(user, &relative, Need::Read).await?;
+
+    let meta = fs::metadata(&file)
+        .await
+        .map_err(|e| scope.io_error(&e, "read file metadata failed"))?;
+    if !meta.is_file() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let name = relative
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    Ok((file, name))
+}
+
+/// Writes every file of a multipart request into the directory `query.path`.
+/// Files are staged outside the server directory and only moved into place
+/// once complete.
+pub async fn upload(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    query: UploadQuery,
+    mut multipart: Multipart,
+) -> Result<Vec<UploadedFile>, StatusCode> {
+    debug!(server_uuid = %uuid, path = query.path, "upload files started");
+    let dir = parse(&query.path)?;
+    let scope = open(&state, uuid).await?;
+
+    let mut remaining = state.config.files.max_upload_bytes;
+    let mut uploaded = Vec::new();
+    while let Some(field) = multipart.next_field().await.map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "read multipart field failed");
+        StatusCode::BAD_REQUEST
+    })? {
+        // Plain form fields carry no file
+        let Some(name) = field.file_name().map(str::to_string) else {
+            continue;
+        };
+        let relative = match file_path(&name) {
+            Some(name) if name.components().count() == 1 => dir.join(name),
+            _ => return Err(StatusCode::BAD_REQUEST),
+        };
+        let target = scope.resolve(user, &relative, Need::Write).await?;
+        if !query.overwrite && fs::symlink_metadata(&target).await.is_ok() {
+            return Err(StatusCode::CONFLICT);
+        }
+
+        let staging = files::staging_path(&state);
+        let written = async {
+            let mut file = File::create(&staging).await?;
+            let written = files::write_stream(&mut file, field, remaining).await?;
+            file.sync_all().await?;
+            Ok::<_, UploadError>(written)
+        }
+        .await;
+        let written = match written {
+            Ok(written) => written,
+            Err(e) => {
+                files::remove_part(&staging).await;
+                return Err(scope.upload_error(&e));
+            }
+        };
+
+        let placed = place(&staging, &target).await;
+        files::remove_part(&staging).await;
+        placed.map_err(|e| scope.io_error(&e, "move uploaded file failed"))?;
+
+        remaining -= written;
+        info!(
+            server_uuid = %uuid,
+            path = %relative.display(),
+            size_bytes = written,
+            username = user.username,
+            "file uploaded"
+        );
+        uploaded.push(UploadedFile {
+            path: display_path(&relative),
+            size: written,
+        });
+    }
+
+    Ok(uploaded)
+}
+
+/// Starts a resumable upload. Chunks are sent with `append`, the file is
+/// moved into place once all of it arrived.
+pub async fn start_upload(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: NewUpload,
+) -> Result<UploadStatus, StatusCode> {
+    debug!(server_uuid = %uuid, path = request.path, "start file upload started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "file upload validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    if request.size as u64 > state.config.files.max_upload_bytes {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let relative = parse(&request.path)?;
+    let scope = open(&state, uuid).await?;
+    let target = scope.resolve(user, &relative, Need::Write).await?;
+    match fs::symlink_metadata(&target).await {
+        Ok(meta) if meta.is_dir() => return Err(StatusCode::CONFLICT),
+        Ok(_) if !request.overwrite => return Err(StatusCode::CONFLICT),
+        _ => {}
+    }
+
+    files::sweep(&state).await;
+    let upload = FileUpload {
+        uuid: Uuid::new_v4(),
+        server_uuid: uuid,
+        user_uuid: user.uuid,
+        path: display_path(&relative),
+        size: request.size,
+        overwrite: request.overwrite,
+        created_at: Utc::now(),
+    };
+    File::create(files::part_path(&state, upload.uuid))
+        .await
+        .map_err(|e| scope.io_error(&e, "create upload part failed"))?;
+    let upload = db::file::create_upload(&state.db_pool, &upload)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "record file upload failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        server_uuid = %uuid,
+        upload_uuid = %upload.uuid,
+        path = upload.path,
+        size_bytes = upload.size,
+        "file upload started"
+    );
+    if upload.size == 0 {
+        finish(&state, &scope, user, &upload).await?;
+    }
+    Ok(UploadStatus {
+        completed: upload.size == 0,
+        upload,
+        received: 0,
+    })
+}
+
+pub async fn upload_status(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    upload_uuid: Uuid,
+) -> Result<UploadStatus, StatusCode> {
+    debug!(upload_uuid = %upload_uuid, "fetch file upload started");
+    let upload = get_upload(&state, user, uuid, upload_uuid).await?;
+    let received = received(&state, &upload).await?;
+    Ok(UploadStatus {
+        upload,
+        received,
+        completed: false,
+    })
+}
+
+/// Appends a chunk starting at `offset`, which has to match what was received
+/// so far. A broken connection keeps what arrived, the client resumes from
+/// the offset `upload_status` reports.
+pub async fn append(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    upload_uuid: Uuid,
+    offset: i64,
+    body: Body,
+) -> Result<UploadStatus, StatusCode> {
+    debug!(upload_uuid = %upload_uuid, offset, "append file upload started");
+    let upload = get_upload(&state, user, uuid, upload_uuid).await?;
+
+    if !state.files.claim(upload_uuid) {
+        warn!(upload_uuid = %upload_uuid, "file upload already being written");
+        return Err(StatusCode::CONFLICT);
+    }
+    let result = append_claimed(&state, user, upload, offset, body).await;
+    state.files.release(upload_uuid);
+    result
+}
+
+pub async fn cancel_upload(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    upload_uuid: Uuid,
+) -> Result<(), StatusCode> {
+    debug!(upload_uuid = %upload_uuid, "cancel file upload started");
+    get_upload(&state, user, uuid, upload_uuid).await?;
+
+    if !state.files.claim(upload_uuid) {
+        return Err(StatusCode::CONFLICT);
+    }
+    let deleted = db::file::delete_upload(&state.db_pool, upload_uuid).await;
+    state.files.release(upload_uuid);
+    deleted.map_err(|e| {
+        error!(error = %e, upload_uuid = %upload_uuid, "delete file upload failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    files::remove_part(&files::part_path(&state, upload_uuid)).await;
+
+    info!(upload_uuid = %upload_uuid, "file upload cancelled");
+    Ok(())
+}
+
+/// Compresses `request.paths` into one archive as a job. Entries are named
+/// relative to the directory the paths share, anything the user cannot read
+/// is left out. The archive is written outside the server directory and
+/// moved into place once complete.
+pub async fn compress(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: CompressRequest,
+) -> Result<Job, StatusCode> {
+    debug!(server_uuid = %uuid, destination = request.destination, "compress files started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "compress files validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    let format = request
+        .format
+        .or_else(|| ArchiveFormat::from_name(&request.destination))
+        .ok_or(StatusCode::BAD_REQUEST)?;
+
+    let scope = open(&state, uuid).await?;
+    let mut sources = Vec::new();
+    let mut requested = Vec::new();
+    for path in &request.paths {
+        let relative = parse(path)?;
+        let resolved = scope.resolve(user, &relative, Need::Read).await?;
+        fs::symlink_metadata(&resolved)
+            .await
+            .map_err(|e| scope.io_error(&e, "read file metadata failed"))?;
+        sources.push(scope.sandbox.relative(&resolved));
+        requested.push(display_path(&relative));
+    }
+    // Symlinks can name the same file twice, an archive holds it once
+    sources.sort();
+    sources.dedup_by(|nested, parent| nested.starts_with(parent));
+    let base = common_parent(&sources);
+    let entries = sources
+        .iter()
+        .map(|source| source.strip_prefix(&base).unwrap_or(source).to_path_buf())
+        .collect::<Vec<_>>();
+
+    let destination = parse(&request.destination)?;
+    let target = scope.resolve(user, &destination, Need::Write).await?;
+    match fs::symlink_metadata(&target).await {
+        Ok(meta) if meta.is_dir() => return Err(StatusCode::CONFLICT),
+        Ok(_) if !request.overwrite => return Err(StatusCode::CONFLICT),
+        _ => {}
+    }
+
+    let root = scope.sandbox.root().join(&base);
+    let include = {
+        let Scope { sandbox, rules, .. } = scope;
+        let user = user.clone();
+        let root = root.clone();
+        move |relative: &Path| {
+            !sandbox.is_excluded(&root.join(relative))
+                && file_access(&rules, &user, &base.join(relative)) >= FileAccess::Read
+        }
+    };
+    let overwrite = request.overwrite;
+    let new_job = NewJob {
+        kind: JobKind::Compress,
+        server_uuid: Some(uuid),
+        description: format!(
+            "compress {} into {}",
+            requested.join(", "),
+            display_path(&destination)
+        ),
+        created_by: Some(user.uuid),
+        cancellable: true,
+    };
+    let task_state = state.clone();
+    let (job, _) = jobs::start(&state, new_job, move |job| async move {
+        let state = task_state;
+        let staging = files::staging_path(&state);
+        let result = async {
+            let dest = staging.clone();
+            let handle = job.clone();
+            let summary = spawn_blocking(move || {
+                archive::compress(&root, &entries, &dest, format, &include, &handle)
+            })
+            .await
+            .map_err(io::Error::other)??;
+
+            job.step("moving archive into place");
+            if !overwrite && fs::symlink_metadata(&target).await.is_ok() {
+                return Err(ArchiveError::Conflict(display_path(&destination)));
+            }
+            place(&staging, &target).await?;
+            Ok(ArchiveSummary {
+                path: display_path(&destination),
+                ..summary
+            })
+        }
+        .await;
+        files::remove_part(&staging).await;
+
+        let summary = result.map_err(|e| e.to_string())?;
+        Ok(serde_json::to_value(summary).ok())
+    })
+    .await
+    .map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "queue compress job failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    info!(
+        job_uuid = %job.uuid,
+        server_uuid = %uuid,
+        username = user.username,
+        "compress files job queued"
+    );
+    Ok(job)
+}
+
+/// Extracts an archive into a directory as a job, the archive's own one
+/// unless `request.destination` is set. Every entry has to be writable for
+/// the user, see [`archive::extract`] for what else is checked.
+pub async fn extract(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: ExtractRequest,
+) -> Result<Job, StatusCode> {
+    debug!(server_uuid = %uuid, path = request.path, "extract archive started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "extract archive validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let relative = parse(&request.path)?;
+    let scope = open(&state, uuid).await?;
+    let source = scope.resolve(user, &relative, Need::Read).await?;
+    let meta = fs::metadata(&source)
+        .await
+        .map_err(|e| scope.io_error(&e, "read file metadata failed"))?;
+    if !meta.is_file() {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+
+    let destination = match &request.destination {
+        Some(destination) => parse(destination)?,
+        None => relative.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    let dest = scope.resolve(user, &destination, Need::Write).await?;
+    if fs::metadata(&dest).await.is_ok_and(|meta| !meta.is_dir()) {
+        return Err(StatusCode::CONFLICT);
+    }
+    let resolved = scope.sandbox.relative(&dest);
+    let shown = display_path(&destination);
+
+    let allowed = {
+        let Scope { sandbox, rules, .. } = scope;
+        let user = user.clone();
+        let dest = dest.clone();
+        // Checked on the requested and the resolved destination, like any
+        // other path
+        move |entry: &Path| {
+            !sandbox.is_excluded(&dest.join(entry))
+                && file_access(&rules, &user, &destination.join(entry)) == FileAccess::Write
+                && file_access(&rules, &user, &resolved.join(entry)) == FileAccess::Write
+        }
+    };
+    let limits = ExtractLimits {
+        max_bytes: state.config.files.max_extract_bytes,
+        max_entries: state.config.files.max_extract_entries,
+    };
+    let overwrite = request.overwrite;
+    let new_job = NewJob {
+        kind: JobKind::Extract,
+        server_uuid: Some(uuid),
+        description: format!("extract {} into {}", display_path(&relative), shown),
+        created_by: Some(user.uuid),
+        cancellable: true,
+    };
+    let (job, _) = jobs::start(&state, new_job, move |job| async move {
+        let summary = spawn_blocking(move || {
+            archive::extract(&source, &dest, limits, overwrite, &allowed, &job)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+        .map_err(|e| e.to_string())?;
+
+        let summary = ArchiveSummary {
+            path: shown,
+            ..summary
+        };
+        Ok(serde_json::to_value(summary).ok())
+    })
+    .await
+    .map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "queue extract job failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    info!(
+        job_uuid = %job.uuid,
+        server_uuid = %uuid,
+        path = request.path,
+        username = user.username,
+        "extract archive job queued"
+    );
+    Ok(job)
+}
+
+pub async fn get_rules(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<FileRule>, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch file rules started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    db::file::get_rules_for_server(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch file rules failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn create_rule(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    new_rule: NewFileRule,
+) -> Result<FileRule, StatusCode> {
+    debug!(server_uuid = %uuid, "create file rule started");
+    new_rule.validate().map_err(|e| {
+        error!(error = %e, "file rule validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    if let Some(user_uuid) = new_rule.user_uuid {
+        let exists = db::user::exists_by_uuid(&state.db_pool, user_uuid)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "check rule user failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if !exists {
+            return Err(StatusCode::BAD_REQUEST);
+        }
+    }
+
+    let rule = db::file::create_rule(&state.db_pool, new_rule.into_rule(uuid))
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "create file rule failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        rule_uuid = %rule.uuid,
+        server_uuid = %uuid,
+        path = rule.path,
+        access = rule.access.as_str(),
+        "file rule created"
+    );
+    Ok(rule)
+}
+
+pub async fn delete_rule(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    rule_uuid: Uuid,
+) -> Result<(), StatusCode> {
+    debug!(rule_uuid = %rule_uuid, "delete file rule started");
+    let deleted = db::file::delete_rule(&state.db_pool, uuid, rule_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, rule_uuid = %rule_uuid, "delete file rule failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    info!(rule_uuid = %rule_uuid, "file rule deleted");
+    Ok(())
+}
+
+async fn append_claimed(
+    state: &Arc<AppState>,
+    user: &InternalUser,
+    upload: FileUpload,
+    offset: i64,
+    body: Body,
+) -> Result<UploadStatus, StatusCode> {
+    let received = received(state, &upload).await?;
+    if offset != received {
+        warn!(upload_uuid = %upload.uuid, offset, received, "file upload offset mismatch");
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let part = files::part_path(state, upload.uuid);
+    let mut file = OpenOptions::new()
+        .append(true)
+        .open(&part)
+        .await
+        .map_err(|e| {
+            error!(error = %e, upload_uuid = %upload.uuid, "open upload part failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    let limit = (upload.size - received) as u64;
+    if let Err(e) = files::write_stream(&mut file, body.into_data_stream(), limit).await {
+        warn!(error = %e, upload_uuid = %upload.uuid, "append file upload failed");
+        if matches!(e, UploadError::TooLarge) {
+            // Drop the chunk so the upload can go on from the last good offset
+            let _ = file.set_len(received as u64).await;
+        }
+        return Err(upload_error_status(&e));
+    }
+    let received = file
+        .metadata()
+        .await
+        .map(|meta| meta.len() as i64)
+        .map_err(|e| {
+            error!(error = %e, upload_uuid = %upload.uuid, "read upload part failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    drop(file);
+
+    let completed = received == upload.size;
+    if completed {
+        let scope = open(state, upload.server_uuid).await?;
+        finish(state, &scope, user, &upload).await?;
+    }
+    Ok(UploadStatus {
+        upload,
+        received,
+        completed,
+    })
+}
+
+/// Moves a complete upload into place. Access and conflicts are checked again
+/// since either may have changed while the chunks came in.
+async fn finish(
+    state: &AppState,
+    scope: &Scope,
+    user: &InternalUser,
+    upload: &FileUpload,
+) -> Result<(), StatusCode> {
+    let relative = PathBuf::from(&upload.path);
+    let target = scope.resolve(user, &relative, Need::Write).await?;
+    if !upload.overwrite && fs::symlink_metadata(&target).await.is_ok() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let part = files::part_path(state, upload.uuid);
+    place(&part, &target)
+        .await
+        .map_err(|e| scope.io_error(&e, "move uploaded file failed"))?;
+    db::file::delete_upload(&state.db_pool, upload.uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, upload_uuid = %upload.uuid, "delete file upload failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(
+        server_uuid = %upload.server_uuid,
+        upload_uuid = %upload.uuid,
+        path = upload.path,
+        username = user.username,
+        "file upload completed"
+    );
+    Ok(())
+}
+
+/// Moves a staged file to `target`, creating missing parent directories.
+async fn place(staged: &Path, target: &Path) -> io::Result<()> {
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    sandbox::move_file(staged, target).await
+}
+
+async fn get_upload(
+    state: &AppState,
+    user: &InternalUser,
+    uuid: Uuid,
+    upload_uuid: Uuid,
+) -> Result<FileUpload, StatusCode> {
+    db::file::get_upload(&state.db_pool, uuid, upload_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, upload_uuid = %upload_uuid, "fetch file upload failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        // Uploads belong to whoever started them
+        .filter(|upload| upload.user_uuid == user.uuid)
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+async fn received(state: &AppState, upload: &FileUpload) -> Result<i64, StatusCode> {
+    fs::metadata(files::part_path(state, upload.uuid))
+        .await
+        .map(|meta| meta.len() as i64)
+        .map_err(|e| {
+            error!(error = %e, upload_uuid = %upload.uuid, "read upload part failed");
+            match e.kind() {
+                ErrorKind::NotFound => StatusCode::GONE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            }
+        })
+}
+
+/// The deepest directory containing every one of `paths`.
+fn common_parent(paths: &[PathBuf]) -> PathBuf {
+    let mut parents = paths
+        .iter()
+        .map(|path| path.parent().unwrap_or(Path::new("")));
+    let Some(first) = parents.next() else {
+        return PathBuf::new();
+    };
+    parents.fold(first.to_path_buf(), |common, parent| {
+        common
+            .components()
+            .zip(parent.components())
+            .take_while(|(a, b)| a == b)
+            .map(|(a, _)| a)
+            .collect()
+    })
+}
+
+async fn open(state: &Arc<AppState>, uuid: Uuid) -> Result<Scope, StatusCode> {
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let sandbox = Sandbox::open(
+        Path::new(&server.working_dir),
+        &[&state.config.backup.dir, &state.config.files.upload_dir],
+    )
+    .await
+    .map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "open server directory failed");
+        sandbox_status(&e)
+    })?;
+    let rules = db::file::get_rules_for_server(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch file rules failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    Ok(Scope {
+        server_uuid: uuid,
+        sandbox,
+        rules,
+    })
+}
+
+impl Scope {
+    /// Checks `need` on `path`. Callers check the path a request named and
+    /// the path it resolved to, so symlinks cannot get around a rule.
+    fn require(&self, user: &InternalUser, path: &Path, need: Need) -> Result<(), StatusCode> {
+        let (access, needed) = match need {
+            Need::Read => (file_access(&self.rules, user, path), FileAccess::Read),
+            Need::Write => (file_access(&self.rules, user, path), FileAccess::Write),
+            Need::Subtree => (subtree_access(&self.rules, user, path), FileAccess::Write),
+        };
+        if access >= needed {
+            return Ok(());
+        }
+        warn!(
+            server_uuid = %self.server_uuid,
+            username = user.username,
+            path = %path.display(),
+            "file access denied"
+        );
+        Err(StatusCode::FORBIDDEN)
+    }
+
+    /// Where `path` lives, without following a symlink in the last component.
+    async fn locate(
+        &self,
+        user: &InternalUser,
+        path: &Path,
+        need: Need,
+    ) -> Result<PathBuf, StatusCode> {
+        self.require(user, path, need)?;
+        let located = self
+            .sandbox
+            .locate(path)
+            .await
+            .map_err(|e| self.sandbox_error(&e, path))?;
+        self.require(user, &self.sandbox.relative(&located), need)?;
+        Ok(located)
+    }
+
+    /// `locate` with symlinks followed, for reading and writing contents.
+    async fn resolve(
+        &self,
+        user: &InternalUser,
+        path: &Path,
+        need: Need,
+    ) -> Result<PathBuf, StatusCode> {
+        let located = self.locate(user, path, need).await?;
+        let resolved = self
+            .sandbox
+            .follow(&located)
+            .await
+            .map_err(|e| self.sandbox_error(&e, path))?;
+        self.require(user, &self.sandbox.relative(&resolved), need)?;
+        Ok(resolved)
+    }
+
+    fn sandbox_error(&self, e: &SandboxError, path: &Path) -> StatusCode {
+        match e {
+            SandboxError::Escape => {
+                warn!(server_uuid = %self.server_uuid, path = %path.display(), "path escapes server directory");
+            }
+            SandboxError::Io(e) => {
+                debug!(error = %e, server_uuid = %self.server_uuid, "resolve path failed");
+            }
+        }
+        sandbox_status(e)
+    }
+
+    fn io_error(&self, e: &io::Error, message: &str) -> StatusCode {
+        let status = io_status(e);
+        if status.is_server_error() {
+            error!(error = %e, server_uuid = %self.server_uuid, message);
+        } else {
+            debug!(error = %e, server_uuid = %self.server_uuid, message);
+        }
+        status
+    }
+
+    fn upload_error(&self, e: &UploadError) -> StatusCode {
+        warn!(error = %e, server_uuid = %self.server_uuid, "upload file failed");
+        upload_error_status(e)
+    }
+}
+
+fn parse(path: &str) -> Result<PathBuf, StatusCode> {
+    file_path(path).ok_or(StatusCode::BAD_REQUEST)
+}
+
+fn kind(meta: &Metadata) -> FileKind {
+    if meta.is_symlink() {
+        FileKind::Symlink
+    } else if meta.is_dir() {
+        FileKind::Directory
+    } else {
+        FileKind::File
+    }
+}
+
+fn modified(meta: &Metadata) -> Option<DateTime<Utc>> {
+    meta.modified().ok().map(DateTime::<Utc>::from)
+}
+
+fn sandbox_status(e: &SandboxError) -> StatusCode {
+    match e {
+        SandboxError::Escape => StatusCode::FORBIDDEN,
+        SandboxError::Io(e) => io_status(e),
+    }
+}
+
+fn upload_error_status(e: &UploadError) -> StatusCode {
+    match e {
+        UploadError::TooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        UploadError::Body(_) => StatusCode::BAD_REQUEST,
+        UploadError::Io(e) => io_status(e),
+    }
+}
+
+fn io_status(e: &io::Error) -> StatusCode {
+    match e.kind() {
+        ErrorKind::NotFound => StatusCode::NOT_FOUND,
+        ErrorKind::AlreadyExists | ErrorKind::DirectoryNotEmpty => StatusCode::CONFLICT,
+        ErrorKind::NotADirectory
+        | ErrorKind::IsADirectory
+        | ErrorKind::InvalidInput
+        | ErrorKind::InvalidFilename => StatusCode::BAD_REQUEST,
+        ErrorKind::StorageFull | ErrorKind::QuotaExceeded => StatusCode::INSUFFICIENT_STORAGE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
@@ -0,0 +1,179 @@
+use crate::{
+    core::server_routines,
+    domain::{
+        install::{InstallRequest, Software, SoftwareBuild, SoftwareVersion},
+        java::JavaRequirement,
+        job::{Job, JobKind, NewJob},
+        server::Server,
+        user::InternalUser,
+        validation,
+    },
+    infra::db,
+    installer::{self, InstallError},
+    java,
+    jobs::{self, JobHandle},
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+pub async fn versions(
+    state: Arc<AppState>,
+    software: Software,
+) -> Result<Vec<SoftwareVersion>, StatusCode> {
+    debug!(
+        software = software.as_str(),
+        "fetch software versions started"
+    );
+    installer::versions(&state, software)
+        .await
+        .map_err(|e| metadata_status(&e, software))
+}
+
+pub async fn builds(
+    state: Arc<AppState>,
+    software: Software,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, StatusCode> {
+    debug!(
+        software = software.as_str(),
+        version, "fetch software builds started"
+    );
+    validation::validate_software_version(version).map_err(|_| StatusCode::BAD_REQUEST)?;
+    installer::builds(&state, software, version)
+        .await
+        .map_err(|e| metadata_status(&e, software))
+}
+
+/// Queues a job installing server software into a stopped server and
+/// pointing the server at it.
+pub async fn install(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    request: InstallRequest,
+) -> Result<Job, StatusCode> {
+    debug!(server_uuid = %uuid, software = request.software.as_str(), "install software started");
+    request.validate().map_err(|e| {
+        error!(error = %e, "install request validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    if state.supervisor.state(uuid).await.is_alive() {
+        return Err(StatusCode::CONFLICT);
+    }
+    if !state.installer.claim(uuid) {
+        warn!(server_uuid = %uuid, "install already running");
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let description = format!(
+        "install {} {} on {}",
+        request.software.as_str(),
+        request.version.as_deref().unwrap_or("latest"),
+        server.name
+    );
+    let new_job = NewJob {
+        kind: JobKind::Install,
+        server_uuid: Some(uuid),
+        description,
+        created_by: Some(user.uuid),
+        cancellable: true,
+    };
+    let task_state = state.clone();
+    let started = jobs::start(&state, new_job, move |job| async move {
+        let state = task_state;
+        let outcome: Result<_, String> = async {
+            let outcome = installer::install(&state, &server, &request, &job)
+                .await
+                .map_err(|e| e.to_string())?;
+
+            job.step("updating server");
+            // Reread the record, it may have changed while the job ran
+            let mut server = db::server::get_by_uuid(&state.db_pool, uuid)
+                .await
+                .map_err(|e| e.to_string())?
+                .ok_or_else(|| "server was deleted".to_string())?;
+            if server.launch_command.is_some() {
+                job.log("server has a custom launch command, left as is");
+            } else {
+                select_java(&state, &mut server, &outcome.version, &job).await;
+            }
+            server.jar_file = outcome.jar_file.clone();
+            server.minecraft_version = Some(outcome.version.clone());
+            db::server::update(&state.db_pool, server)
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(outcome)
+        }
+        .await;
+        state.installer.release(uuid);
+
+        let outcome = outcome.inspect_err(|e| {
+            warn!(error = e, server_uuid = %uuid, "install software failed");
+        })?;
+        info!(
+            server_uuid = %uuid,
+            software = outcome.software.as_str(),
+            version = outcome.version,
+            build = outcome.build,
+            "software installed"
+        );
+        Ok(serde_json::to_value(outcome).ok())
+    })
+    .await;
+
+    match started {
+        Ok((job, _)) => {
+            info!(job_uuid = %job.uuid, server_uuid = %uuid, "install software queued");
+            Ok(job)
+        }
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, "queue install job failed");
+            state.installer.release(uuid);
+            Err(StatusCode::INTERNAL_SERVER_ERROR)
+        }
+    }
+}
+
+/// Points the server at an installed runtime that fits the game version when
+/// the one it has does not, so it can start right away.
+async fn select_java(state: &AppState, server: &mut Server, version: &str, job: &JobHandle) {
+    let Some(requirement) = JavaRequirement::for_minecraft(version) else {
+        return;
+    };
+    if let Ok(runtime) = java::probe(&server.java_path).await
+        && requirement.allows(runtime.major)
+    {
+        return;
+    }
+
+    match requirement.pick(&state.java.list()) {
+        Some(runtime) => {
+            job.log(format!(
+                "minecraft {version} needs {requirement}, switching to {} ({})",
+                runtime.path, runtime.version
+            ));
+            server.java_path = runtime.path.clone();
+        }
+        None => job.log(format!(
+            "minecraft {version} needs {requirement}, none is installed"
+        )),
+    }
+}
+
+fn metadata_status(e: &InstallError, software: Software) -> StatusCode {
+    match e {
+        InstallError::NotFound(_) => StatusCode::NOT_FOUND,
+        _ => {
+            error!(error = %e, software = software.as_str(), "fetch software metadata failed");
+            StatusCode::BAD_GATEWAY
+        }
+    }
+}
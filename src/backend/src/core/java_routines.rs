@@ -0,0 +1,86 @@
+use crate::{
+    core::server_routines,
+    domain::java::{
+        JavaRecommendation, JavaRecommendationQuery, JavaRequirement, JavaRuntime, ServerJava,
+    },
+    java,
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+pub fn list(state: Arc<AppState>) -> Vec<JavaRuntime> {
+    debug!("list java runtimes started");
+    state.java.list()
+}
+
+pub async fn scan(state: Arc<AppState>) -> Vec<JavaRuntime> {
+    debug!("rescan java runtimes started");
+    java::scan(&state).await
+}
+
+pub fn recommend(
+    state: Arc<AppState>,
+    query: JavaRecommendationQuery,
+) -> Result<JavaRecommendation, StatusCode> {
+    debug!(
+        minecraft_version = query.minecraft_version,
+        "recommend java runtime started"
+    );
+    query.validate().map_err(|e| {
+        error!(error = %e, "java recommendation query validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    // Snapshots have no known requirement
+    let requirement =
+        JavaRequirement::for_minecraft(&query.minecraft_version).ok_or(StatusCode::NOT_FOUND)?;
+    let runtime = requirement.pick(&state.java.list()).cloned();
+    Ok(JavaRecommendation {
+        minecraft_version: query.minecraft_version,
+        requirement,
+        runtime,
+    })
+}
+
+/// Probes the runtime a server is set up with and checks it the way a start
+/// would.
+pub async fn server_java(state: Arc<AppState>, uuid: Uuid) -> Result<ServerJava, StatusCode> {
+    debug!(server_uuid = %uuid, "check server java started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let runtime = java::probe(&server.java_path)
+        .await
+        .inspect_err(|e| warn!(error = %e, server_uuid = %uuid, "probe java runtime failed"))
+        .ok();
+    let requirement = server
+        .minecraft_version
+        .as_deref()
+        .and_then(JavaRequirement::for_minecraft);
+    let compatible = match (&runtime, &requirement) {
+        (Some(runtime), Some(requirement)) => requirement.allows(runtime.major),
+        _ => true,
+    };
+    // Only worth pointing out when it is not what the server already uses
+    let recommended = requirement
+        .and_then(|requirement| requirement.pick(&state.java.list()).cloned())
+        .filter(|recommended| {
+            runtime
+                .as_ref()
+                .is_none_or(|runtime| runtime.path != recommended.path)
+        });
+
+    Ok(ServerJava {
+        java_path: server.java_path,
+        runtime,
+        minecraft_version: server.minecraft_version,
+        requirement,
+        compatible,
+        recommended,
+    })
+}
@@ -0,0 +1,80 @@
+use crate::{
+    domain::{
+        job::{Job, JobQuery},
+        user::InternalUser,
+    },
+    infra::db,
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use tokio::sync::broadcast;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+/// Jobs matching `query`, newest first. Jobs still running are shown as they
+/// are now rather than as last saved.
+pub async fn list(state: Arc<AppState>, query: JobQuery) -> Result<Vec<Job>, StatusCode> {
+    debug!("fetch jobs started");
+    let jobs = db::job::list(&state.db_pool, &query).await.map_err(|e| {
+        error!(error = %e, "fetch jobs failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+
+    Ok(jobs
+        .into_iter()
+        .map(|job| state.jobs.get(job.uuid).unwrap_or(job))
+        .collect())
+}
+
+pub async fn get(state: Arc<AppState>, job_uuid: Uuid) -> Result<Job, StatusCode> {
+    debug!(job_uuid = %job_uuid, "fetch job started");
+    if let Some(job) = state.jobs.get(job_uuid) {
+        return Ok(job);
+    }
+
+    db::job::get_by_uuid(&state.db_pool, job_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, job_uuid = %job_uuid, "fetch job failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// Asks a job to stop. Finished jobs and ones that cannot be cancelled are a
+/// conflict.
+pub async fn cancel(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    job_uuid: Uuid,
+) -> Result<Job, StatusCode> {
+    debug!(job_uuid = %job_uuid, "cancel job started");
+    let job = get(state.clone(), job_uuid).await?;
+    if !job.can_cancel(user) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+    if job.status.is_finished() || !job.cancellable {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    // The job may have finished since it was looked up
+    let job = state.jobs.cancel(job_uuid).ok_or(StatusCode::CONFLICT)?;
+    info!(
+        job_uuid = %job_uuid,
+        kind = job.kind.as_str(),
+        username = user.username,
+        "job cancellation requested"
+    );
+    Ok(job)
+}
+
+/// Jobs not finished yet that match `query`, and every update from now on.
+/// Updates are not filtered, see [`JobQuery::matches`].
+pub fn subscribe(state: &AppState, query: &JobQuery) -> (Vec<Job>, broadcast::Receiver<Job>) {
+    let (jobs, rx) = state.jobs.subscribe();
+    let jobs = jobs.into_iter().filter(|job| query.matches(job)).collect();
+    (jobs, rx)
+}
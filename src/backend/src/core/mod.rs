@@ -1 +1,17 @@
+pub mod backup_routines;
+pub mod command_routines;
+pub mod console_routines;
+pub mod countdown_routines;
+pub mod crash_routines;
+pub mod destination_routines;
+pub mod file_routines;
+pub mod install_routines;
+pub mod java_routines;
+pub mod job_routines;
+pub mod player_list_routines;
+pub mod player_routines;
+pub mod properties_routines;
+pub mod schedule_routines;
+pub mod server_routines;
+pub mod status_routines;
 pub mod user_routines;
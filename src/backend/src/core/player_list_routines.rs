@@ -0,0 +1,516 @@
+use crate::{
+    core::{command_routines, server_routines},
+    domain::{
+        command::CommandSource,
+        player_lists::{
+            BannedIpEntry, BannedPlayerEntry, LIST_DATE_FORMAT, ListChange, NEVER_EXPIRES, NewBan,
+            NewIpBan, NewOp, NewWhitelistEntry, OpEntry, PlayerProfile, WhitelistEntry,
+            single_line,
+        },
+        server::{ProcessState, Server},
+        user::InternalUser,
+        validation::validate_player_name,
+    },
+    infra::minecraft::{
+        mojang, ping,
+        player_lists::{self, BANNED_IPS, BANNED_PLAYERS, OPS, WHITELIST},
+        properties,
+    },
+    prelude::*,
+};
+use std::{path::Path, sync::Arc};
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use serde::{Serialize, de::DeserializeOwned};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+const DEFAULT_BAN_REASON: &str = "Banned by an operator.";
+
+pub async fn whitelist(
+    state: Arc<AppState>,
+    uuid: Uuid,
+) -> Result<Vec<WhitelistEntry>, StatusCode> {
+    let server = server_routines::get_by_uuid(state, uuid).await?;
+    read(&server, WHITELIST).await
+}
+
+pub async fn add_whitelist(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    entry: NewWhitelistEntry,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "add whitelist entry started");
+    validate(&entry)?;
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let command_text = list_command("whitelist add", &entry.name, None);
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let profile = resolve(&state, &server, &entry.name).await?;
+    let mut entries: Vec<WhitelistEntry> = read(&server, WHITELIST).await?;
+    entries.retain(|e| !same_player(&e.uuid, &e.name, &profile));
+    entries.push(WhitelistEntry {
+        uuid: profile.uuid,
+        name: profile.name,
+    });
+    write(&server, WHITELIST, &entries).await?;
+
+    info!(server_uuid = %uuid, player = entry.name, "player whitelisted");
+    Ok(ListChange::file())
+}
+
+pub async fn remove_whitelist(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    name: String,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "remove whitelist entry started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let name = valid_name(name)?;
+
+    let command_text = list_command("whitelist remove", &name, None);
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let mut entries: Vec<WhitelistEntry> = read(&server, WHITELIST).await?;
+    remove_by_name(&mut entries, &name, |e| &e.name)?;
+    write(&server, WHITELIST, &entries).await?;
+
+    info!(server_uuid = %uuid, player = name, "player removed from whitelist");
+    Ok(ListChange::file())
+}
+
+pub async fn ops(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<OpEntry>, StatusCode> {
+    let server = server_routines::get_by_uuid(state, uuid).await?;
+    read(&server, OPS).await
+}
+
+/// The `op` command always grants the server's default level without the
+/// player limit bypass, anything else can only be written while stopped.
+pub async fn add_op(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    op: NewOp,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "add operator started");
+    validate(&op)?;
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let default_level = default_op_level(&server).await;
+
+    let custom = op.level.is_some_and(|level| level != default_level) || op.bypasses_player_limit;
+    if custom && is_running(&state, &server).await? {
+        warn!(server_uuid = %uuid, "custom op level requires the server to be stopped");
+        return Err(StatusCode::CONFLICT);
+    }
+    let command_text = list_command("op", &op.name, None);
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let profile = resolve(&state, &server, &op.name).await?;
+    let mut entries: Vec<OpEntry> = read(&server, OPS).await?;
+    entries.retain(|e| !same_player(&e.uuid, &e.name, &profile));
+    entries.push(OpEntry {
+        uuid: profile.uuid,
+        name: profile.name,
+        level: op.level.unwrap_or(default_level),
+        bypasses_player_limit: op.bypasses_player_limit,
+    });
+    write(&server, OPS, &entries).await?;
+
+    info!(server_uuid = %uuid, player = op.name, "player opped");
+    Ok(ListChange::file())
+}
+
+pub async fn remove_op(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    name: String,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "remove operator started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let name = valid_name(name)?;
+
+    let command_text = list_command("deop", &name, None);
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let mut entries: Vec<OpEntry> = read(&server, OPS).await?;
+    remove_by_name(&mut entries, &name, |e| &e.name)?;
+    write(&server, OPS, &entries).await?;
+
+    info!(server_uuid = %uuid, player = name, "player deopped");
+    Ok(ListChange::file())
+}
+
+pub async fn bans(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<BannedPlayerEntry>, StatusCode> {
+    let server = server_routines::get_by_uuid(state, uuid).await?;
+    read(&server, BANNED_PLAYERS).await
+}
+
+pub async fn add_ban(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    ban: NewBan,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "ban player started");
+    validate(&ban)?;
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let reason = ban.reason.as_deref().map(single_line);
+
+    let command_text = list_command("ban", &ban.name, reason.as_deref());
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let profile = resolve(&state, &server, &ban.name).await?;
+    let mut entries: Vec<BannedPlayerEntry> = read(&server, BANNED_PLAYERS).await?;
+    entries.retain(|e| !same_player(&e.uuid, &e.name, &profile));
+    entries.push(BannedPlayerEntry {
+        uuid: profile.uuid,
+        name: profile.name,
+        created: Utc::now().format(LIST_DATE_FORMAT).to_string(),
+        source: user.username.clone(),
+        expires: NEVER_EXPIRES.to_string(),
+        reason: reason.unwrap_or_else(|| DEFAULT_BAN_REASON.to_string()),
+    });
+    write(&server, BANNED_PLAYERS, &entries).await?;
+
+    info!(server_uuid = %uuid, player = ban.name, "player banned");
+    Ok(ListChange::file())
+}
+
+pub async fn remove_ban(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    name: String,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "pardon player started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let name = valid_name(name)?;
+
+    let command_text = list_command("pardon", &name, None);
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let mut entries: Vec<BannedPlayerEntry> = read(&server, BANNED_PLAYERS).await?;
+    remove_by_name(&mut entries, &name, |e| &e.name)?;
+    write(&server, BANNED_PLAYERS, &entries).await?;
+
+    info!(server_uuid = %uuid, player = name, "player pardoned");
+    Ok(ListChange::file())
+}
+
+pub async fn ip_bans(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<BannedIpEntry>, StatusCode> {
+    let server = server_routines::get_by_uuid(state, uuid).await?;
+    read(&server, BANNED_IPS).await
+}
+
+pub async fn add_ip_ban(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    ban: NewIpBan,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "ban ip started");
+    validate(&ban)?;
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let reason = ban.reason.as_deref().map(single_line);
+
+    let command_text = list_command("ban-ip", &ban.ip.to_string(), reason.as_deref());
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let ip = ban.ip.to_string();
+    let mut entries: Vec<BannedIpEntry> = read(&server, BANNED_IPS).await?;
+    entries.retain(|e| e.ip != ip);
+    entries.push(BannedIpEntry {
+        ip,
+        created: Utc::now().format(LIST_DATE_FORMAT).to_string(),
+        source: user.username.clone(),
+        expires: NEVER_EXPIRES.to_string(),
+        reason: reason.unwrap_or_else(|| DEFAULT_BAN_REASON.to_string()),
+    });
+    write(&server, BANNED_IPS, &entries).await?;
+
+    info!(server_uuid = %uuid, ip = %ban.ip, "ip banned");
+    Ok(ListChange::file())
+}
+
+pub async fn remove_ip_ban(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    ip: String,
+) -> Result<ListChange, StatusCode> {
+    debug!(server_uuid = %uuid, "pardon ip started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+    let ip: std::net::IpAddr = ip.parse().map_err(|_| {
+        warn!(ip, "invalid ip address");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let command_text = list_command("pardon-ip", &ip.to_string(), None);
+    if let Some(change) = via_command(&state, user, &server, command_text).await? {
+        return Ok(change);
+    }
+
+    let ip = ip.to_string();
+    let mut entries: Vec<BannedIpEntry> = read(&server, BANNED_IPS).await?;
+    remove_by_name(&mut entries, &ip, |e| &e.ip)?;
+    write(&server, BANNED_IPS, &entries).await?;
+
+    info!(server_uuid = %uuid, ip, "ip pardoned");
+    Ok(ListChange::file())
+}
+
+/// Whether changes have to go through commands. A server the daemon did not
+/// launch counts as running while it answers a ping, it would overwrite the
+/// files on its next save.
+async fn is_running(state: &AppState, server: &Server) -> Result<bool, StatusCode> {
+    match state.supervisor.state(server.uuid).await {
+        // Too late for a command, too early for the files
+        ProcessState::Stopping => {
+            warn!(server_uuid = %server.uuid, "server is stopping");
+            return Err(StatusCode::CONFLICT);
+        }
+        process if process.is_alive() => return Ok(true),
+        _ => {}
+    }
+
+    let answered = ping::ping(
+        "127.0.0.1",
+        server.server_port as u16,
+        state.config.status.ping_timeout,
+    )
+    .await
+    .is_ok();
+    if answered && !server.rcon_available() {
+        warn!(server_uuid = %server.uuid, "server runs outside the daemon without rcon");
+        return Err(StatusCode::CONFLICT);
+    }
+    Ok(answered)
+}
+
+/// Sends the command making a change while the server runs. Otherwise the
+/// caller edits the files, but only once the command rules allow the command,
+/// so stopping the server does not get around them.
+async fn via_command(
+    state: &AppState,
+    user: &InternalUser,
+    server: &Server,
+    command: String,
+) -> Result<Option<ListChange>, StatusCode> {
+    if !is_running(state, server).await? {
+        command_routines::authorize(state, user, server, &command).await?;
+        return Ok(None);
+    }
+
+    let response =
+        command_routines::run_checked(state, user, server, &command, CommandSource::Api).await?;
+    info!(server_uuid = %server.uuid, command, "player list command sent");
+    Ok(Some(ListChange::command(response)))
+}
+
+/// The console command making a player list change, `verb` may be two words.
+fn list_command(verb: &str, target: &str, reason: Option<&str>) -> String {
+    match reason {
+        Some(reason) => format!("{verb} {target} {reason}"),
+        None => format!("{verb} {target}"),
+    }
+}
+
+/// Finds the UUID for a name the way the server would: profiles it has seen
+/// first, then the offline UUID or the Mojang API depending on `online-mode`.
+async fn resolve(
+    state: &AppState,
+    server: &Server,
+    name: &str,
+) -> Result<PlayerProfile, StatusCode> {
+    let dir = Path::new(&server.working_dir);
+    if let Some(profile) = player_lists::cached_profile(dir, name).await {
+        return Ok(profile);
+    }
+
+    let online_mode = properties::load(dir)
+        .await
+        .ok()
+        .and_then(|file| file.get("online-mode").map(|v| v != "false"))
+        .unwrap_or(true);
+    if !online_mode {
+        return Ok(player_lists::offline_profile(name));
+    }
+
+    mojang::profile_by_name(&state.http, name)
+        .await
+        .map_err(|e| {
+            error!(error = %e, player = name, "profile lookup failed");
+            StatusCode::BAD_GATEWAY
+        })?
+        .ok_or_else(|| {
+            warn!(player = name, "no such player");
+            StatusCode::NOT_FOUND
+        })
+}
+
+async fn default_op_level(server: &Server) -> u8 {
+    properties::load(Path::new(&server.working_dir))
+        .await
+        .ok()
+        .and_then(|file| file.get("op-permission-level")?.parse().ok())
+        .unwrap_or(4)
+}
+
+fn same_player(uuid: &str, name: &str, profile: &PlayerProfile) -> bool {
+    uuid.eq_ignore_ascii_case(&profile.uuid) || name.eq_ignore_ascii_case(&profile.name)
+}
+
+fn remove_by_name<T>(
+    entries: &mut Vec<T>,
+    name: &str,
+    key: impl Fn(&T) -> &String,
+) -> Result<(), StatusCode> {
+    let before = entries.len();
+    entries.retain(|e| !key(e).eq_ignore_ascii_case(name));
+    if entries.len() == before {
+        return Err(StatusCode::NOT_FOUND);
+    }
+    Ok(())
+}
+
+fn valid_name(name: String) -> Result<String, StatusCode> {
+    validate_player_name(&name).map_err(|_| {
+        warn!(player = name, "invalid player name");
+        StatusCode::BAD_REQUEST
+    })?;
+    Ok(name)
+}
+
+fn validate(input: &impl Validate) -> Result<(), StatusCode> {
+    input.validate().map_err(|e| {
+        error!(error = %e, "player list validation failed");
+        StatusCode::BAD_REQUEST
+    })
+}
+
+async fn read<T: DeserializeOwned>(server: &Server, file: &str) -> Result<Vec<T>, StatusCode> {
+    player_lists::load(Path::new(&server.working_dir), file)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %server.uuid, file, "read player list failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+async fn write<T: Serialize>(server: &Server, file: &str, entries: &[T]) -> Result<(), StatusCode> {
+    player_lists::save(Path::new(&server.working_dir), file, entries)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %server.uuid, file, "write player list failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::{
+        command::{CommandRule, denied_verb},
+        user_prems::UserPermissions,
+    };
+
+    fn moderator() -> InternalUser {
+        InternalUser {
+            uuid: Uuid::new_v4(),
+            username: "moderator".to_string(),
+            email: None,
+            password_hash: String::new(),
+            first_name: None,
+            last_name: None,
+            permissions: UserPermissions::default(),
+        }
+    }
+
+    fn deny(verb: &str) -> CommandRule {
+        CommandRule {
+            uuid: Uuid::new_v4(),
+            server_uuid: None,
+            user_uuid: None,
+            action: None,
+            verb: verb.to_string(),
+            allow: false,
+        }
+    }
+
+    /// Every change with the verb the rules hold it to, the same command is
+    /// sent while running and authorized before the files are edited.
+    fn changes() -> Vec<(String, &'static str)> {
+        vec![
+            (list_command("whitelist add", "Steve", None), "whitelist"),
+            (list_command("whitelist remove", "Steve", None), "whitelist"),
+            (list_command("op", "Steve", None), "op"),
+            (list_command("deop", "Steve", None), "deop"),
+            (list_command("ban", "Steve", Some("griefing")), "ban"),
+            (list_command("pardon", "Steve", None), "pardon"),
+            (list_command("ban-ip", "203.0.113.7", None), "ban-ip"),
+            (list_command("pardon-ip", "203.0.113.7", None), "pardon-ip"),
+        ]
+    }
+
+    #[test]
+    fn list_commands_carry_the_reason() {
+        assert_eq!(list_command("op", "Steve", None), "op Steve");
+        assert_eq!(
+            list_command("ban", "Steve", Some("griefing spawn")),
+            "ban Steve griefing spawn"
+        );
+        assert_eq!(
+            list_command("whitelist add", "Alex", None),
+            "whitelist add Alex"
+        );
+    }
+
+    #[test]
+    fn denied_verbs_refuse_list_changes() {
+        let user = moderator();
+        let server = Uuid::new_v4();
+        for (command, verb) in changes() {
+            assert_eq!(
+                denied_verb(&[deny(verb)], &user, server, &command).as_deref(),
+                Some(verb),
+                "{command}"
+            );
+            assert_eq!(
+                denied_verb(&[deny("stop")], &user, server, &command),
+                None,
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn root_may_change_lists_regardless_of_rules() {
+        let mut root = moderator();
+        root.permissions.root = true;
+        let rules = [deny("*")];
+        for (command, _) in changes() {
+            assert_eq!(denied_verb(&rules, &root, Uuid::new_v4(), &command), None);
+        }
+    }
+}
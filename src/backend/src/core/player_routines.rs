@@ -0,0 +1,41 @@
+use crate::{
+    core::server_routines,
+    domain::players::{PlayerSession, ServerPlayers},
+    infra::db,
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+const DEFAULT_SESSION_LIMIT: i64 = 50;
+const MAX_SESSION_LIMIT: i64 = 500;
+
+pub async fn online(state: Arc<AppState>, uuid: Uuid) -> Result<ServerPlayers, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch online players started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+    Ok(state.players.players(uuid))
+}
+
+pub async fn sessions(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    player_name: String,
+    limit: Option<i64>,
+) -> Result<Vec<PlayerSession>, StatusCode> {
+    debug!(server_uuid = %uuid, player = player_name, "fetch player sessions started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let limit = limit
+        .unwrap_or(DEFAULT_SESSION_LIMIT)
+        .clamp(1, MAX_SESSION_LIMIT);
+    db::player::get_sessions(&state.db_pool, uuid, &player_name, limit)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch player sessions failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
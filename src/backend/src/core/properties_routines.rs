@@ -0,0 +1,132 @@
+use crate::{
+    core::server_routines,
+    domain::{
+        properties::{
+            PropertiesUpdate, Property, PropertyChanges, ServerProperties, find_spec,
+            normalize_value,
+        },
+        server::UpdateServer,
+    },
+    infra::minecraft::properties::{self, Properties},
+    prelude::*,
+};
+use std::{path::Path, sync::Arc};
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use crate::state::AppState;
+
+pub async fn get(state: Arc<AppState>, uuid: Uuid) -> Result<ServerProperties, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch server properties started");
+    let server = server_routines::get_by_uuid(state, uuid).await?;
+    let file = load(&server.working_dir).await?;
+
+    Ok(ServerProperties {
+        properties: listing(&file),
+    })
+}
+
+pub async fn update(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    changes: PropertyChanges,
+) -> Result<PropertiesUpdate, StatusCode> {
+    debug!(server_uuid = %uuid, "update server properties started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let normalized = changes
+        .iter()
+        .map(|(key, value)| normalize_value(key, value).map(|value| (key.as_str(), value)))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| {
+            error!(error = %e, "server properties validation failed");
+            StatusCode::BAD_REQUEST
+        })?;
+
+    let mut file = load(&server.working_dir).await?;
+    let changed: Vec<String> = normalized
+        .iter()
+        .filter(|(key, value)| file.set(key, value))
+        .map(|(key, _)| key.to_string())
+        .collect();
+
+    if !changed.is_empty() {
+        properties::save(Path::new(&server.working_dir), &file)
+            .await
+            .map_err(|e| {
+                error!(error = %e, server_uuid = %uuid, "write server properties failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        sync_server(state.clone(), uuid, &file, &changed).await?;
+        info!(server_uuid = %uuid, changed = ?changed, "server properties updated");
+    }
+
+    let restart_required = !changed.is_empty() && state.supervisor.state(uuid).await.is_alive();
+    Ok(PropertiesUpdate {
+        properties: listing(&file),
+        changed,
+        restart_required,
+    })
+}
+
+/// Keeps the ports and RCON password the daemon connects with in line with
+/// what the server will listen on.
+async fn sync_server(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    file: &Properties,
+    changed: &[String],
+) -> Result<(), StatusCode> {
+    let value = |key: &str| {
+        changed
+            .iter()
+            .any(|c| c == key)
+            .then(|| file.get(key))
+            .flatten()
+    };
+    let port = |key: &str| value(key).and_then(|v| v.parse::<i32>().ok());
+
+    let update = UpdateServer {
+        server_port: port("server-port"),
+        rcon_port: port("rcon.port").map(Some),
+        query_port: port("query.port").map(Some),
+        rcon_password: value("rcon.password")
+            .filter(|password| !password.is_empty())
+            .map(str::to_string),
+        ..Default::default()
+    };
+    if update.server_port.is_none()
+        && update.rcon_port.is_none()
+        && update.query_port.is_none()
+        && update.rcon_password.is_none()
+    {
+        return Ok(());
+    }
+
+    server_routines::update(state, uuid, update).await?;
+    Ok(())
+}
+
+async fn load(working_dir: &str) -> Result<Properties, StatusCode> {
+    properties::load(Path::new(working_dir)).await.map_err(|e| {
+        error!(error = %e, working_dir, "read server properties failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+fn listing(file: &Properties) -> Vec<Property> {
+    file.entries()
+        .filter_map(|(key, value)| {
+            let spec = find_spec(key);
+            if spec.is_some_and(|spec| spec.secret) {
+                return None;
+            }
+            Some(Property {
+                key: key.to_string(),
+                value: value.to_string(),
+                kind: spec.map(|spec| spec.kind),
+            })
+        })
+        .collect()
+}
@@ -0,0 +1,218 @@
+use crate::{
+    core::server_routines,
+    domain::{
+        command::denied_verb,
+        schedule::{
+            NewScheduledTask, ScheduledTask, TaskAction, TaskRun, UpdateScheduledTask,
+            validate_action,
+        },
+        user::InternalUser,
+        user_prems::UserActions,
+    },
+    infra::db,
+    prelude::*,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use chrono::Utc;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+const DEFAULT_RUN_LIMIT: i64 = 50;
+const MAX_RUN_LIMIT: i64 = 500;
+
+pub async fn list(state: Arc<AppState>, uuid: Uuid) -> Result<Vec<ScheduledTask>, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch scheduled tasks started");
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    db::schedule::get_tasks_for_server(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch scheduled tasks failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    task_uuid: Uuid,
+) -> Result<ScheduledTask, StatusCode> {
+    debug!(server_uuid = %uuid, task_uuid = %task_uuid, "fetch scheduled task started");
+    db::schedule::get_task(&state.db_pool, uuid, task_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, task_uuid = %task_uuid, "fetch scheduled task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+pub async fn create(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    new_task: NewScheduledTask,
+) -> Result<ScheduledTask, StatusCode> {
+    debug!(server_uuid = %uuid, username = user.username, "create scheduled task started");
+
+    new_task.validate().map_err(|e| {
+        error!(error = %e, "scheduled task validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let mut task = ScheduledTask::new(new_task, uuid, user.uuid);
+    authorize(&state, user, &task).await?;
+    task.next_run_at = next_run(&task);
+
+    let task = db::schedule::create_task(&state.db_pool, &task)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "create scheduled task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    state.scheduler.upsert(task.clone());
+
+    info!(
+        task_uuid = %task.uuid,
+        server_uuid = %uuid,
+        action = task.action.as_str(),
+        cron = task.cron,
+        "scheduled task created"
+    );
+    Ok(task)
+}
+
+pub async fn update(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    task_uuid: Uuid,
+    update: UpdateScheduledTask,
+) -> Result<ScheduledTask, StatusCode> {
+    debug!(task_uuid = %task_uuid, username = user.username, "update scheduled task started");
+
+    update.validate().map_err(|e| {
+        error!(error = %e, "scheduled task update validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let mut task = get(state.clone(), uuid, task_uuid).await?;
+    task.apply(update);
+    validate_action(
+        task.action,
+        task.command.as_deref(),
+        task.countdown_secs.is_some(),
+    )
+    .map_err(|e| {
+        error!(error = %e, "scheduled task update validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+    authorize(&state, user, &task).await?;
+    task.next_run_at = next_run(&task);
+
+    let task = db::schedule::update_task(&state.db_pool, &task)
+        .await
+        .map_err(|e| {
+            error!(error = %e, task_uuid = %task_uuid, "update scheduled task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    state.scheduler.upsert(task.clone());
+
+    info!(task_uuid = %task.uuid, "scheduled task updated");
+    Ok(task)
+}
+
+pub async fn delete(state: Arc<AppState>, uuid: Uuid, task_uuid: Uuid) -> Result<(), StatusCode> {
+    debug!(task_uuid = %task_uuid, "delete scheduled task started");
+
+    let deleted = db::schedule::delete_task(&state.db_pool, uuid, task_uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, task_uuid = %task_uuid, "delete scheduled task failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    state.scheduler.remove(task_uuid);
+    info!(task_uuid = %task_uuid, "scheduled task deleted");
+    Ok(())
+}
+
+pub async fn runs(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    task_uuid: Uuid,
+    limit: Option<i64>,
+) -> Result<Vec<TaskRun>, StatusCode> {
+    debug!(task_uuid = %task_uuid, "fetch task runs started");
+    get(state.clone(), uuid, task_uuid).await?;
+
+    let limit = limit.unwrap_or(DEFAULT_RUN_LIMIT).clamp(1, MAX_RUN_LIMIT);
+    db::schedule::get_runs(&state.db_pool, task_uuid, limit)
+        .await
+        .map_err(|e| {
+            error!(error = %e, task_uuid = %task_uuid, "fetch task runs failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+fn next_run(task: &ScheduledTask) -> Option<chrono::DateTime<Utc>> {
+    if task.enabled {
+        task.next_after(Utc::now())
+    } else {
+        None
+    }
+}
+
+/// Tasks run unattended, so whoever schedules one must be allowed to perform
+/// its action right now, command rules included.
+async fn authorize(
+    state: &AppState,
+    user: &InternalUser,
+    task: &ScheduledTask,
+) -> Result<(), StatusCode> {
+    if user.permissions.root {
+        return Ok(());
+    }
+
+    let required = match task.action {
+        TaskAction::Start | TaskAction::Stop | TaskAction::Restart => UserActions::ControlServers,
+        TaskAction::Command => UserActions::SendCommands,
+        TaskAction::Backup => UserActions::ManageBackups,
+    };
+    if !user.permissions.permissions.contains(&required) {
+        warn!(
+            username = user.username,
+            action = task.action.as_str(),
+            "task action not permitted"
+        );
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    if let Some(command) = task.command.as_deref() {
+        let rules = db::command::get_rules_for_server(&state.db_pool, task.server_uuid)
+            .await
+            .map_err(|e| {
+                error!(error = %e, server_uuid = %task.server_uuid, "fetch command rules failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if let Some(verb) = denied_verb(&rules, user, task.server_uuid, command) {
+            warn!(
+                username = user.username,
+                verb, "scheduled command denied by rules"
+            );
+            return Err(StatusCode::FORBIDDEN);
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use crate::{
+    backup, countdown, crash,
+    domain::{
+        launch::LaunchPlan,
+        server::{
+            InternalNewServer, NewServer, Server, ServerProcess, UpdateServer, validate_memory,
+        },
+    },
+    infra::db,
+    players,
+    prelude::*,
+    status,
+    supervisor::SupervisorError,
+};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::state::AppState;
+
+pub async fn create(
+    state: Arc<AppState>,
+    owner: Uuid,
+    new_server: NewServer,
+) -> Result<Server, StatusCode> {
+    debug!("create server started");
+
+    new_server.validate().map_err(|e| {
+        error!(error = %e, "server validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let name_taken = db::server::exists_by_name(&state.db_pool, &new_server.name)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "check server name failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+    if name_taken {
+        warn!(server_name = new_server.name, "server name already in use");
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let rcon_password = seal_rcon_password(&state, new_server.rcon_password.as_deref())?;
+    let internal = InternalNewServer::new(new_server, owner, rcon_password);
+    let server = db::server::create(&state.db_pool, internal)
+        .await
+        .map_err(|e| {
+            error!(error = %e, "create server failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    players::watch(state.clone(), server.uuid).await;
+    crash::watch(state.clone(), server.uuid).await;
+    info!(server_uuid = %server.uuid, "server created");
+    Ok(server)
+}
+
+fn seal_rcon_password(
+    state: &AppState,
+    password: Option<&str>,
+) -> Result<Option<Vec<u8>>, StatusCode> {
+    password
+        .map(|password| state.secrets.encrypt_str(password))
+        .transpose()
+        .map_err(|e| {
+            error!(error = %e, "seal rcon password failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })
+}
+
+pub async fn get_all(state: Arc<AppState>) -> Result<Vec<Server>, StatusCode> {
+    debug!("fetch all servers started");
+    db::server::get_all(&state.db_pool).await.map_err(|e| {
+        error!(error = %e, "fetch all servers failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
+}
+
+pub async fn get_by_uuid(state: Arc<AppState>, uuid: Uuid) -> Result<Server, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch server by uuid started");
+    db::server::get_by_uuid(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "fetch server failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?
+        .ok_or(StatusCode::NOT_FOUND)
+}
+
+/// The argv a start of the server runs, rendered from its current record.
+pub async fn launch_plan(state: Arc<AppState>, uuid: Uuid) -> Result<LaunchPlan, StatusCode> {
+    debug!(server_uuid = %uuid, "render server launch started");
+    let server = get_by_uuid(state, uuid).await?;
+    Ok(server.launch_plan())
+}
+
+pub async fn update(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    update: UpdateServer,
+) -> Result<Server, StatusCode> {
+    debug!(server_uuid = %uuid, "update server started");
+
+    update.validate().map_err(|e| {
+        error!(error = %e, "server update validation failed");
+        StatusCode::BAD_REQUEST
+    })?;
+
+    let mut server = get_by_uuid(state.clone(), uuid).await?;
+
+    if let Some(name) = update.name.as_deref()
+        && name != server.name
+    {
+        let name_taken = db::server::exists_by_name(&state.db_pool, name)
+            .await
+            .map_err(|e| {
+                error!(error = %e, "check server name failed");
+                StatusCode::INTERNAL_SERVER_ERROR
+            })?;
+        if name_taken {
+            warn!(server_name = name, "server name already in use");
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+
+    if let Some(sealed) = seal_rcon_password(&state, update.rcon_password.as_deref())? {
+        server.rcon_password = Some(sealed);
+    }
+    server.apply(update);
+    let (min_heap_mb, max_heap_mb) = server.heap();
+    validate_memory(server.min_memory_mb, server.max_memory_mb)
+        .and_then(|()| validate_memory(min_heap_mb, max_heap_mb))
+        .map_err(|e| {
+            error!(error = %e, "server update validation failed");
+            StatusCode::BAD_REQUEST
+        })?;
+
+    let server = db::server::update(&state.db_pool, server)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "update server failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    info!(server_uuid = %server.uuid, "server updated");
+    Ok(server)
+}
+
+pub async fn delete(state: Arc<AppState>, uuid: Uuid) -> Result<(), StatusCode> {
+    debug!(server_uuid = %uuid, "delete server started");
+
+    if state.supervisor.state(uuid).await.is_alive() {
+        warn!(server_uuid = %uuid, "refusing to delete running server");
+        return Err(StatusCode::CONFLICT);
+    }
+
+    let deleted = db::server::delete(&state.db_pool, uuid)
+        .await
+        .map_err(|e| {
+            error!(error = %e, server_uuid = %uuid, "delete server failed");
+            StatusCode::INTERNAL_SERVER_ERROR
+        })?;
+
+    if !deleted {
+        return Err(StatusCode::NOT_FOUND);
+    }
+
+    players::forget(&state, uuid);
+    crash::forget(&state, uuid);
+    state.scheduler.forget_server(uuid);
+    countdown::cancel(&state, uuid);
+    backup::forget(&state, uuid).await;
+    status::forget(&state, uuid).await;
+    info!(server_uuid = %uuid, "server deleted");
+    Ok(())
+}
+
+pub fn supervisor_status(e: &SupervisorError) -> StatusCode {
+    match e {
+        SupervisorError::AlreadyRunning
+        | SupervisorError::NotRunning
+        | SupervisorError::Maintenance => StatusCode::CONFLICT,
+        SupervisorError::EmptyCommand => StatusCode::BAD_REQUEST,
+        SupervisorError::IncompatibleJava { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+async fn process(state: &AppState, uuid: Uuid) -> ServerProcess {
+    ServerProcess {
+        uuid,
+        state: state.supervisor.state(uuid).await,
+    }
+}
+
+pub async fn start(state: Arc<AppState>, uuid: Uuid) -> Result<ServerProcess, StatusCode> {
+    debug!(server_uuid = %uuid, "start server started");
+    let server = get_by_uuid(state.clone(), uuid).await?;
+
+    state.supervisor.start(&server).await.map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "start server failed");
+        supervisor_status(&e)
+    })?;
+
+    Ok(process(&state, uuid).await)
+}
+
+pub async fn stop(state: Arc<AppState>, uuid: Uuid) -> Result<ServerProcess, StatusCode> {
+    debug!(server_uuid = %uuid, "stop server started");
+    get_by_uuid(state.clone(), uuid).await?;
+
+    state.supervisor.stop(uuid).await.map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "stop server failed");
+        supervisor_status(&e)
+    })?;
+
+    Ok(process(&state, uuid).await)
+}
+
+pub async fn restart(state: Arc<AppState>, uuid: Uuid) -> Result<ServerProcess, StatusCode> {
+    debug!(server_uuid = %uuid, "restart server started");
+    let server = get_by_uuid(state.clone(), uuid).await?;
+
+    // Stopping can take a while, let the caller follow the state instead of waiting
+    let task_state = state.clone();
+    tokio::spawn(async move {
+        if let Err(e) = task_state.supervisor.restart(&server).await {
+            error!(error = %e, server_uuid = %server.uuid, "restart server failed");
+        }
+    });
+
+    Ok(process(&state, uuid).await)
+}
+
+pub async fn kill(state: Arc<AppState>, uuid: Uuid) -> Result<ServerProcess, StatusCode> {
+    debug!(server_uuid = %uuid, "kill server started");
+    get_by_uuid(state.clone(), uuid).await?;
+
+    state.supervisor.kill(uuid).await.map_err(|e| {
+        error!(error = %e, server_uuid = %uuid, "kill server failed");
+        supervisor_status(&e)
+    })?;
+
+    Ok(process(&state, uuid).await)
+}
+
+pub async fn auto_start(state: Arc<AppState>) {
+    let servers = match get_all(state.clone()).await {
+        Ok(servers) => servers,
+        Err(_) => return,
+    };
+
+    for server in servers.iter().filter(|s| s.auto_start) {
+        match state.supervisor.start(server).await {
+            Ok(()) => info!(server_uuid = %server.uuid, "server auto started"),
+            Err(e) => error!(error = %e, server_uuid = %server.uuid, "auto start server failed"),
+        }
+    }
+}
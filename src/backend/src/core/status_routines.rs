@@ -0,0 +1,42 @@
+use crate::{core::server_routines, domain::status::ServerStatus, prelude::*};
+use std::sync::Arc;
+
+use axum::http::StatusCode;
+use uuid::Uuid;
+
+use crate::{state::AppState, status};
+
+pub async fn get(state: Arc<AppState>, uuid: Uuid) -> Result<ServerStatus, StatusCode> {
+    debug!(server_uuid = %uuid, "fetch server status started");
+    let server = server_routines::get_by_uuid(state.clone(), uuid).await?;
+
+    let process = state.supervisor.state(uuid).await;
+    let poll = state.status.get(uuid).await;
+    let query = match &poll {
+        Some(result) if result.reachable => status::query_status(&state, &server).await,
+        _ => None,
+    };
+
+    let status = match poll {
+        Some(result) => ServerStatus {
+            uuid,
+            process,
+            reachable: result.reachable,
+            checked_at: Some(result.checked_at),
+            ping: result.ping,
+            error: result.error,
+            query,
+        },
+        None => ServerStatus {
+            uuid,
+            process,
+            reachable: false,
+            checked_at: None,
+            ping: None,
+            error: None,
+            query,
+        },
+    };
+
+    Ok(status)
+}
@@ -1,9 +1,9 @@
 use crate::{
-    auth::{gen_jwt, verify_password},
+    auth::{gen_csrf_token, gen_jwt, verify_password},
     domain::{
-        api::LoginData,
+        api::{LoginData, SessionTokens},
         user::InternalUser,
-        user_prems::{ExtUserPermissions, UserPermissions},
+        user_prems::UserPermissions,
     },
     infra::db,
     prelude::*,
@@ -24,7 +24,7 @@ use crate::{
 pub async fn login(
     state: Arc<AppState>,
     login_data: LoginData,
-) -> Result<(String, User), StatusCode> {
+) -> Result<(SessionTokens, User), StatusCode> {
     debug!(username = login_data.username.as_str(), "login started");
 
     let user = db::user::get_by_username(&state.db_pool, &login_data.username)
@@ -47,12 +47,13 @@ pub async fn login(
         return Err(StatusCode::UNAUTHORIZED);
     }
 
-    let token = gen_jwt(user.username.clone()).map_err(|e| {
+    let csrf = gen_csrf_token();
+    let jwt = gen_jwt(user.username.clone(), csrf.clone()).map_err(|e| {
         error!(error = %e, username = login_data.username.as_str(), "generate jwt failed");
         StatusCode::INTERNAL_SERVER_ERROR
     })?;
 
-    Ok((token, User::from(user)))
+    Ok((SessionTokens { jwt, csrf }, User::from(user)))
 }
 
 pub async fn create(state: Arc<AppState>, new_user: NewUser) -> Result<User, StatusCode> {
@@ -0,0 +1,245 @@
+use std::{
+    collections::{HashMap, hash_map::Entry},
+    sync::{Arc, Mutex},
+};
+
+use chrono::{DateTime, TimeDelta, Utc};
+use tokio::{sync::watch, task::JoinHandle, time::sleep};
+use uuid::Uuid;
+
+use crate::{
+    core::command_routines,
+    domain::{
+        countdown::{Countdown, CountdownEnd, ShutdownMode},
+        server::Server,
+    },
+    infra::db,
+    prelude::*,
+    state::AppState,
+};
+
+struct Active {
+    countdown: Countdown,
+    cancel: watch::Sender<bool>,
+}
+
+/// Countdowns to a stop or restart, at most one per server.
+#[derive(Default)]
+pub struct Countdowns {
+    active: Mutex<HashMap<Uuid, Active>>,
+}
+
+impl Countdowns {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The server's countdown with only the warnings still to come.
+    pub fn get(&self, server_uuid: Uuid) -> Option<Countdown> {
+        let active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let mut countdown = active.get(&server_uuid)?.countdown.clone();
+
+        let remaining = (countdown.ends_at - Utc::now()).num_seconds().max(0) as u32;
+        countdown.announce_at.retain(|secs| *secs <= remaining);
+        Some(countdown)
+    }
+
+    /// Drops the countdown once it can no longer be cancelled.
+    fn finish(&self, countdown: &Countdown) {
+        let mut active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        if active
+            .get(&countdown.server_uuid)
+            .is_some_and(|a| a.countdown.uuid == countdown.uuid)
+        {
+            active.remove(&countdown.server_uuid);
+        }
+    }
+}
+
+/// Starts counting down on `server`, `None` if it already has a countdown.
+/// The handle resolves once the server was stopped or restarted, or the
+/// countdown was cancelled.
+pub fn begin(
+    state: Arc<AppState>,
+    server: Server,
+    countdown: Countdown,
+) -> Option<JoinHandle<CountdownEnd>> {
+    let (cancel, cancelled) = watch::channel(false);
+
+    let mut active = state
+        .countdowns
+        .active
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    let Entry::Vacant(slot) = active.entry(server.uuid) else {
+        return None;
+    };
+    slot.insert(Active {
+        countdown: countdown.clone(),
+        cancel,
+    });
+    drop(active);
+
+    Some(tokio::spawn(run(state, server, countdown, cancelled)))
+}
+
+/// Cancels the server's countdown, returns it if there was one.
+pub fn cancel(state: &AppState, server_uuid: Uuid) -> Option<Countdown> {
+    let active = state
+        .countdowns
+        .active
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&server_uuid)?;
+    active.cancel.send_replace(true);
+    Some(active.countdown)
+}
+
+async fn run(
+    state: Arc<AppState>,
+    server: Server,
+    countdown: Countdown,
+    mut cancelled: watch::Receiver<bool>,
+) -> CountdownEnd {
+    let uuid = server.uuid;
+
+    for remaining in &countdown.announce_at {
+        let at = countdown.ends_at - TimeDelta::seconds(i64::from(*remaining));
+        if wait_until(at, &mut cancelled).await {
+            return abort(&state, &server, &countdown).await;
+        }
+        let announcement = countdown.announcement(*remaining);
+        announce(&state, &server, &countdown.style.command(&announcement)).await;
+    }
+    if wait_until(countdown.ends_at, &mut cancelled).await {
+        return abort(&state, &server, &countdown).await;
+    }
+
+    // From here on the shutdown goes ahead
+    state.countdowns.finish(&countdown);
+    command_routines::save_world(
+        &state,
+        &server,
+        "save-all",
+        state.config.countdown.save_timeout,
+    )
+    .await;
+
+    let result = match countdown.mode {
+        ShutdownMode::Stop => state.supervisor.stop(uuid).await,
+        ShutdownMode::Restart => {
+            // Settings may have changed while counting down
+            let server = match db::server::get_by_uuid(&state.db_pool, uuid).await {
+                Ok(Some(fresh)) => fresh,
+                Ok(None) => return CountdownEnd::Failed("server was deleted".to_string()),
+                Err(e) => {
+                    error!(error = %e, server_uuid = %uuid, "refetch server for restart failed");
+                    server
+                }
+            };
+            state.supervisor.restart(&server).await
+        }
+    };
+
+    match result {
+        Ok(()) => {
+            info!(server_uuid = %uuid, mode = countdown.mode.as_str(), "countdown completed");
+            CountdownEnd::Completed
+        }
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, mode = countdown.mode.as_str(), "countdown shutdown failed");
+            CountdownEnd::Failed(e.to_string())
+        }
+    }
+}
+
+/// Sleeps until `at`, returns whether the countdown was cancelled meanwhile.
+async fn wait_until(at: DateTime<Utc>, cancelled: &mut watch::Receiver<bool>) -> bool {
+    let delay = (at - Utc::now()).to_std().unwrap_or_default();
+    tokio::select! {
+        _ = sleep(delay) => false,
+        // A dropped sender means the countdown was discarded
+        _ = cancelled.wait_for(|cancelled| *cancelled) => true,
+    }
+}
+
+async fn abort(state: &AppState, server: &Server, countdown: &Countdown) -> CountdownEnd {
+    info!(server_uuid = %server.uuid, mode = countdown.mode.as_str(), "countdown cancelled");
+    if state.supervisor.state(server.uuid).await.is_alive() {
+        let message = &state.config.countdown.cancelled_message;
+        announce(state, server, &countdown.style.command(message)).await;
+    }
+    CountdownEnd::Cancelled
+}
+
+async fn announce(state: &AppState, server: &Server, command: &str) {
+    // dispatch logs its own failures, a missed warning must not stop the countdown
+    let _ = command_routines::dispatch(state, server, command).await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::countdown::AnnounceStyle;
+
+    fn countdown(server_uuid: Uuid) -> Countdown {
+        Countdown::new(
+            server_uuid,
+            ShutdownMode::Stop,
+            60,
+            &[],
+            "{action} in {time}".to_string(),
+            AnnounceStyle::Say,
+        )
+    }
+
+    fn track(countdowns: &Countdowns, countdown: &Countdown) -> watch::Receiver<bool> {
+        let (cancel, cancelled) = watch::channel(false);
+        countdowns.active.lock().unwrap().insert(
+            countdown.server_uuid,
+            Active {
+                countdown: countdown.clone(),
+                cancel,
+            },
+        );
+        cancelled
+    }
+
+    #[test]
+    fn finish_drops_its_own_countdown() {
+        let countdowns = Countdowns::new();
+        let current = countdown(Uuid::new_v4());
+        let _cancelled = track(&countdowns, &current);
+
+        countdowns.finish(&current);
+        assert!(countdowns.get(current.server_uuid).is_none());
+    }
+
+    #[test]
+    fn finish_after_cancel_leaves_the_next_countdown() {
+        let countdowns = Countdowns::new();
+        let server = Uuid::new_v4();
+        let cancelled = countdown(server);
+        let _old = track(&countdowns, &cancelled);
+
+        // Cancelled and replaced while the old task was about to finish
+        countdowns.active.lock().unwrap().remove(&server);
+        let next = countdown(server);
+        let _new = track(&countdowns, &next);
+        countdowns.finish(&cancelled);
+
+        assert_eq!(countdowns.get(server).map(|c| c.uuid), Some(next.uuid));
+    }
+
+    #[test]
+    fn get_skips_warnings_already_given() {
+        let countdowns = Countdowns::new();
+        let mut current = countdown(Uuid::new_v4());
+        current.announce_at = vec![300, 60, 10];
+        current.ends_at = Utc::now() + TimeDelta::seconds(90);
+        let _cancelled = track(&countdowns, &current);
+
+        let remaining = countdowns.get(current.server_uuid).unwrap();
+        assert_eq!(remaining.announce_at, [60, 10]);
+    }
+}
@@ -0,0 +1,324 @@
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant, SystemTime},
+};
+
+use chrono::{DateTime, Utc};
+use tokio::{fs, io::AsyncReadExt, sync::watch, task::JoinHandle, time::sleep};
+use uuid::Uuid;
+
+use crate::{
+    config::CrashCfg,
+    domain::{crash::CrashRecord, server::ProcessState},
+    infra::db,
+    prelude::*,
+    state::AppState,
+};
+
+const CRASH_REPORTS_DIR: &str = "crash-reports";
+
+/// Records crashes and restarts crashed servers with exponential backoff.
+#[derive(Default)]
+pub struct CrashGuard {
+    /// Automatic restarts per server inside the restart window.
+    restarts: Mutex<HashMap<Uuid, VecDeque<Instant>>>,
+    tasks: Mutex<HashMap<Uuid, JoinHandle<()>>>,
+}
+
+impl CrashGuard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Books a restart and returns how long to wait for it, `None` once the
+    /// server used up its restarts for the window.
+    fn next_restart(&self, uuid: Uuid, cfg: &CrashCfg) -> Option<Duration> {
+        let mut restarts = self.restarts.lock().unwrap_or_else(|e| e.into_inner());
+        let history = restarts.entry(uuid).or_default();
+
+        let now = Instant::now();
+        while history
+            .front()
+            .is_some_and(|at| now.duration_since(*at) > cfg.restart_window)
+        {
+            history.pop_front();
+        }
+        if history.len() >= cfg.max_restarts {
+            return None;
+        }
+
+        let attempt = history.len() as u32;
+        history.push_back(now);
+        Some(
+            cfg.backoff_initial
+                .saturating_mul(2u32.saturating_pow(attempt))
+                .min(cfg.backoff_max),
+        )
+    }
+}
+
+/// Starts guarding every known server. Must run before any server is started.
+pub async fn init(state: Arc<AppState>) {
+    match db::server::get_all(&state.db_pool).await {
+        Ok(servers) => {
+            for server in servers {
+                watch(state.clone(), server.uuid).await;
+            }
+        }
+        Err(e) => error!(error = %e, "fetch servers for crash guard failed"),
+    }
+}
+
+/// Starts guarding a server, once.
+pub async fn watch(state: Arc<AppState>, uuid: Uuid) {
+    if state
+        .crashes
+        .tasks
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains_key(&uuid)
+    {
+        return;
+    }
+
+    let process = state.supervisor.watch(uuid).await;
+    let task = tokio::spawn(guard(state.clone(), uuid, process));
+
+    let mut tasks = state
+        .crashes
+        .tasks
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    match tasks.entry(uuid) {
+        Entry::Occupied(_) => task.abort(),
+        Entry::Vacant(slot) => {
+            slot.insert(task);
+        }
+    }
+}
+
+/// Stops guarding a deleted server.
+pub fn forget(state: &AppState, uuid: Uuid) {
+    if let Some(task) = state
+        .crashes
+        .tasks
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&uuid)
+    {
+        task.abort();
+    }
+    state
+        .crashes
+        .restarts
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&uuid);
+}
+
+async fn guard(state: Arc<AppState>, uuid: Uuid, mut process: watch::Receiver<ProcessState>) {
+    while process.changed().await.is_ok() {
+        let current = *process.borrow_and_update();
+        if current == ProcessState::Crashed {
+            handle_crash(&state, uuid).await;
+        }
+    }
+}
+
+async fn handle_crash(state: &Arc<AppState>, uuid: Uuid) {
+    let Some(exit) = state.supervisor.last_exit(uuid).await else {
+        return;
+    };
+    let cfg = &state.config.crash;
+
+    let server = match db::server::get_by_uuid(&state.db_pool, uuid).await {
+        Ok(Some(server)) => server,
+        Ok(None) => return,
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, "fetch crashed server failed");
+            return;
+        }
+    };
+
+    let delay = if cfg.auto_restart {
+        state.crashes.next_restart(uuid, cfg)
+    } else {
+        None
+    };
+
+    let scrollback = state.supervisor.scrollback(uuid).await;
+    let console = scrollback
+        .iter()
+        .skip(scrollback.len().saturating_sub(cfg.console_lines))
+        .map(|line| line.line.as_str())
+        .collect::<Vec<_>>()
+        .join("\n");
+
+    let report = latest_report(
+        &Path::new(&server.working_dir).join(CRASH_REPORTS_DIR),
+        exit.started_at,
+        cfg.max_report_bytes,
+    )
+    .await;
+
+    let record = CrashRecord {
+        uuid: Uuid::new_v4(),
+        server_uuid: uuid,
+        kind: exit.kind,
+        exit_code: exit.code,
+        signal: exit.signal,
+        report_file: report.as_ref().map(|(name, _)| name.clone()),
+        report: report.map(|(_, text)| text),
+        console,
+        restart_scheduled: delay.is_some(),
+        started_at: exit.started_at,
+        crashed_at: exit.exited_at,
+    };
+    if let Err(e) = db::crash::create(&state.db_pool, &record).await {
+        error!(error = %e, server_uuid = %uuid, "record crash failed");
+    }
+
+    match delay {
+        Some(delay) => {
+            warn!(
+                server_uuid = %uuid,
+                kind = record.kind.as_str(),
+                delay_secs = delay.as_secs(),
+                "server crashed, restart scheduled"
+            );
+            tokio::spawn(restart_later(state.clone(), uuid, delay));
+        }
+        None if cfg.auto_restart => error!(
+            server_uuid = %uuid,
+            kind = record.kind.as_str(),
+            "server crashed too often, not restarting"
+        ),
+        None => warn!(server_uuid = %uuid, kind = record.kind.as_str(), "server crashed"),
+    }
+}
+
+async fn restart_later(state: Arc<AppState>, uuid: Uuid, delay: Duration) {
+    sleep(delay).await;
+
+    // Someone started or deleted the server in the meantime
+    if state.supervisor.state(uuid).await != ProcessState::Crashed {
+        return;
+    }
+    let server = match db::server::get_by_uuid(&state.db_pool, uuid).await {
+        Ok(Some(server)) => server,
+        Ok(None) => return,
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, "fetch server for restart failed");
+            return;
+        }
+    };
+
+    match state.supervisor.start(&server).await {
+        Ok(()) => info!(server_uuid = %uuid, "crashed server restarted"),
+        Err(e) => error!(error = %e, server_uuid = %uuid, "restart crashed server failed"),
+    }
+}
+
+/// Newest crash report written since `since`, truncated to `max_bytes`.
+async fn latest_report(
+    dir: &Path,
+    since: DateTime<Utc>,
+    max_bytes: usize,
+) -> Option<(String, String)> {
+    let since = SystemTime::from(since);
+    let mut entries = fs::read_dir(dir).await.ok()?;
+
+    let mut newest: Option<(SystemTime, std::path::PathBuf)> = None;
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let Ok(modified) = entry.metadata().await.and_then(|m| m.modified()) else {
+            continue;
+        };
+        if modified >= since && newest.as_ref().is_none_or(|(at, _)| modified > *at) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+
+    let (_, path) = newest?;
+    let file = fs::File::open(&path).await.ok()?;
+    let mut bytes = Vec::new();
+    file.take(max_bytes as u64)
+        .read_to_end(&mut bytes)
+        .await
+        .ok()?;
+
+    let name = path.file_name()?.to_string_lossy().into_owned();
+    Some((name, String::from_utf8_lossy(&bytes).into_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cfg() -> CrashCfg {
+        CrashCfg {
+            backoff_initial: Duration::from_secs(5),
+            backoff_max: Duration::from_secs(60),
+            max_restarts: 10,
+            restart_window: Duration::from_secs(600),
+            ..CrashCfg::default()
+        }
+    }
+
+    #[test]
+    fn delay_doubles_up_to_the_cap() {
+        let guard = CrashGuard::new();
+        let uuid = Uuid::new_v4();
+        let delays: Vec<u64> = (0..6)
+            .map(|_| guard.next_restart(uuid, &cfg()).unwrap().as_secs())
+            .collect();
+        assert_eq!(delays, [5, 10, 20, 40, 60, 60]);
+
+        // Other servers keep their own count
+        assert_eq!(
+            guard.next_restart(Uuid::new_v4(), &cfg()),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn restarts_stop_once_the_window_is_used_up() {
+        let cfg = CrashCfg {
+            max_restarts: 2,
+            ..cfg()
+        };
+        let guard = CrashGuard::new();
+        let uuid = Uuid::new_v4();
+        assert!(guard.next_restart(uuid, &cfg).is_some());
+        assert!(guard.next_restart(uuid, &cfg).is_some());
+        assert_eq!(guard.next_restart(uuid, &cfg), None);
+        assert_eq!(guard.next_restart(uuid, &cfg), None);
+    }
+
+    #[test]
+    fn restarts_outside_the_window_are_forgotten() {
+        let cfg = CrashCfg {
+            max_restarts: 2,
+            ..cfg()
+        };
+        let guard = CrashGuard::new();
+        let uuid = Uuid::new_v4();
+        let Some(expired) = Instant::now().checked_sub(cfg.restart_window + Duration::from_secs(1))
+        else {
+            return;
+        };
+        guard
+            .restarts
+            .lock()
+            .unwrap()
+            .insert(uuid, VecDeque::from([expired, expired]));
+
+        assert_eq!(guard.next_restart(uuid, &cfg), Some(cfg.backoff_initial));
+        assert_eq!(
+            guard.next_restart(uuid, &cfg),
+            Some(cfg.backoff_initial * 2)
+        );
+        assert_eq!(guard.next_restart(uuid, &cfg), None);
+    }
+}
@@ -11,4 +11,11 @@ pub struct AuthClaims {
     pub exp: i64,
     pub iat: i64,
     pub username: String,
+    pub csrf: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SessionTokens {
+    pub jwt: String,
+    pub csrf: String,
 }
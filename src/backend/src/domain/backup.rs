@@ -0,0 +1,409 @@
+use std::{
+    cmp::Reverse,
+    collections::HashSet,
+    path::{Component, Path, PathBuf},
+};
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::domain::validation;
+
+/// Maps a time to the retention period (day, ISO week or month) it falls in.
+type PeriodOf = fn(&DateTime<Utc>) -> (i32, u32);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupScope {
+    /// The whole server directory.
+    #[default]
+    Full,
+    /// Only the world folders of the configured `level-name`.
+    Worlds,
+}
+
+/// How a backup is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupMode {
+    /// A self contained `tar.gz` archive.
+    #[default]
+    Archive,
+    /// A manifest over the server's content addressed chunk store, files and
+    /// region chunks unchanged since an earlier backup are stored only once.
+    Dedup,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupStatus {
+    Running,
+    Completed,
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Backup {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    #[sqlx(try_from = "String")]
+    pub scope: BackupScope,
+    #[sqlx(try_from = "String")]
+    pub mode: BackupMode,
+    #[sqlx(try_from = "String")]
+    pub status: BackupStatus,
+    /// Archive or manifest name inside the server's backup directory.
+    pub file_name: String,
+    /// Disk space the backup added, for dedup backups only the chunks it was
+    /// the first to store plus its manifest.
+    pub size_bytes: Option<i64>,
+    /// Hex SHA-256 of the archive or manifest.
+    pub sha256: Option<String>,
+    pub error: Option<String>,
+    pub created_by: Option<Uuid>,
+    pub started_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NewBackup {
+    #[serde(default)]
+    pub scope: BackupScope,
+    /// Falls back to the configured default mode.
+    pub mode: Option<BackupMode>,
+}
+
+/// Result of checking a backup against its recorded checksums.
+#[derive(Debug, Clone, Serialize)]
+pub struct BackupVerification {
+    pub backup_uuid: Uuid,
+    pub ok: bool,
+    /// Chunks read and hashed, zero for archives.
+    pub checked_chunks: u64,
+    /// Missing or corrupt files and chunks, capped at a hundred.
+    pub problems: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+#[validate(schema(function = "validate_restore"))]
+pub struct RestoreRequest {
+    /// Archive paths to restore, files or folders such as `world/DIM-1`.
+    /// Everything in the archive when absent.
+    pub paths: Option<Vec<String>>,
+    /// Restore into a new server instead of over the existing one.
+    #[validate(nested)]
+    pub new_server: Option<RestoreServer>,
+}
+
+/// The server a backup is restored into, every setting not listed here is
+/// copied from the server the backup was taken of.
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct RestoreServer {
+    #[validate(
+        length(min = 1, max = 32),
+        custom(function = "validation::validate_server_name")
+    )]
+    pub name: String,
+    #[validate(custom(function = "validation::validate_abs_path"))]
+    pub working_dir: String,
+    /// Also written to the restored `server.properties`.
+    #[validate(range(min = 1, max = 65535))]
+    pub server_port: Option<i32>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RestoreOutcome {
+    pub server_uuid: Uuid,
+    /// Where the replaced files were moved, absent when nothing was replaced.
+    pub snapshot_dir: Option<String>,
+    pub restored_entries: u64,
+    /// Whether the server was running before and started again.
+    pub restarted: bool,
+}
+
+fn validate_restore(request: &RestoreRequest) -> Result<(), ValidationError> {
+    let Some(paths) = &request.paths else {
+        return Ok(());
+    };
+    if paths.is_empty() || paths.len() > 64 || paths.iter().any(|p| restore_path(p).is_none()) {
+        return Err(ValidationError::new("restore_paths"));
+    }
+    Ok(())
+}
+
+/// A restore path as a plain relative path, `None` if it is empty or tries to
+/// leave the server directory.
+pub fn restore_path(path: &str) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+/// Which completed backups survive a new one. Each rule keeps the newest
+/// backup of its last N periods, a backup kept by any rule is kept. A policy
+/// of all zeros keeps everything.
+#[derive(Debug, Clone, Serialize, Deserialize, Validate, FromRow)]
+pub struct RetentionPolicy {
+    #[validate(range(min = 0, max = 1000))]
+    pub keep_last: i32,
+    #[validate(range(min = 0, max = 1000))]
+    pub keep_daily: i32,
+    #[validate(range(min = 0, max = 1000))]
+    pub keep_weekly: i32,
+    #[validate(range(min = 0, max = 1000))]
+    pub keep_monthly: i32,
+}
+
+impl RetentionPolicy {
+    pub fn keeps_everything(&self) -> bool {
+        self.keep_last == 0
+            && self.keep_daily == 0
+            && self.keep_weekly == 0
+            && self.keep_monthly == 0
+    }
+
+    /// Completed backups the policy drops. Running and failed ones are never
+    /// selected.
+    pub fn expired<'a>(&self, backups: &'a [Backup]) -> Vec<&'a Backup> {
+        if self.keeps_everything() {
+            return Vec::new();
+        }
+
+        let mut completed: Vec<&Backup> = backups
+            .iter()
+            .filter(|b| b.status == BackupStatus::Completed)
+            .collect();
+        completed.sort_by_key(|b| Reverse(b.started_at));
+
+        let mut kept: HashSet<Uuid> = completed
+            .iter()
+            .take(self.keep_last.max(0) as usize)
+            .map(|b| b.uuid)
+            .collect();
+
+        let periods: [(i32, PeriodOf); 3] = [
+            (self.keep_daily, |at| (at.year(), at.ordinal())),
+            (self.keep_weekly, |at| {
+                let week = at.iso_week();
+                (week.year(), week.week())
+            }),
+            (self.keep_monthly, |at| (at.year(), at.month())),
+        ];
+        for (limit, period_of) in periods {
+            let mut last = None;
+            let mut count = 0;
+            for backup in &completed {
+                if count >= limit {
+                    break;
+                }
+                let period = period_of(&backup.started_at);
+                if last != Some(period) {
+                    kept.insert(backup.uuid);
+                    last = Some(period);
+                    count += 1;
+                }
+            }
+        }
+
+        completed
+            .into_iter()
+            .filter(|b| !kept.contains(&b.uuid))
+            .collect()
+    }
+}
+
+impl BackupScope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupScope::Full => "full",
+            BackupScope::Worlds => "worlds",
+        }
+    }
+}
+
+impl TryFrom<String> for BackupScope {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "full" => Ok(BackupScope::Full),
+            "worlds" => Ok(BackupScope::Worlds),
+            other => Err(format!("unknown backup scope {other}")),
+        }
+    }
+}
+
+impl BackupMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupMode::Archive => "archive",
+            BackupMode::Dedup => "dedup",
+        }
+    }
+}
+
+impl TryFrom<String> for BackupMode {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "archive" => Ok(BackupMode::Archive),
+            "dedup" => Ok(BackupMode::Dedup),
+            other => Err(format!("unknown backup mode {other}")),
+        }
+    }
+}
+
+impl BackupStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BackupStatus::Running => "running",
+            BackupStatus::Completed => "completed",
+            BackupStatus::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for BackupStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "running" => Ok(BackupStatus::Running),
+            "completed" => Ok(BackupStatus::Completed),
+            "failed" => Ok(BackupStatus::Failed),
+            other => Err(format!("unknown backup status {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn policy(
+        keep_last: i32,
+        keep_daily: i32,
+        keep_weekly: i32,
+        keep_monthly: i32,
+    ) -> RetentionPolicy {
+        RetentionPolicy {
+            keep_last,
+            keep_daily,
+            keep_weekly,
+            keep_monthly,
+        }
+    }
+
+    fn backup(month: u32, day: u32, hour: u32, status: BackupStatus) -> Backup {
+        Backup {
+            uuid: Uuid::new_v4(),
+            server_uuid: Uuid::nil(),
+            scope: BackupScope::Full,
+            mode: BackupMode::Archive,
+            status,
+            file_name: format!("{month:02}-{day:02}-{hour:02}.tar.gz"),
+            size_bytes: Some(1),
+            sha256: None,
+            error: None,
+            created_by: None,
+            started_at: Utc.with_ymd_and_hms(2024, month, day, hour, 0, 0).unwrap(),
+            completed_at: None,
+        }
+    }
+
+    /// Two completed backups a day, 1 May to 20 June 2024, in no particular order.
+    fn history() -> Vec<Backup> {
+        let mut backups = Vec::new();
+        for (month, days) in [(5, 31), (6, 20)] {
+            for day in 1..=days {
+                backups.push(backup(month, day, 18, BackupStatus::Completed));
+                backups.push(backup(month, day, 6, BackupStatus::Completed));
+            }
+        }
+        backups.reverse();
+        backups
+    }
+
+    fn kept<'a>(policy: &RetentionPolicy, backups: &'a [Backup]) -> Vec<&'a str> {
+        let expired: HashSet<Uuid> = policy.expired(backups).iter().map(|b| b.uuid).collect();
+        let mut kept: Vec<&Backup> = backups
+            .iter()
+            .filter(|b| b.status == BackupStatus::Completed && !expired.contains(&b.uuid))
+            .collect();
+        kept.sort_by_key(|b| Reverse(b.started_at));
+        kept.iter().map(|b| b.file_name.as_str()).collect()
+    }
+
+    #[test]
+    fn all_zero_policy_keeps_everything() {
+        assert!(policy(0, 0, 0, 0).expired(&history()).is_empty());
+    }
+
+    #[test]
+    fn keep_last_keeps_the_newest() {
+        let backups = history();
+        assert_eq!(
+            kept(&policy(3, 0, 0, 0), &backups),
+            ["06-20-18.tar.gz", "06-20-06.tar.gz", "06-19-18.tar.gz"]
+        );
+        assert_eq!(
+            policy(3, 0, 0, 0).expired(&backups).len(),
+            backups.len() - 3
+        );
+    }
+
+    #[test]
+    fn periods_keep_their_newest_backup() {
+        let backups = history();
+        assert_eq!(
+            kept(&policy(0, 2, 0, 0), &backups),
+            ["06-20-18.tar.gz", "06-19-18.tar.gz"]
+        );
+        // 20 June 2024 is a Thursday, the weeks before start on 10 and 3 June
+        assert_eq!(
+            kept(&policy(0, 0, 3, 0), &backups),
+            ["06-20-18.tar.gz", "06-16-18.tar.gz", "06-09-18.tar.gz"]
+        );
+        assert_eq!(
+            kept(&policy(0, 0, 0, 5), &backups),
+            ["06-20-18.tar.gz", "05-31-18.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn a_backup_kept_by_any_rule_survives() {
+        let backups = history();
+        assert_eq!(
+            kept(&policy(1, 2, 0, 2), &backups),
+            ["06-20-18.tar.gz", "06-19-18.tar.gz", "05-31-18.tar.gz"]
+        );
+        assert_eq!(
+            kept(&policy(3, 2, 0, 0), &backups),
+            ["06-20-18.tar.gz", "06-20-06.tar.gz", "06-19-18.tar.gz"]
+        );
+    }
+
+    #[test]
+    fn running_and_failed_backups_are_never_selected_or_counted() {
+        let backups = vec![
+            backup(6, 21, 6, BackupStatus::Running),
+            backup(6, 20, 18, BackupStatus::Failed),
+            backup(6, 20, 6, BackupStatus::Completed),
+            backup(6, 19, 6, BackupStatus::Completed),
+        ];
+        let expired = policy(1, 0, 0, 0).expired(&backups);
+        assert_eq!(expired.len(), 1);
+        assert_eq!(expired[0].file_name, "06-19-06.tar.gz");
+    }
+}
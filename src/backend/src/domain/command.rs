@@ -0,0 +1,348 @@
+use std::fmt::Display;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::domain::{user::InternalUser, user_prems::UserActions};
+
+/// Matches every command verb.
+pub const WILDCARD_VERB: &str = "*";
+
+/// The one command running others, after its `run` subcommand.
+const EXECUTE_VERB: &str = "execute";
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CommandRequest {
+    #[validate(length(min = 1, max = 1024), custom(function = "validate_single_line"))]
+    pub command: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct HistoryQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CommandSource {
+    Api,
+    Console,
+}
+
+/// Allows or denies a command verb. A rule targets either one user, every
+/// holder of a permission (a role), or everyone when both are empty, and is
+/// scoped to one server or to all of them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommandRule {
+    pub uuid: Uuid,
+    pub server_uuid: Option<Uuid>,
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<UserActions>,
+    pub verb: String,
+    pub allow: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_rule_subject"))]
+pub struct NewCommandRule {
+    pub server_uuid: Option<Uuid>,
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<UserActions>,
+    #[validate(length(min = 1, max = 64))]
+    pub verb: String,
+    pub allow: bool,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct CommandRuleRow {
+    pub uuid: Uuid,
+    pub server_uuid: Option<Uuid>,
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<Json<UserActions>>,
+    pub verb: String,
+    pub allow: bool,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CommandEntry {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub user_uuid: Uuid,
+    pub username: String,
+    pub command: String,
+    #[sqlx(try_from = "String")]
+    pub source: CommandSource,
+    pub allowed: bool,
+    pub executed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone)]
+pub struct NewCommandEntry {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub user_uuid: Uuid,
+    pub command: String,
+    pub source: CommandSource,
+    pub allowed: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandOutcome {
+    pub command: String,
+    pub verb: String,
+    pub allowed: bool,
+    /// Command output, only available when the command went through RCON.
+    pub response: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct UnknownCommandSource(String);
+
+pub fn validate_single_line(input: &str) -> Result<(), ValidationError> {
+    // A newline would smuggle a second command past the rules
+    if input.contains(['\n', '\r']) {
+        Err(ValidationError::new("single_line"))
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_rule_subject(rule: &NewCommandRule) -> Result<(), ValidationError> {
+    if rule.user_uuid.is_some() && rule.action.is_some() {
+        Err(ValidationError::new("rule_subject"))
+    } else {
+        Ok(())
+    }
+}
+
+/// Extracts the verb rules are matched on, `/Minecraft:OP steve` becomes `op`
+/// so namespaced aliases cannot bypass a rule.
+pub fn command_verb(command: &str) -> String {
+    let first = command
+        .trim()
+        .trim_start_matches('/')
+        .split_whitespace()
+        .next()
+        .unwrap_or_default();
+    normalize_verb(first)
+}
+
+/// Every verb a command runs, the first one and those `execute … run` nests,
+/// `execute as @s run op steve` yields `execute` and `op`. A command is only
+/// allowed if each of them is.
+pub fn command_verbs(command: &str) -> Vec<String> {
+    let mut words = command
+        .trim()
+        .trim_start_matches('/')
+        .split_whitespace()
+        .map(normalize_verb);
+    let mut verbs = Vec::new();
+    let mut verb = words.next().unwrap_or_default();
+    loop {
+        let nests = verb == EXECUTE_VERB;
+        verbs.push(verb);
+        if !nests {
+            return verbs;
+        }
+        // Subcommands of execute come before `run`, none of them runs anything
+        if !words.any(|word| word == "run") {
+            return verbs;
+        }
+        verb = words.next().unwrap_or_default();
+    }
+}
+
+fn normalize_verb(word: &str) -> String {
+    let verb = word.rsplit(':').next().unwrap_or(word);
+    verb.to_lowercase()
+}
+
+impl CommandSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CommandSource::Api => "api",
+            CommandSource::Console => "console",
+        }
+    }
+}
+
+impl TryFrom<String> for CommandSource {
+    type Error = UnknownCommandSource;
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "api" => Ok(CommandSource::Api),
+            "console" => Ok(CommandSource::Console),
+            _ => Err(UnknownCommandSource(value)),
+        }
+    }
+}
+
+impl Display for UnknownCommandSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "unknown command source: {}", self.0)
+    }
+}
+
+impl std::error::Error for UnknownCommandSource {}
+
+impl From<CommandRuleRow> for CommandRule {
+    fn from(value: CommandRuleRow) -> Self {
+        Self {
+            uuid: value.uuid,
+            server_uuid: value.server_uuid,
+            user_uuid: value.user_uuid,
+            action: value.action.map(|a| a.0),
+            verb: value.verb,
+            allow: value.allow,
+        }
+    }
+}
+
+impl NewCommandRule {
+    pub fn into_rule(self) -> CommandRule {
+        CommandRule {
+            uuid: Uuid::new_v4(),
+            server_uuid: self.server_uuid,
+            user_uuid: self.user_uuid,
+            action: self.action,
+            verb: command_verb(&self.verb),
+            allow: self.allow,
+        }
+    }
+}
+
+impl CommandRule {
+    /// How specific the rule is for the user, `None` if it does not apply at all.
+    fn specificity(&self, user: &InternalUser, server_uuid: Uuid) -> Option<u8> {
+        let subject = match (self.user_uuid, self.action) {
+            (Some(uuid), _) if uuid == user.uuid => 2,
+            (Some(_), _) => return None,
+            (None, Some(action)) if user.permissions.permissions.contains(&action) => 1,
+            (None, Some(_)) => return None,
+            (None, None) => 0,
+        };
+        let scope = match self.server_uuid {
+            Some(uuid) if uuid == server_uuid => 1,
+            Some(_) => return None,
+            None => 0,
+        };
+        Some(subject * 2 + scope)
+    }
+}
+
+/// Decides whether `user` may run `verb` on the server. The most specific
+/// matching rule wins (user over role over everyone, then server over global),
+/// a deny wins a tie and a verb no rule mentions is allowed. Root is never
+/// restricted. An allowlist is built with a `*` deny plus specific allows.
+pub fn command_allowed(
+    rules: &[CommandRule],
+    user: &InternalUser,
+    server_uuid: Uuid,
+    verb: &str,
+) -> bool {
+    if user.permissions.root {
+        return true;
+    }
+
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let specificity = rule.specificity(user, server_uuid)?;
+            let verb_rank = match rule.verb.as_str() {
+                v if v == verb => 1,
+                WILDCARD_VERB => 0,
+                _ => return None,
+            };
+            // Exact verbs beat the wildcard at the same specificity, denies beat allows
+            Some(((specificity, verb_rank, !rule.allow), rule.allow))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, allow)| allow)
+        .unwrap_or(true)
+}
+
+/// The first verb of `command` the rules deny `user`, checking the commands
+/// nested in `execute` too. `None` if the whole command is allowed.
+pub fn denied_verb(
+    rules: &[CommandRule],
+    user: &InternalUser,
+    server_uuid: Uuid,
+    command: &str,
+) -> Option<String> {
+    command_verbs(command)
+        .into_iter()
+        .find(|verb| !command_allowed(rules, user, server_uuid, verb))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::user_prems::UserPermissions;
+
+    fn moderator() -> InternalUser {
+        InternalUser {
+            uuid: Uuid::new_v4(),
+            username: "moderator".to_string(),
+            email: None,
+            password_hash: String::new(),
+            first_name: None,
+            last_name: None,
+            permissions: UserPermissions::default(),
+        }
+    }
+
+    fn deny(verb: &str) -> CommandRule {
+        CommandRule {
+            uuid: Uuid::new_v4(),
+            server_uuid: None,
+            user_uuid: None,
+            action: None,
+            verb: verb.to_string(),
+            allow: false,
+        }
+    }
+
+    #[test]
+    fn verb_ignores_slash_namespace_and_case() {
+        assert_eq!(command_verb("/Minecraft:OP steve"), "op");
+        assert_eq!(command_verb("  say hi"), "say");
+        assert_eq!(command_verb(""), "");
+    }
+
+    #[test]
+    fn verbs_follow_execute_run() {
+        assert_eq!(command_verbs("say run away"), ["say"]);
+        assert_eq!(
+            command_verbs("execute as @s run op Mallory"),
+            ["execute", "op"]
+        );
+        assert_eq!(
+            command_verbs("/execute at @p run minecraft:execute as @s RUN stop"),
+            ["execute", "execute", "stop"]
+        );
+        assert_eq!(command_verbs("execute if entity @p"), ["execute"]);
+    }
+
+    #[test]
+    fn nested_denied_verb_denies_the_command() {
+        let user = moderator();
+        let server = Uuid::new_v4();
+        let rules = [deny("op"), deny("stop")];
+
+        assert_eq!(
+            denied_verb(&rules, &user, server, "execute as @s run op Mallory"),
+            Some("op".to_string())
+        );
+        assert_eq!(
+            denied_verb(&rules, &user, server, "execute run stop"),
+            Some("stop".to_string())
+        );
+        assert_eq!(
+            denied_verb(&rules, &user, server, "execute as @s run say hi"),
+            None
+        );
+    }
+}
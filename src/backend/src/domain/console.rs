@@ -0,0 +1,52 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::domain::command::CommandOutcome;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConsoleStream {
+    Stdout,
+    Stderr,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ConsoleLine {
+    pub stream: ConsoleStream,
+    pub line: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Frames sent to console WebSocket clients.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ConsoleEvent {
+    Line(ConsoleLine),
+    /// The client fell behind and this many lines were dropped.
+    Lagged {
+        skipped: u64,
+    },
+    /// Result of a command sent by this client.
+    Command(CommandOutcome),
+    Error {
+        status: u16,
+        message: String,
+    },
+}
+
+/// Frames console WebSocket clients may send.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ConsoleInput {
+    Command { command: String },
+}
+
+impl ConsoleLine {
+    pub fn new(stream: ConsoleStream, line: impl Into<String>) -> Self {
+        Self {
+            stream,
+            line: line.into(),
+            timestamp: Utc::now(),
+        }
+    }
+}
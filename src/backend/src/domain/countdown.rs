@@ -0,0 +1,199 @@
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::domain::{command::validate_single_line, player_lists::single_line};
+
+/// Longest countdown accepted, one day.
+pub const MAX_COUNTDOWN_SECS: u32 = 86_400;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ShutdownMode {
+    Stop,
+    Restart,
+}
+
+/// How countdown messages are shown in game.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AnnounceStyle {
+    /// `say`, prefixed with `[Server]` in chat.
+    Say,
+    /// `tellraw @a`, shown as is.
+    Tellraw,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CountdownRequest {
+    pub mode: ShutdownMode,
+    #[validate(range(max = MAX_COUNTDOWN_SECS))]
+    pub delay_secs: u32,
+    /// Seconds before the end at which players are warned.
+    #[validate(length(max = 32))]
+    pub announce_at: Option<Vec<u32>>,
+    /// Message template, `{action}` and `{time}` are filled in.
+    #[validate(length(min = 1, max = 256), custom(function = "validate_single_line"))]
+    pub message: Option<String>,
+    pub style: Option<AnnounceStyle>,
+}
+
+/// A running countdown to a stop or restart.
+#[derive(Debug, Clone, Serialize)]
+pub struct Countdown {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub mode: ShutdownMode,
+    pub started_at: DateTime<Utc>,
+    pub ends_at: DateTime<Utc>,
+    /// Remaining warnings, in seconds before the end, largest first.
+    pub announce_at: Vec<u32>,
+    pub message: String,
+    pub style: AnnounceStyle,
+}
+
+/// How a countdown ended.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CountdownEnd {
+    Completed,
+    Cancelled,
+    Failed(String),
+}
+
+impl ShutdownMode {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ShutdownMode::Stop => "stop",
+            ShutdownMode::Restart => "restart",
+        }
+    }
+
+    /// Word used for `{action}` in announcements.
+    pub fn verb(&self) -> &'static str {
+        match self {
+            ShutdownMode::Stop => "stopping",
+            ShutdownMode::Restart => "restarting",
+        }
+    }
+}
+
+impl AnnounceStyle {
+    /// Console command broadcasting `text` to every player.
+    pub fn command(&self, text: &str) -> String {
+        let text = single_line(text);
+        match self {
+            AnnounceStyle::Say => format!("say {text}"),
+            AnnounceStyle::Tellraw => {
+                format!("tellraw @a {}", json!({ "text": text, "color": "gold" }))
+            }
+        }
+    }
+}
+
+impl Countdown {
+    pub fn new(
+        server_uuid: Uuid,
+        mode: ShutdownMode,
+        delay_secs: u32,
+        announce_at: &[u32],
+        message: String,
+        style: AnnounceStyle,
+    ) -> Self {
+        let started_at = Utc::now();
+        Self {
+            uuid: Uuid::new_v4(),
+            server_uuid,
+            mode,
+            started_at,
+            ends_at: started_at + TimeDelta::seconds(i64::from(delay_secs)),
+            announce_at: announce_points(delay_secs, announce_at),
+            message,
+            style,
+        }
+    }
+
+    pub fn announcement(&self, remaining_secs: u32) -> String {
+        self.message
+            .replace("{action}", self.mode.verb())
+            .replace("{time}", &describe_secs(remaining_secs))
+    }
+}
+
+/// Warning points that fit in a countdown of `delay_secs`, largest first and
+/// without duplicates. The countdown length itself is always announced.
+pub fn announce_points(delay_secs: u32, announce_at: &[u32]) -> Vec<u32> {
+    let mut points: Vec<u32> = announce_at
+        .iter()
+        .copied()
+        .filter(|secs| *secs > 0 && *secs < delay_secs)
+        .collect();
+    if delay_secs > 0 {
+        points.push(delay_secs);
+    }
+    points.sort_unstable_by(|a, b| b.cmp(a));
+    points.dedup();
+    points
+}
+
+/// `300` reads `5 minutes`, `90` reads `90 seconds`.
+pub fn describe_secs(secs: u32) -> String {
+    let (count, unit) = match secs {
+        s if s >= 3600 && s % 3600 == 0 => (s / 3600, "hour"),
+        s if s >= 60 && s % 60 == 0 => (s / 60, "minute"),
+        s => (s, "second"),
+    };
+    if count == 1 {
+        format!("1 {unit}")
+    } else {
+        format!("{count} {unit}s")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn announce_points_are_deduplicated_largest_first() {
+        assert_eq!(
+            announce_points(300, &[10, 60, 10, 30, 60]),
+            [300, 60, 30, 10]
+        );
+        assert_eq!(announce_points(60, &[60, 5, 30]), [60, 30, 5]);
+    }
+
+    #[test]
+    fn announce_points_outside_the_countdown_are_dropped() {
+        assert_eq!(announce_points(30, &[0, 60, 30, 300, 10]), [30, 10]);
+        assert!(announce_points(0, &[0, 10, 60]).is_empty());
+        assert_eq!(announce_points(1, &[]), [1]);
+    }
+
+    #[test]
+    fn describe_secs_picks_the_largest_whole_unit() {
+        assert_eq!(describe_secs(1), "1 second");
+        assert_eq!(describe_secs(0), "0 seconds");
+        assert_eq!(describe_secs(90), "90 seconds");
+        assert_eq!(describe_secs(60), "1 minute");
+        assert_eq!(describe_secs(300), "5 minutes");
+        assert_eq!(describe_secs(5400), "90 minutes");
+        assert_eq!(describe_secs(3600), "1 hour");
+        assert_eq!(describe_secs(86_400), "24 hours");
+    }
+
+    #[test]
+    fn announcement_fills_in_action_and_time() {
+        let countdown = Countdown::new(
+            Uuid::new_v4(),
+            ShutdownMode::Restart,
+            120,
+            &[60],
+            "Server {action} in {time}".to_string(),
+            AnnounceStyle::Say,
+        );
+        assert_eq!(countdown.announce_at, [120, 60]);
+        assert_eq!(countdown.announcement(60), "Server restarting in 1 minute");
+    }
+}
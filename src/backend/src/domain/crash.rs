@@ -0,0 +1,88 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+/// Why a server process exited.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExitKind {
+    /// Asked to stop, or exited with status 0.
+    Clean,
+    Crash,
+    /// The JVM reported an `OutOfMemoryError`, or the process was SIGKILLed
+    /// without the daemon asking, which is what the kernel OOM killer does.
+    OutOfMemory,
+}
+
+impl ExitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ExitKind::Clean => "clean",
+            ExitKind::Crash => "crash",
+            ExitKind::OutOfMemory => "out_of_memory",
+        }
+    }
+}
+
+impl TryFrom<String> for ExitKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "clean" => Ok(ExitKind::Clean),
+            "crash" => Ok(ExitKind::Crash),
+            "out_of_memory" => Ok(ExitKind::OutOfMemory),
+            other => Err(format!("unknown exit kind {other}")),
+        }
+    }
+}
+
+/// How the last run of a server process ended.
+#[derive(Debug, Clone, Serialize)]
+pub struct ProcessExit {
+    pub kind: ExitKind,
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    pub started_at: DateTime<Utc>,
+    pub exited_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CrashRecord {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    #[sqlx(try_from = "String")]
+    pub kind: ExitKind,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    /// File name inside `crash-reports/` written by this run, if any.
+    pub report_file: Option<String>,
+    pub report: Option<String>,
+    /// Console output right before the exit.
+    pub console: String,
+    /// Whether an automatic restart was scheduled.
+    pub restart_scheduled: bool,
+    pub started_at: DateTime<Utc>,
+    pub crashed_at: DateTime<Utc>,
+}
+
+/// Crash record without the report and console bodies.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct CrashSummary {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    #[sqlx(try_from = "String")]
+    pub kind: ExitKind,
+    pub exit_code: Option<i32>,
+    pub signal: Option<i32>,
+    pub report_file: Option<String>,
+    pub restart_scheduled: bool,
+    pub started_at: DateTime<Utc>,
+    pub crashed_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct CrashQuery {
+    pub limit: Option<i64>,
+}
@@ -0,0 +1,252 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::domain::validation;
+
+fn default_ssh_port() -> u16 {
+    22
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Where a destination keeps its copies. Secrets are sent next to the config
+/// and never returned.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum DestinationConfig {
+    /// A directory on the daemon host.
+    Local { path: String },
+    /// A directory that must be a mount point, so an unmounted share fails the
+    /// copy instead of filling the local disk.
+    Mounted { path: String },
+    /// Uploads with the OpenSSH `sftp` client and key authentication. Host
+    /// keys are checked against `known_hosts_file`, or trusted on first use
+    /// when it is not set.
+    Sftp {
+        host: String,
+        #[serde(default = "default_ssh_port")]
+        port: u16,
+        username: String,
+        identity_file: String,
+        remote_dir: String,
+        known_hosts_file: Option<String>,
+    },
+    /// S3 or a compatible object store such as MinIO. The secret key is the
+    /// destination secret.
+    S3 {
+        /// Base URL, e.g. `https://s3.eu-central-1.amazonaws.com`.
+        endpoint: String,
+        region: String,
+        bucket: String,
+        /// Prepended to every object key.
+        #[serde(default)]
+        prefix: String,
+        access_key_id: String,
+        /// `endpoint/bucket/key` rather than `bucket.endpoint/key`.
+        #[serde(default = "default_true")]
+        path_style: bool,
+    },
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BackupDestination {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub name: String,
+    #[sqlx(json)]
+    pub config: DestinationConfig,
+    /// Sealed with the daemon secret, never leaves the daemon.
+    #[serde(skip)]
+    pub secret: Option<Vec<u8>>,
+    /// Encrypt copies with the configured backup key before they leave.
+    pub encrypt: bool,
+    pub enabled: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_new_destination"))]
+pub struct NewDestination {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    pub config: DestinationConfig,
+    #[validate(length(min = 1, max = 256))]
+    pub secret: Option<String>,
+    #[serde(default)]
+    pub encrypt: bool,
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateDestination {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    #[validate(length(min = 1, max = 256))]
+    pub secret: Option<String>,
+    pub encrypt: Option<bool>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CopyStatus {
+    Uploading,
+    Uploaded,
+    Failed,
+}
+
+/// A backup archive copied to a destination.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct BackupCopy {
+    pub backup_uuid: Uuid,
+    pub destination_uuid: Uuid,
+    #[sqlx(try_from = "String")]
+    pub status: CopyStatus,
+    /// Object or file name at the destination.
+    pub remote_name: String,
+    pub encrypted: bool,
+    pub attempts: i32,
+    pub error: Option<String>,
+    pub updated_at: DateTime<Utc>,
+}
+
+fn validate_new_destination(destination: &NewDestination) -> Result<(), ValidationError> {
+    destination.config.validate_config()?;
+    if matches!(destination.config, DestinationConfig::S3 { .. }) && destination.secret.is_none() {
+        return Err(ValidationError::new("destination_secret"));
+    }
+    Ok(())
+}
+
+/// Rejects text that would break out of a quoted `sftp` batch argument.
+fn validate_batch_safe(input: &str) -> Result<(), ValidationError> {
+    if input.is_empty() || input.contains(['"', '\n', '\r']) {
+        Err(ValidationError::new("sftp_argument"))
+    } else {
+        Ok(())
+    }
+}
+
+fn validate_ssh_word(input: &str) -> Result<(), ValidationError> {
+    // Would otherwise be read as an option or split the destination
+    if input.is_empty()
+        || input.starts_with('-')
+        || input.contains(|c: char| c.is_whitespace() || c == '@')
+    {
+        Err(ValidationError::new("ssh_argument"))
+    } else {
+        Ok(())
+    }
+}
+
+impl DestinationConfig {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            DestinationConfig::Local { .. } => "local",
+            DestinationConfig::Mounted { .. } => "mounted",
+            DestinationConfig::Sftp { .. } => "sftp",
+            DestinationConfig::S3 { .. } => "s3",
+        }
+    }
+
+    fn validate_config(&self) -> Result<(), ValidationError> {
+        match self {
+            DestinationConfig::Local { path } | DestinationConfig::Mounted { path } => {
+                validation::validate_abs_path(path)
+            }
+            DestinationConfig::Sftp {
+                host,
+                port,
+                username,
+                identity_file,
+                remote_dir,
+                known_hosts_file,
+            } => {
+                validate_ssh_word(host)?;
+                validate_ssh_word(username)?;
+                validation::validate_abs_path(identity_file)?;
+                validate_batch_safe(remote_dir)?;
+                if let Some(file) = known_hosts_file {
+                    validation::validate_abs_path(file)?;
+                }
+                if *port == 0 {
+                    return Err(ValidationError::new("port"));
+                }
+                Ok(())
+            }
+            DestinationConfig::S3 {
+                endpoint,
+                region,
+                bucket,
+                access_key_id,
+                ..
+            } => {
+                let valid_bucket = (3..=63).contains(&bucket.len())
+                    && bucket.chars().all(|c| {
+                        c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-'
+                    });
+                if !(endpoint.starts_with("https://") || endpoint.starts_with("http://"))
+                    || region.is_empty()
+                    || access_key_id.is_empty()
+                    || !valid_bucket
+                {
+                    return Err(ValidationError::new("s3_destination"));
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl BackupDestination {
+    /// Applies a partial update, the secret is sealed by the caller.
+    pub fn apply(&mut self, update: UpdateDestination) {
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(encrypt) = update.encrypt {
+            self.encrypt = encrypt;
+        }
+        if let Some(enabled) = update.enabled {
+            self.enabled = enabled;
+        }
+    }
+}
+
+/// Name a backup file is stored under at a destination.
+pub fn remote_name(file_name: &str, encrypted: bool) -> String {
+    if encrypted {
+        format!("{file_name}.enc")
+    } else {
+        file_name.to_string()
+    }
+}
+
+impl CopyStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CopyStatus::Uploading => "uploading",
+            CopyStatus::Uploaded => "uploaded",
+            CopyStatus::Failed => "failed",
+        }
+    }
+}
+
+impl TryFrom<String> for CopyStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "uploading" => Ok(CopyStatus::Uploading),
+            "uploaded" => Ok(CopyStatus::Uploaded),
+            "failed" => Ok(CopyStatus::Failed),
+            other => Err(format!("unknown copy status {other}")),
+        }
+    }
+}
@@ -0,0 +1,549 @@
+use std::path::{Component, Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::domain::{user::InternalUser, user_prems::UserActions};
+
+/// What a user may do below a path, from least to most.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileAccess {
+    None,
+    Read,
+    Write,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FileKind {
+    File,
+    Directory,
+    Symlink,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileEntry {
+    pub name: String,
+    /// Relative to the server directory, `/` separated.
+    pub path: String,
+    pub kind: FileKind,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// What the requesting user may do with the entry.
+    pub access: FileAccess,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct DirListing {
+    pub path: String,
+    pub entries: Vec<FileEntry>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileContent {
+    pub path: String,
+    pub size: u64,
+    pub modified: Option<DateTime<Utc>>,
+    /// Binary files are not returned, they can be downloaded instead.
+    pub binary: bool,
+    pub content: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct PathQuery {
+    /// Defaults to the server directory itself.
+    #[serde(default)]
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DeleteQuery {
+    pub path: String,
+    /// Delete directories with everything in them.
+    #[serde(default)]
+    pub recursive: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct UploadQuery {
+    /// Directory the uploaded files are written to.
+    #[serde(default)]
+    pub path: String,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct WriteFile {
+    #[validate(custom(function = "validate_target_path"))]
+    pub path: String,
+    pub content: String,
+    /// Modification time the client read the file at, the write is refused
+    /// if the file changed since.
+    pub expected_modified: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct RenameRequest {
+    #[validate(custom(function = "validate_target_path"))]
+    pub from: String,
+    #[validate(custom(function = "validate_target_path"))]
+    pub to: String,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NewDirectory {
+    #[validate(custom(function = "validate_target_path"))]
+    pub path: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadedFile {
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ArchiveFormat {
+    Zip,
+    TarGz,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct CompressRequest {
+    #[validate(length(min = 1, max = 256), custom(function = "validate_target_paths"))]
+    pub paths: Vec<String>,
+    #[validate(length(max = 1024), custom(function = "validate_target_path"))]
+    pub destination: String,
+    /// Taken from the destination's extension when not set.
+    pub format: Option<ArchiveFormat>,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct ExtractRequest {
+    #[validate(length(max = 1024), custom(function = "validate_target_path"))]
+    pub path: String,
+    /// Directory to extract into, the archive's own directory when not set.
+    #[validate(length(max = 1024), custom(function = "validate_file_path"))]
+    pub destination: Option<String>,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+/// What a compress or extract job did, recorded as its result.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ArchiveSummary {
+    /// The archive written or the directory extracted into.
+    pub path: String,
+    pub entries: u64,
+    /// Symlinks and special files, which are never archived or extracted.
+    pub skipped: u64,
+    /// Size of the archive written, or of the files extracted.
+    pub size_bytes: u64,
+}
+
+/// Narrows file access on one server. A rule targets one user, every holder
+/// of a permission or everyone, and covers its path and everything below.
+/// Rules never grant more than the user's `ReadFiles` or `ManageFiles`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileRule {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<UserActions>,
+    /// Empty for the whole server directory.
+    pub path: String,
+    pub access: FileAccess,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_rule_subject"))]
+pub struct NewFileRule {
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<UserActions>,
+    #[serde(default)]
+    #[validate(length(max = 1024), custom(function = "validate_file_path"))]
+    pub path: String,
+    pub access: FileAccess,
+}
+
+#[derive(Debug, Clone, FromRow)]
+pub struct FileRuleRow {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub user_uuid: Option<Uuid>,
+    pub action: Option<Json<UserActions>>,
+    pub path: String,
+    #[sqlx(try_from = "String")]
+    pub access: FileAccess,
+}
+
+/// A resumable upload. Chunks are appended at the announced offset until
+/// `size` bytes arrived, then the file is moved into place.
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct FileUpload {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    #[serde(skip)]
+    pub user_uuid: Uuid,
+    pub path: String,
+    pub size: i64,
+    pub overwrite: bool,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NewUpload {
+    #[validate(length(max = 1024), custom(function = "validate_target_path"))]
+    pub path: String,
+    #[validate(range(min = 0))]
+    pub size: i64,
+    #[serde(default)]
+    pub overwrite: bool,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UploadStatus {
+    #[serde(flatten)]
+    pub upload: FileUpload,
+    /// Bytes received so far, the offset the next chunk starts at.
+    pub received: i64,
+    pub completed: bool,
+}
+
+impl FileAccess {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FileAccess::None => "none",
+            FileAccess::Read => "read",
+            FileAccess::Write => "write",
+        }
+    }
+}
+
+impl TryFrom<String> for FileAccess {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "none" => Ok(FileAccess::None),
+            "read" => Ok(FileAccess::Read),
+            "write" => Ok(FileAccess::Write),
+            other => Err(format!("unknown file access {other}")),
+        }
+    }
+}
+
+impl ArchiveFormat {
+    /// The format a file name's extension calls for.
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_ascii_lowercase();
+        if name.ends_with(".zip") {
+            Some(ArchiveFormat::Zip)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(ArchiveFormat::TarGz)
+        } else {
+            None
+        }
+    }
+}
+
+impl From<FileRuleRow> for FileRule {
+    fn from(value: FileRuleRow) -> Self {
+        Self {
+            uuid: value.uuid,
+            server_uuid: value.server_uuid,
+            user_uuid: value.user_uuid,
+            action: value.action.map(|a| a.0),
+            path: value.path,
+            access: value.access,
+        }
+    }
+}
+
+impl NewFileRule {
+    pub fn into_rule(self, server_uuid: Uuid) -> FileRule {
+        FileRule {
+            uuid: Uuid::new_v4(),
+            server_uuid,
+            user_uuid: self.user_uuid,
+            action: self.action,
+            path: file_path(&self.path)
+                .map(|path| display_path(&path))
+                .unwrap_or_default(),
+            access: self.access,
+        }
+    }
+}
+
+impl FileRule {
+    /// How specific the rule's subject is for the user, `None` if it does not
+    /// apply to them.
+    fn specificity(&self, user: &InternalUser) -> Option<u8> {
+        match (self.user_uuid, self.action) {
+            (Some(uuid), _) if uuid == user.uuid => Some(2),
+            (Some(_), _) => None,
+            (None, Some(action)) if user.permissions.permissions.contains(&action) => Some(1),
+            (None, Some(_)) => None,
+            (None, None) => Some(0),
+        }
+    }
+}
+
+/// A path relative to the server directory with `.` dropped, `None` if it is
+/// absolute or climbs out with `..`. The empty path is the directory itself.
+pub fn file_path(path: &str) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in Path::new(path).components() {
+        match component {
+            Component::Normal(part) => clean.push(part),
+            Component::CurDir => {}
+            _ => return None,
+        }
+    }
+    Some(clean)
+}
+
+/// A relative path as returned to clients, `/` separated.
+pub fn display_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// What `user` may do with `path` on a server with `rules`. The most specific
+/// rule wins: user over role over everyone, then the deepest path, and the
+/// lesser access on a tie. Without a matching rule the user's permissions
+/// decide. Root is never restricted.
+pub fn file_access(rules: &[FileRule], user: &InternalUser, path: &Path) -> FileAccess {
+    if user.permissions.root {
+        return FileAccess::Write;
+    }
+
+    let ceiling = access_ceiling(user);
+    rules
+        .iter()
+        .filter_map(|rule| {
+            let specificity = rule.specificity(user)?;
+            let rule_path = Path::new(&rule.path);
+            if !path.starts_with(rule_path) {
+                return None;
+            }
+            let depth = rule_path.components().count();
+            Some((
+                (specificity, depth, std::cmp::Reverse(rule.access)),
+                rule.access,
+            ))
+        })
+        .max_by_key(|(rank, _)| *rank)
+        .map(|(_, access)| access.min(ceiling))
+        .unwrap_or(ceiling)
+}
+
+/// The most file access the user's permissions allow on any server.
+pub fn access_ceiling(user: &InternalUser) -> FileAccess {
+    let permissions = &user.permissions.permissions;
+    if user.permissions.root || permissions.contains(&UserActions::ManageFiles) {
+        FileAccess::Write
+    } else if permissions.contains(&UserActions::ReadFiles) {
+        FileAccess::Read
+    } else {
+        FileAccess::None
+    }
+}
+
+/// The least access `user` has anywhere at or below `path`, which is what
+/// deleting or moving a whole directory needs.
+pub fn subtree_access(rules: &[FileRule], user: &InternalUser, path: &Path) -> FileAccess {
+    rules
+        .iter()
+        .filter(|rule| Path::new(&rule.path).starts_with(path))
+        .map(|rule| file_access(rules, user, Path::new(&rule.path)))
+        .fold(file_access(rules, user, path), FileAccess::min)
+}
+
+fn validate_file_path(input: &str) -> Result<(), ValidationError> {
+    match file_path(input) {
+        Some(_) => Ok(()),
+        None => Err(ValidationError::new("file_path")),
+    }
+}
+
+fn validate_target_path(input: &str) -> Result<(), ValidationError> {
+    match file_path(input) {
+        Some(path) if !path.as_os_str().is_empty() => Ok(()),
+        _ => Err(ValidationError::new("file_path")),
+    }
+}
+
+fn validate_target_paths(input: &[String]) -> Result<(), ValidationError> {
+    input.iter().try_for_each(|path| validate_target_path(path))
+}
+
+fn validate_rule_subject(rule: &NewFileRule) -> Result<(), ValidationError> {
+    if rule.user_uuid.is_some() && rule.action.is_some() {
+        Err(ValidationError::new("rule_subject"))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::user_prems::UserPermissions;
+
+    fn editor() -> InternalUser {
+        InternalUser {
+            uuid: Uuid::new_v4(),
+            username: "editor".to_string(),
+            email: None,
+            password_hash: String::new(),
+            first_name: None,
+            last_name: None,
+            permissions: UserPermissions {
+                root: false,
+                permissions: [UserActions::ReadFiles, UserActions::ManageFiles].into(),
+            },
+        }
+    }
+
+    fn rule(
+        user_uuid: Option<Uuid>,
+        action: Option<UserActions>,
+        path: &str,
+        access: FileAccess,
+    ) -> FileRule {
+        FileRule {
+            uuid: Uuid::new_v4(),
+            server_uuid: Uuid::nil(),
+            user_uuid,
+            action,
+            path: path.to_string(),
+            access,
+        }
+    }
+
+    #[test]
+    fn file_path_refuses_parents_and_absolute_paths() {
+        assert_eq!(
+            file_path("world/./level.dat"),
+            Some(PathBuf::from("world/level.dat"))
+        );
+        assert_eq!(file_path(""), Some(PathBuf::new()));
+        assert_eq!(file_path("../other"), None);
+        assert_eq!(file_path("world/../../other"), None);
+        assert_eq!(file_path("/etc/passwd"), None);
+    }
+
+    #[test]
+    fn user_rule_beats_role_beats_everyone() {
+        let user = editor();
+        let path = Path::new("world/level.dat");
+        let everyone = rule(None, None, "", FileAccess::None);
+        let role = rule(None, Some(UserActions::ReadFiles), "", FileAccess::Read);
+        let own = rule(Some(user.uuid), None, "", FileAccess::Write);
+
+        assert_eq!(
+            file_access(std::slice::from_ref(&everyone), &user, path),
+            FileAccess::None
+        );
+        assert_eq!(
+            file_access(&[everyone.clone(), role.clone()], &user, path),
+            FileAccess::Read
+        );
+        assert_eq!(
+            file_access(&[role, own, everyone], &user, path),
+            FileAccess::Write
+        );
+    }
+
+    #[test]
+    fn rules_for_others_do_not_apply() {
+        let user = editor();
+        let rules = [
+            rule(Some(Uuid::new_v4()), None, "", FileAccess::None),
+            rule(None, Some(UserActions::ManageUsers), "", FileAccess::None),
+        ];
+
+        assert_eq!(
+            file_access(&rules, &user, Path::new("server.properties")),
+            FileAccess::Write
+        );
+    }
+
+    #[test]
+    fn deepest_path_wins_and_deny_wins_a_tie() {
+        let user = editor();
+        let rules = [
+            rule(None, None, "", FileAccess::Read),
+            rule(None, None, "world", FileAccess::Write),
+        ];
+        assert_eq!(
+            file_access(&rules, &user, Path::new("world/level.dat")),
+            FileAccess::Write
+        );
+        assert_eq!(
+            file_access(&rules, &user, Path::new("server.properties")),
+            FileAccess::Read
+        );
+
+        let tied = [
+            rule(None, None, "world", FileAccess::Write),
+            rule(None, None, "world", FileAccess::None),
+        ];
+        assert_eq!(
+            file_access(&tied, &user, Path::new("world/level.dat")),
+            FileAccess::None
+        );
+    }
+
+    #[test]
+    fn rules_never_exceed_permissions_and_root_is_unrestricted() {
+        let mut user = editor();
+        user.permissions
+            .permissions
+            .remove(&UserActions::ManageFiles);
+        let rules = [rule(Some(user.uuid), None, "", FileAccess::Write)];
+        assert_eq!(
+            file_access(&rules, &user, Path::new("world")),
+            FileAccess::Read
+        );
+
+        user.permissions.root = true;
+        let rules = [rule(Some(user.uuid), None, "", FileAccess::None)];
+        assert_eq!(
+            file_access(&rules, &user, Path::new("world")),
+            FileAccess::Write
+        );
+    }
+
+    #[test]
+    fn subtree_access_takes_a_stricter_nested_rule() {
+        let user = editor();
+        let rules = [rule(None, None, "world/playerdata", FileAccess::Read)];
+
+        assert_eq!(
+            file_access(&rules, &user, Path::new("world")),
+            FileAccess::Write
+        );
+        assert_eq!(
+            subtree_access(&rules, &user, Path::new("world")),
+            FileAccess::Read
+        );
+        assert_eq!(
+            subtree_access(&rules, &user, Path::new("plugins")),
+            FileAccess::Write
+        );
+    }
+}
@@ -0,0 +1,68 @@
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::domain::validation;
+
+/// Server software the installer can set up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Software {
+    Vanilla,
+    Paper,
+    Purpur,
+    Fabric,
+    Forge,
+    NeoForge,
+}
+
+/// A game version some software is available for.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoftwareVersion {
+    pub version: String,
+    /// Releases as opposed to snapshots, pre-releases and betas.
+    pub stable: bool,
+}
+
+/// One build of some software for a game version, newest first in listings.
+/// Loader versions for Fabric, full versions for Forge and NeoForge.
+#[derive(Debug, Clone, Serialize)]
+pub struct SoftwareBuild {
+    pub build: String,
+    pub stable: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct InstallRequest {
+    pub software: Software,
+    /// Game version, the latest stable one if not set.
+    #[validate(custom(function = "validation::validate_software_version"))]
+    pub version: Option<String>,
+    /// Build for that version, the latest stable one if not set.
+    #[validate(custom(function = "validation::validate_software_version"))]
+    pub build: Option<String>,
+}
+
+/// What an install job set up, recorded as its result.
+#[derive(Debug, Clone, Serialize)]
+pub struct InstallOutcome {
+    pub software: Software,
+    pub version: String,
+    pub build: String,
+    /// What the server launches now, relative to its directory.
+    pub jar_file: String,
+    /// Whether the artifact came from the local cache.
+    pub cached: bool,
+}
+
+impl Software {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Software::Vanilla => "vanilla",
+            Software::Paper => "paper",
+            Software::Purpur => "purpur",
+            Software::Fabric => "fabric",
+            Software::Forge => "forge",
+            Software::NeoForge => "neoforge",
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use std::fmt;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::domain::validation;
+
+lazy_static! {
+    /// Releases, pre-releases and release candidates, `1.20.5`, `1.21-rc1`.
+    static ref MINECRAFT_VERSION: Regex = Regex::new(r"^1\.(\d+)(?:\.(\d+))?(?:-.*)?$").unwrap();
+}
+
+/// A Java installation found on the host.
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaRuntime {
+    /// The `java` binary, symlinks resolved.
+    pub path: String,
+    pub home: String,
+    /// As `java -version` reports it, `21.0.2` or `1.8.0_392`.
+    pub version: String,
+    /// Feature release, 8 for `1.8.0_392`.
+    pub major: u32,
+    /// From the `release` file of the installation, if it has one.
+    pub vendor: Option<String>,
+}
+
+/// Java releases a game version runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct JavaRequirement {
+    pub min_major: u32,
+    /// Newer runtimes break some old versions, Forge for 1.12 only runs on 8.
+    pub max_major: Option<u32>,
+    pub recommended_major: u32,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct JavaRecommendationQuery {
+    #[validate(custom(function = "validation::validate_software_version"))]
+    pub minecraft_version: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct JavaRecommendation {
+    pub minecraft_version: String,
+    pub requirement: JavaRequirement,
+    /// The installed runtime closest to the recommended release, if any fits.
+    pub runtime: Option<JavaRuntime>,
+}
+
+/// The runtime a server is set up with, checked against its game version.
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerJava {
+    pub java_path: String,
+    /// Not set when the binary could not be run.
+    pub runtime: Option<JavaRuntime>,
+    pub minecraft_version: Option<String>,
+    pub requirement: Option<JavaRequirement>,
+    /// `false` only when both are known and do not fit, which the server
+    /// refuses to start with.
+    pub compatible: bool,
+    /// A better fitting installed runtime, when there is one.
+    pub recommended: Option<JavaRuntime>,
+}
+
+impl JavaRequirement {
+    /// What a game version needs, `None` for snapshots and anything else
+    /// that is not a release.
+    pub fn for_minecraft(version: &str) -> Option<Self> {
+        let captures = MINECRAFT_VERSION.captures(version)?;
+        let minor: u32 = captures[1].parse().ok()?;
+        let patch: u32 = captures
+            .get(2)
+            .map_or(Some(0), |patch| patch.as_str().parse().ok())?;
+
+        let (min_major, max_major, recommended_major) = match (minor, patch) {
+            (0..=12, _) => (8, Some(8), 8),
+            (13..=16, _) => (8, None, 8),
+            (17, _) => (16, None, 17),
+            (18..=19, _) | (20, 0..=4) => (17, None, 17),
+            _ => (21, None, 21),
+        };
+        Some(Self {
+            min_major,
+            max_major,
+            recommended_major,
+        })
+    }
+
+    pub fn allows(&self, major: u32) -> bool {
+        major >= self.min_major && self.max_major.is_none_or(|max| major <= max)
+    }
+
+    /// The runtime to use out of `runtimes`, the recommended release if it is
+    /// installed, otherwise the closest one that fits, newer winning ties.
+    pub fn pick<'a>(&self, runtimes: &'a [JavaRuntime]) -> Option<&'a JavaRuntime> {
+        runtimes
+            .iter()
+            .filter(|runtime| self.allows(runtime.major))
+            .min_by_key(|runtime| {
+                (
+                    runtime.major.abs_diff(self.recommended_major),
+                    u32::MAX - runtime.major,
+                )
+            })
+    }
+}
+
+impl fmt::Display for JavaRequirement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.max_major {
+            Some(max) if max == self.min_major => write!(f, "java {max}"),
+            Some(max) => write!(f, "java {} to {max}", self.min_major),
+            None => write!(f, "java {} or newer", self.min_major),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn requirement(version: &str) -> Option<(u32, Option<u32>, u32)> {
+        JavaRequirement::for_minecraft(version)
+            .map(|r| (r.min_major, r.max_major, r.recommended_major))
+    }
+
+    fn runtime(major: u32) -> JavaRuntime {
+        JavaRuntime {
+            path: format!("/usr/lib/jvm/java-{major}/bin/java"),
+            home: format!("/usr/lib/jvm/java-{major}"),
+            version: major.to_string(),
+            major,
+            vendor: None,
+        }
+    }
+
+    #[test]
+    fn requirements_follow_the_game_version() {
+        assert_eq!(requirement("1.8.9"), Some((8, Some(8), 8)));
+        assert_eq!(requirement("1.12.2"), Some((8, Some(8), 8)));
+        assert_eq!(requirement("1.13"), Some((8, None, 8)));
+        assert_eq!(requirement("1.16.5"), Some((8, None, 8)));
+        assert_eq!(requirement("1.17.1"), Some((16, None, 17)));
+        assert_eq!(requirement("1.18"), Some((17, None, 17)));
+        assert_eq!(requirement("1.20.4"), Some((17, None, 17)));
+        assert_eq!(requirement("1.20.5"), Some((21, None, 21)));
+        assert_eq!(requirement("1.20.5-pre1"), Some((21, None, 21)));
+        assert_eq!(requirement("1.21"), Some((21, None, 21)));
+        assert_eq!(requirement("1.21.4-rc1"), Some((21, None, 21)));
+    }
+
+    #[test]
+    fn snapshots_and_old_versions_have_no_requirement() {
+        assert_eq!(requirement("24w14a"), None);
+        assert_eq!(requirement("b1.7.3"), None);
+        assert_eq!(requirement("1.21.x"), None);
+        assert_eq!(requirement("2.0"), None);
+    }
+
+    #[test]
+    fn allows_checks_both_bounds() {
+        let legacy = JavaRequirement::for_minecraft("1.12.2").unwrap();
+        assert!(legacy.allows(8));
+        assert!(!legacy.allows(7));
+        assert!(!legacy.allows(11));
+
+        let modern = JavaRequirement::for_minecraft("1.17.1").unwrap();
+        assert!(!modern.allows(11));
+        assert!(modern.allows(16));
+        assert!(modern.allows(25));
+    }
+
+    #[test]
+    fn pick_prefers_the_recommended_then_the_closest_newer() {
+        let installed = [runtime(8), runtime(17), runtime(21), runtime(25)];
+        let pick = |version: &str, runtimes: &[JavaRuntime]| {
+            JavaRequirement::for_minecraft(version)
+                .unwrap()
+                .pick(runtimes)
+                .map(|runtime| runtime.major)
+        };
+
+        assert_eq!(pick("1.12.2", &installed), Some(8));
+        assert_eq!(pick("1.18.2", &installed), Some(17));
+        assert_eq!(pick("1.21.1", &installed), Some(21));
+        // 16 and 18 are as far from 17, the newer one wins
+        assert_eq!(pick("1.17.1", &[runtime(16), runtime(18)]), Some(18));
+        assert_eq!(pick("1.21.1", &[runtime(8), runtime(25)]), Some(25));
+        assert_eq!(pick("1.12.2", &[runtime(17), runtime(21)]), None);
+        assert_eq!(pick("1.21.1", &[]), None);
+    }
+
+    #[test]
+    fn requirements_display_their_range() {
+        let show = |version: &str| JavaRequirement::for_minecraft(version).unwrap().to_string();
+        assert_eq!(show("1.12.2"), "java 8");
+        assert_eq!(show("1.18.2"), "java 17 or newer");
+        let range = JavaRequirement {
+            min_major: 8,
+            max_major: Some(11),
+            recommended_major: 8,
+        };
+        assert_eq!(range.to_string(), "java 8 to 11");
+    }
+}
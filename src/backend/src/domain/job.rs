@@ -0,0 +1,178 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sqlx::{prelude::FromRow, types::Json};
+use uuid::Uuid;
+
+use crate::domain::{user::InternalUser, user_prems::UserActions};
+
+/// What a job does, each kind has its own concurrency limit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobKind {
+    Backup,
+    Restore,
+    Compress,
+    Extract,
+    Install,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobStatus {
+    /// Waiting for a free slot of its kind.
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// The daemon stopped while the job was queued or running.
+    Interrupted,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct Job {
+    pub uuid: Uuid,
+    #[sqlx(try_from = "String")]
+    pub kind: JobKind,
+    pub server_uuid: Option<Uuid>,
+    #[sqlx(try_from = "String")]
+    pub status: JobStatus,
+    pub description: String,
+    /// What the job is doing right now.
+    pub step: Option<String>,
+    /// Units of work in total, bytes for file operations. Unknown for jobs
+    /// that only report steps.
+    pub total: Option<i64>,
+    pub processed: i64,
+    /// Latest log lines, older ones are dropped.
+    pub log: Vec<String>,
+    /// What a completed job produced, shaped by its kind.
+    pub result: Option<Json<Value>>,
+    pub error: Option<String>,
+    pub cancellable: bool,
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub started_at: Option<DateTime<Utc>>,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// A job about to be queued.
+#[derive(Debug, Clone)]
+pub struct NewJob {
+    pub kind: JobKind,
+    pub server_uuid: Option<Uuid>,
+    pub description: String,
+    pub created_by: Option<Uuid>,
+    /// Whether the job may be cancelled. Jobs holding on to something until
+    /// their work ends cannot be, not even while queued.
+    pub cancellable: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct JobQuery {
+    pub server_uuid: Option<Uuid>,
+    pub kind: Option<JobKind>,
+    pub status: Option<JobStatus>,
+    pub limit: Option<i64>,
+}
+
+/// Frames sent to job stream WebSocket clients.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum JobEvent {
+    /// The current state of a job, sent whenever it changes.
+    Job(Box<Job>),
+    /// The client fell behind and this many updates were dropped.
+    Lagged { skipped: u64 },
+}
+
+impl JobKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobKind::Backup => "backup",
+            JobKind::Restore => "restore",
+            JobKind::Compress => "compress",
+            JobKind::Extract => "extract",
+            JobKind::Install => "install",
+        }
+    }
+
+    /// The permission that allows cancelling jobs of this kind started by
+    /// someone else.
+    pub fn action(&self) -> UserActions {
+        match self {
+            JobKind::Backup | JobKind::Restore => UserActions::ManageBackups,
+            JobKind::Compress | JobKind::Extract => UserActions::ManageFiles,
+            JobKind::Install => UserActions::ManageServers,
+        }
+    }
+}
+
+impl TryFrom<String> for JobKind {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "backup" => Ok(JobKind::Backup),
+            "restore" => Ok(JobKind::Restore),
+            "compress" => Ok(JobKind::Compress),
+            "extract" => Ok(JobKind::Extract),
+            "install" => Ok(JobKind::Install),
+            other => Err(format!("unknown job kind {other}")),
+        }
+    }
+}
+
+impl JobStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            JobStatus::Queued => "queued",
+            JobStatus::Running => "running",
+            JobStatus::Completed => "completed",
+            JobStatus::Failed => "failed",
+            JobStatus::Cancelled => "cancelled",
+            JobStatus::Interrupted => "interrupted",
+        }
+    }
+
+    pub fn is_finished(&self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+impl TryFrom<String> for JobStatus {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "queued" => Ok(JobStatus::Queued),
+            "running" => Ok(JobStatus::Running),
+            "completed" => Ok(JobStatus::Completed),
+            "failed" => Ok(JobStatus::Failed),
+            "cancelled" => Ok(JobStatus::Cancelled),
+            "interrupted" => Ok(JobStatus::Interrupted),
+            other => Err(format!("unknown job status {other}")),
+        }
+    }
+}
+
+impl JobQuery {
+    /// Whether `job` passes the filters, the limit aside.
+    pub fn matches(&self, job: &Job) -> bool {
+        self.server_uuid
+            .is_none_or(|uuid| job.server_uuid == Some(uuid))
+            && self.kind.is_none_or(|kind| job.kind == kind)
+            && self.status.is_none_or(|status| job.status == status)
+    }
+}
+
+impl Job {
+    /// Whoever started the job may cancel it, as may anyone allowed to manage
+    /// what it works on.
+    pub fn can_cancel(&self, user: &InternalUser) -> bool {
+        user.permissions.root
+            || self.created_by == Some(user.uuid)
+            || user.permissions.permissions.contains(&self.kind.action())
+    }
+}
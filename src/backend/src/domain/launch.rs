@@ -0,0 +1,449 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::domain::validation;
+
+/// Heaps from this size on get the large heap variant of Aikar's flags.
+const AIKAR_LARGE_HEAP_MB: i32 = 12 * 1024;
+
+/// The heap comes from the profile or the memory fields of the server and the
+/// launch target from its jar file, extra arguments cannot set them.
+const HEAP_ARGS: [&str; 5] = [
+    "-Xms",
+    "-Xmx",
+    "-XX:InitialHeapSize=",
+    "-XX:MinHeapSize=",
+    "-XX:MaxHeapSize=",
+];
+const LAUNCH_TARGET_ARGS: [&str; 4] = ["-jar", "-cp", "-classpath", "--class-path"];
+
+/// Garbage collector setups a launch profile can start from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GcPreset {
+    /// Whatever the JVM picks.
+    #[default]
+    None,
+    G1,
+    /// Aikar's G1 tuning for Minecraft servers, https://docs.papermc.io/paper/aikars-flags
+    Aikar,
+    Zgc,
+}
+
+/// Structured replacement for a hand written launch command. The launch
+/// target comes from the jar file of the server, everything else from here.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+#[serde(default)]
+#[validate(schema(function = "validate_launch_profile"))]
+pub struct LaunchProfile {
+    /// `-Xms`, the server's `min_memory_mb` when not set.
+    #[validate(range(min = 128))]
+    pub min_heap_mb: Option<i32>,
+    /// `-Xmx`, the server's `max_memory_mb` when not set.
+    #[validate(range(min = 128))]
+    pub max_heap_mb: Option<i32>,
+    pub gc_preset: GcPreset,
+    /// Extra JVM options, after the preset so they can override its flags.
+    pub jvm_args: Vec<String>,
+    /// Passed as `-Dkey=value`, in key order.
+    pub system_properties: BTreeMap<String, String>,
+    /// Arguments to the server itself, after the launch target.
+    pub server_args: Vec<String>,
+}
+
+/// Where the argv of a launch comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LaunchSource {
+    /// Java path, memory fields, launch profile and jar file.
+    Profile,
+    /// The launch command of the server, split into words like a shell would.
+    Custom,
+}
+
+/// What starting a server runs, for troubleshooting.
+#[derive(Debug, Clone, Serialize)]
+pub struct LaunchPlan {
+    pub source: LaunchSource,
+    /// Set when part of the server settings is not applied.
+    pub warning: Option<String>,
+    pub argv: Vec<String>,
+    /// The same, as a line to paste into a shell.
+    pub command: String,
+}
+
+impl Default for LaunchProfile {
+    fn default() -> Self {
+        Self {
+            min_heap_mb: None,
+            max_heap_mb: None,
+            gc_preset: GcPreset::None,
+            jvm_args: Vec::new(),
+            system_properties: BTreeMap::new(),
+            server_args: vec!["nogui".to_string()],
+        }
+    }
+}
+
+impl GcPreset {
+    pub fn flags(&self, max_memory_mb: i32) -> Vec<String> {
+        match self {
+            GcPreset::None => Vec::new(),
+            GcPreset::G1 => vec!["-XX:+UseG1GC".to_string()],
+            GcPreset::Aikar => {
+                let (new_size, max_new_size, region_size, reserve, occupancy) =
+                    if max_memory_mb >= AIKAR_LARGE_HEAP_MB {
+                        (40, 50, "16M", 15, 20)
+                    } else {
+                        (30, 40, "8M", 20, 15)
+                    };
+                vec![
+                    "-XX:+UseG1GC".to_string(),
+                    "-XX:+ParallelRefProcEnabled".to_string(),
+                    "-XX:MaxGCPauseMillis=200".to_string(),
+                    "-XX:+UnlockExperimentalVMOptions".to_string(),
+                    "-XX:+DisableExplicitGC".to_string(),
+                    "-XX:+AlwaysPreTouch".to_string(),
+                    format!("-XX:G1NewSizePercent={new_size}"),
+                    format!("-XX:G1MaxNewSizePercent={max_new_size}"),
+                    format!("-XX:G1HeapRegionSize={region_size}"),
+                    format!("-XX:G1ReservePercent={reserve}"),
+                    "-XX:G1HeapWastePercent=5".to_string(),
+                    "-XX:G1MixedGCCountTarget=4".to_string(),
+                    format!("-XX:InitiatingHeapOccupancyPercent={occupancy}"),
+                    "-XX:G1MixedGCLiveThresholdPercent=90".to_string(),
+                    "-XX:G1RSetUpdatingPauseTimePercent=5".to_string(),
+                    "-XX:SurvivorRatio=32".to_string(),
+                    "-XX:+PerfDisableSharedMem".to_string(),
+                    "-XX:MaxTenuringThreshold=1".to_string(),
+                    "-Dusing.aikars.flags=https://mcflags.emc.gs".to_string(),
+                    "-Daikars.new.flags=true".to_string(),
+                ]
+            }
+            GcPreset::Zgc => vec!["-XX:+UseZGC".to_string()],
+        }
+    }
+}
+
+impl LaunchProfile {
+    /// Minimum and maximum heap in MiB, falling back to the server's memory
+    /// fields.
+    pub fn heap(&self, min_memory_mb: i32, max_memory_mb: i32) -> (i32, i32) {
+        (
+            self.min_heap_mb.unwrap_or(min_memory_mb),
+            self.max_heap_mb.unwrap_or(max_memory_mb),
+        )
+    }
+
+    /// JVM options between the heap and the launch target.
+    pub fn jvm_options(&self, max_memory_mb: i32) -> Vec<String> {
+        let mut options = self.gc_preset.flags(max_memory_mb);
+        options.extend(self.jvm_args.iter().cloned());
+        options.extend(
+            self.system_properties
+                .iter()
+                .map(|(key, value)| format!("-D{key}={value}")),
+        );
+        options
+    }
+}
+
+impl LaunchPlan {
+    pub fn new(source: LaunchSource, argv: Vec<String>) -> Self {
+        let warning = match source {
+            LaunchSource::Profile => None,
+            LaunchSource::Custom => Some(
+                "the launch command replaces the java path, memory limits and launch profile"
+                    .to_string(),
+            ),
+        };
+        let command = argv
+            .iter()
+            .map(|arg| shell_quote(arg))
+            .collect::<Vec<_>>()
+            .join(" ");
+        Self {
+            source,
+            warning,
+            argv,
+            command,
+        }
+    }
+}
+
+/// Single quotes an argument unless it is made of characters no shell treats
+/// specially.
+fn shell_quote(arg: &str) -> String {
+    let plain = !arg.is_empty()
+        && arg.chars().all(|c| {
+            c.is_ascii_alphanumeric()
+                || matches!(c, '-' | '_' | '.' | '/' | ':' | '=' | '@' | '+' | ',' | '%')
+        });
+    if plain {
+        arg.to_string()
+    } else {
+        format!("'{}'", arg.replace('\'', r"'\''"))
+    }
+}
+
+fn validate_launch_profile(profile: &LaunchProfile) -> Result<(), ValidationError> {
+    if let (Some(min), Some(max)) = (profile.min_heap_mb, profile.max_heap_mb)
+        && min > max
+    {
+        return Err(ValidationError::new("memory_range"));
+    }
+    if profile.jvm_args.len() > 64
+        || profile.system_properties.len() > 64
+        || profile.server_args.len() > 32
+    {
+        return Err(ValidationError::new("launch_profile_size"));
+    }
+
+    for arg in &profile.jvm_args {
+        validate_arg(arg)?;
+        if !arg.starts_with('-') {
+            return Err(ValidationError::new("jvm_arg"));
+        }
+        if HEAP_ARGS.iter().any(|heap| arg.starts_with(heap))
+            || LAUNCH_TARGET_ARGS.contains(&arg.as_str())
+        {
+            return Err(ValidationError::new("reserved_jvm_arg"));
+        }
+        // Two collectors make the JVM refuse to start
+        if profile.gc_preset != GcPreset::None && arg.starts_with("-XX:+Use") && arg.ends_with("GC")
+        {
+            return Err(ValidationError::new("gc_preset_conflict"));
+        }
+    }
+    for (key, value) in &profile.system_properties {
+        validation::validate_property_key(key)?;
+        if value.len() > 1024 || value.chars().any(char::is_control) {
+            return Err(ValidationError::new("system_property"));
+        }
+    }
+    for arg in &profile.server_args {
+        validate_arg(arg)?;
+    }
+    Ok(())
+}
+
+/// One argv entry, passed as is without a shell.
+fn validate_arg(arg: &str) -> Result<(), ValidationError> {
+    if arg.is_empty() || arg.len() > 256 || arg.chars().any(char::is_control) {
+        Err(ValidationError::new("launch_arg"))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn profile() -> LaunchProfile {
+        LaunchProfile::default()
+    }
+
+    fn code(profile: &LaunchProfile) -> Option<String> {
+        validate_launch_profile(profile)
+            .err()
+            .map(|e| e.code.to_string())
+    }
+
+    #[test]
+    fn default_profile_is_valid() {
+        assert_eq!(code(&profile()), None);
+        assert!(profile().validate().is_ok());
+    }
+
+    #[test]
+    fn oversized_profiles_are_refused() {
+        let mut jvm = profile();
+        jvm.jvm_args = vec!["-Dx=1".to_string(); 65];
+        assert_eq!(code(&jvm).as_deref(), Some("launch_profile_size"));
+
+        let mut properties = profile();
+        properties.system_properties = (0..65)
+            .map(|i| (format!("k{i}"), "v".to_string()))
+            .collect();
+        assert_eq!(code(&properties).as_deref(), Some("launch_profile_size"));
+
+        let mut server = profile();
+        server.server_args = vec!["nogui".to_string(); 33];
+        assert_eq!(code(&server).as_deref(), Some("launch_profile_size"));
+
+        let mut long = profile();
+        long.server_args = vec!["a".repeat(257)];
+        assert_eq!(code(&long).as_deref(), Some("launch_arg"));
+    }
+
+    #[test]
+    fn heap_and_launch_target_are_reserved() {
+        for arg in [
+            "-Xmx4G",
+            "-Xms1G",
+            "-XX:MaxHeapSize=8G",
+            "-XX:InitialHeapSize=1G",
+            "-XX:MinHeapSize=512M",
+            "-jar",
+            "-cp",
+            "-classpath",
+            "--class-path",
+        ] {
+            let mut reserved = profile();
+            reserved.jvm_args = vec![arg.to_string()];
+            assert_eq!(
+                code(&reserved).as_deref(),
+                Some("reserved_jvm_arg"),
+                "{arg}"
+            );
+        }
+
+        let mut positional = profile();
+        positional.jvm_args = vec!["server.jar".to_string()];
+        assert_eq!(code(&positional).as_deref(), Some("jvm_arg"));
+
+        let mut fine = profile();
+        fine.jvm_args = vec![
+            "-Xss2M".to_string(),
+            "-XX:+UseStringDeduplication".to_string(),
+        ];
+        assert_eq!(code(&fine), None);
+    }
+
+    #[test]
+    fn heap_falls_back_to_the_memory_fields() {
+        let mut profile = profile();
+        assert_eq!(profile.heap(1024, 4096), (1024, 4096));
+
+        profile.max_heap_mb = Some(8192);
+        assert_eq!(profile.heap(1024, 4096), (1024, 8192));
+        profile.min_heap_mb = Some(2048);
+        assert_eq!(profile.heap(1024, 4096), (2048, 8192));
+        assert!(profile.validate().is_ok());
+
+        profile.min_heap_mb = Some(16384);
+        assert_eq!(code(&profile).as_deref(), Some("memory_range"));
+
+        profile.min_heap_mb = Some(64);
+        assert!(profile.validate().is_err());
+    }
+
+    #[test]
+    fn a_second_collector_conflicts_with_the_preset() {
+        let mut conflict = profile();
+        conflict.gc_preset = GcPreset::Aikar;
+        conflict.jvm_args = vec!["-XX:+UseZGC".to_string()];
+        assert_eq!(code(&conflict).as_deref(), Some("gc_preset_conflict"));
+
+        // Without a preset the collector is the profile's own choice
+        conflict.gc_preset = GcPreset::None;
+        assert_eq!(code(&conflict), None);
+    }
+
+    #[test]
+    fn system_properties_are_checked() {
+        let mut key = profile();
+        key.system_properties
+            .insert("bad key".to_string(), "v".to_string());
+        assert_eq!(code(&key).as_deref(), Some("property_key"));
+
+        let mut value = profile();
+        value
+            .system_properties
+            .insert("file.encoding".to_string(), "UTF-8\n-Xmx64G".to_string());
+        assert_eq!(code(&value).as_deref(), Some("system_property"));
+
+        value
+            .system_properties
+            .insert("file.encoding".to_string(), "x".repeat(1025));
+        assert_eq!(code(&value).as_deref(), Some("system_property"));
+    }
+
+    #[test]
+    fn control_characters_and_empty_args_are_refused() {
+        for arg in ["", "nogui\n", "--world\tname"] {
+            let mut server = profile();
+            server.server_args = vec![arg.to_string()];
+            assert_eq!(code(&server).as_deref(), Some("launch_arg"), "{arg:?}");
+        }
+        let mut jvm = profile();
+        jvm.jvm_args = vec!["-Dx=\u{0}".to_string()];
+        assert_eq!(code(&jvm).as_deref(), Some("launch_arg"));
+    }
+
+    #[test]
+    fn aikar_flags_follow_the_heap_size() {
+        let small = GcPreset::Aikar.flags(AIKAR_LARGE_HEAP_MB - 1);
+        assert!(small.contains(&"-XX:G1NewSizePercent=30".to_string()));
+        assert!(small.contains(&"-XX:G1HeapRegionSize=8M".to_string()));
+        assert!(small.contains(&"-XX:InitiatingHeapOccupancyPercent=15".to_string()));
+
+        let large = GcPreset::Aikar.flags(AIKAR_LARGE_HEAP_MB);
+        assert!(large.contains(&"-XX:G1NewSizePercent=40".to_string()));
+        assert!(large.contains(&"-XX:G1HeapRegionSize=16M".to_string()));
+        assert!(large.contains(&"-XX:InitiatingHeapOccupancyPercent=20".to_string()));
+        assert_eq!(small.len(), large.len());
+
+        assert!(GcPreset::None.flags(4096).is_empty());
+        assert_eq!(GcPreset::Zgc.flags(4096), ["-XX:+UseZGC"]);
+    }
+
+    #[test]
+    fn jvm_options_put_the_preset_first_and_properties_last() {
+        let profile = LaunchProfile {
+            gc_preset: GcPreset::G1,
+            jvm_args: vec!["-XX:MaxGCPauseMillis=100".to_string()],
+            system_properties: BTreeMap::from([
+                ("paper.log".to_string(), "true".to_string()),
+                ("file.encoding".to_string(), "UTF-8".to_string()),
+            ]),
+            ..LaunchProfile::default()
+        };
+        assert_eq!(
+            profile.jvm_options(4096),
+            [
+                "-XX:+UseG1GC",
+                "-XX:MaxGCPauseMillis=100",
+                "-Dfile.encoding=UTF-8",
+                "-Dpaper.log=true",
+            ]
+        );
+    }
+
+    #[test]
+    fn shell_quote_leaves_plain_args_alone() {
+        assert_eq!(shell_quote("-Xmx4096M"), "-Xmx4096M");
+        assert_eq!(shell_quote("/usr/bin/java"), "/usr/bin/java");
+        assert_eq!(shell_quote("-Dkey=a,b:c@d+e%f"), "-Dkey=a,b:c@d+e%f");
+        assert_eq!(shell_quote(""), "''");
+        assert_eq!(shell_quote("my world"), "'my world'");
+        assert_eq!(shell_quote("$HOME"), "'$HOME'");
+        assert_eq!(shell_quote("a;rm -rf"), "'a;rm -rf'");
+        assert_eq!(shell_quote("it's"), r"'it'\''s'");
+    }
+
+    #[test]
+    fn plans_render_a_pasteable_command() {
+        let plan = LaunchPlan::new(
+            LaunchSource::Profile,
+            vec![
+                "java".to_string(),
+                "-jar".to_string(),
+                "server.jar".to_string(),
+                "--world".to_string(),
+                "my world".to_string(),
+            ],
+        );
+        assert_eq!(plan.command, "java -jar server.jar --world 'my world'");
+        assert_eq!(plan.warning, None);
+
+        let custom = LaunchPlan::new(LaunchSource::Custom, vec!["./start.sh".to_string()]);
+        assert_eq!(custom.command, "./start.sh");
+        assert!(custom.warning.is_some());
+        let json = serde_json::to_value(&custom).unwrap();
+        assert_eq!(json["source"], "custom");
+    }
+}
@@ -1,4 +1,21 @@
 pub mod api;
+pub mod backup;
+pub mod command;
+pub mod console;
+pub mod countdown;
+pub mod crash;
+pub mod destination;
+pub mod file;
+pub mod install;
+pub mod java;
+pub mod job;
+pub mod launch;
+pub mod player_lists;
+pub mod players;
+pub mod properties;
+pub mod schedule;
+pub mod server;
+pub mod status;
 pub mod user;
 pub mod user_prems;
 pub mod validation;
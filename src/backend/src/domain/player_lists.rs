@@ -0,0 +1,125 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+use crate::domain::validation::validate_player_name;
+
+/// Date format the server uses for `created` and `expires`.
+pub const LIST_DATE_FORMAT: &str = "%Y-%m-%d %H:%M:%S %z";
+pub const NEVER_EXPIRES: &str = "forever";
+
+/// `whitelist.json` entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WhitelistEntry {
+    pub uuid: String,
+    pub name: String,
+}
+
+/// `ops.json` entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpEntry {
+    pub uuid: String,
+    pub name: String,
+    pub level: u8,
+    pub bypasses_player_limit: bool,
+}
+
+/// `banned-players.json` entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BannedPlayerEntry {
+    pub uuid: String,
+    pub name: String,
+    pub created: String,
+    pub source: String,
+    pub expires: String,
+    pub reason: String,
+}
+
+/// `banned-ips.json` entry.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BannedIpEntry {
+    pub ip: String,
+    pub created: String,
+    pub source: String,
+    pub expires: String,
+    pub reason: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NewWhitelistEntry {
+    #[validate(custom(function = "validate_player_name"))]
+    pub name: String,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NewOp {
+    #[validate(custom(function = "validate_player_name"))]
+    pub name: String,
+    /// Defaults to the server's `op-permission-level`.
+    #[validate(range(min = 1, max = 4))]
+    pub level: Option<u8>,
+    #[serde(default)]
+    pub bypasses_player_limit: bool,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NewBan {
+    #[validate(custom(function = "validate_player_name"))]
+    pub name: String,
+    #[validate(length(max = 256))]
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct NewIpBan {
+    pub ip: IpAddr,
+    #[validate(length(max = 256))]
+    pub reason: Option<String>,
+}
+
+/// Single line text the console commands accept, newlines would split them.
+pub fn single_line(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeVia {
+    /// The list file was rewritten while the server was stopped.
+    File,
+    /// The server was running and got the equivalent command.
+    Command,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ListChange {
+    pub via: ChangeVia,
+    /// Command output, only available over RCON.
+    pub response: Option<String>,
+}
+
+impl ListChange {
+    pub fn file() -> Self {
+        Self {
+            via: ChangeVia::File,
+            response: None,
+        }
+    }
+
+    pub fn command(response: Option<String>) -> Self {
+        Self {
+            via: ChangeVia::Command,
+            response,
+        }
+    }
+}
+
+/// Minecraft profile as resolved by name.
+#[derive(Debug, Clone, Deserialize)]
+pub struct PlayerProfile {
+    /// Dashed UUID as the list files store it.
+    pub uuid: String,
+    pub name: String,
+}
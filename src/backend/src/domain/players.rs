@@ -0,0 +1,87 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+
+/// Player related line recognised in a server log.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LogEvent {
+    /// Logged by the authenticator before the login in online mode.
+    Identified {
+        name: String,
+        uuid: Uuid,
+    },
+    LoggedIn {
+        name: String,
+        ip: String,
+    },
+    Joined {
+        name: String,
+    },
+    Left {
+        name: String,
+    },
+    Chat {
+        name: String,
+        message: String,
+    },
+    Death {
+        name: String,
+        message: String,
+    },
+    Advancement {
+        name: String,
+        advancement: String,
+    },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OnlinePlayer {
+    pub name: String,
+    pub uuid: Option<Uuid>,
+    pub ip: Option<String>,
+    pub joined_at: DateTime<Utc>,
+    #[serde(skip)]
+    pub session: Uuid,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PlayerActivity {
+    Join { name: String },
+    Leave { name: String },
+    Chat { name: String, message: String },
+    Death { name: String, message: String },
+    Advancement { name: String, advancement: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PlayerEvent {
+    #[serde(flatten)]
+    pub activity: PlayerActivity,
+    pub at: DateTime<Utc>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerPlayers {
+    pub online: Vec<OnlinePlayer>,
+    /// Latest activity seen since the daemon started, newest last.
+    pub recent: Vec<PlayerEvent>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct PlayerSession {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub player_uuid: Option<Uuid>,
+    pub player_name: String,
+    pub ip: Option<String>,
+    pub joined_at: DateTime<Utc>,
+    /// Still online while missing.
+    pub left_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SessionQuery {
+    pub limit: Option<i64>,
+}
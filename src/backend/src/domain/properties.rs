@@ -0,0 +1,184 @@
+use std::collections::BTreeMap;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::domain::validation::validate_property_key;
+
+/// How a known `server.properties` value is validated.
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum PropertyKind {
+    Bool,
+    Int { min: i64, max: i64 },
+    Enum { values: &'static [&'static str] },
+    Text,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PropertySpec {
+    pub key: &'static str,
+    pub kind: PropertyKind,
+    /// Never sent back to the client.
+    pub secret: bool,
+}
+
+const PORT: PropertyKind = PropertyKind::Int { min: 1, max: 65535 };
+const BOOL: PropertyKind = PropertyKind::Bool;
+const TEXT: PropertyKind = PropertyKind::Text;
+
+const fn int(min: i64, max: i64) -> PropertyKind {
+    PropertyKind::Int { min, max }
+}
+
+const fn spec(key: &'static str, kind: PropertyKind) -> PropertySpec {
+    PropertySpec {
+        key,
+        kind,
+        secret: false,
+    }
+}
+
+/// Keys understood by the vanilla dedicated server.
+pub const KNOWN_PROPERTIES: &[PropertySpec] = &[
+    spec("accepts-transfers", BOOL),
+    spec("allow-flight", BOOL),
+    spec("allow-nether", BOOL),
+    spec("broadcast-console-to-ops", BOOL),
+    spec("broadcast-rcon-to-ops", BOOL),
+    spec(
+        "difficulty",
+        PropertyKind::Enum {
+            values: &["peaceful", "easy", "normal", "hard"],
+        },
+    ),
+    spec("enable-command-block", BOOL),
+    spec("enable-jmx-monitoring", BOOL),
+    spec("enable-query", BOOL),
+    spec("enable-rcon", BOOL),
+    spec("enable-status", BOOL),
+    spec("enforce-secure-profile", BOOL),
+    spec("enforce-whitelist", BOOL),
+    spec("entity-broadcast-range-percentage", int(10, 1000)),
+    spec("force-gamemode", BOOL),
+    spec("function-permission-level", int(1, 4)),
+    spec(
+        "gamemode",
+        PropertyKind::Enum {
+            values: &["survival", "creative", "adventure", "spectator"],
+        },
+    ),
+    spec("generate-structures", BOOL),
+    spec("generator-settings", TEXT),
+    spec("hardcore", BOOL),
+    spec("hide-online-players", BOOL),
+    spec("initial-disabled-packs", TEXT),
+    spec("initial-enabled-packs", TEXT),
+    spec("level-name", TEXT),
+    spec("level-seed", TEXT),
+    spec("level-type", TEXT),
+    spec("log-ips", BOOL),
+    spec("max-chained-neighbor-updates", int(-1, i32::MAX as i64)),
+    spec("max-players", int(0, i32::MAX as i64)),
+    spec("max-tick-time", int(-1, i64::MAX)),
+    spec("max-world-size", int(1, 29_999_984)),
+    spec("motd", TEXT),
+    spec("network-compression-threshold", int(-1, i32::MAX as i64)),
+    spec("online-mode", BOOL),
+    spec("op-permission-level", int(0, 4)),
+    spec("player-idle-timeout", int(0, i32::MAX as i64)),
+    spec("prevent-proxy-connections", BOOL),
+    spec("pvp", BOOL),
+    spec("query.port", PORT),
+    spec("rate-limit", int(0, i32::MAX as i64)),
+    PropertySpec {
+        key: "rcon.password",
+        kind: TEXT,
+        secret: true,
+    },
+    spec("rcon.port", PORT),
+    spec("require-resource-pack", BOOL),
+    spec("resource-pack", TEXT),
+    spec("resource-pack-id", TEXT),
+    spec("resource-pack-prompt", TEXT),
+    spec("resource-pack-sha1", TEXT),
+    spec("server-ip", TEXT),
+    spec("server-port", PORT),
+    spec("simulation-distance", int(3, 32)),
+    spec("spawn-monsters", BOOL),
+    spec("spawn-protection", int(0, i32::MAX as i64)),
+    spec("sync-chunk-writes", BOOL),
+    spec("text-filtering-config", TEXT),
+    spec("use-native-transport", BOOL),
+    spec("view-distance", int(3, 32)),
+    spec("white-list", BOOL),
+];
+
+pub fn find_spec(key: &str) -> Option<&'static PropertySpec> {
+    KNOWN_PROPERTIES.iter().find(|spec| spec.key == key)
+}
+
+/// Turns a JSON value sent by the client into the text written to the file,
+/// checking it against the schema when the key is known.
+pub fn normalize_value(key: &str, value: &Value) -> Result<String, String> {
+    validate_property_key(key).map_err(|_| format!("invalid property key {key:?}"))?;
+
+    let text = match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        _ => return Err(format!("{key}: expected a string, number or boolean")),
+    };
+
+    let Some(spec) = find_spec(key) else {
+        return Ok(text);
+    };
+
+    match spec.kind {
+        PropertyKind::Bool => match text.to_ascii_lowercase().as_str() {
+            "true" => Ok("true".into()),
+            "false" => Ok("false".into()),
+            _ => Err(format!("{key}: expected true or false")),
+        },
+        PropertyKind::Int { min, max } => match text.trim().parse::<i64>() {
+            Ok(n) if (min..=max).contains(&n) => Ok(n.to_string()),
+            _ => Err(format!(
+                "{key}: expected an integer between {min} and {max}"
+            )),
+        },
+        PropertyKind::Enum { values } => {
+            let lower = text.to_ascii_lowercase();
+            if values.contains(&lower.as_str()) {
+                Ok(lower)
+            } else {
+                Err(format!("{key}: expected one of {}", values.join(", ")))
+            }
+        }
+        PropertyKind::Text => Ok(text),
+    }
+}
+
+/// Property changes as sent to `PATCH /api/servers/{uuid}/properties`.
+pub type PropertyChanges = BTreeMap<String, Value>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Property {
+    pub key: String,
+    pub value: String,
+    /// Missing for keys the vanilla server does not know, e.g. from plugins.
+    pub kind: Option<PropertyKind>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerProperties {
+    pub properties: Vec<Property>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PropertiesUpdate {
+    pub properties: Vec<Property>,
+    pub changed: Vec<String>,
+    /// The server only reads the file at startup, so any change made while it
+    /// runs waits for the next restart.
+    pub restart_required: bool,
+}
@@ -0,0 +1,409 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use croner::Cron;
+use serde::{Deserialize, Serialize};
+use sqlx::prelude::FromRow;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::domain::{command::validate_single_line, countdown::MAX_COUNTDOWN_SECS};
+
+pub const DEFAULT_TIMEZONE: &str = "UTC";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskAction {
+    Start,
+    Stop,
+    Restart,
+    /// Sends the task's command to the console.
+    Command,
+    Backup,
+}
+
+/// What happens to runs that fell into a period the daemon was down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CatchUp {
+    /// Record the missed runs and wait for the next occurrence.
+    #[default]
+    Skip,
+    /// Run once on startup no matter how many runs were missed.
+    RunOnce,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunOutcome {
+    Success,
+    Failed,
+    /// The daemon was down when the run was due.
+    Missed,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct ScheduledTask {
+    pub uuid: Uuid,
+    pub server_uuid: Uuid,
+    pub name: String,
+    /// Cron expression, five fields or six with leading seconds.
+    pub cron: String,
+    /// IANA time zone the expression is evaluated in.
+    pub timezone: String,
+    #[sqlx(try_from = "String")]
+    pub action: TaskAction,
+    pub command: Option<String>,
+    /// Stops and restarts warn players for this long first.
+    pub countdown_secs: Option<i32>,
+    #[sqlx(try_from = "String")]
+    pub catch_up: CatchUp,
+    pub enabled: bool,
+    pub created_by: Option<Uuid>,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub next_run_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_new_task"))]
+pub struct NewScheduledTask {
+    #[validate(length(min = 1, max = 64))]
+    pub name: String,
+    #[validate(custom(function = "validate_cron"))]
+    pub cron: String,
+    #[validate(custom(function = "validate_timezone"))]
+    pub timezone: Option<String>,
+    pub action: TaskAction,
+    #[validate(length(min = 1, max = 1024), custom(function = "validate_single_line"))]
+    pub command: Option<String>,
+    #[validate(range(min = 1, max = MAX_COUNTDOWN_SECS))]
+    pub countdown_secs: Option<u32>,
+    #[serde(default)]
+    pub catch_up: CatchUp,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+pub struct UpdateScheduledTask {
+    #[validate(length(min = 1, max = 64))]
+    pub name: Option<String>,
+    #[validate(custom(function = "validate_cron"))]
+    pub cron: Option<String>,
+    #[validate(custom(function = "validate_timezone"))]
+    pub timezone: Option<String>,
+    pub action: Option<TaskAction>,
+    #[validate(length(min = 1, max = 1024), custom(function = "validate_single_line"))]
+    pub command: Option<String>,
+    /// `0` removes the countdown.
+    #[validate(range(max = MAX_COUNTDOWN_SECS))]
+    pub countdown_secs: Option<u32>,
+    pub catch_up: Option<CatchUp>,
+    pub enabled: Option<bool>,
+}
+
+#[derive(Debug, Clone, Serialize, FromRow)]
+pub struct TaskRun {
+    pub uuid: Uuid,
+    pub task_uuid: Uuid,
+    /// Occurrence the run belongs to.
+    pub scheduled_for: DateTime<Utc>,
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    #[sqlx(try_from = "String")]
+    pub outcome: RunOutcome,
+    /// Command output or the reason the run failed or was missed.
+    pub message: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct RunQuery {
+    pub limit: Option<i64>,
+}
+
+fn validate_cron(input: &str) -> Result<(), ValidationError> {
+    Cron::from_str(input)
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("cron"))
+}
+
+fn validate_timezone(input: &str) -> Result<(), ValidationError> {
+    Tz::from_str(input)
+        .map(|_| ())
+        .map_err(|_| ValidationError::new("timezone"))
+}
+
+fn validate_new_task(task: &NewScheduledTask) -> Result<(), ValidationError> {
+    validate_action(
+        task.action,
+        task.command.as_deref(),
+        task.countdown_secs.is_some(),
+    )
+}
+
+/// Command tasks need a command, every other action must not carry one. Only
+/// stops and restarts count down.
+pub fn validate_action(
+    action: TaskAction,
+    command: Option<&str>,
+    countdown: bool,
+) -> Result<(), ValidationError> {
+    if (action == TaskAction::Command) != command.is_some() {
+        return Err(ValidationError::new("task_command"));
+    }
+    if countdown && !matches!(action, TaskAction::Stop | TaskAction::Restart) {
+        return Err(ValidationError::new("task_countdown"));
+    }
+    Ok(())
+}
+
+impl ScheduledTask {
+    pub fn new(value: NewScheduledTask, server_uuid: Uuid, created_by: Uuid) -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            server_uuid,
+            name: value.name,
+            cron: value.cron,
+            timezone: value
+                .timezone
+                .unwrap_or_else(|| DEFAULT_TIMEZONE.to_string()),
+            action: value.action,
+            command: value.command,
+            countdown_secs: value.countdown_secs.map(|secs| secs as i32),
+            catch_up: value.catch_up,
+            enabled: value.enabled.unwrap_or(true),
+            created_by: Some(created_by),
+            last_run_at: None,
+            next_run_at: None,
+        }
+    }
+
+    /// Applies a partial update. Switching away from the command action drops
+    /// the command, switching to anything but a stop or restart the countdown.
+    pub fn apply(&mut self, update: UpdateScheduledTask) {
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(cron) = update.cron {
+            self.cron = cron;
+        }
+        if let Some(timezone) = update.timezone {
+            self.timezone = timezone;
+        }
+        if let Some(action) = update.action {
+            if action != TaskAction::Command {
+                self.command = None;
+            }
+            if !matches!(action, TaskAction::Stop | TaskAction::Restart) {
+                self.countdown_secs = None;
+            }
+            self.action = action;
+        }
+        if let Some(command) = update.command {
+            self.command = Some(command);
+        }
+        if let Some(secs) = update.countdown_secs {
+            self.countdown_secs = (secs > 0).then_some(secs as i32);
+        }
+        if let Some(catch_up) = update.catch_up {
+            self.catch_up = catch_up;
+        }
+        if let Some(enabled) = update.enabled {
+            self.enabled = enabled;
+        }
+    }
+
+    /// Occurrences strictly after `after`, in UTC. Empty for an expression or
+    /// zone that no longer parses.
+    pub fn occurrences_after(
+        &self,
+        after: DateTime<Utc>,
+    ) -> impl Iterator<Item = DateTime<Utc>> + use<> {
+        let schedule = Cron::from_str(&self.cron)
+            .ok()
+            .zip(Tz::from_str(&self.timezone).ok());
+        schedule.into_iter().flat_map(move |(cron, tz)| {
+            cron.iter_after(after.with_timezone(&tz))
+                .map(|at| at.with_timezone(&Utc))
+        })
+    }
+
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.occurrences_after(after).next()
+    }
+}
+
+impl TaskAction {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskAction::Start => "start",
+            TaskAction::Stop => "stop",
+            TaskAction::Restart => "restart",
+            TaskAction::Command => "command",
+            TaskAction::Backup => "backup",
+        }
+    }
+}
+
+impl TryFrom<String> for TaskAction {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "start" => Ok(TaskAction::Start),
+            "stop" => Ok(TaskAction::Stop),
+            "restart" => Ok(TaskAction::Restart),
+            "command" => Ok(TaskAction::Command),
+            "backup" => Ok(TaskAction::Backup),
+            other => Err(format!("unknown task action {other}")),
+        }
+    }
+}
+
+impl CatchUp {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CatchUp::Skip => "skip",
+            CatchUp::RunOnce => "run_once",
+        }
+    }
+}
+
+impl TryFrom<String> for CatchUp {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "skip" => Ok(CatchUp::Skip),
+            "run_once" => Ok(CatchUp::RunOnce),
+            other => Err(format!("unknown catch up policy {other}")),
+        }
+    }
+}
+
+impl RunOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunOutcome::Success => "success",
+            RunOutcome::Failed => "failed",
+            RunOutcome::Missed => "missed",
+        }
+    }
+}
+
+impl TryFrom<String> for RunOutcome {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        match value.as_str() {
+            "success" => Ok(RunOutcome::Success),
+            "failed" => Ok(RunOutcome::Failed),
+            "missed" => Ok(RunOutcome::Missed),
+            other => Err(format!("unknown run outcome {other}")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn task(cron: &str, timezone: &str) -> ScheduledTask {
+        let new = NewScheduledTask {
+            name: "test".to_string(),
+            cron: cron.to_string(),
+            timezone: Some(timezone.to_string()),
+            action: TaskAction::Backup,
+            command: None,
+            countdown_secs: None,
+            catch_up: CatchUp::Skip,
+            enabled: None,
+        };
+        ScheduledTask::new(new, Uuid::new_v4(), Uuid::new_v4())
+    }
+
+    fn utc(y: i32, mo: u32, d: u32, h: u32, mi: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap()
+    }
+
+    #[test]
+    fn next_fire_is_strictly_after() {
+        let task = task("0 4 * * *", "UTC");
+        assert_eq!(
+            task.next_after(utc(2024, 5, 1, 3, 59, 59)),
+            Some(utc(2024, 5, 1, 4, 0, 0))
+        );
+        assert_eq!(
+            task.next_after(utc(2024, 5, 1, 4, 0, 0)),
+            Some(utc(2024, 5, 2, 4, 0, 0))
+        );
+    }
+
+    #[test]
+    fn six_fields_lead_with_seconds() {
+        let task = task("30 */15 * * * *", "UTC");
+        let fires: Vec<_> = task
+            .occurrences_after(utc(2024, 5, 1, 10, 0, 0))
+            .take(3)
+            .collect();
+        assert_eq!(
+            fires,
+            [
+                utc(2024, 5, 1, 10, 0, 30),
+                utc(2024, 5, 1, 10, 15, 30),
+                utc(2024, 5, 1, 10, 30, 30)
+            ]
+        );
+    }
+
+    #[test]
+    fn expression_is_evaluated_in_the_task_zone() {
+        // 04:00 in Berlin is 02:00 UTC in summer and 03:00 UTC in winter
+        let task = task("0 4 * * *", "Europe/Berlin");
+        assert_eq!(
+            task.next_after(utc(2024, 7, 1, 0, 0, 0)),
+            Some(utc(2024, 7, 1, 2, 0, 0))
+        );
+        assert_eq!(
+            task.next_after(utc(2024, 12, 1, 0, 0, 0)),
+            Some(utc(2024, 12, 1, 3, 0, 0))
+        );
+    }
+
+    #[test]
+    fn weekday_and_month_fields() {
+        // Mondays at 06:30, 2024-05-01 is a Wednesday
+        let mondays = task("30 6 * * MON", "UTC");
+        assert_eq!(
+            mondays.next_after(utc(2024, 5, 1, 0, 0, 0)),
+            Some(utc(2024, 5, 6, 6, 30, 0))
+        );
+        let new_year = task("0 0 1 JAN *", "UTC");
+        assert_eq!(
+            new_year.next_after(utc(2024, 5, 1, 0, 0, 0)),
+            Some(utc(2025, 1, 1, 0, 0, 0))
+        );
+    }
+
+    #[test]
+    fn broken_schedule_never_fires() {
+        assert_eq!(
+            task("not a cron", "UTC").next_after(utc(2024, 5, 1, 0, 0, 0)),
+            None
+        );
+        assert_eq!(
+            task("0 4 * * *", "Mars/Olympus").next_after(utc(2024, 5, 1, 0, 0, 0)),
+            None
+        );
+    }
+
+    #[test]
+    fn validation_accepts_what_the_scheduler_runs() {
+        assert!(validate_cron("0 4 * * *").is_ok());
+        assert!(validate_cron("*/10 * * * * *").is_ok());
+        assert!(validate_cron("61 * * * *").is_err());
+        assert!(validate_timezone("America/New_York").is_ok());
+        assert!(validate_timezone("EST5EDT9").is_err());
+    }
+}
@@ -0,0 +1,356 @@
+use serde::{Deserialize, Deserializer, Serialize};
+use sqlx::{prelude::FromRow, types::Json};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::domain::{
+    launch::{LaunchPlan, LaunchProfile, LaunchSource},
+    validation,
+};
+
+/// Lifecycle of a server process as tracked by the supervisor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ProcessState {
+    Stopped,
+    Starting,
+    Running,
+    Stopping,
+    Crashed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerProcess {
+    pub uuid: Uuid,
+    pub state: ProcessState,
+}
+
+fn default_java_path() -> String {
+    "java".to_string()
+}
+
+#[derive(Debug, Clone, Deserialize, Validate)]
+#[validate(schema(function = "validate_new_memory"))]
+pub struct NewServer {
+    #[validate(
+        length(min = 1, max = 32),
+        custom(function = "validation::validate_server_name")
+    )]
+    pub name: String,
+    #[validate(custom(function = "validation::validate_abs_path"))]
+    pub working_dir: String,
+    #[validate(length(min = 1, max = 255))]
+    pub jar_file: String,
+    #[validate(
+        length(min = 1, max = 1024),
+        custom(function = "validate_launch_command")
+    )]
+    pub launch_command: Option<String>,
+    #[serde(default = "default_java_path")]
+    #[validate(length(min = 1, max = 1024))]
+    pub java_path: String,
+    #[validate(range(min = 128))]
+    pub min_memory_mb: i32,
+    #[validate(range(min = 128))]
+    pub max_memory_mb: i32,
+    #[validate(range(min = 1, max = 65535))]
+    pub server_port: i32,
+    #[validate(range(min = 1, max = 65535))]
+    pub rcon_port: Option<i32>,
+    #[validate(range(min = 1, max = 65535))]
+    pub query_port: Option<i32>,
+    #[serde(default)]
+    pub auto_start: bool,
+    #[validate(length(min = 1, max = 128))]
+    pub rcon_password: Option<String>,
+    /// Game version the server runs, the Java runtime is checked against it.
+    #[validate(custom(function = "validation::validate_software_version"))]
+    pub minecraft_version: Option<String>,
+    #[serde(default)]
+    #[validate(nested)]
+    pub launch_profile: LaunchProfile,
+}
+
+#[derive(Debug, Clone, Default, Deserialize, Validate)]
+pub struct UpdateServer {
+    #[validate(
+        length(min = 1, max = 32),
+        custom(function = "validation::validate_server_name")
+    )]
+    pub name: Option<String>,
+    #[validate(custom(function = "validation::validate_abs_path"))]
+    pub working_dir: Option<String>,
+    #[validate(length(min = 1, max = 255))]
+    pub jar_file: Option<String>,
+    /// `null` clears the fields that are optional on the server, an absent
+    /// field leaves them as they are.
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(
+        length(min = 1, max = 1024),
+        custom(function = "validate_launch_command")
+    )]
+    pub launch_command: Option<Option<String>>,
+    #[validate(length(min = 1, max = 1024))]
+    pub java_path: Option<String>,
+    #[validate(range(min = 128))]
+    pub min_memory_mb: Option<i32>,
+    #[validate(range(min = 128))]
+    pub max_memory_mb: Option<i32>,
+    #[validate(range(min = 1, max = 65535))]
+    pub server_port: Option<i32>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1, max = 65535))]
+    pub rcon_port: Option<Option<i32>>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(range(min = 1, max = 65535))]
+    pub query_port: Option<Option<i32>>,
+    pub auto_start: Option<bool>,
+    #[validate(length(min = 1, max = 128))]
+    pub rcon_password: Option<String>,
+    #[serde(default, deserialize_with = "double_option")]
+    #[validate(custom(function = "validation::validate_software_version"))]
+    pub minecraft_version: Option<Option<String>>,
+    /// Replaces the whole profile.
+    #[validate(nested)]
+    pub launch_profile: Option<LaunchProfile>,
+}
+
+#[derive(Debug, Clone)]
+pub struct InternalNewServer {
+    pub uuid: Uuid,
+    pub name: String,
+    pub working_dir: String,
+    pub jar_file: String,
+    pub launch_command: Option<String>,
+    pub java_path: String,
+    pub min_memory_mb: i32,
+    pub max_memory_mb: i32,
+    pub server_port: i32,
+    pub rcon_port: Option<i32>,
+    pub query_port: Option<i32>,
+    pub auto_start: bool,
+    pub owner: Uuid,
+    pub rcon_password: Option<Vec<u8>>,
+    pub minecraft_version: Option<String>,
+    pub launch_profile: LaunchProfile,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Server {
+    pub uuid: Uuid,
+    pub name: String,
+    pub working_dir: String,
+    pub jar_file: String,
+    pub launch_command: Option<String>,
+    pub java_path: String,
+    pub min_memory_mb: i32,
+    pub max_memory_mb: i32,
+    pub server_port: i32,
+    pub rcon_port: Option<i32>,
+    pub query_port: Option<i32>,
+    pub auto_start: bool,
+    pub owner: Uuid,
+    /// Sealed with the daemon secret, never leaves the daemon.
+    #[serde(skip)]
+    pub rcon_password: Option<Vec<u8>>,
+    /// Set by the installer or by hand, unknown for servers set up before.
+    pub minecraft_version: Option<String>,
+    /// How the server is launched, unless it has a custom launch command.
+    pub launch_profile: Json<LaunchProfile>,
+}
+
+/// Tells a field sent as `null` (`Some(None)`) apart from one not sent at all
+/// (`None`, through `#[serde(default)]`).
+fn double_option<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+/// Splits a custom launch command into words the way a POSIX shell would,
+/// `None` when its quotes do not close or no word is left.
+pub fn split_launch_command(command: &str) -> Option<Vec<String>> {
+    shlex::split(command).filter(|words| !words.is_empty())
+}
+
+fn validate_launch_command(command: &str) -> Result<(), ValidationError> {
+    split_launch_command(command)
+        .map(|_| ())
+        .ok_or_else(|| ValidationError::new("launch_command"))
+}
+
+fn validate_new_memory(server: &NewServer) -> Result<(), ValidationError> {
+    validate_memory(server.min_memory_mb, server.max_memory_mb)?;
+    let (min_heap_mb, max_heap_mb) = server
+        .launch_profile
+        .heap(server.min_memory_mb, server.max_memory_mb);
+    validate_memory(min_heap_mb, max_heap_mb)
+}
+
+pub fn validate_memory(min_memory_mb: i32, max_memory_mb: i32) -> Result<(), ValidationError> {
+    if min_memory_mb <= max_memory_mb {
+        Ok(())
+    } else {
+        Err(ValidationError::new("memory_range"))
+    }
+}
+
+impl ProcessState {
+    /// Whether a process exists (or is being spawned) for the server.
+    pub fn is_alive(self) -> bool {
+        matches!(
+            self,
+            ProcessState::Starting | ProcessState::Running | ProcessState::Stopping
+        )
+    }
+}
+
+impl InternalNewServer {
+    pub fn new(value: NewServer, owner: Uuid, rcon_password: Option<Vec<u8>>) -> Self {
+        Self {
+            uuid: Uuid::new_v4(),
+            name: value.name,
+            working_dir: value.working_dir,
+            jar_file: value.jar_file,
+            launch_command: value.launch_command,
+            java_path: value.java_path,
+            min_memory_mb: value.min_memory_mb,
+            max_memory_mb: value.max_memory_mb,
+            server_port: value.server_port,
+            rcon_port: value.rcon_port,
+            query_port: value.query_port,
+            auto_start: value.auto_start,
+            owner,
+            rcon_password,
+            minecraft_version: value.minecraft_version,
+            launch_profile: value.launch_profile,
+        }
+    }
+}
+
+impl Server {
+    /// Applies a partial update, leaving every field that was not sent untouched.
+    /// The RCON password is sealed by the caller and not applied here.
+    pub fn apply(&mut self, update: UpdateServer) {
+        if let Some(name) = update.name {
+            self.name = name;
+        }
+        if let Some(working_dir) = update.working_dir {
+            self.working_dir = working_dir;
+        }
+        if let Some(jar_file) = update.jar_file {
+            self.jar_file = jar_file;
+        }
+        if let Some(launch_command) = update.launch_command {
+            self.launch_command = launch_command;
+        }
+        if let Some(java_path) = update.java_path {
+            self.java_path = java_path;
+        }
+        if let Some(min_memory_mb) = update.min_memory_mb {
+            self.min_memory_mb = min_memory_mb;
+        }
+        if let Some(max_memory_mb) = update.max_memory_mb {
+            self.max_memory_mb = max_memory_mb;
+        }
+        if let Some(server_port) = update.server_port {
+            self.server_port = server_port;
+        }
+        if let Some(rcon_port) = update.rcon_port {
+            self.rcon_port = rcon_port;
+        }
+        if let Some(query_port) = update.query_port {
+            self.query_port = query_port;
+        }
+        if let Some(auto_start) = update.auto_start {
+            self.auto_start = auto_start;
+        }
+        if let Some(minecraft_version) = update.minecraft_version {
+            self.minecraft_version = minecraft_version;
+        }
+        if let Some(launch_profile) = update.launch_profile {
+            self.launch_profile = Json(launch_profile);
+        }
+    }
+
+    /// Whether commands can be sent over RCON when stdin is unavailable.
+    pub fn rcon_available(&self) -> bool {
+        self.rcon_port.is_some() && self.rcon_password.is_some()
+    }
+
+    /// Minimum and maximum heap in MiB the launch profile starts the JVM with.
+    pub fn heap(&self) -> (i32, i32) {
+        self.launch_profile
+            .heap(self.min_memory_mb, self.max_memory_mb)
+    }
+
+    /// Builds the argv used to launch the server, a custom launch command wins
+    /// over the java path, memory limits and launch profile. A jar file
+    /// starting with `@` is a java argument file, as newer Forge installs use.
+    pub fn launch_args(&self) -> Vec<String> {
+        if let Some(command) = self.launch_command.as_deref() {
+            // Checked when it was set, one that does not parse starts nothing
+            return split_launch_command(command).unwrap_or_default();
+        }
+
+        let (min_heap_mb, max_heap_mb) = self.heap();
+        let mut args = vec![
+            self.java_path.clone(),
+            format!("-Xms{min_heap_mb}M"),
+            format!("-Xmx{max_heap_mb}M"),
+        ];
+        args.extend(self.launch_profile.jvm_options(max_heap_mb));
+        if !self.jar_file.starts_with('@') {
+            args.push("-jar".to_string());
+        }
+        args.push(self.jar_file.clone());
+        args.extend(self.launch_profile.server_args.iter().cloned());
+        args
+    }
+
+    pub fn launch_plan(&self) -> LaunchPlan {
+        let source = if self.launch_command.is_some() {
+            LaunchSource::Custom
+        } else {
+            LaunchSource::Profile
+        };
+        LaunchPlan::new(source, self.launch_args())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn launch_commands_split_like_a_shell() {
+        assert_eq!(
+            split_launch_command("java -Xmx4G -jar server.jar nogui").unwrap(),
+            ["java", "-Xmx4G", "-jar", "server.jar", "nogui"]
+        );
+        assert_eq!(
+            split_launch_command(r#"java "-Dfoo=a b" -jar '/srv/my server/paper.jar'"#).unwrap(),
+            ["java", "-Dfoo=a b", "-jar", "/srv/my server/paper.jar"]
+        );
+        assert_eq!(
+            split_launch_command(r#"java -Dmotd="it's on" -jar server\ 1.jar"#).unwrap(),
+            ["java", "-Dmotd=it's on", "-jar", "server 1.jar"]
+        );
+    }
+
+    #[test]
+    fn unparsable_launch_commands_are_refused() {
+        for command in [
+            r#"java "-Dfoo=a b -jar server.jar"#,
+            "java 'unclosed",
+            "   ",
+            "",
+        ] {
+            assert_eq!(split_launch_command(command), None, "{command:?}");
+            assert!(validate_launch_command(command).is_err());
+        }
+        assert!(validate_launch_command("./start.sh").is_ok());
+    }
+}
@@ -0,0 +1,78 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::domain::server::ProcessState;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlayerSample {
+    pub name: String,
+    pub id: String,
+}
+
+/// What a Server List Ping reported.
+#[derive(Debug, Clone, Serialize)]
+pub struct PingStatus {
+    pub version: String,
+    pub protocol: i32,
+    pub motd: String,
+    pub players_online: i64,
+    pub players_max: i64,
+    pub sample: Vec<PlayerSample>,
+    /// `data:image/png;base64,...` as sent by the server.
+    pub favicon: Option<String>,
+    pub latency_ms: Option<u64>,
+    /// Answered through the pre 1.7 protocol, which carries no sample or favicon.
+    pub legacy: bool,
+}
+
+/// Basic stat of the GameSpy4 query protocol.
+#[derive(Debug, Clone, Serialize)]
+pub struct QueryBasic {
+    pub motd: String,
+    pub game_type: String,
+    pub map: String,
+    pub players_online: i64,
+    pub players_max: i64,
+    pub host_port: u16,
+    pub host_ip: String,
+}
+
+/// Full stat of the GameSpy4 query protocol.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct QueryStatus {
+    pub motd: String,
+    pub game_type: String,
+    pub game_id: String,
+    pub version: String,
+    /// Server software as reported in front of the plugin list, none on vanilla.
+    pub server_mod: Option<String>,
+    pub plugins: Vec<String>,
+    pub map: String,
+    pub players_online: i64,
+    pub players_max: i64,
+    pub host_port: u16,
+    pub host_ip: String,
+    pub players: Vec<String>,
+}
+
+/// Last poll result for a server.
+#[derive(Debug, Clone, Serialize)]
+pub struct PollResult {
+    pub reachable: bool,
+    pub checked_at: DateTime<Utc>,
+    pub ping: Option<PingStatus>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ServerStatus {
+    pub uuid: Uuid,
+    pub process: ProcessState,
+    pub reachable: bool,
+    pub checked_at: Option<DateTime<Utc>>,
+    pub ping: Option<PingStatus>,
+    pub error: Option<String>,
+    /// Only present when the server has a query port and answered it.
+    pub query: Option<QueryStatus>,
+}
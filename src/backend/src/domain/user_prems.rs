@@ -7,6 +7,16 @@ use uuid::Uuid;
 #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash, Serialize, Deserialize)]
 pub enum UserActions {
     ManageUsers,
+    ViewServers,
+    ManageServers,
+    ControlServers,
+    ReadConsole,
+    SendCommands,
+    ManagePlayers,
+    ManageSchedules,
+    ManageBackups,
+    ReadFiles,
+    ManageFiles,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
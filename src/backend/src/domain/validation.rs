@@ -1,9 +1,15 @@
+use std::path::Path;
+
 use lazy_static::lazy_static;
 use regex::Regex;
 use validator::ValidationError;
 
 lazy_static! {
     static ref ALPHANUM: Regex = Regex::new(r"^[a-zA-Z0-9]+$").unwrap();
+    static ref SERVER_NAME: Regex = Regex::new(r"^[a-zA-Z0-9_-]+$").unwrap();
+    static ref PLAYER_NAME: Regex = Regex::new(r"^[a-zA-Z0-9_]{1,16}$").unwrap();
+    static ref PROPERTY_KEY: Regex = Regex::new(r"^[a-zA-Z0-9._-]{1,64}$").unwrap();
+    static ref SOFTWARE_VERSION: Regex = Regex::new(r"^[a-zA-Z0-9][a-zA-Z0-9._+-]{0,63}$").unwrap();
 }
 
 pub fn validate_alphanum(input: &str) -> Result<(), ValidationError> {
@@ -13,3 +19,45 @@ pub fn validate_alphanum(input: &str) -> Result<(), ValidationError> {
         Err(ValidationError::new("alphanum"))
     }
 }
+
+pub fn validate_server_name(input: &str) -> Result<(), ValidationError> {
+    if SERVER_NAME.is_match(input) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("server_name"))
+    }
+}
+
+pub fn validate_abs_path(input: &str) -> Result<(), ValidationError> {
+    if Path::new(input).is_absolute() {
+        Ok(())
+    } else {
+        Err(ValidationError::new("absolute_path"))
+    }
+}
+
+pub fn validate_property_key(input: &str) -> Result<(), ValidationError> {
+    if PROPERTY_KEY.is_match(input) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("property_key"))
+    }
+}
+
+pub fn validate_player_name(input: &str) -> Result<(), ValidationError> {
+    if PLAYER_NAME.is_match(input) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("player_name"))
+    }
+}
+
+/// A game, build or loader version. They end up in URLs and cache paths, so
+/// nothing that could leave a path segment.
+pub fn validate_software_version(input: &str) -> Result<(), ValidationError> {
+    if SOFTWARE_VERSION.is_match(input) {
+        Ok(())
+    } else {
+        Err(ValidationError::new("software_version"))
+    }
+}
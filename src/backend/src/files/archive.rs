@@ -0,0 +1,760 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Seek, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Datelike, Local, Timelike};
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use tar::{Builder, EntryType, Header};
+use thiserror::Error;
+use uuid::Uuid;
+use zip::{CompressionMethod, ZipArchive, ZipWriter, result::ZipError, write::SimpleFileOptions};
+
+use crate::{
+    domain::file::{ArchiveFormat, ArchiveSummary, file_path},
+    infra::archive,
+    jobs::JobHandle,
+};
+
+#[derive(Debug, Error)]
+pub enum ArchiveError {
+    #[error("not a zip, tar or tar.gz archive")]
+    UnsupportedFormat,
+    #[error("entry {0} points outside the destination")]
+    UnsafePath(String),
+    #[error("no write access to {0}")]
+    Forbidden(String),
+    #[error("{0} already exists")]
+    Conflict(String),
+    #[error("archive expands to more than {0} bytes")]
+    TooLarge(u64),
+    #[error("archive has more than {0} entries")]
+    TooManyEntries(u64),
+    #[error("{needed} bytes needed but only {available} are free")]
+    NoSpace { needed: u64, available: u64 },
+    #[error(transparent)]
+    Zip(#[from] ZipError),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Caps on what one extraction may write, so an archive bomb runs into them
+/// rather than filling the disk.
+#[derive(Debug, Clone, Copy)]
+pub struct ExtractLimits {
+    pub max_bytes: u64,
+    pub max_entries: u64,
+}
+
+/// Writes `entries` (paths relative to `root`) into an archive at `dest`.
+/// Symlinks and special files are skipped, as is anything `include` rejects,
+/// which gets the path relative to `root`. Progress is reported in bytes
+/// read. Blocking.
+pub fn compress(
+    root: &Path,
+    entries: &[PathBuf],
+    dest: &Path,
+    format: ArchiveFormat,
+    include: &dyn Fn(&Path) -> bool,
+    job: &JobHandle,
+) -> Result<ArchiveSummary, ArchiveError> {
+    job.step("compressing");
+    // Sizing everything first gives the job a total to report against
+    let mut total = 0;
+    archive::walk(root, entries, &[], &[], &mut |relative, _, meta| {
+        if meta.is_file() && include(relative) {
+            total += meta.len();
+        }
+        Ok(())
+    })?;
+    job.set_total(total);
+
+    let mut summary = ArchiveSummary::default();
+    let file = BufWriter::new(File::create(dest)?);
+    let file = match format {
+        ArchiveFormat::Zip => write_zip(root, entries, file, include, job, &mut summary)?,
+        ArchiveFormat::TarGz => write_tar_gz(root, entries, file, include, job, &mut summary)?,
+    };
+    let file = file.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+    summary.size_bytes = file.metadata()?.len();
+    Ok(summary)
+}
+
+/// Extracts `archive` (zip, tar or tar.gz) into the directory `dest`. Every
+/// entry is checked before anything is written: names have to stay inside
+/// `dest`, `allowed` has to accept them (it gets the path relative to
+/// `dest`), and nothing may be overwritten unless `overwrite`. Symlinks,
+/// hard links and special files are skipped. Declared sizes are checked
+/// against `limits` and the free space up front, actual sizes while writing
+/// since a zip can lie about them. `dest` is only created once the checks
+/// passed, a failure part way leaves what was already extracted in place.
+/// Progress is reported in bytes written. Blocking.
+pub fn extract(
+    archive: &Path,
+    dest: &Path,
+    limits: ExtractLimits,
+    overwrite: bool,
+    allowed: &dyn Fn(&Path) -> bool,
+    job: &JobHandle,
+) -> Result<ArchiveSummary, ArchiveError> {
+    let format = detect(archive)?;
+
+    job.step("checking entries");
+    let mut declared = 0u64;
+    let mut count = 0u64;
+    for_each_entry(archive, format, &mut |entry, _| {
+        job.check_cancelled()?;
+        count += 1;
+        if count > limits.max_entries {
+            return Err(ArchiveError::TooManyEntries(limits.max_entries));
+        }
+        let Some(kind) = entry.kind else {
+            return Ok(());
+        };
+        check_entry(dest, &entry.path, kind, overwrite, allowed)?;
+        declared = declared.saturating_add(entry.size);
+        if declared > limits.max_bytes {
+            return Err(ArchiveError::TooLarge(limits.max_bytes));
+        }
+        Ok(())
+    })?;
+    fs::create_dir_all(dest)?;
+    let available = free_space(dest)?;
+    if declared > available {
+        return Err(ArchiveError::NoSpace {
+            needed: declared,
+            available,
+        });
+    }
+    job.set_total(declared);
+
+    job.step("extracting");
+    let mut summary = ArchiveSummary::default();
+    for_each_entry(archive, format, &mut |entry, reader| {
+        job.check_cancelled()?;
+        let Some(kind) = entry.kind else {
+            summary.skipped += 1;
+            return Ok(());
+        };
+        let target = make_parents(dest, &entry.path)?;
+        match kind {
+            EntryKind::Directory => make_dir(&target, &entry.path)?,
+            EntryKind::File => {
+                let budget = limits.max_bytes - summary.size_bytes;
+                summary.size_bytes += write_entry(&target, reader, entry.mode, budget, job)
+                    .map_err(|e| match e.kind() {
+                        ErrorKind::FileTooLarge => ArchiveError::TooLarge(limits.max_bytes),
+                        _ => e.into(),
+                    })?;
+            }
+        }
+        summary.entries += 1;
+        Ok(())
+    })?;
+    Ok(summary)
+}
+
+#[derive(Debug, Clone, Copy)]
+enum Format {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    File,
+    Directory,
+}
+
+/// One archive entry, `kind` is `None` for the ones never extracted.
+struct Entry {
+    path: PathBuf,
+    kind: Option<EntryKind>,
+    size: u64,
+    mode: Option<u32>,
+}
+
+fn detect(path: &Path) -> Result<Format, ArchiveError> {
+    let mut header = [0u8; 512];
+    let mut file = File::open(path)?;
+    let mut read = 0;
+    while read < header.len() {
+        match file.read(&mut header[read..])? {
+            0 => break,
+            n => read += n,
+        }
+    }
+
+    if header[..read].starts_with(b"PK\x03\x04") || header[..read].starts_with(b"PK\x05\x06") {
+        Ok(Format::Zip)
+    } else if header[..read].starts_with(&[0x1f, 0x8b]) {
+        Ok(Format::TarGz)
+    } else if read == header.len() && &header[257..262] == b"ustar" {
+        Ok(Format::Tar)
+    } else {
+        Err(ArchiveError::UnsupportedFormat)
+    }
+}
+
+fn for_each_entry(
+    archive: &Path,
+    format: Format,
+    visit: &mut dyn FnMut(Entry, &mut dyn Read) -> Result<(), ArchiveError>,
+) -> Result<(), ArchiveError> {
+    let file = BufReader::new(File::open(archive)?);
+    match format {
+        Format::Zip => zip_entries(file, visit),
+        Format::Tar => tar_entries(file, visit),
+        Format::TarGz => tar_entries(GzDecoder::new(file), visit),
+    }
+}
+
+fn zip_entries<R: Read + Seek>(
+    reader: R,
+    visit: &mut dyn FnMut(Entry, &mut dyn Read) -> Result<(), ArchiveError>,
+) -> Result<(), ArchiveError> {
+    let mut zip = ZipArchive::new(reader)?;
+    for index in 0..zip.len() {
+        let mut file = zip.by_index(index)?;
+        let name = file.name().to_string();
+        let path = file_path(name.trim_end_matches('/')).ok_or(ArchiveError::UnsafePath(name))?;
+        let kind = if file.is_symlink() {
+            None
+        } else if file.is_dir() {
+            Some(EntryKind::Directory)
+        } else {
+            Some(EntryKind::File)
+        };
+        let entry = Entry {
+            path,
+            kind,
+            size: file.size(),
+            mode: file.unix_mode(),
+        };
+        visit(entry, &mut file)?;
+    }
+    Ok(())
+}
+
+fn tar_entries<R: Read>(
+    reader: R,
+    visit: &mut dyn FnMut(Entry, &mut dyn Read) -> Result<(), ArchiveError>,
+) -> Result<(), ArchiveError> {
+    let mut tar = tar::Archive::new(reader);
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let kind = match entry.header().entry_type() {
+            EntryType::Regular | EntryType::Continuous => Some(EntryKind::File),
+            EntryType::Directory => Some(EntryKind::Directory),
+            // Extended headers describe the entry after them and are read
+            // by the tar crate itself
+            EntryType::XHeader | EntryType::XGlobalHeader => continue,
+            EntryType::GNULongName | EntryType::GNULongLink => continue,
+            _ => None,
+        };
+        let path = file_path(&name).ok_or(ArchiveError::UnsafePath(name))?;
+        let entry_info = Entry {
+            path,
+            kind,
+            size: entry.size(),
+            mode: entry.header().mode().ok(),
+        };
+        visit(entry_info, &mut entry)?;
+    }
+    Ok(())
+}
+
+/// Refuses entries the extraction must not write, before anything is.
+fn check_entry(
+    dest: &Path,
+    path: &Path,
+    kind: EntryKind,
+    overwrite: bool,
+    allowed: &dyn Fn(&Path) -> bool,
+) -> Result<(), ArchiveError> {
+    let display = path.display().to_string();
+    if path.as_os_str().is_empty() {
+        // The destination itself, a `./` entry
+        return match kind {
+            EntryKind::Directory => Ok(()),
+            EntryKind::File => Err(ArchiveError::UnsafePath(display)),
+        };
+    }
+    if !allowed(path) {
+        return Err(ArchiveError::Forbidden(display));
+    }
+
+    match fs::symlink_metadata(dest.join(path)) {
+        Ok(meta) if meta.is_dir() && kind == EntryKind::Directory => Ok(()),
+        Ok(meta) if meta.is_dir() => Err(ArchiveError::Conflict(display)),
+        Ok(_) if kind == EntryKind::Directory || !overwrite => Err(ArchiveError::Conflict(display)),
+        Ok(_) => Ok(()),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Creates the missing parents of `path` inside `dest` and returns where the
+/// entry goes. Existing parents have to be real directories, a symlink among
+/// them could lead the entry out of `dest`.
+fn make_parents(dest: &Path, path: &Path) -> Result<PathBuf, ArchiveError> {
+    let mut dir = dest.to_path_buf();
+    for component in path.parent().into_iter().flat_map(Path::components) {
+        dir.push(component);
+        make_dir(&dir, path)?;
+    }
+    Ok(dest.join(path))
+}
+
+fn make_dir(dir: &Path, entry: &Path) -> Result<(), ArchiveError> {
+    match fs::symlink_metadata(dir) {
+        Ok(meta) if meta.is_dir() => Ok(()),
+        Ok(_) => Err(ArchiveError::UnsafePath(entry.display().to_string())),
+        Err(e) if e.kind() == ErrorKind::NotFound => match fs::create_dir(dir) {
+            Err(e) if e.kind() != ErrorKind::AlreadyExists => Err(e.into()),
+            _ => make_dir(dir, entry),
+        },
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes one file through a temporary sibling, which replaces a symlink
+/// rather than following it. Fails with `FileTooLarge` past `budget` bytes.
+fn write_entry(
+    target: &Path,
+    reader: &mut dyn Read,
+    mode: Option<u32>,
+    budget: u64,
+    job: &JobHandle,
+) -> io::Result<u64> {
+    let name = target.file_name().unwrap_or_default().to_string_lossy();
+    let temp = target.with_file_name(format!(".{name}.{}.tmp", Uuid::new_v4().simple()));
+    let written = (|| {
+        let file = OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .mode(0o644)
+            .custom_flags(nix::libc::O_NOFOLLOW)
+            .open(&temp)?;
+        let mut writer = CountingWriter {
+            inner: BufWriter::new(file),
+            job,
+            written: 0,
+        };
+        io::copy(&mut reader.take(budget.saturating_add(1)), &mut writer)?;
+        if writer.written > budget {
+            return Err(io::Error::from(ErrorKind::FileTooLarge));
+        }
+        let file = writer.inner.into_inner().map_err(|e| e.into_error())?;
+        if let Some(mode) = mode {
+            // Only permission bits, no setuid or sticky bits from an archive
+            file.set_permissions(fs::Permissions::from_mode(mode & 0o777 | 0o600))?;
+        }
+        file.sync_all()?;
+        fs::rename(&temp, target)?;
+        Ok(writer.written)
+    })();
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    written
+}
+
+fn write_zip<W: Write + Seek>(
+    root: &Path,
+    entries: &[PathBuf],
+    writer: W,
+    include: &dyn Fn(&Path) -> bool,
+    job: &JobHandle,
+    summary: &mut ArchiveSummary,
+) -> Result<W, ArchiveError> {
+    let mut zip = ZipWriter::new(writer);
+    let base = SimpleFileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    archive::walk(root, entries, &[], &[], &mut |relative, path, meta| {
+        job.check_cancelled()?;
+        if !include(relative) {
+            return Ok(());
+        }
+        let name = relative.to_string_lossy();
+        let mut options = base.unix_permissions(meta.permissions().mode() & 0o777);
+        if let Some(time) = zip_time(meta) {
+            options = options.last_modified_time(time);
+        }
+
+        if meta.is_dir() {
+            zip.add_directory(name, options).map_err(io::Error::other)?;
+        } else if meta.is_file() {
+            let Some(file) = open_source(path)? else {
+                return Ok(());
+            };
+            zip.start_file(name, options.large_file(meta.len() >= u32::MAX as u64))
+                .map_err(io::Error::other)?;
+            let mut reader = CountingReader { inner: file, job };
+            io::copy(&mut reader, &mut zip)?;
+        } else {
+            summary.skipped += 1;
+            return Ok(());
+        }
+        summary.entries += 1;
+        Ok(())
+    })?;
+
+    Ok(zip.finish()?)
+}
+
+fn write_tar_gz<W: Write>(
+    root: &Path,
+    entries: &[PathBuf],
+    writer: W,
+    include: &dyn Fn(&Path) -> bool,
+    job: &JobHandle,
+    summary: &mut ArchiveSummary,
+) -> Result<W, ArchiveError> {
+    let mut builder = Builder::new(GzEncoder::new(writer, Compression::default()));
+    builder.follow_symlinks(false);
+
+    archive::walk(root, entries, &[], &[], &mut |relative, path, meta| {
+        job.check_cancelled()?;
+        if !include(relative) {
+            return Ok(());
+        }
+        if meta.is_dir() {
+            builder.append_dir(relative, path)?;
+        } else if meta.is_file() {
+            let Some(file) = open_source(path)? else {
+                return Ok(());
+            };
+            let mut header = Header::new_gnu();
+            header.set_metadata(meta);
+            header.set_entry_type(EntryType::Regular);
+            let reader = CountingReader {
+                inner: file.take(meta.len()),
+                job,
+            };
+            builder.append_data(&mut header, relative, reader)?;
+        } else {
+            summary.skipped += 1;
+            return Ok(());
+        }
+        summary.entries += 1;
+        Ok(())
+    })?;
+
+    Ok(builder.into_inner()?.finish()?)
+}
+
+/// Files may vanish while the server keeps running.
+fn open_source(path: &Path) -> io::Result<Option<File>> {
+    match File::open(path) {
+        Ok(file) => Ok(Some(file)),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Zip timestamps are local time without a zone, from 1980 on.
+fn zip_time(meta: &fs::Metadata) -> Option<zip::DateTime> {
+    let modified = DateTime::<Local>::from(meta.modified().ok()?);
+    zip::DateTime::from_date_and_time(
+        u16::try_from(modified.year()).ok()?,
+        modified.month() as u8,
+        modified.day() as u8,
+        modified.hour() as u8,
+        modified.minute() as u8,
+        modified.second() as u8,
+    )
+    .ok()
+}
+
+fn free_space(dir: &Path) -> io::Result<u64> {
+    let stat = nix::sys::statvfs::statvfs(dir).map_err(io::Error::from)?;
+    Ok((stat.blocks_available() as u64).saturating_mul(stat.fragment_size() as u64))
+}
+
+/// Reports what is read as job progress and stops once it is cancelled.
+struct CountingReader<'a, R> {
+    inner: R,
+    job: &'a JobHandle,
+}
+
+impl<R: Read> Read for CountingReader<'_, R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.job.check_cancelled()?;
+        let n = self.inner.read(buf)?;
+        self.job.advance(n as u64);
+        Ok(n)
+    }
+}
+
+/// Reports what is written as job progress and stops once it is cancelled.
+struct CountingWriter<'a, W> {
+    inner: W,
+    job: &'a JobHandle,
+    written: u64,
+}
+
+impl<W: Write> Write for CountingWriter<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.job.check_cancelled()?;
+        let n = self.inner.write(buf)?;
+        self.written += n as u64;
+        self.job.advance(n as u64);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use crate::domain::job::JobKind;
+
+    use super::*;
+
+    const LIMITS: ExtractLimits = ExtractLimits {
+        max_bytes: 1024 * 1024,
+        max_entries: 100,
+    };
+
+    fn job() -> JobHandle {
+        JobHandle::detached(JobKind::Extract)
+    }
+
+    /// A zip of `(name, contents)` files, names written as given.
+    fn zip_of(path: &Path, files: &[(&str, &[u8])]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, contents) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(contents).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    /// A tar of files named as given, bypassing the checks the tar crate
+    /// does when writing paths.
+    fn tar_of(names: &[&str]) -> Vec<u8> {
+        let mut builder = Builder::new(Vec::new());
+        for name in names {
+            let mut header = Header::new_gnu();
+            header.as_old_mut().name[..name.len()].copy_from_slice(name.as_bytes());
+            header.set_entry_type(EntryType::Regular);
+            header.set_mode(0o644);
+            header.set_size(4);
+            header.set_cksum();
+            builder.append(&header, &b"evil"[..]).unwrap();
+        }
+        builder.into_inner().unwrap()
+    }
+
+    fn extract_all(
+        archive: &Path,
+        dest: &Path,
+        overwrite: bool,
+    ) -> Result<ArchiveSummary, ArchiveError> {
+        extract(archive, dest, LIMITS, overwrite, &|_| true, &job())
+    }
+
+    #[test]
+    fn zip_entries_leaving_the_destination_are_refused() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dest = scratch.path().join("world");
+
+        for name in ["../evil.txt", "world/../../evil.txt", "/tmp/evil.txt"] {
+            let archive = scratch.path().join("slip.zip");
+            zip_of(&archive, &[("fine.txt", b"fine"), (name, b"evil")]);
+
+            let result = extract_all(&archive, &dest, false);
+            assert!(matches!(result, Err(ArchiveError::UnsafePath(_))), "{name}");
+        }
+        // Checked before anything was written
+        assert!(!dest.exists());
+        assert!(!scratch.path().join("evil.txt").exists());
+    }
+
+    #[test]
+    fn tar_entries_leaving_the_destination_are_refused() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dest = scratch.path().join("world");
+
+        for name in ["../evil", "a/../../evil", "/tmp/evil"] {
+            let archive = scratch.path().join("slip.tar");
+            fs::write(&archive, tar_of(&["fine", name])).unwrap();
+            let result = extract_all(&archive, &dest, false);
+            assert!(matches!(result, Err(ArchiveError::UnsafePath(_))), "{name}");
+
+            let archive = scratch.path().join("slip.tar.gz");
+            let mut gz = GzEncoder::new(File::create(&archive).unwrap(), Compression::fast());
+            gz.write_all(&tar_of(&["fine", name])).unwrap();
+            gz.finish().unwrap();
+            let result = extract_all(&archive, &dest, false);
+            assert!(matches!(result, Err(ArchiveError::UnsafePath(_))), "{name}");
+        }
+        assert!(!dest.exists());
+        assert!(!scratch.path().join("evil").exists());
+    }
+
+    #[test]
+    fn symlink_entries_are_skipped() {
+        let scratch = tempfile::tempdir().unwrap();
+        let archive = scratch.path().join("links.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        zip.add_symlink("escape", "/etc", SimpleFileOptions::default())
+            .unwrap();
+        zip.start_file("escape/passwd", SimpleFileOptions::default())
+            .unwrap();
+        zip.write_all(b"evil").unwrap();
+        zip.finish().unwrap();
+
+        let dest = scratch.path().join("world");
+        let summary = extract_all(&archive, &dest, false).unwrap();
+        assert_eq!(summary.skipped, 1);
+        assert_eq!(summary.entries, 1);
+        let escape = fs::symlink_metadata(dest.join("escape")).unwrap();
+        assert!(escape.is_dir());
+        assert_eq!(fs::read(dest.join("escape/passwd")).unwrap(), b"evil");
+    }
+
+    #[test]
+    fn symlinked_directories_in_the_destination_are_not_followed() {
+        let scratch = tempfile::tempdir().unwrap();
+        let (dest, outside) = (scratch.path().join("world"), scratch.path().join("outside"));
+        fs::create_dir_all(&dest).unwrap();
+        fs::create_dir_all(&outside).unwrap();
+        symlink(&outside, dest.join("plugins")).unwrap();
+
+        let archive = scratch.path().join("plugins.zip");
+        zip_of(&archive, &[("plugins/evil.jar", b"evil")]);
+        let result = extract_all(&archive, &dest, false);
+
+        assert!(matches!(result, Err(ArchiveError::UnsafePath(_))));
+        assert!(!outside.join("evil.jar").exists());
+    }
+
+    #[test]
+    fn overwriting_a_symlinked_file_replaces_the_link() {
+        let scratch = tempfile::tempdir().unwrap();
+        let (dest, secret) = (scratch.path().join("world"), scratch.path().join("secret"));
+        fs::create_dir_all(&dest).unwrap();
+        fs::write(&secret, b"secret").unwrap();
+        symlink(&secret, dest.join("server.properties")).unwrap();
+
+        let archive = scratch.path().join("config.zip");
+        zip_of(&archive, &[("server.properties", b"motd=hi")]);
+        let refused = extract_all(&archive, &dest, false);
+        assert!(matches!(refused, Err(ArchiveError::Conflict(_))));
+
+        extract_all(&archive, &dest, true).unwrap();
+        assert_eq!(fs::read(&secret).unwrap(), b"secret");
+        let replaced = dest.join("server.properties");
+        assert!(fs::symlink_metadata(&replaced).unwrap().is_file());
+        assert_eq!(fs::read(replaced).unwrap(), b"motd=hi");
+    }
+
+    #[test]
+    fn limits_and_rules_stop_extraction_up_front() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dest = scratch.path().join("world");
+        let archive = scratch.path().join("big.zip");
+        zip_of(
+            &archive,
+            &[("a", &[0; 600]), ("b", &[0; 600]), ("c/d", b"d")],
+        );
+
+        let small = ExtractLimits {
+            max_bytes: 1000,
+            max_entries: 100,
+        };
+        let result = extract(&archive, &dest, small, false, &|_| true, &job());
+        assert!(matches!(result, Err(ArchiveError::TooLarge(1000))));
+
+        let few = ExtractLimits {
+            max_bytes: 1 << 20,
+            max_entries: 2,
+        };
+        let result = extract(&archive, &dest, few, false, &|_| true, &job());
+        assert!(matches!(result, Err(ArchiveError::TooManyEntries(2))));
+
+        let result = extract(
+            &archive,
+            &dest,
+            LIMITS,
+            false,
+            &|p| !p.starts_with("c"),
+            &job(),
+        );
+        assert!(matches!(result, Err(ArchiveError::Forbidden(path)) if path == "c/d"));
+        assert!(!dest.exists());
+    }
+
+    #[test]
+    fn unknown_formats_are_refused() {
+        let scratch = tempfile::tempdir().unwrap();
+        let archive = scratch.path().join("notes.txt");
+        fs::write(&archive, b"just text").unwrap();
+        let result = extract_all(&archive, &scratch.path().join("world"), false);
+        assert!(matches!(result, Err(ArchiveError::UnsupportedFormat)));
+    }
+
+    #[test]
+    fn compressed_trees_extract_to_the_same_files() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path().join("server");
+        fs::create_dir_all(root.join("world/region")).unwrap();
+        fs::write(root.join("server.properties"), b"motd=hi").unwrap();
+        fs::write(root.join("world/region/r.0.0.mca"), vec![7; 5000]).unwrap();
+        fs::write(root.join("world/session.lock"), b"lock").unwrap();
+        fs::set_permissions(
+            root.join("server.properties"),
+            fs::Permissions::from_mode(0o600),
+        )
+        .unwrap();
+
+        for (format, name) in [
+            (ArchiveFormat::Zip, "out.zip"),
+            (ArchiveFormat::TarGz, "out.tar.gz"),
+        ] {
+            let archive = scratch.path().join(name);
+            let skip_lock = |p: &Path| !p.ends_with("session.lock");
+            let packed = compress(
+                &root,
+                &[PathBuf::from("server.properties"), PathBuf::from("world")],
+                &archive,
+                format,
+                &skip_lock,
+                &job(),
+            )
+            .unwrap();
+            assert_eq!(packed.entries, 4, "{name}");
+
+            let dest = scratch.path().join(format!("{name}.d"));
+            let unpacked = extract_all(&archive, &dest, false).unwrap();
+            assert_eq!(unpacked.entries, 4, "{name}");
+            assert_eq!(unpacked.size_bytes, 5007, "{name}");
+            assert_eq!(
+                fs::read(dest.join("server.properties")).unwrap(),
+                b"motd=hi"
+            );
+            assert_eq!(
+                fs::read(dest.join("world/region/r.0.0.mca")).unwrap(),
+                vec![7; 5000]
+            );
+            assert!(!dest.join("world/session.lock").exists());
+            let mode = fs::metadata(dest.join("server.properties"))
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600, "{name}");
+        }
+    }
+}
@@ -0,0 +1,156 @@
+pub mod archive;
+
+use std::{
+    collections::HashSet,
+    fmt::Display,
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use axum::body::Bytes;
+use chrono::Utc;
+use futures_util::{Stream, StreamExt};
+use thiserror::Error;
+use tokio::{
+    fs::{self, File},
+    io::AsyncWriteExt,
+};
+use uuid::Uuid;
+
+use crate::{infra::db, prelude::*, state::AppState};
+
+pub const PART_EXTENSION: &str = "part";
+
+#[derive(Debug, Error)]
+pub enum UploadError {
+    #[error("upload exceeds its size limit")]
+    TooLarge,
+    #[error("read request body failed: {0}")]
+    Body(String),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// Tracks resumable uploads a request is currently writing to, so two
+/// requests never append to the same one.
+#[derive(Default)]
+pub struct FileManager {
+    writing: Mutex<HashSet<Uuid>>,
+}
+
+impl FileManager {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the upload for one request, `false` if another is writing.
+    pub fn claim(&self, upload_uuid: Uuid) -> bool {
+        self.writing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(upload_uuid)
+    }
+
+    pub fn release(&self, upload_uuid: Uuid) {
+        self.writing
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&upload_uuid);
+    }
+}
+
+/// Where the received part of an upload is kept until it is complete.
+pub fn part_path(state: &AppState, upload_uuid: Uuid) -> PathBuf {
+    state
+        .config
+        .files
+        .upload_dir
+        .join(format!("{upload_uuid}.{PART_EXTENSION}"))
+}
+
+/// A staging file for a multipart upload, written in one request.
+pub fn staging_path(state: &AppState) -> PathBuf {
+    state
+        .config
+        .files
+        .upload_dir
+        .join(format!("{}.{PART_EXTENSION}", Uuid::new_v4()))
+}
+
+pub async fn init(state: &AppState) {
+    if let Err(e) = fs::create_dir_all(&state.config.files.upload_dir).await {
+        error!(error = %e, "create upload directory failed");
+        return;
+    }
+    sweep(state).await;
+
+    // Parts without an upload were left by multipart requests or uploads
+    // dropped while the daemon was down
+    let live = match db::file::get_upload_uuids(&state.db_pool).await {
+        Ok(uuids) => uuids.into_iter().collect::<HashSet<_>>(),
+        Err(e) => {
+            error!(error = %e, "fetch file uploads failed");
+            return;
+        }
+    };
+    let Ok(mut entries) = fs::read_dir(&state.config.files.upload_dir).await else {
+        return;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let path = entry.path();
+        let upload_uuid = path
+            .file_stem()
+            .and_then(|stem| Uuid::try_parse(&stem.to_string_lossy()).ok());
+        if upload_uuid.is_none_or(|uuid| !live.contains(&uuid)) {
+            remove_part(&path).await;
+        }
+    }
+}
+
+/// Drops resumable uploads older than the configured lifetime.
+pub async fn sweep(state: &AppState) {
+    let cutoff =
+        Utc::now() - chrono::Duration::from_std(state.config.files.upload_ttl).unwrap_or_default();
+    let expired = match db::file::delete_expired_uploads(&state.db_pool, cutoff).await {
+        Ok(expired) => expired,
+        Err(e) => {
+            error!(error = %e, "delete expired file uploads failed");
+            return;
+        }
+    };
+
+    for upload_uuid in expired {
+        info!(upload_uuid = %upload_uuid, "file upload expired");
+        remove_part(&part_path(state, upload_uuid)).await;
+    }
+}
+
+/// Appends `body` to `file`, failing once more than `limit` bytes arrive.
+/// Returns the number of bytes written.
+pub async fn write_stream<S, E>(file: &mut File, body: S, limit: u64) -> Result<u64, UploadError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    E: Display,
+{
+    let mut body = std::pin::pin!(body);
+    let mut written = 0u64;
+    while let Some(chunk) = body.next().await {
+        let chunk = chunk.map_err(|e| UploadError::Body(e.to_string()))?;
+        written += chunk.len() as u64;
+        if written > limit {
+            return Err(UploadError::TooLarge);
+        }
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(written)
+}
+
+pub async fn remove_part(path: &Path) {
+    match fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == ErrorKind::NotFound => {}
+        Err(e) => warn!(error = %e, path = %path.display(), "remove upload part failed"),
+    }
+}
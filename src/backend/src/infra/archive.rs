@@ -0,0 +1,219 @@
+use std::{
+    collections::BTreeSet,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use flate2::{Compression, read::GzDecoder, write::GzEncoder};
+use sha2::{Digest, Sha256};
+use tar::{Archive, Builder, EntryType, Header};
+
+/// Size and checksum of a written archive.
+#[derive(Debug, Clone)]
+pub struct ArchiveInfo {
+    pub size_bytes: u64,
+    pub sha256: String,
+}
+
+/// Counts and hashes everything written through it.
+struct HashingWriter<W> {
+    inner: W,
+    hasher: Sha256,
+    written: u64,
+}
+
+impl<W: Write> Write for HashingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        self.written += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+/// Writes `entries` (paths relative to `root`, files or directories) into a
+/// gzip compressed tarball at `dest`, walked as described for [`walk`].
+/// Blocking, run it on a blocking thread.
+pub fn write_tar_gz(
+    root: &Path,
+    entries: &[PathBuf],
+    dest: &Path,
+    level: u32,
+    exclude: &[PathBuf],
+    skip_names: &[&str],
+) -> io::Result<ArchiveInfo> {
+    let file = File::create(dest)?;
+    let writer = HashingWriter {
+        inner: BufWriter::new(file),
+        hasher: Sha256::new(),
+        written: 0,
+    };
+    let mut builder = Builder::new(GzEncoder::new(writer, Compression::new(level.min(9))));
+    builder.follow_symlinks(false);
+
+    walk(
+        root,
+        entries,
+        exclude,
+        skip_names,
+        &mut |relative, path, metadata| {
+            if metadata.is_dir() {
+                builder.append_dir(relative, path)
+            } else if metadata.is_file() {
+                let file = match File::open(path) {
+                    Ok(file) => file,
+                    Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+                    Err(e) => return Err(e),
+                };
+                let mut header = Header::new_gnu();
+                header.set_metadata(metadata);
+                header.set_entry_type(EntryType::Regular);
+                builder.append_data(&mut header, relative, file.take(metadata.len()))
+            } else {
+                builder.append_path_with_name(path, relative)
+            }
+        },
+    )?;
+
+    let mut writer = builder.into_inner()?.finish()?;
+    writer.flush()?;
+    let file = writer.inner.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+
+    Ok(ArchiveInfo {
+        size_bytes: writer.written,
+        sha256: format!("{:x}", writer.hasher.finalize()),
+    })
+}
+
+/// Visits `entries` (paths relative to `root`) and everything below them in
+/// sorted order, parents before children. Symlinks are visited but not
+/// followed, other special files are skipped, as are paths in `exclude` and
+/// file names in `skip_names`. `visit` gets the relative path, the full path
+/// and its metadata. Blocking.
+pub fn walk(
+    root: &Path,
+    entries: &[PathBuf],
+    exclude: &[PathBuf],
+    skip_names: &[&str],
+    visit: &mut dyn FnMut(&Path, &Path, &fs::Metadata) -> io::Result<()>,
+) -> io::Result<()> {
+    let walker = Walker {
+        root,
+        exclude,
+        skip_names,
+    };
+    for entry in entries {
+        walker.visit(entry, visit)?;
+    }
+    Ok(())
+}
+
+struct Walker<'a> {
+    root: &'a Path,
+    exclude: &'a [PathBuf],
+    skip_names: &'a [&'a str],
+}
+
+impl Walker<'_> {
+    fn visit(
+        &self,
+        relative: &Path,
+        visit: &mut dyn FnMut(&Path, &Path, &fs::Metadata) -> io::Result<()>,
+    ) -> io::Result<()> {
+        let path = self.root.join(relative);
+        if self
+            .exclude
+            .iter()
+            .any(|excluded| path.starts_with(excluded))
+            || relative
+                .file_name()
+                .is_some_and(|name| self.skip_names.iter().any(|skip| name == *skip))
+        {
+            return Ok(());
+        }
+
+        // Files may vanish while the server keeps running
+        let metadata = match fs::symlink_metadata(&path) {
+            Ok(metadata) => metadata,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+            Err(e) => return Err(e),
+        };
+
+        if metadata.is_dir() {
+            if !relative.as_os_str().is_empty() {
+                visit(relative, &path, &metadata)?;
+            }
+            let mut children = fs::read_dir(&path)?
+                .map(|entry| entry.map(|e| e.file_name()))
+                .collect::<io::Result<Vec<_>>>()?;
+            children.sort();
+            for child in children {
+                self.visit(&relative.join(child), visit)?;
+            }
+        } else if metadata.is_file() || metadata.file_type().is_symlink() {
+            visit(relative, &path, &metadata)?;
+        }
+        Ok(())
+    }
+}
+
+/// Hex SHA-256 of a file. Blocking.
+pub fn sha256_file(path: &Path) -> io::Result<String> {
+    let mut file = BufReader::new(File::open(path)?);
+    let mut hasher = Sha256::new();
+    io::copy(&mut file, &mut hasher)?;
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// First path component of every entry in a gzip compressed tarball. Blocking.
+pub fn top_level_entries(archive: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    let mut archive = open_tar_gz(archive)?;
+    let mut top = BTreeSet::new();
+    for entry in archive.entries()? {
+        if let Some(first) = normalized(&entry?.path()?).components().next() {
+            top.insert(PathBuf::from(first.as_os_str()));
+        }
+    }
+    Ok(top)
+}
+
+/// Extracts a gzip compressed tarball into `dest`, limited to entries at or
+/// below one of `only` when it is not empty. Entries escaping `dest` are
+/// skipped. Returns how many entries were written. Blocking.
+pub fn extract_tar_gz(archive: &Path, dest: &Path, only: &[PathBuf]) -> io::Result<u64> {
+    let mut archive = open_tar_gz(archive)?;
+    archive.set_preserve_permissions(true);
+    archive.set_overwrite(true);
+
+    let mut extracted = 0;
+    for entry in archive.entries()? {
+        let mut entry = entry?;
+        let path = normalized(&entry.path()?);
+        if !only.is_empty() && !only.iter().any(|selected| path.starts_with(selected)) {
+            continue;
+        }
+        if entry.unpack_in(dest)? {
+            extracted += 1;
+        }
+    }
+    Ok(extracted)
+}
+
+fn open_tar_gz(path: &Path) -> io::Result<Archive<GzDecoder<BufReader<File>>>> {
+    Ok(Archive::new(GzDecoder::new(BufReader::new(File::open(
+        path,
+    )?))))
+}
+
+/// Drops `.` components, tar tools commonly prefix every entry with `./`.
+fn normalized(path: &Path) -> PathBuf {
+    path.components()
+        .filter(|c| !matches!(c, Component::CurDir))
+        .collect()
+}
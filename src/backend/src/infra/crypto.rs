@@ -0,0 +1,362 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    path::Path,
+};
+
+use aes_gcm::{
+    Aes256Gcm, Nonce,
+    aead::{Aead, AeadCore, KeyInit, OsRng, rand_core::RngCore},
+};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+const NONCE_LEN: usize = 12;
+const FILE_MAGIC: &[u8; 8] = b"RMSEAL01";
+const NONCE_PREFIX_LEN: usize = 7;
+/// Plaintext bytes per sealed file segment.
+const SEGMENT_LEN: usize = 1024 * 1024;
+const TAG_LEN: usize = 16;
+
+#[derive(Debug, Error)]
+pub enum CryptoError {
+    #[error("encrypt secret failed")]
+    Encrypt,
+    #[error("decrypt secret failed")]
+    Decrypt,
+    #[error("encrypted secret is truncated")]
+    Truncated,
+    #[error("not a sealed file")]
+    NotSealed,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// AES-256-GCM sealing of secrets stored at rest, ciphertexts are laid out as
+/// `nonce || ciphertext || tag`.
+pub struct SecretBox {
+    cipher: Aes256Gcm,
+}
+
+impl SecretBox {
+    /// Derives the key from an arbitrary passphrase with SHA-256.
+    pub fn from_passphrase(passphrase: &str) -> Self {
+        let key = Sha256::digest(passphrase.as_bytes());
+        Self {
+            cipher: Aes256Gcm::new(&key),
+        }
+    }
+
+    pub fn encrypt(&self, plaintext: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+        let ciphertext = self
+            .cipher
+            .encrypt(&nonce, plaintext)
+            .map_err(|_| CryptoError::Encrypt)?;
+
+        let mut sealed = Vec::with_capacity(NONCE_LEN + ciphertext.len());
+        sealed.extend_from_slice(&nonce);
+        sealed.extend_from_slice(&ciphertext);
+        Ok(sealed)
+    }
+
+    pub fn decrypt(&self, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        if sealed.len() < NONCE_LEN {
+            return Err(CryptoError::Truncated);
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce: [u8; NONCE_LEN] = nonce.try_into().map_err(|_| CryptoError::Truncated)?;
+        self.cipher
+            .decrypt(&Nonce::from(nonce), ciphertext)
+            .map_err(|_| CryptoError::Decrypt)
+    }
+
+    pub fn encrypt_str(&self, plaintext: &str) -> Result<Vec<u8>, CryptoError> {
+        self.encrypt(plaintext.as_bytes())
+    }
+
+    pub fn decrypt_str(&self, sealed: &[u8]) -> Result<String, CryptoError> {
+        String::from_utf8(self.decrypt(sealed)?).map_err(|_| CryptoError::Decrypt)
+    }
+
+    /// Seals a file of any size segment by segment, laid out as
+    /// `magic || nonce prefix || segments`. Every segment nonce carries its
+    /// index and whether it is the last, so reordered, dropped or cut off
+    /// segments fail to open. Blocking.
+    pub fn encrypt_file(&self, src: &Path, dest: &Path) -> Result<(), CryptoError> {
+        let mut reader = BufReader::new(File::open(src)?);
+        let mut writer = BufWriter::new(File::create(dest)?);
+
+        let mut prefix = [0; NONCE_PREFIX_LEN];
+        OsRng.fill_bytes(&mut prefix);
+        writer.write_all(FILE_MAGIC)?;
+        writer.write_all(&prefix)?;
+
+        let mut segment = vec![0; SEGMENT_LEN];
+        let mut index = 0;
+        loop {
+            let read = fill(&mut reader, &mut segment)?;
+            // A short segment ends the file, an empty one when it divides evenly
+            let last = read < SEGMENT_LEN;
+            let sealed = self
+                .cipher
+                .encrypt(&segment_nonce(&prefix, index, last), &segment[..read])
+                .map_err(|_| CryptoError::Encrypt)?;
+            writer.write_all(&sealed)?;
+            if last {
+                break;
+            }
+            index += 1;
+        }
+
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        Ok(())
+    }
+
+    /// Opens a file written by [`SecretBox::encrypt_file`]. Blocking.
+    pub fn decrypt_file(&self, src: &Path, dest: &Path) -> Result<(), CryptoError> {
+        let mut reader = BufReader::new(File::open(src)?);
+        let mut header = [0; FILE_MAGIC.len() + NONCE_PREFIX_LEN];
+        reader.read_exact(&mut header).map_err(|e| match e.kind() {
+            ErrorKind::UnexpectedEof => CryptoError::NotSealed,
+            _ => CryptoError::Io(e),
+        })?;
+        let (magic, prefix) = header.split_at(FILE_MAGIC.len());
+        if magic != FILE_MAGIC {
+            return Err(CryptoError::NotSealed);
+        }
+
+        let mut writer = BufWriter::new(File::create(dest)?);
+        let mut segment = vec![0; SEGMENT_LEN + TAG_LEN];
+        let mut index = 0;
+        loop {
+            let read = fill(&mut reader, &mut segment)?;
+            let last = read < segment.len();
+            if read < TAG_LEN {
+                return Err(CryptoError::Truncated);
+            }
+            let plain = self
+                .cipher
+                .decrypt(&segment_nonce(prefix, index, last), &segment[..read])
+                .map_err(|_| CryptoError::Decrypt)?;
+            writer.write_all(&plain)?;
+            if last {
+                break;
+            }
+            index += 1;
+        }
+
+        writer
+            .into_inner()
+            .map_err(|e| e.into_error())?
+            .sync_all()?;
+        Ok(())
+    }
+}
+
+fn segment_nonce(
+    prefix: &[u8],
+    index: u32,
+    last: bool,
+) -> Nonce<<Aes256Gcm as AeadCore>::NonceSize> {
+    let mut nonce = [0; NONCE_LEN];
+    nonce[..NONCE_PREFIX_LEN].copy_from_slice(prefix);
+    nonce[NONCE_PREFIX_LEN..NONCE_LEN - 1].copy_from_slice(&index.to_be_bytes());
+    nonce[NONCE_LEN - 1] = u8::from(last);
+    Nonce::from(nonce)
+}
+
+/// Reads until `buf` is full or the input ends.
+fn fill(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+
+    const HEADER_LEN: usize = FILE_MAGIC.len() + NONCE_PREFIX_LEN;
+
+    fn data(len: usize) -> Vec<u8> {
+        (0..len).map(|i| (i * 31 % 251) as u8).collect()
+    }
+
+    /// Seals `plain` and hands back the sealed bytes.
+    fn seal(secrets: &SecretBox, scratch: &TempDir, plain: &[u8]) -> Vec<u8> {
+        let (src, sealed) = (scratch.path().join("plain"), scratch.path().join("sealed"));
+        std::fs::write(&src, plain).unwrap();
+        secrets.encrypt_file(&src, &sealed).unwrap();
+        std::fs::read(sealed).unwrap()
+    }
+
+    fn open(secrets: &SecretBox, scratch: &TempDir, sealed: &[u8]) -> Result<Vec<u8>, CryptoError> {
+        let (src, dest) = (
+            scratch.path().join("tampered"),
+            scratch.path().join("opened"),
+        );
+        std::fs::write(&src, sealed).unwrap();
+        secrets.decrypt_file(&src, &dest)?;
+        Ok(std::fs::read(dest).unwrap())
+    }
+
+    #[test]
+    fn secrets_round_trip() {
+        let secrets = SecretBox::from_passphrase("correct horse");
+        let sealed = secrets.encrypt_str("rcon password").unwrap();
+
+        assert_eq!(sealed.len(), NONCE_LEN + "rcon password".len() + TAG_LEN);
+        assert_eq!(secrets.decrypt_str(&sealed).unwrap(), "rcon password");
+        // Fresh nonce every time
+        assert_ne!(secrets.encrypt_str("rcon password").unwrap(), sealed);
+    }
+
+    #[test]
+    fn secrets_reject_wrong_key_tampering_and_truncation() {
+        let secrets = SecretBox::from_passphrase("correct horse");
+        let sealed = secrets.encrypt(b"secret").unwrap();
+
+        let other = SecretBox::from_passphrase("battery staple");
+        assert!(matches!(other.decrypt(&sealed), Err(CryptoError::Decrypt)));
+
+        let mut flipped = sealed.clone();
+        *flipped.last_mut().unwrap() ^= 1;
+        assert!(matches!(
+            secrets.decrypt(&flipped),
+            Err(CryptoError::Decrypt)
+        ));
+
+        assert!(matches!(
+            secrets.decrypt(&sealed[..NONCE_LEN - 1]),
+            Err(CryptoError::Truncated)
+        ));
+        assert!(matches!(
+            secrets.decrypt(&sealed[..NONCE_LEN + 4]),
+            Err(CryptoError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn files_round_trip_at_segment_boundaries() {
+        let scratch = tempfile::tempdir().unwrap();
+        let secrets = SecretBox::from_passphrase("backup key");
+
+        for len in [
+            0,
+            1,
+            SEGMENT_LEN - 1,
+            SEGMENT_LEN,
+            SEGMENT_LEN + 1,
+            2 * SEGMENT_LEN,
+        ] {
+            let plain = data(len);
+            let sealed = seal(&secrets, &scratch, &plain);
+
+            // An evenly divided file ends with an empty segment
+            let segments = len / SEGMENT_LEN + 1;
+            assert_eq!(sealed.len(), HEADER_LEN + len + segments * TAG_LEN, "{len}");
+            assert_eq!(&sealed[..FILE_MAGIC.len()], FILE_MAGIC);
+            assert_eq!(open(&secrets, &scratch, &sealed).unwrap(), plain, "{len}");
+        }
+    }
+
+    #[test]
+    fn files_reject_tampering() {
+        let scratch = tempfile::tempdir().unwrap();
+        let secrets = SecretBox::from_passphrase("backup key");
+        let sealed = seal(&secrets, &scratch, &data(SEGMENT_LEN + 100));
+
+        let mut flipped = sealed.clone();
+        flipped[HEADER_LEN + 10] ^= 1;
+        assert!(matches!(
+            open(&secrets, &scratch, &flipped),
+            Err(CryptoError::Decrypt)
+        ));
+
+        let mut prefix = sealed.clone();
+        prefix[FILE_MAGIC.len()] ^= 1;
+        assert!(matches!(
+            open(&secrets, &scratch, &prefix),
+            Err(CryptoError::Decrypt)
+        ));
+
+        let other = SecretBox::from_passphrase("another key");
+        assert!(matches!(
+            open(&other, &scratch, &sealed),
+            Err(CryptoError::Decrypt)
+        ));
+    }
+
+    #[test]
+    fn files_reject_reordered_dropped_and_cut_segments() {
+        let scratch = tempfile::tempdir().unwrap();
+        let secrets = SecretBox::from_passphrase("backup key");
+        let sealed = seal(&secrets, &scratch, &data(2 * SEGMENT_LEN + 100));
+        let (header, body) = sealed.split_at(HEADER_LEN);
+        let full = SEGMENT_LEN + TAG_LEN;
+        let (first, rest) = body.split_at(full);
+        let (second, last) = rest.split_at(full);
+
+        let swapped = [header, second, first, last].concat();
+        assert!(matches!(
+            open(&secrets, &scratch, &swapped),
+            Err(CryptoError::Decrypt)
+        ));
+
+        let dropped = [header, first, last].concat();
+        assert!(matches!(
+            open(&secrets, &scratch, &dropped),
+            Err(CryptoError::Decrypt)
+        ));
+
+        // Cut after a full segment, nothing is left to end the file
+        let cut = [header, first, second].concat();
+        assert!(matches!(
+            open(&secrets, &scratch, &cut),
+            Err(CryptoError::Truncated)
+        ));
+
+        // Cut inside a segment, the short rest passes as a last segment
+        let short = &sealed[..HEADER_LEN + full + 100];
+        assert!(matches!(
+            open(&secrets, &scratch, short),
+            Err(CryptoError::Decrypt)
+        ));
+
+        let stub = &sealed[..HEADER_LEN + full + TAG_LEN - 1];
+        assert!(matches!(
+            open(&secrets, &scratch, stub),
+            Err(CryptoError::Truncated)
+        ));
+    }
+
+    #[test]
+    fn plain_files_are_not_sealed() {
+        let scratch = tempfile::tempdir().unwrap();
+        let secrets = SecretBox::from_passphrase("backup key");
+
+        assert!(matches!(
+            open(&secrets, &scratch, b"RMSEAL"),
+            Err(CryptoError::NotSealed)
+        ));
+        let mut other = vec![0; HEADER_LEN + 64];
+        other[..8].copy_from_slice(b"PK\x03\x04zip!");
+        assert!(matches!(
+            open(&secrets, &scratch, &other),
+            Err(CryptoError::NotSealed)
+        ));
+    }
+}
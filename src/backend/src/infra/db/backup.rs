@@ -0,0 +1,200 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::backup::{Backup, BackupStatus, RetentionPolicy},
+    prelude::*,
+};
+
+pub async fn create(pool: &PgPool, backup: &Backup) -> Result<Backup> {
+    debug!(backup_uuid = %backup.uuid, "insert backup started");
+    let backup = sqlx::query_as::<_, Backup>(
+        r#"
+        INSERT INTO backups (uuid, server_uuid, scope, mode, status, file_name, created_by,
+            started_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING uuid, server_uuid, scope, mode, status, file_name, size_bytes, sha256, error,
+            created_by, started_at, completed_at
+        "#,
+    )
+    .bind(backup.uuid)
+    .bind(backup.server_uuid)
+    .bind(backup.scope.as_str())
+    .bind(backup.mode.as_str())
+    .bind(backup.status.as_str())
+    .bind(&backup.file_name)
+    .bind(backup.created_by)
+    .bind(backup.started_at)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(backup_uuid = %backup.uuid, "insert backup completed");
+    Ok(backup)
+}
+
+pub async fn complete(pool: &PgPool, uuid: Uuid, size_bytes: i64, sha256: &str) -> Result<Backup> {
+    debug!(backup_uuid = %uuid, "complete backup started");
+    let backup = sqlx::query_as::<_, Backup>(
+        r#"
+        UPDATE backups
+        SET status = $2, size_bytes = $3, sha256 = $4, completed_at = now()
+        WHERE uuid = $1
+        RETURNING uuid, server_uuid, scope, mode, status, file_name, size_bytes, sha256, error,
+            created_by, started_at, completed_at
+        "#,
+    )
+    .bind(uuid)
+    .bind(BackupStatus::Completed.as_str())
+    .bind(size_bytes)
+    .bind(sha256)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(backup_uuid = %uuid, "complete backup completed");
+    Ok(backup)
+}
+
+pub async fn fail(pool: &PgPool, uuid: Uuid, error: &str) -> Result<Backup> {
+    debug!(backup_uuid = %uuid, "fail backup started");
+    let backup = sqlx::query_as::<_, Backup>(
+        r#"
+        UPDATE backups
+        SET status = $2, error = $3, completed_at = now()
+        WHERE uuid = $1
+        RETURNING uuid, server_uuid, scope, mode, status, file_name, size_bytes, sha256, error,
+            created_by, started_at, completed_at
+        "#,
+    )
+    .bind(uuid)
+    .bind(BackupStatus::Failed.as_str())
+    .bind(error)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(backup_uuid = %uuid, "fail backup completed");
+    Ok(backup)
+}
+
+/// Marks every backup still running as failed, returns the affected rows.
+pub async fn fail_running(pool: &PgPool, error: &str) -> Result<Vec<Backup>> {
+    debug!("fail running backups started");
+    let backups = sqlx::query_as::<_, Backup>(
+        r#"
+        UPDATE backups
+        SET status = $2, error = $3, completed_at = now()
+        WHERE status = $1
+        RETURNING uuid, server_uuid, scope, mode, status, file_name, size_bytes, sha256, error,
+            created_by, started_at, completed_at
+        "#,
+    )
+    .bind(BackupStatus::Running.as_str())
+    .bind(BackupStatus::Failed.as_str())
+    .bind(error)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(
+        backup_count = backups.len(),
+        "fail running backups completed"
+    );
+    Ok(backups)
+}
+
+pub async fn get_for_server(pool: &PgPool, server_uuid: Uuid) -> Result<Vec<Backup>> {
+    debug!(server_uuid = %server_uuid, "fetch backups started");
+    let backups = sqlx::query_as::<_, Backup>(
+        r#"
+        SELECT uuid, server_uuid, scope, mode, status, file_name, size_bytes, sha256, error,
+            created_by, started_at, completed_at
+        FROM backups
+        WHERE server_uuid = $1
+        ORDER BY started_at DESC
+        "#,
+    )
+    .bind(server_uuid)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch backups completed");
+    Ok(backups)
+}
+
+pub async fn get_by_uuid(pool: &PgPool, server_uuid: Uuid, uuid: Uuid) -> Result<Option<Backup>> {
+    debug!(backup_uuid = %uuid, "fetch backup by uuid started");
+    let backup = sqlx::query_as::<_, Backup>(
+        r#"
+        SELECT uuid, server_uuid, scope, mode, status, file_name, size_bytes, sha256, error,
+            created_by, started_at, completed_at
+        FROM backups
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(backup_uuid = %uuid, "fetch backup by uuid completed");
+    Ok(backup)
+}
+
+pub async fn delete(pool: &PgPool, uuid: Uuid) -> Result<bool> {
+    debug!(backup_uuid = %uuid, "delete backup started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM backups
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(backup_uuid = %uuid, "delete backup completed");
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn get_retention(pool: &PgPool, server_uuid: Uuid) -> Result<Option<RetentionPolicy>> {
+    debug!(server_uuid = %server_uuid, "fetch backup retention started");
+    let policy = sqlx::query_as::<_, RetentionPolicy>(
+        r#"
+        SELECT keep_last, keep_daily, keep_weekly, keep_monthly
+        FROM backup_retention
+        WHERE server_uuid = $1
+        "#,
+    )
+    .bind(server_uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch backup retention completed");
+    Ok(policy)
+}
+
+pub async fn set_retention(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    policy: &RetentionPolicy,
+) -> Result<RetentionPolicy> {
+    debug!(server_uuid = %server_uuid, "upsert backup retention started");
+    let policy = sqlx::query_as::<_, RetentionPolicy>(
+        r#"
+        INSERT INTO backup_retention (server_uuid, keep_last, keep_daily, keep_weekly, keep_monthly)
+        VALUES ($1, $2, $3, $4, $5)
+        ON CONFLICT (server_uuid) DO UPDATE
+        SET keep_last = $2, keep_daily = $3, keep_weekly = $4, keep_monthly = $5
+        RETURNING keep_last, keep_daily, keep_weekly, keep_monthly
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(policy.keep_last)
+    .bind(policy.keep_daily)
+    .bind(policy.keep_weekly)
+    .bind(policy.keep_monthly)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "upsert backup retention completed");
+    Ok(policy)
+}
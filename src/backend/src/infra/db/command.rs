@@ -0,0 +1,126 @@
+use anyhow::Result;
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    domain::command::{CommandEntry, CommandRule, CommandRuleRow, NewCommandEntry},
+    prelude::*,
+};
+
+pub async fn create_rule(pool: &PgPool, rule: CommandRule) -> Result<CommandRule> {
+    debug!(rule_uuid = %rule.uuid, "insert command rule started");
+    let row = sqlx::query_as::<_, CommandRuleRow>(
+        r#"
+        INSERT INTO command_rules (uuid, server_uuid, user_uuid, action, verb, allow)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING uuid, server_uuid, user_uuid, action, verb, allow
+        "#,
+    )
+    .bind(rule.uuid)
+    .bind(rule.server_uuid)
+    .bind(rule.user_uuid)
+    .bind(rule.action.map(Json))
+    .bind(&rule.verb)
+    .bind(rule.allow)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(rule_uuid = %row.uuid, "insert command rule completed");
+    Ok(CommandRule::from(row))
+}
+
+pub async fn get_all_rules(pool: &PgPool) -> Result<Vec<CommandRule>> {
+    debug!("fetch all command rules started");
+    let rows = sqlx::query_as::<_, CommandRuleRow>(
+        r#"
+        SELECT uuid, server_uuid, user_uuid, action, verb, allow
+        FROM command_rules
+        ORDER BY verb ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    debug!("fetch all command rules completed");
+    Ok(rows.into_iter().map(CommandRule::from).collect())
+}
+
+pub async fn get_rules_for_server(pool: &PgPool, server_uuid: Uuid) -> Result<Vec<CommandRule>> {
+    debug!(server_uuid = %server_uuid, "fetch command rules for server started");
+    let rows = sqlx::query_as::<_, CommandRuleRow>(
+        r#"
+        SELECT uuid, server_uuid, user_uuid, action, verb, allow
+        FROM command_rules
+        WHERE server_uuid IS NULL OR server_uuid = $1
+        "#,
+    )
+    .bind(server_uuid)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch command rules for server completed");
+    Ok(rows.into_iter().map(CommandRule::from).collect())
+}
+
+pub async fn delete_rule(pool: &PgPool, uuid: Uuid) -> Result<bool> {
+    debug!(rule_uuid = %uuid, "delete command rule started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM command_rules
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(rule_uuid = %uuid, "delete command rule completed");
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn create_entry(pool: &PgPool, entry: NewCommandEntry) -> Result<()> {
+    debug!(server_uuid = %entry.server_uuid, "insert command history entry started");
+    sqlx::query(
+        r#"
+        INSERT INTO command_history (uuid, server_uuid, user_uuid, command, source, allowed)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(entry.uuid)
+    .bind(entry.server_uuid)
+    .bind(entry.user_uuid)
+    .bind(&entry.command)
+    .bind(entry.source.as_str())
+    .bind(entry.allowed)
+    .execute(pool)
+    .await?;
+
+    debug!(server_uuid = %entry.server_uuid, "insert command history entry completed");
+    Ok(())
+}
+
+pub async fn get_history(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    limit: i64,
+) -> Result<Vec<CommandEntry>> {
+    debug!(server_uuid = %server_uuid, "fetch command history started");
+    let entries = sqlx::query_as::<_, CommandEntry>(
+        r#"
+        SELECT h.uuid, h.server_uuid, h.user_uuid, u.username, h.command, h.source,
+            h.allowed, h.executed_at
+        FROM command_history h
+        JOIN users u ON u.uuid = h.user_uuid
+        WHERE h.server_uuid = $1
+        ORDER BY h.executed_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch command history completed");
+    Ok(entries)
+}
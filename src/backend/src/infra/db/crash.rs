@@ -0,0 +1,83 @@
+use anyhow::Result;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::crash::{CrashRecord, CrashSummary},
+    prelude::*,
+};
+
+pub async fn create(pool: &PgPool, crash: &CrashRecord) -> Result<()> {
+    debug!(crash_uuid = %crash.uuid, "insert crash started");
+    sqlx::query(
+        r#"
+        INSERT INTO crashes (uuid, server_uuid, kind, exit_code, signal, report_file, report,
+            console, restart_scheduled, started_at, crashed_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
+        "#,
+    )
+    .bind(crash.uuid)
+    .bind(crash.server_uuid)
+    .bind(crash.kind.as_str())
+    .bind(crash.exit_code)
+    .bind(crash.signal)
+    .bind(&crash.report_file)
+    .bind(&crash.report)
+    .bind(&crash.console)
+    .bind(crash.restart_scheduled)
+    .bind(crash.started_at)
+    .bind(crash.crashed_at)
+    .execute(pool)
+    .await?;
+
+    debug!(crash_uuid = %crash.uuid, "insert crash completed");
+    Ok(())
+}
+
+pub async fn get_for_server(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    limit: i64,
+) -> Result<Vec<CrashSummary>> {
+    debug!(server_uuid = %server_uuid, "fetch crashes started");
+    let crashes = sqlx::query_as::<_, CrashSummary>(
+        r#"
+        SELECT uuid, server_uuid, kind, exit_code, signal, report_file, restart_scheduled,
+            started_at, crashed_at
+        FROM crashes
+        WHERE server_uuid = $1
+        ORDER BY crashed_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch crashes completed");
+    Ok(crashes)
+}
+
+pub async fn get_by_uuid(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    uuid: Uuid,
+) -> Result<Option<CrashRecord>> {
+    debug!(crash_uuid = %uuid, "fetch crash by uuid started");
+    let crash = sqlx::query_as::<_, CrashRecord>(
+        r#"
+        SELECT uuid, server_uuid, kind, exit_code, signal, report_file, report, console,
+            restart_scheduled, started_at, crashed_at
+        FROM crashes
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(crash_uuid = %uuid, "fetch crash by uuid completed");
+    Ok(crash)
+}
@@ -0,0 +1,193 @@
+use anyhow::Result;
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    domain::destination::{BackupCopy, BackupDestination, CopyStatus},
+    prelude::*,
+};
+
+pub async fn create(pool: &PgPool, destination: &BackupDestination) -> Result<BackupDestination> {
+    debug!(destination_uuid = %destination.uuid, "insert backup destination started");
+    let destination = sqlx::query_as::<_, BackupDestination>(
+        r#"
+        INSERT INTO backup_destinations (uuid, server_uuid, name, config, secret, encrypt, enabled)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        RETURNING uuid, server_uuid, name, config, secret, encrypt, enabled, created_at
+        "#,
+    )
+    .bind(destination.uuid)
+    .bind(destination.server_uuid)
+    .bind(&destination.name)
+    .bind(Json(&destination.config))
+    .bind(&destination.secret)
+    .bind(destination.encrypt)
+    .bind(destination.enabled)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(destination_uuid = %destination.uuid, "insert backup destination completed");
+    Ok(destination)
+}
+
+pub async fn get_for_server(pool: &PgPool, server_uuid: Uuid) -> Result<Vec<BackupDestination>> {
+    debug!(server_uuid = %server_uuid, "fetch backup destinations started");
+    let destinations = sqlx::query_as::<_, BackupDestination>(
+        r#"
+        SELECT uuid, server_uuid, name, config, secret, encrypt, enabled, created_at
+        FROM backup_destinations
+        WHERE server_uuid = $1
+        ORDER BY name ASC
+        "#,
+    )
+    .bind(server_uuid)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch backup destinations completed");
+    Ok(destinations)
+}
+
+pub async fn get_by_uuid(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    uuid: Uuid,
+) -> Result<Option<BackupDestination>> {
+    debug!(destination_uuid = %uuid, "fetch backup destination started");
+    let destination = sqlx::query_as::<_, BackupDestination>(
+        r#"
+        SELECT uuid, server_uuid, name, config, secret, encrypt, enabled, created_at
+        FROM backup_destinations
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(destination_uuid = %uuid, "fetch backup destination completed");
+    Ok(destination)
+}
+
+pub async fn update(pool: &PgPool, destination: &BackupDestination) -> Result<BackupDestination> {
+    debug!(destination_uuid = %destination.uuid, "update backup destination started");
+    let destination = sqlx::query_as::<_, BackupDestination>(
+        r#"
+        UPDATE backup_destinations
+        SET name = $2, secret = $3, encrypt = $4, enabled = $5
+        WHERE uuid = $1
+        RETURNING uuid, server_uuid, name, config, secret, encrypt, enabled, created_at
+        "#,
+    )
+    .bind(destination.uuid)
+    .bind(&destination.name)
+    .bind(&destination.secret)
+    .bind(destination.encrypt)
+    .bind(destination.enabled)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(destination_uuid = %destination.uuid, "update backup destination completed");
+    Ok(destination)
+}
+
+pub async fn delete(pool: &PgPool, server_uuid: Uuid, uuid: Uuid) -> Result<bool> {
+    debug!(destination_uuid = %uuid, "delete backup destination started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM backup_destinations
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(destination_uuid = %uuid, "delete backup destination completed");
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn exists_by_name(pool: &PgPool, server_uuid: Uuid, name: &str) -> Result<bool> {
+    debug!(server_uuid = %server_uuid, "check backup destination name started");
+    let exists = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1 FROM backup_destinations WHERE server_uuid = $1 AND name = $2
+        )
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(name)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "check backup destination name completed");
+    Ok(exists)
+}
+
+pub async fn set_copy(pool: &PgPool, copy: &BackupCopy) -> Result<()> {
+    debug!(backup_uuid = %copy.backup_uuid, "upsert backup copy started");
+    sqlx::query(
+        r#"
+        INSERT INTO backup_copies (backup_uuid, destination_uuid, status, remote_name, encrypted,
+            attempts, error, updated_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, now())
+        ON CONFLICT (backup_uuid, destination_uuid) DO UPDATE
+        SET status = $3, remote_name = $4, encrypted = $5, attempts = $6, error = $7,
+            updated_at = now()
+        "#,
+    )
+    .bind(copy.backup_uuid)
+    .bind(copy.destination_uuid)
+    .bind(copy.status.as_str())
+    .bind(&copy.remote_name)
+    .bind(copy.encrypted)
+    .bind(copy.attempts)
+    .bind(&copy.error)
+    .execute(pool)
+    .await?;
+
+    debug!(backup_uuid = %copy.backup_uuid, "upsert backup copy completed");
+    Ok(())
+}
+
+pub async fn get_copies(pool: &PgPool, backup_uuid: Uuid) -> Result<Vec<BackupCopy>> {
+    debug!(backup_uuid = %backup_uuid, "fetch backup copies started");
+    let copies = sqlx::query_as::<_, BackupCopy>(
+        r#"
+        SELECT backup_uuid, destination_uuid, status, remote_name, encrypted, attempts, error,
+            updated_at
+        FROM backup_copies
+        WHERE backup_uuid = $1
+        ORDER BY updated_at DESC
+        "#,
+    )
+    .bind(backup_uuid)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(backup_uuid = %backup_uuid, "fetch backup copies completed");
+    Ok(copies)
+}
+
+/// Marks every copy still uploading as failed, the upload died with the daemon.
+pub async fn fail_uploading(pool: &PgPool, error: &str) -> Result<u64> {
+    debug!("fail uploading backup copies started");
+    let result = sqlx::query(
+        r#"
+        UPDATE backup_copies
+        SET status = $2, error = $3, updated_at = now()
+        WHERE status = $1
+        "#,
+    )
+    .bind(CopyStatus::Uploading.as_str())
+    .bind(CopyStatus::Failed.as_str())
+    .bind(error)
+    .execute(pool)
+    .await?;
+
+    debug!("fail uploading backup copies completed");
+    Ok(result.rows_affected())
+}
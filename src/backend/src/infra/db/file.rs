@@ -0,0 +1,159 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+use crate::{
+    domain::file::{FileRule, FileRuleRow, FileUpload},
+    prelude::*,
+};
+
+pub async fn create_rule(pool: &PgPool, rule: FileRule) -> Result<FileRule> {
+    debug!(rule_uuid = %rule.uuid, "insert file rule started");
+    let row = sqlx::query_as::<_, FileRuleRow>(
+        r#"
+        INSERT INTO file_rules (uuid, server_uuid, user_uuid, action, path, access)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING uuid, server_uuid, user_uuid, action, path, access
+        "#,
+    )
+    .bind(rule.uuid)
+    .bind(rule.server_uuid)
+    .bind(rule.user_uuid)
+    .bind(rule.action.map(Json))
+    .bind(&rule.path)
+    .bind(rule.access.as_str())
+    .fetch_one(pool)
+    .await?;
+
+    debug!(rule_uuid = %row.uuid, "insert file rule completed");
+    Ok(FileRule::from(row))
+}
+
+pub async fn get_rules_for_server(pool: &PgPool, server_uuid: Uuid) -> Result<Vec<FileRule>> {
+    debug!(server_uuid = %server_uuid, "fetch file rules started");
+    let rows = sqlx::query_as::<_, FileRuleRow>(
+        r#"
+        SELECT uuid, server_uuid, user_uuid, action, path, access
+        FROM file_rules
+        WHERE server_uuid = $1
+        ORDER BY path ASC
+        "#,
+    )
+    .bind(server_uuid)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch file rules completed");
+    Ok(rows.into_iter().map(FileRule::from).collect())
+}
+
+pub async fn delete_rule(pool: &PgPool, server_uuid: Uuid, uuid: Uuid) -> Result<bool> {
+    debug!(rule_uuid = %uuid, "delete file rule started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM file_rules
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(rule_uuid = %uuid, "delete file rule completed");
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn create_upload(pool: &PgPool, upload: &FileUpload) -> Result<FileUpload> {
+    debug!(upload_uuid = %upload.uuid, "insert file upload started");
+    let upload = sqlx::query_as::<_, FileUpload>(
+        r#"
+        INSERT INTO file_uploads (uuid, server_uuid, user_uuid, path, size, overwrite)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        RETURNING uuid, server_uuid, user_uuid, path, size, overwrite, created_at
+        "#,
+    )
+    .bind(upload.uuid)
+    .bind(upload.server_uuid)
+    .bind(upload.user_uuid)
+    .bind(&upload.path)
+    .bind(upload.size)
+    .bind(upload.overwrite)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(upload_uuid = %upload.uuid, "insert file upload completed");
+    Ok(upload)
+}
+
+pub async fn get_upload(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    uuid: Uuid,
+) -> Result<Option<FileUpload>> {
+    debug!(upload_uuid = %uuid, "fetch file upload started");
+    let upload = sqlx::query_as::<_, FileUpload>(
+        r#"
+        SELECT uuid, server_uuid, user_uuid, path, size, overwrite, created_at
+        FROM file_uploads
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(upload_uuid = %uuid, "fetch file upload completed");
+    Ok(upload)
+}
+
+pub async fn get_upload_uuids(pool: &PgPool) -> Result<Vec<Uuid>> {
+    debug!("fetch file upload uuids started");
+    let uuids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        SELECT uuid
+        FROM file_uploads
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    debug!("fetch file upload uuids completed");
+    Ok(uuids)
+}
+
+pub async fn delete_upload(pool: &PgPool, uuid: Uuid) -> Result<bool> {
+    debug!(upload_uuid = %uuid, "delete file upload started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM file_uploads
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(upload_uuid = %uuid, "delete file upload completed");
+    Ok(result.rows_affected() > 0)
+}
+
+/// Drops uploads started before `cutoff` and returns their uuids.
+pub async fn delete_expired_uploads(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<Vec<Uuid>> {
+    debug!("delete expired file uploads started");
+    let uuids = sqlx::query_scalar::<_, Uuid>(
+        r#"
+        DELETE FROM file_uploads
+        WHERE created_at < $1
+        RETURNING uuid
+        "#,
+    )
+    .bind(cutoff)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(count = uuids.len(), "delete expired file uploads completed");
+    Ok(uuids)
+}
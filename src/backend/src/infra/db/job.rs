@@ -0,0 +1,157 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::job::{Job, JobQuery, JobStatus},
+    prelude::*,
+};
+
+/// Rows returned by default and at most.
+const DEFAULT_LIMIT: i64 = 100;
+const MAX_LIMIT: i64 = 1000;
+
+pub async fn create(pool: &PgPool, job: &Job) -> Result<Job> {
+    debug!(job_uuid = %job.uuid, "insert job started");
+    let job = sqlx::query_as::<_, Job>(
+        r#"
+        INSERT INTO jobs (uuid, kind, server_uuid, status, description, cancellable, created_by,
+            created_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING uuid, kind, server_uuid, status, description, step, total, processed, log,
+            result, error, cancellable, created_by, created_at, started_at, finished_at
+        "#,
+    )
+    .bind(job.uuid)
+    .bind(job.kind.as_str())
+    .bind(job.server_uuid)
+    .bind(job.status.as_str())
+    .bind(&job.description)
+    .bind(job.cancellable)
+    .bind(job.created_by)
+    .bind(job.created_at)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(job_uuid = %job.uuid, "insert job completed");
+    Ok(job)
+}
+
+/// Writes the state a job moved to, its status, progress, log and outcome.
+pub async fn update(pool: &PgPool, job: &Job) -> Result<()> {
+    debug!(job_uuid = %job.uuid, "update job started");
+    sqlx::query(
+        r#"
+        UPDATE jobs
+        SET status = $2, step = $3, total = $4, processed = $5, log = $6, result = $7,
+            error = $8, started_at = $9, finished_at = $10
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(job.uuid)
+    .bind(job.status.as_str())
+    .bind(&job.step)
+    .bind(job.total)
+    .bind(job.processed)
+    .bind(&job.log)
+    .bind(&job.result)
+    .bind(&job.error)
+    .bind(job.started_at)
+    .bind(job.finished_at)
+    .execute(pool)
+    .await?;
+
+    debug!(job_uuid = %job.uuid, "update job completed");
+    Ok(())
+}
+
+pub async fn get_by_uuid(pool: &PgPool, uuid: Uuid) -> Result<Option<Job>> {
+    debug!(job_uuid = %uuid, "fetch job by uuid started");
+    let job = sqlx::query_as::<_, Job>(
+        r#"
+        SELECT uuid, kind, server_uuid, status, description, step, total, processed, log,
+            result, error, cancellable, created_by, created_at, started_at, finished_at
+        FROM jobs
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(job_uuid = %uuid, "fetch job by uuid completed");
+    Ok(job)
+}
+
+/// Jobs matching every filter set in `query`, newest first.
+pub async fn list(pool: &PgPool, query: &JobQuery) -> Result<Vec<Job>> {
+    debug!("fetch jobs started");
+    let jobs = sqlx::query_as::<_, Job>(
+        r#"
+        SELECT uuid, kind, server_uuid, status, description, step, total, processed, log,
+            result, error, cancellable, created_by, created_at, started_at, finished_at
+        FROM jobs
+        WHERE ($1::UUID IS NULL OR server_uuid = $1)
+            AND ($2::VARCHAR IS NULL OR kind = $2)
+            AND ($3::VARCHAR IS NULL OR status = $3)
+        ORDER BY created_at DESC
+        LIMIT $4
+        "#,
+    )
+    .bind(query.server_uuid)
+    .bind(query.kind.map(|kind| kind.as_str()))
+    .bind(query.status.map(|status| status.as_str()))
+    .bind(query.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT))
+    .fetch_all(pool)
+    .await?;
+
+    debug!(job_count = jobs.len(), "fetch jobs completed");
+    Ok(jobs)
+}
+
+/// Marks every queued or running job as interrupted, returns the affected rows.
+pub async fn interrupt_unfinished(pool: &PgPool, error: &str) -> Result<Vec<Job>> {
+    debug!("interrupt unfinished jobs started");
+    let jobs = sqlx::query_as::<_, Job>(
+        r#"
+        UPDATE jobs
+        SET status = $3, error = $4, finished_at = now()
+        WHERE status IN ($1, $2)
+        RETURNING uuid, kind, server_uuid, status, description, step, total, processed, log,
+            result, error, cancellable, created_by, created_at, started_at, finished_at
+        "#,
+    )
+    .bind(JobStatus::Queued.as_str())
+    .bind(JobStatus::Running.as_str())
+    .bind(JobStatus::Interrupted.as_str())
+    .bind(error)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(
+        job_count = jobs.len(),
+        "interrupt unfinished jobs completed"
+    );
+    Ok(jobs)
+}
+
+/// Deletes jobs that finished before `cutoff`, returns how many.
+pub async fn delete_finished_before(pool: &PgPool, cutoff: DateTime<Utc>) -> Result<u64> {
+    debug!("delete finished jobs started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM jobs
+        WHERE finished_at < $1
+        "#,
+    )
+    .bind(cutoff)
+    .execute(pool)
+    .await?;
+
+    debug!(
+        job_count = result.rows_affected(),
+        "delete finished jobs completed"
+    );
+    Ok(result.rows_affected())
+}
@@ -1,4 +1,13 @@
+pub mod backup;
+pub mod command;
+pub mod crash;
+pub mod destination;
+pub mod file;
+pub mod job;
 pub mod perms;
+pub mod player;
+pub mod schedule;
+pub mod server;
 pub mod user;
 
 use std::time::Duration;
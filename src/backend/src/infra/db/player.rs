@@ -0,0 +1,89 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{domain::players::PlayerSession, prelude::*};
+
+pub async fn create_session(pool: &PgPool, session: &PlayerSession) -> Result<()> {
+    debug!(session_uuid = %session.uuid, "insert player session started");
+    sqlx::query(
+        r#"
+        INSERT INTO player_sessions (uuid, server_uuid, player_uuid, player_name, ip, joined_at)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        "#,
+    )
+    .bind(session.uuid)
+    .bind(session.server_uuid)
+    .bind(session.player_uuid)
+    .bind(&session.player_name)
+    .bind(&session.ip)
+    .bind(session.joined_at)
+    .execute(pool)
+    .await?;
+
+    debug!(session_uuid = %session.uuid, "insert player session completed");
+    Ok(())
+}
+
+pub async fn close_session(pool: &PgPool, uuid: Uuid, left_at: DateTime<Utc>) -> Result<()> {
+    debug!(session_uuid = %uuid, "close player session started");
+    sqlx::query(
+        r#"
+        UPDATE player_sessions
+        SET left_at = $2
+        WHERE uuid = $1 AND left_at IS NULL
+        "#,
+    )
+    .bind(uuid)
+    .bind(left_at)
+    .execute(pool)
+    .await?;
+
+    debug!(session_uuid = %uuid, "close player session completed");
+    Ok(())
+}
+
+/// Closes every open session, of one server or of all of them.
+pub async fn close_open_sessions(pool: &PgPool, server_uuid: Option<Uuid>) -> Result<u64> {
+    debug!(server_uuid = ?server_uuid, "close open player sessions started");
+    let result = sqlx::query(
+        r#"
+        UPDATE player_sessions
+        SET left_at = now()
+        WHERE left_at IS NULL AND ($1::UUID IS NULL OR server_uuid = $1)
+        "#,
+    )
+    .bind(server_uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(server_uuid = ?server_uuid, "close open player sessions completed");
+    Ok(result.rows_affected())
+}
+
+pub async fn get_sessions(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    player_name: &str,
+    limit: i64,
+) -> Result<Vec<PlayerSession>> {
+    debug!(server_uuid = %server_uuid, player = player_name, "fetch player sessions started");
+    let sessions = sqlx::query_as::<_, PlayerSession>(
+        r#"
+        SELECT uuid, server_uuid, player_uuid, player_name, ip, joined_at, left_at
+        FROM player_sessions
+        WHERE server_uuid = $1 AND LOWER(player_name) = LOWER($2)
+        ORDER BY joined_at DESC
+        LIMIT $3
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(player_name)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, player = player_name, "fetch player sessions completed");
+    Ok(sessions)
+}
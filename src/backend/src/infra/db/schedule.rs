@@ -0,0 +1,215 @@
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::{
+    domain::schedule::{ScheduledTask, TaskRun},
+    prelude::*,
+};
+
+pub async fn create_task(pool: &PgPool, task: &ScheduledTask) -> Result<ScheduledTask> {
+    debug!(task_uuid = %task.uuid, "insert scheduled task started");
+    let task = sqlx::query_as::<_, ScheduledTask>(
+        r#"
+        INSERT INTO scheduled_tasks (uuid, server_uuid, name, cron, timezone, action, command,
+            countdown_secs, catch_up, enabled, created_by, last_run_at, next_run_at)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13)
+        RETURNING uuid, server_uuid, name, cron, timezone, action, command, countdown_secs, catch_up,
+            enabled, created_by, last_run_at, next_run_at
+        "#,
+    )
+    .bind(task.uuid)
+    .bind(task.server_uuid)
+    .bind(&task.name)
+    .bind(&task.cron)
+    .bind(&task.timezone)
+    .bind(task.action.as_str())
+    .bind(&task.command)
+    .bind(task.countdown_secs)
+    .bind(task.catch_up.as_str())
+    .bind(task.enabled)
+    .bind(task.created_by)
+    .bind(task.last_run_at)
+    .bind(task.next_run_at)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(task_uuid = %task.uuid, "insert scheduled task completed");
+    Ok(task)
+}
+
+pub async fn get_all_tasks(pool: &PgPool) -> Result<Vec<ScheduledTask>> {
+    debug!("fetch all scheduled tasks started");
+    let tasks = sqlx::query_as::<_, ScheduledTask>(
+        r#"
+        SELECT uuid, server_uuid, name, cron, timezone, action, command, countdown_secs, catch_up,
+            enabled, created_by, last_run_at, next_run_at
+        FROM scheduled_tasks
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    debug!(
+        task_count = tasks.len(),
+        "fetch all scheduled tasks completed"
+    );
+    Ok(tasks)
+}
+
+pub async fn get_tasks_for_server(pool: &PgPool, server_uuid: Uuid) -> Result<Vec<ScheduledTask>> {
+    debug!(server_uuid = %server_uuid, "fetch scheduled tasks started");
+    let tasks = sqlx::query_as::<_, ScheduledTask>(
+        r#"
+        SELECT uuid, server_uuid, name, cron, timezone, action, command, countdown_secs, catch_up,
+            enabled, created_by, last_run_at, next_run_at
+        FROM scheduled_tasks
+        WHERE server_uuid = $1
+        ORDER BY name
+        "#,
+    )
+    .bind(server_uuid)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(server_uuid = %server_uuid, "fetch scheduled tasks completed");
+    Ok(tasks)
+}
+
+pub async fn get_task(
+    pool: &PgPool,
+    server_uuid: Uuid,
+    uuid: Uuid,
+) -> Result<Option<ScheduledTask>> {
+    debug!(task_uuid = %uuid, "fetch scheduled task started");
+    let task = sqlx::query_as::<_, ScheduledTask>(
+        r#"
+        SELECT uuid, server_uuid, name, cron, timezone, action, command, countdown_secs, catch_up,
+            enabled, created_by, last_run_at, next_run_at
+        FROM scheduled_tasks
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(task_uuid = %uuid, "fetch scheduled task completed");
+    Ok(task)
+}
+
+pub async fn update_task(pool: &PgPool, task: &ScheduledTask) -> Result<ScheduledTask> {
+    debug!(task_uuid = %task.uuid, "update scheduled task started");
+    let task = sqlx::query_as::<_, ScheduledTask>(
+        r#"
+        UPDATE scheduled_tasks
+        SET name = $2, cron = $3, timezone = $4, action = $5, command = $6, countdown_secs = $7,
+            catch_up = $8, enabled = $9, next_run_at = $10
+        WHERE uuid = $1
+        RETURNING uuid, server_uuid, name, cron, timezone, action, command, countdown_secs, catch_up,
+            enabled, created_by, last_run_at, next_run_at
+        "#,
+    )
+    .bind(task.uuid)
+    .bind(&task.name)
+    .bind(&task.cron)
+    .bind(&task.timezone)
+    .bind(task.action.as_str())
+    .bind(&task.command)
+    .bind(task.countdown_secs)
+    .bind(task.catch_up.as_str())
+    .bind(task.enabled)
+    .bind(task.next_run_at)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(task_uuid = %task.uuid, "update scheduled task completed");
+    Ok(task)
+}
+
+/// Stores when the task last ran and when it is due next.
+pub async fn set_run_times(
+    pool: &PgPool,
+    uuid: Uuid,
+    last_run_at: Option<DateTime<Utc>>,
+    next_run_at: Option<DateTime<Utc>>,
+) -> Result<()> {
+    debug!(task_uuid = %uuid, "update task run times started");
+    sqlx::query(
+        r#"
+        UPDATE scheduled_tasks
+        SET last_run_at = COALESCE($2, last_run_at), next_run_at = $3
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .bind(last_run_at)
+    .bind(next_run_at)
+    .execute(pool)
+    .await?;
+
+    debug!(task_uuid = %uuid, "update task run times completed");
+    Ok(())
+}
+
+pub async fn delete_task(pool: &PgPool, server_uuid: Uuid, uuid: Uuid) -> Result<bool> {
+    debug!(task_uuid = %uuid, "delete scheduled task started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM scheduled_tasks
+        WHERE server_uuid = $1 AND uuid = $2
+        "#,
+    )
+    .bind(server_uuid)
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(task_uuid = %uuid, "delete scheduled task completed");
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn create_run(pool: &PgPool, run: &TaskRun) -> Result<()> {
+    debug!(task_uuid = %run.task_uuid, "insert task run started");
+    sqlx::query(
+        r#"
+        INSERT INTO task_runs (uuid, task_uuid, scheduled_for, started_at, finished_at, outcome,
+            message)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(run.uuid)
+    .bind(run.task_uuid)
+    .bind(run.scheduled_for)
+    .bind(run.started_at)
+    .bind(run.finished_at)
+    .bind(run.outcome.as_str())
+    .bind(&run.message)
+    .execute(pool)
+    .await?;
+
+    debug!(task_uuid = %run.task_uuid, "insert task run completed");
+    Ok(())
+}
+
+pub async fn get_runs(pool: &PgPool, task_uuid: Uuid, limit: i64) -> Result<Vec<TaskRun>> {
+    debug!(task_uuid = %task_uuid, "fetch task runs started");
+    let runs = sqlx::query_as::<_, TaskRun>(
+        r#"
+        SELECT uuid, task_uuid, scheduled_for, started_at, finished_at, outcome, message
+        FROM task_runs
+        WHERE task_uuid = $1
+        ORDER BY scheduled_for DESC, started_at DESC
+        LIMIT $2
+        "#,
+    )
+    .bind(task_uuid)
+    .bind(limit)
+    .fetch_all(pool)
+    .await?;
+
+    debug!(task_uuid = %task_uuid, "fetch task runs completed");
+    Ok(runs)
+}
@@ -0,0 +1,152 @@
+use crate::{
+    domain::server::{InternalNewServer, Server},
+    prelude::*,
+};
+use anyhow::Result;
+use sqlx::{PgPool, types::Json};
+use uuid::Uuid;
+
+pub async fn create(pool: &PgPool, new_server: InternalNewServer) -> Result<Server> {
+    debug!(server_uuid = %new_server.uuid, "insert server started");
+    let server = sqlx::query_as::<_, Server>(
+        r#"
+        INSERT INTO servers (uuid, name, working_dir, jar_file, launch_command, java_path,
+            min_memory_mb, max_memory_mb, server_port, rcon_port, query_port, auto_start, owner,
+            rcon_password, minecraft_version, launch_profile)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+        RETURNING uuid, name, working_dir, jar_file, launch_command, java_path,
+            min_memory_mb, max_memory_mb, server_port, rcon_port, query_port, auto_start, owner,
+            rcon_password, minecraft_version, launch_profile
+        "#,
+    )
+    .bind(new_server.uuid)
+    .bind(&new_server.name)
+    .bind(&new_server.working_dir)
+    .bind(&new_server.jar_file)
+    .bind(&new_server.launch_command)
+    .bind(&new_server.java_path)
+    .bind(new_server.min_memory_mb)
+    .bind(new_server.max_memory_mb)
+    .bind(new_server.server_port)
+    .bind(new_server.rcon_port)
+    .bind(new_server.query_port)
+    .bind(new_server.auto_start)
+    .bind(new_server.owner)
+    .bind(&new_server.rcon_password)
+    .bind(&new_server.minecraft_version)
+    .bind(Json(&new_server.launch_profile))
+    .fetch_one(pool)
+    .await?;
+
+    debug!(server_uuid = %server.uuid, "insert server completed");
+    Ok(server)
+}
+
+pub async fn get_by_uuid(pool: &PgPool, uuid: Uuid) -> Result<Option<Server>> {
+    debug!(server_uuid = %uuid, "fetch server by uuid started");
+    let server = sqlx::query_as::<_, Server>(
+        r#"
+        SELECT uuid, name, working_dir, jar_file, launch_command, java_path,
+            min_memory_mb, max_memory_mb, server_port, rcon_port, query_port, auto_start, owner,
+            rcon_password, minecraft_version, launch_profile
+        FROM servers
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .fetch_optional(pool)
+    .await?;
+
+    debug!(server_uuid = %uuid, "fetch server by uuid completed");
+    Ok(server)
+}
+
+pub async fn get_all(pool: &PgPool) -> Result<Vec<Server>> {
+    debug!("fetch all servers started");
+    let servers = sqlx::query_as::<_, Server>(
+        r#"
+        SELECT uuid, name, working_dir, jar_file, launch_command, java_path,
+            min_memory_mb, max_memory_mb, server_port, rcon_port, query_port, auto_start, owner,
+            rcon_password, minecraft_version, launch_profile
+        FROM servers
+        ORDER BY name ASC
+        "#,
+    )
+    .fetch_all(pool)
+    .await?;
+
+    debug!("fetch all servers completed");
+    Ok(servers)
+}
+
+pub async fn update(pool: &PgPool, server: Server) -> Result<Server> {
+    debug!(server_uuid = %server.uuid, "update server started");
+    let server = sqlx::query_as::<_, Server>(
+        r#"
+        UPDATE servers
+        SET name = $2, working_dir = $3, jar_file = $4, launch_command = $5, java_path = $6,
+            min_memory_mb = $7, max_memory_mb = $8, server_port = $9, rcon_port = $10,
+            query_port = $11, auto_start = $12, rcon_password = $13, minecraft_version = $14,
+            launch_profile = $15
+        WHERE uuid = $1
+        RETURNING uuid, name, working_dir, jar_file, launch_command, java_path,
+            min_memory_mb, max_memory_mb, server_port, rcon_port, query_port, auto_start, owner,
+            rcon_password, minecraft_version, launch_profile
+        "#,
+    )
+    .bind(server.uuid)
+    .bind(&server.name)
+    .bind(&server.working_dir)
+    .bind(&server.jar_file)
+    .bind(&server.launch_command)
+    .bind(&server.java_path)
+    .bind(server.min_memory_mb)
+    .bind(server.max_memory_mb)
+    .bind(server.server_port)
+    .bind(server.rcon_port)
+    .bind(server.query_port)
+    .bind(server.auto_start)
+    .bind(&server.rcon_password)
+    .bind(&server.minecraft_version)
+    .bind(&server.launch_profile)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(server_uuid = %server.uuid, "update server completed");
+    Ok(server)
+}
+
+pub async fn delete(pool: &PgPool, uuid: Uuid) -> Result<bool> {
+    debug!(server_uuid = %uuid, "delete server started");
+    let result = sqlx::query(
+        r#"
+        DELETE FROM servers
+        WHERE uuid = $1
+        "#,
+    )
+    .bind(uuid)
+    .execute(pool)
+    .await?;
+
+    debug!(server_uuid = %uuid, "delete server completed");
+    Ok(result.rows_affected() > 0)
+}
+
+pub async fn exists_by_name(pool: &PgPool, name: &str) -> Result<bool> {
+    debug!(server_name = %name, "check server existence started");
+    let exists = sqlx::query_scalar::<_, bool>(
+        r#"
+        SELECT EXISTS(
+            SELECT 1
+            FROM servers
+            WHERE name = $1
+        )
+        "#,
+    )
+    .bind(name)
+    .fetch_one(pool)
+    .await?;
+
+    debug!(server_name = %name, "check server existence completed");
+    Ok(exists)
+}
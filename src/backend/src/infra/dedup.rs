@@ -0,0 +1,1006 @@
+use std::{
+    collections::{BTreeSet, HashMap, HashSet, VecDeque},
+    fs::{self, File, Permissions},
+    io::{self, BufReader, BufWriter, ErrorKind, Read, Write},
+    ops::Range,
+    os::unix::fs::{PermissionsExt, symlink},
+    path::{Component, Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+
+use flate2::{
+    Compression,
+    read::ZlibDecoder,
+    write::{GzEncoder, ZlibEncoder},
+};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tar::{Builder, EntryType, Header};
+
+use crate::infra::archive::{self, ArchiveInfo};
+
+const MANIFEST_VERSION: u32 = 1;
+/// Piece size for everything but region files.
+const BLOCK_SIZE: usize = 1024 * 1024;
+/// Anvil and the older McRegion format share the same layout.
+const REGION_EXTENSIONS: &[&str] = &["mca", "mcr"];
+const SECTOR_SIZE: usize = 4096;
+/// Chunk locations followed by their timestamps.
+const REGION_HEADER_SIZE: usize = 2 * SECTOR_SIZE;
+const PARTIAL_EXTENSION: &str = "part";
+const STORED_RAW: u8 = 0;
+const STORED_ZLIB: u8 = 1;
+const MAX_PROBLEMS: usize = 100;
+
+/// Content addressed chunk store, every chunk is named by the hex SHA-256 of
+/// its content and written once no matter how many backups use it.
+#[derive(Debug, Clone)]
+pub struct ChunkStore {
+    root: PathBuf,
+    level: u32,
+}
+
+/// Files and folders of one dedup backup. File contents live in the chunk
+/// store, each file points at a recipe chunk listing its pieces in order.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub version: u32,
+    pub entries: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum ManifestEntry {
+    Dir {
+        path: PathBuf,
+        mode: u32,
+    },
+    File {
+        path: PathBuf,
+        mode: u32,
+        size: u64,
+        mtime_ns: i64,
+        recipe: String,
+    },
+    Symlink {
+        path: PathBuf,
+        target: PathBuf,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotStats {
+    pub files: u64,
+    /// Files whose size and modification time matched the previous manifest
+    /// and were not read again.
+    pub unchanged_files: u64,
+    pub new_chunks: u64,
+    /// Bytes the new chunks take on disk.
+    pub new_bytes: u64,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SnapshotCheck {
+    pub checked_chunks: u64,
+    pub problems: Vec<String>,
+}
+
+impl ChunkStore {
+    /// Store rooted at `root`, new chunks are compressed with zlib `level`.
+    pub fn new(root: PathBuf, level: u32) -> Self {
+        Self { root, level }
+    }
+
+    fn path(&self, id: &str) -> PathBuf {
+        self.root.join(&id[..2]).join(id)
+    }
+
+    pub fn contains(&self, id: &str) -> bool {
+        id.len() > 2 && self.path(id).is_file()
+    }
+
+    /// Stores `data` unless an identical chunk exists. Returns the chunk id and
+    /// the bytes written, zero when it was already stored.
+    pub fn put(&self, data: &[u8]) -> io::Result<(String, u64)> {
+        let id = format!("{:x}", Sha256::digest(data));
+        let path = self.path(&id);
+        if path.is_file() {
+            return Ok((id, 0));
+        }
+
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::new(self.level.min(9)));
+        encoder.write_all(data)?;
+        let compressed = encoder.finish()?;
+        // Region chunks are compressed by the game already
+        let (tag, body) = if compressed.len() < data.len() {
+            (STORED_ZLIB, compressed.as_slice())
+        } else {
+            (STORED_RAW, data)
+        };
+
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let partial = path.with_extension(PARTIAL_EXTENSION);
+        let mut file = File::create(&partial)?;
+        file.write_all(&[tag])?;
+        file.write_all(body)?;
+        file.sync_all()?;
+        fs::rename(&partial, &path)?;
+
+        Ok((id, 1 + body.len() as u64))
+    }
+
+    /// Reads a chunk and checks it still hashes to its id.
+    pub fn get(&self, id: &str) -> io::Result<Vec<u8>> {
+        if id.len() <= 2 {
+            return Err(invalid(format!("invalid chunk id {id}")));
+        }
+        let stored = fs::read(self.path(id))?;
+        let data = match stored.split_first() {
+            Some((&STORED_RAW, body)) => body.to_vec(),
+            Some((&STORED_ZLIB, body)) => {
+                let mut data = Vec::new();
+                ZlibDecoder::new(body).read_to_end(&mut data)?;
+                data
+            }
+            _ => return Err(invalid(format!("chunk {id} has an unknown encoding"))),
+        };
+
+        if format!("{:x}", Sha256::digest(&data)) != id {
+            return Err(invalid(format!("chunk {id} does not match its hash")));
+        }
+        Ok(data)
+    }
+
+    /// Removes every chunk not in `keep`, and partial writes left behind by an
+    /// interrupted backup. Returns the number of files and bytes removed.
+    pub fn retain(&self, keep: &HashSet<String>) -> io::Result<(u64, u64)> {
+        let prefixes = match fs::read_dir(&self.root) {
+            Ok(prefixes) => prefixes,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok((0, 0)),
+            Err(e) => return Err(e),
+        };
+
+        let (mut files, mut bytes) = (0, 0);
+        for prefix in prefixes {
+            let prefix = prefix?;
+            if !prefix.file_type()?.is_dir() {
+                continue;
+            }
+            for chunk in fs::read_dir(prefix.path())? {
+                let chunk = chunk?;
+                if keep.contains(chunk.file_name().to_string_lossy().as_ref()) {
+                    continue;
+                }
+                bytes += chunk.metadata().map(|m| m.len()).unwrap_or_default();
+                fs::remove_file(chunk.path())?;
+                files += 1;
+            }
+            // Only succeeds once the prefix is empty
+            let _ = fs::remove_dir(prefix.path());
+        }
+        Ok((files, bytes))
+    }
+}
+
+impl ManifestEntry {
+    pub fn path(&self) -> &Path {
+        match self {
+            ManifestEntry::Dir { path, .. }
+            | ManifestEntry::File { path, .. }
+            | ManifestEntry::Symlink { path, .. } => path,
+        }
+    }
+}
+
+/// Stores `entries` (paths relative to `root`, walked as for
+/// [`archive::walk`]) in the chunk store. Files whose size and modification
+/// time match `previous` reuse its recipe without being read. Blocking.
+pub fn write_snapshot(
+    root: &Path,
+    entries: &[PathBuf],
+    store: &ChunkStore,
+    previous: Option<&Manifest>,
+    exclude: &[PathBuf],
+    skip_names: &[&str],
+) -> io::Result<(Manifest, SnapshotStats)> {
+    let known: HashMap<&Path, (u64, i64, &str)> = previous
+        .map(|manifest| manifest.entries.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .filter_map(|entry| match entry {
+            ManifestEntry::File {
+                path,
+                size,
+                mtime_ns,
+                recipe,
+                ..
+            } => Some((path.as_path(), (*size, *mtime_ns, recipe.as_str()))),
+            _ => None,
+        })
+        .collect();
+
+    let mut manifest = Manifest {
+        version: MANIFEST_VERSION,
+        entries: Vec::new(),
+    };
+    let mut stats = SnapshotStats::default();
+
+    archive::walk(
+        root,
+        entries,
+        exclude,
+        skip_names,
+        &mut |relative, path, metadata| {
+            let mode = metadata.permissions().mode();
+            let entry = if metadata.is_dir() {
+                ManifestEntry::Dir {
+                    path: relative.to_path_buf(),
+                    mode,
+                }
+            } else if metadata.is_file() {
+                let mtime_ns = mtime_ns(metadata);
+                let unchanged = known
+                    .get(relative)
+                    .filter(|(size, mtime, recipe)| {
+                        *size == metadata.len() && *mtime == mtime_ns && store.contains(recipe)
+                    })
+                    .map(|(size, _, recipe)| (recipe.to_string(), *size));
+
+                let (recipe, size) = match unchanged {
+                    Some(unchanged) => {
+                        stats.unchanged_files += 1;
+                        unchanged
+                    }
+                    None => match store_file(store, path, &mut stats) {
+                        Ok(stored) => stored,
+                        // Files may vanish while the server keeps running
+                        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+                        Err(e) => return Err(e),
+                    },
+                };
+                stats.files += 1;
+                ManifestEntry::File {
+                    path: relative.to_path_buf(),
+                    mode,
+                    size,
+                    mtime_ns,
+                    recipe,
+                }
+            } else {
+                ManifestEntry::Symlink {
+                    path: relative.to_path_buf(),
+                    target: fs::read_link(path)?,
+                }
+            };
+            manifest.entries.push(entry);
+            Ok(())
+        },
+    )?;
+
+    Ok((manifest, stats))
+}
+
+/// Splits a file into pieces, stores them and then the recipe listing them.
+/// Returns the recipe id and the bytes read.
+fn store_file(
+    store: &ChunkStore,
+    path: &Path,
+    stats: &mut SnapshotStats,
+) -> io::Result<(String, u64)> {
+    let mut put = |data: &[u8]| -> io::Result<String> {
+        let (id, written) = store.put(data)?;
+        if written > 0 {
+            stats.new_chunks += 1;
+            stats.new_bytes += written;
+        }
+        Ok(id)
+    };
+
+    let mut pieces = Vec::new();
+    let mut size = 0;
+    if is_region_file(path) {
+        let data = fs::read(path)?;
+        for range in region_pieces(&data) {
+            pieces.push(put(&data[range])?);
+        }
+        size = data.len() as u64;
+    } else {
+        let mut file = File::open(path)?;
+        let mut block = vec![0; BLOCK_SIZE];
+        loop {
+            let read = fill(&mut file, &mut block)?;
+            if read == 0 {
+                break;
+            }
+            pieces.push(put(&block[..read])?);
+            size += read as u64;
+        }
+    }
+
+    let recipe = put(pieces.join("\n").as_bytes())?;
+    Ok((recipe, size))
+}
+
+fn is_region_file(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| REGION_EXTENSIONS.iter().any(|region| ext == *region))
+}
+
+/// Splits a region file along the chunks its header points at, so a chunk the
+/// game did not rewrite is the same piece in every backup, wherever it moved
+/// within the file. Anything between chunks becomes a piece of its own.
+fn region_pieces(data: &[u8]) -> Vec<Range<usize>> {
+    if data.len() <= REGION_HEADER_SIZE {
+        // Too short to hold a header, the game has not written the file yet
+        return (!data.is_empty())
+            .then_some(0..data.len())
+            .into_iter()
+            .collect();
+    }
+
+    let mut spans: Vec<Range<usize>> = data[..SECTOR_SIZE]
+        .chunks_exact(4)
+        .filter_map(|location| {
+            let offset = u32::from_be_bytes([0, location[0], location[1], location[2]]) as usize
+                * SECTOR_SIZE;
+            let sectors = location[3] as usize * SECTOR_SIZE;
+            (offset >= REGION_HEADER_SIZE && sectors > 0 && offset < data.len())
+                .then(|| offset..(offset + sectors).min(data.len()))
+        })
+        .collect();
+    spans.sort_by_key(|span| span.start);
+
+    let mut pieces = Vec::with_capacity(spans.len() + 2);
+    pieces.push(0..REGION_HEADER_SIZE);
+    let mut cursor = REGION_HEADER_SIZE;
+    for span in spans {
+        // Overlapping entries only happen in damaged files, keep the bytes once
+        let start = span.start.max(cursor);
+        if start >= span.end {
+            continue;
+        }
+        if start > cursor {
+            pieces.push(cursor..start);
+        }
+        pieces.push(start..span.end);
+        cursor = span.end;
+    }
+    if cursor < data.len() {
+        pieces.push(cursor..data.len());
+    }
+    pieces
+}
+
+/// Reads until `buf` is full or the file ends.
+fn fill(file: &mut File, buf: &mut [u8]) -> io::Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match file.read(&mut buf[read..]) {
+            Ok(0) => break,
+            Ok(n) => read += n,
+            Err(e) if e.kind() == ErrorKind::Interrupted => {}
+            Err(e) => return Err(e),
+        }
+    }
+    Ok(read)
+}
+
+fn mtime_ns(metadata: &fs::Metadata) -> i64 {
+    metadata
+        .modified()
+        .ok()
+        .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+        .map(|since| since.as_nanos() as i64)
+        .unwrap_or_default()
+}
+
+fn recipe_pieces(recipe: &[u8]) -> impl Iterator<Item = &str> {
+    std::str::from_utf8(recipe)
+        .unwrap_or_default()
+        .lines()
+        .filter(|id| !id.is_empty())
+}
+
+/// Writes the manifest as JSON, returns its size and checksum. Blocking.
+pub fn write_manifest(manifest: &Manifest, dest: &Path) -> io::Result<ArchiveInfo> {
+    let json = serde_json::to_vec(manifest)?;
+    let mut file = File::create(dest)?;
+    file.write_all(&json)?;
+    file.sync_all()?;
+
+    Ok(ArchiveInfo {
+        size_bytes: json.len() as u64,
+        sha256: format!("{:x}", Sha256::digest(&json)),
+    })
+}
+
+/// Blocking.
+pub fn read_manifest(path: &Path) -> io::Result<Manifest> {
+    let manifest: Manifest = serde_json::from_reader(BufReader::new(File::open(path)?))?;
+    if manifest.version != MANIFEST_VERSION {
+        return Err(invalid(format!(
+            "unsupported manifest version {}",
+            manifest.version
+        )));
+    }
+    Ok(manifest)
+}
+
+/// First path component of every manifest entry.
+pub fn top_level_entries(manifest: &Manifest) -> BTreeSet<PathBuf> {
+    manifest
+        .entries
+        .iter()
+        .filter_map(|entry| entry.path().components().next())
+        .map(|first| PathBuf::from(first.as_os_str()))
+        .collect()
+}
+
+/// Writes the manifest's files into `dest`, limited to entries at or below one
+/// of `only` when it is not empty. Every chunk is checked against its hash on
+/// the way. Returns how many entries were written. Blocking.
+pub fn restore_snapshot(
+    manifest: &Manifest,
+    store: &ChunkStore,
+    dest: &Path,
+    only: &[PathBuf],
+) -> io::Result<u64> {
+    let mut restored = 0;
+    // Applied last, a read only folder would refuse its own files otherwise
+    let mut dir_modes = Vec::new();
+
+    for entry in &manifest.entries {
+        let relative = entry.path();
+        if !is_plain_relative(relative)
+            || (!only.is_empty() && !only.iter().any(|selected| relative.starts_with(selected)))
+        {
+            continue;
+        }
+
+        let target = dest.join(relative);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        match entry {
+            ManifestEntry::Dir { mode, .. } => {
+                if !fs::symlink_metadata(&target).is_ok_and(|m| m.is_dir()) {
+                    remove_existing(&target)?;
+                    fs::create_dir(&target)?;
+                }
+                dir_modes.push((target, *mode));
+            }
+            ManifestEntry::File {
+                mode,
+                mtime_ns,
+                recipe,
+                ..
+            } => {
+                // Never write through a symlink left in place
+                remove_existing(&target)?;
+                let mut file = BufWriter::new(File::create(&target)?);
+                for piece in recipe_pieces(&store.get(recipe)?) {
+                    file.write_all(&store.get(piece)?)?;
+                }
+                let file = file.into_inner().map_err(|e| e.into_error())?;
+                file.set_permissions(Permissions::from_mode(*mode))?;
+                file.set_modified(UNIX_EPOCH + Duration::from_nanos((*mtime_ns).max(0) as u64))?;
+            }
+            ManifestEntry::Symlink {
+                target: link_target,
+                ..
+            } => {
+                remove_existing(&target)?;
+                symlink(link_target, &target)?;
+            }
+        }
+        restored += 1;
+    }
+
+    for (dir, mode) in dir_modes.into_iter().rev() {
+        fs::set_permissions(dir, Permissions::from_mode(mode))?;
+    }
+    Ok(restored)
+}
+
+/// Reads every chunk the manifest needs once and checks its hash, and that the
+/// pieces of every file add up to its recorded size. Blocking.
+pub fn verify_snapshot(manifest: &Manifest, store: &ChunkStore) -> SnapshotCheck {
+    let mut check = SnapshotCheck::default();
+    let mut checked = HashSet::new();
+    let report = |check: &mut SnapshotCheck, problem: String| {
+        if check.problems.len() < MAX_PROBLEMS {
+            check.problems.push(problem);
+        }
+    };
+
+    for entry in &manifest.entries {
+        let ManifestEntry::File {
+            path, size, recipe, ..
+        } = entry
+        else {
+            continue;
+        };
+
+        let pieces = match store.get(recipe) {
+            Ok(pieces) => pieces,
+            Err(e) => {
+                report(&mut check, format!("{}: {e}", path.display()));
+                continue;
+            }
+        };
+        check.checked_chunks += 1;
+
+        let mut total = 0;
+        for piece in recipe_pieces(&pieces) {
+            match store.get(piece) {
+                Ok(data) => {
+                    total += data.len() as u64;
+                    if checked.insert(piece.to_string()) {
+                        check.checked_chunks += 1;
+                    }
+                }
+                Err(e) => {
+                    report(&mut check, format!("{}: {e}", path.display()));
+                    total = *size;
+                    break;
+                }
+            }
+        }
+        if total != *size {
+            report(
+                &mut check,
+                format!(
+                    "{}: restores {total} bytes, recorded {size}",
+                    path.display()
+                ),
+            );
+        }
+    }
+    check
+}
+
+/// Writes the snapshot as a gzip compressed tarball laid out like an archive
+/// backup, readable without the chunk store. Every chunk is checked against
+/// its hash on the way. Blocking.
+pub fn export_snapshot(
+    manifest: &Manifest,
+    store: &ChunkStore,
+    dest: &Path,
+    level: u32,
+) -> io::Result<()> {
+    let file = BufWriter::new(File::create(dest)?);
+    let mut builder = Builder::new(GzEncoder::new(file, Compression::new(level.min(9))));
+
+    for entry in &manifest.entries {
+        let relative = entry.path();
+        if !is_plain_relative(relative) {
+            continue;
+        }
+
+        let mut header = Header::new_gnu();
+        match entry {
+            ManifestEntry::Dir { mode, .. } => {
+                header.set_entry_type(EntryType::Directory);
+                header.set_mode(*mode);
+                header.set_size(0);
+                builder.append_data(&mut header, relative, io::empty())?;
+            }
+            ManifestEntry::File {
+                mode,
+                size,
+                mtime_ns,
+                recipe,
+                ..
+            } => {
+                header.set_entry_type(EntryType::Regular);
+                header.set_mode(*mode);
+                header.set_size(*size);
+                header.set_mtime((*mtime_ns).max(0) as u64 / 1_000_000_000);
+                let mut reader = FileReader {
+                    store,
+                    pieces: recipe_pieces(&store.get(recipe)?)
+                        .map(str::to_string)
+                        .collect(),
+                    current: io::Cursor::new(Vec::new()),
+                    read: 0,
+                };
+                builder.append_data(&mut header, relative, &mut reader)?;
+                // The header already promised the recorded size
+                if reader.read != *size {
+                    return Err(invalid(format!(
+                        "{} has {} bytes instead of {size}",
+                        relative.display(),
+                        reader.read
+                    )));
+                }
+            }
+            ManifestEntry::Symlink { target, .. } => {
+                header.set_entry_type(EntryType::Symlink);
+                header.set_size(0);
+                builder.append_link(&mut header, relative, target)?;
+            }
+        }
+    }
+
+    let file = builder.into_inner()?.finish()?;
+    file.into_inner().map_err(|e| e.into_error())?.sync_all()
+}
+
+/// Reads a file's pieces from the store one chunk at a time.
+struct FileReader<'a> {
+    store: &'a ChunkStore,
+    pieces: VecDeque<String>,
+    current: io::Cursor<Vec<u8>>,
+    read: u64,
+}
+
+impl Read for FileReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.current.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                self.read += n as u64;
+                return Ok(n);
+            }
+            let Some(piece) = self.pieces.pop_front() else {
+                return Ok(0);
+            };
+            self.current = io::Cursor::new(self.store.get(&piece)?);
+        }
+    }
+}
+
+/// Removes chunks none of `manifests` refer to. Stops without removing
+/// anything when a recipe cannot be read, its pieces would look unused.
+/// Returns the number of files and bytes removed. Blocking.
+pub fn collect_garbage(store: &ChunkStore, manifests: &[Manifest]) -> io::Result<(u64, u64)> {
+    let mut keep = HashSet::new();
+    for manifest in manifests {
+        for entry in &manifest.entries {
+            let ManifestEntry::File { recipe, .. } = entry else {
+                continue;
+            };
+            if !keep.insert(recipe.clone()) {
+                continue;
+            }
+            match store.get(recipe) {
+                Ok(pieces) => keep.extend(recipe_pieces(&pieces).map(str::to_string)),
+                Err(e) if e.kind() == ErrorKind::NotFound => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+    store.retain(&keep)
+}
+
+fn is_plain_relative(path: &Path) -> bool {
+    !path.as_os_str().is_empty()
+        && path
+            .components()
+            .all(|component| matches!(component, Component::Normal(_)))
+}
+
+fn remove_existing(path: &Path) -> io::Result<()> {
+    match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_dir() => fs::remove_dir_all(path),
+        Ok(_) => fs::remove_file(path),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+        Err(e) => Err(e),
+    }
+}
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, message)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand_core::{OsRng, RngCore};
+
+    fn random(len: usize) -> Vec<u8> {
+        let mut data = vec![0; len];
+        OsRng.fill_bytes(&mut data);
+        data
+    }
+
+    /// Region file with the given `(sector, sector_count, fill byte)` chunks.
+    fn region(chunks: &[(u32, u8, u8)], sectors: usize) -> Vec<u8> {
+        let mut data = vec![0; sectors * SECTOR_SIZE];
+        for (slot, (sector, count, fill)) in chunks.iter().enumerate() {
+            let location = (sector << 8) | *count as u32;
+            data[slot * 4..slot * 4 + 4].copy_from_slice(&location.to_be_bytes());
+            let start = *sector as usize * SECTOR_SIZE;
+            data[start..start + *count as usize * SECTOR_SIZE].fill(*fill);
+        }
+        data
+    }
+
+    fn snapshot(
+        root: &Path,
+        store: &ChunkStore,
+        previous: Option<&Manifest>,
+    ) -> (Manifest, SnapshotStats) {
+        write_snapshot(
+            root,
+            &[PathBuf::from("world"), PathBuf::from("server.jar")],
+            store,
+            previous,
+            &[],
+            &["session.lock"],
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn chunks_are_named_by_hash_and_stored_once() {
+        let scratch = tempfile::tempdir().unwrap();
+        let store = ChunkStore::new(scratch.path().join("chunks"), 6);
+
+        let text = b"motd=A Minecraft Server\n".repeat(100);
+        let (id, written) = store.put(&text).unwrap();
+        assert_eq!(id, format!("{:x}", Sha256::digest(&text)));
+        assert!(
+            written > 0 && written < text.len() as u64,
+            "compressible data is zlib stored"
+        );
+        assert_eq!(store.put(&text).unwrap(), (id.clone(), 0));
+        assert_eq!(store.get(&id).unwrap(), text);
+
+        let noise = random(4096);
+        let (noise_id, written) = store.put(&noise).unwrap();
+        assert_eq!(
+            written,
+            1 + noise.len() as u64,
+            "incompressible data is stored raw"
+        );
+        assert_eq!(store.get(&noise_id).unwrap(), noise);
+    }
+
+    #[test]
+    fn tampered_chunk_is_rejected() {
+        let scratch = tempfile::tempdir().unwrap();
+        let store = ChunkStore::new(scratch.path().join("chunks"), 6);
+        let (id, _) = store.put(&random(64)).unwrap();
+
+        let mut stored = fs::read(store.path(&id)).unwrap();
+        stored[10] ^= 0xFF;
+        fs::write(store.path(&id), stored).unwrap();
+        assert_eq!(store.get(&id).unwrap_err().kind(), ErrorKind::InvalidData);
+        assert_eq!(store.get("ab").unwrap_err().kind(), ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn region_files_split_along_their_chunks() {
+        // Chunks at sectors 4 and 2 (listed out of order), a gap at sector 3
+        // and trailing bytes after the last chunk
+        let mut data = region(&[(4, 2, 0xAA), (2, 1, 0xBB)], 6);
+        data.extend_from_slice(&[1, 2, 3]);
+        let pieces = region_pieces(&data);
+        let s = SECTOR_SIZE;
+        assert_eq!(
+            pieces,
+            [
+                0..2 * s,
+                2 * s..3 * s,
+                3 * s..4 * s,
+                4 * s..6 * s,
+                6 * s..6 * s + 3
+            ]
+        );
+
+        assert_eq!(region_pieces(&[]), Vec::<Range<usize>>::new());
+        assert_eq!(region_pieces(&[7; 100]), vec![0..100usize]);
+    }
+
+    #[test]
+    fn damaged_region_entries_keep_every_byte_once() {
+        // Second entry overlaps the first, third points past the end
+        let mut data = region(&[(2, 2, 0xAA), (3, 2, 0xBB)], 5);
+        data[8..12].copy_from_slice(&((40u32 << 8) | 1).to_be_bytes());
+        let pieces = region_pieces(&data);
+        let s = SECTOR_SIZE;
+        assert_eq!(pieces, [0..2 * s, 2 * s..4 * s, 4 * s..5 * s]);
+        assert_eq!(
+            pieces.iter().map(|piece| piece.len()).sum::<usize>(),
+            data.len()
+        );
+    }
+
+    #[test]
+    fn moved_region_chunk_is_the_same_piece() {
+        let before = region(&[(2, 1, 0xAA), (3, 1, 0xBB)], 4);
+        let after = region(&[(3, 1, 0xAA), (2, 1, 0xBB)], 4);
+        let ids = |data: &[u8]| -> HashSet<String> {
+            region_pieces(data)
+                .into_iter()
+                .skip(1)
+                .map(|piece| format!("{:x}", Sha256::digest(&data[piece])))
+                .collect()
+        };
+        assert_eq!(ids(&before), ids(&after));
+    }
+
+    #[test]
+    fn snapshot_restores_byte_for_byte_and_reuses_unchanged_files() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path().join("server");
+        fs::create_dir_all(root.join("world/region")).unwrap();
+        let jar = random(BLOCK_SIZE + 1000);
+        let level = b"level data".to_vec();
+        let mca = region(&[(2, 1, 0xAA)], 3);
+        fs::write(root.join("server.jar"), &jar).unwrap();
+        fs::write(root.join("world/level.dat"), &level).unwrap();
+        fs::write(root.join("world/region/r.0.0.mca"), &mca).unwrap();
+        fs::write(root.join("world/session.lock"), b"lock").unwrap();
+        fs::set_permissions(root.join("server.jar"), Permissions::from_mode(0o750)).unwrap();
+        symlink("level.dat", root.join("world/link.dat")).unwrap();
+        let store = ChunkStore::new(scratch.path().join("chunks"), 6);
+
+        let (manifest, stats) = snapshot(&root, &store, None);
+        assert_eq!(stats.files, 3);
+        assert_eq!(stats.unchanged_files, 0);
+        // Two jar blocks, header and chunk of the region, level.dat and three recipes
+        assert_eq!(stats.new_chunks, 8);
+        assert!(
+            manifest
+                .entries
+                .iter()
+                .all(|e| !e.path().ends_with("session.lock"))
+        );
+
+        let (again, stats) = snapshot(&root, &store, Some(&manifest));
+        assert_eq!(stats.unchanged_files, 3);
+        assert_eq!(stats.new_chunks, 0);
+        assert_eq!(
+            serde_json::to_value(&again).unwrap(),
+            serde_json::to_value(&manifest).unwrap()
+        );
+
+        let dest = scratch.path().join("restored");
+        let restored = restore_snapshot(&manifest, &store, &dest, &[]).unwrap();
+        assert_eq!(restored, manifest.entries.len() as u64);
+        assert_eq!(fs::read(dest.join("server.jar")).unwrap(), jar);
+        assert_eq!(fs::read(dest.join("world/level.dat")).unwrap(), level);
+        assert_eq!(fs::read(dest.join("world/region/r.0.0.mca")).unwrap(), mca);
+        assert_eq!(
+            fs::read_link(dest.join("world/link.dat")).unwrap(),
+            Path::new("level.dat")
+        );
+        let mode = fs::metadata(dest.join("server.jar"))
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o750);
+        assert_eq!(
+            top_level_entries(&manifest),
+            BTreeSet::from([PathBuf::from("server.jar"), PathBuf::from("world")])
+        );
+
+        let partial = scratch.path().join("partial");
+        let restored = restore_snapshot(
+            &manifest,
+            &store,
+            &partial,
+            &[PathBuf::from("world/region")],
+        )
+        .unwrap();
+        assert_eq!(restored, 2);
+        assert!(!partial.join("server.jar").exists());
+    }
+
+    #[test]
+    fn exported_snapshot_extracts_like_an_archive() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path().join("server");
+        fs::create_dir_all(root.join("world/region")).unwrap();
+        let jar = random(2 * BLOCK_SIZE + 10);
+        let mca = region(&[(2, 1, 0xAA), (3, 2, 0xBB)], 5);
+        fs::write(root.join("server.jar"), &jar).unwrap();
+        fs::write(root.join("world/region/r.0.0.mca"), &mca).unwrap();
+        fs::write(root.join("world/empty.dat"), b"").unwrap();
+        symlink("region/r.0.0.mca", root.join("world/link.mca")).unwrap();
+        let store = ChunkStore::new(scratch.path().join("chunks"), 6);
+        let (manifest, _) = snapshot(&root, &store, None);
+
+        let archive = scratch.path().join("export.tar.gz");
+        export_snapshot(&manifest, &store, &archive, 6).unwrap();
+        let dest = scratch.path().join("extracted");
+        fs::create_dir(&dest).unwrap();
+        archive::extract_tar_gz(&archive, &dest, &[]).unwrap();
+
+        assert_eq!(fs::read(dest.join("server.jar")).unwrap(), jar);
+        assert_eq!(fs::read(dest.join("world/region/r.0.0.mca")).unwrap(), mca);
+        assert_eq!(fs::read(dest.join("world/empty.dat")).unwrap(), b"");
+        assert_eq!(
+            archive::top_level_entries(&archive).unwrap(),
+            top_level_entries(&manifest)
+        );
+
+        // A piece gone from the store fails the export instead of cutting a file short
+        let ManifestEntry::File { recipe, .. } = manifest
+            .entries
+            .iter()
+            .find(|e| e.path() == Path::new("server.jar"))
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        let piece = recipe_pieces(&store.get(recipe).unwrap())
+            .last()
+            .unwrap()
+            .to_string();
+        fs::remove_file(store.path(&piece)).unwrap();
+        let failed = export_snapshot(&manifest, &store, &scratch.path().join("broken.tar.gz"), 6);
+        assert_eq!(failed.unwrap_err().kind(), ErrorKind::NotFound);
+    }
+
+    #[test]
+    fn restore_ignores_entries_escaping_the_target() {
+        let scratch = tempfile::tempdir().unwrap();
+        let store = ChunkStore::new(scratch.path().join("chunks"), 6);
+        let manifest = Manifest {
+            version: MANIFEST_VERSION,
+            entries: vec![
+                ManifestEntry::Dir {
+                    path: PathBuf::from("../escape"),
+                    mode: 0o755,
+                },
+                ManifestEntry::Dir {
+                    path: PathBuf::from("/abs"),
+                    mode: 0o755,
+                },
+                ManifestEntry::Dir {
+                    path: PathBuf::from("ok"),
+                    mode: 0o755,
+                },
+            ],
+        };
+        let dest = scratch.path().join("dest");
+        assert_eq!(restore_snapshot(&manifest, &store, &dest, &[]).unwrap(), 1);
+        assert!(dest.join("ok").is_dir());
+        assert!(!scratch.path().join("escape").exists());
+    }
+
+    #[test]
+    fn verify_reports_damage_and_garbage_keeps_what_is_used() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path().join("server");
+        fs::create_dir_all(root.join("world")).unwrap();
+        fs::write(root.join("world/level.dat"), random(100)).unwrap();
+        fs::write(root.join("server.jar"), random(200)).unwrap();
+        let store = ChunkStore::new(scratch.path().join("chunks"), 6);
+        let (manifest, _) = snapshot(&root, &store, None);
+        assert!(verify_snapshot(&manifest, &store).problems.is_empty());
+
+        let (orphan, _) = store.put(b"no longer referenced").unwrap();
+        assert_eq!(
+            collect_garbage(&store, std::slice::from_ref(&manifest))
+                .unwrap()
+                .0,
+            1
+        );
+        assert!(!store.contains(&orphan));
+        assert!(verify_snapshot(&manifest, &store).problems.is_empty());
+
+        let ManifestEntry::File { recipe, .. } = manifest
+            .entries
+            .iter()
+            .find(|e| e.path() == Path::new("server.jar"))
+            .unwrap()
+        else {
+            unreachable!()
+        };
+        let piece = recipe_pieces(&store.get(recipe).unwrap())
+            .next()
+            .unwrap()
+            .to_string();
+        fs::remove_file(store.path(&piece)).unwrap();
+        let check = verify_snapshot(&manifest, &store);
+        assert_eq!(check.problems.len(), 1);
+        assert!(check.problems[0].starts_with("server.jar: "));
+    }
+}
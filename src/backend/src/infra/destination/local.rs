@@ -0,0 +1,70 @@
+use std::{
+    io::ErrorKind,
+    os::unix::fs::MetadataExt,
+    path::{Path, PathBuf},
+};
+
+use tokio::fs;
+
+use super::{Destination, DestinationError};
+
+/// A directory on the daemon host, optionally required to be a mount point.
+#[derive(Debug, Clone)]
+pub struct LocalDir {
+    dir: PathBuf,
+    require_mount: bool,
+}
+
+impl LocalDir {
+    pub fn new(dir: PathBuf, require_mount: bool) -> Self {
+        Self { dir, require_mount }
+    }
+
+    /// An unmounted share leaves an empty directory on the root filesystem
+    /// behind, writing there would quietly fill the local disk.
+    async fn check_mounted(&self) -> Result<(), DestinationError> {
+        if !self.require_mount {
+            return Ok(());
+        }
+        let Some(parent) = self.dir.parent() else {
+            return Ok(());
+        };
+        let dir = fs::metadata(&self.dir).await?;
+        let parent = fs::metadata(parent).await?;
+        if dir.dev() == parent.dev() {
+            return Err(DestinationError::NotMounted(self.dir.display().to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl Destination for LocalDir {
+    async fn upload(&self, file: &Path, name: &str) -> Result<(), DestinationError> {
+        self.check_mounted().await?;
+        if !self.require_mount {
+            fs::create_dir_all(&self.dir).await?;
+        }
+
+        let dest = self.dir.join(name);
+        let partial = self.dir.join(format!("{name}.part"));
+        fs::copy(file, &partial).await?;
+        fs::File::open(&partial).await?.sync_all().await?;
+        fs::rename(&partial, &dest).await?;
+        Ok(())
+    }
+
+    async fn download(&self, name: &str, file: &Path) -> Result<(), DestinationError> {
+        self.check_mounted().await?;
+        fs::copy(self.dir.join(name), file).await?;
+        Ok(())
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), DestinationError> {
+        self.check_mounted().await?;
+        match fs::remove_file(self.dir.join(name)).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
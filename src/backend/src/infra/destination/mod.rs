@@ -0,0 +1,130 @@
+mod local;
+mod s3;
+mod sftp;
+
+use std::{future::Future, path::Path};
+
+use thiserror::Error;
+
+use crate::domain::destination::DestinationConfig;
+
+pub use local::LocalDir;
+pub use s3::S3Bucket;
+pub use sftp::SftpTarget;
+
+#[derive(Debug, Error)]
+pub enum DestinationError {
+    #[error(transparent)]
+    Io(#[from] std::io::Error),
+    #[error("{0} is not a mount point")]
+    NotMounted(String),
+    #[error("sftp failed: {0}")]
+    Sftp(String),
+    #[error("object storage answered {status}: {message}")]
+    S3 { status: u16, message: String },
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error("destination needs a secret")]
+    MissingSecret,
+}
+
+/// Somewhere backup files are copied to, addressed by file name.
+pub trait Destination {
+    /// Uploads the local `file` as `name`, replacing what was there.
+    fn upload(
+        &self,
+        file: &Path,
+        name: &str,
+    ) -> impl Future<Output = Result<(), DestinationError>> + Send;
+
+    /// Downloads `name` into the local `file`.
+    fn download(
+        &self,
+        name: &str,
+        file: &Path,
+    ) -> impl Future<Output = Result<(), DestinationError>> + Send;
+
+    /// Removes `name`, succeeding when it is already gone.
+    fn delete(&self, name: &str) -> impl Future<Output = Result<(), DestinationError>> + Send;
+}
+
+/// Any configured destination.
+#[derive(Debug, Clone)]
+pub enum Remote {
+    Local(LocalDir),
+    Sftp(SftpTarget),
+    S3(S3Bucket),
+}
+
+impl Remote {
+    /// Builds the destination for a config, `secret` is the unsealed
+    /// destination secret.
+    pub fn new(
+        config: &DestinationConfig,
+        secret: Option<String>,
+        http: reqwest::Client,
+    ) -> Result<Self, DestinationError> {
+        Ok(match config {
+            DestinationConfig::Local { path } => Remote::Local(LocalDir::new(path.into(), false)),
+            DestinationConfig::Mounted { path } => Remote::Local(LocalDir::new(path.into(), true)),
+            DestinationConfig::Sftp {
+                host,
+                port,
+                username,
+                identity_file,
+                remote_dir,
+                known_hosts_file,
+            } => Remote::Sftp(SftpTarget {
+                host: host.clone(),
+                port: *port,
+                username: username.clone(),
+                identity_file: identity_file.into(),
+                remote_dir: remote_dir.clone(),
+                known_hosts_file: known_hosts_file.as_ref().map(Into::into),
+            }),
+            DestinationConfig::S3 {
+                endpoint,
+                region,
+                bucket,
+                prefix,
+                access_key_id,
+                path_style,
+            } => Remote::S3(S3Bucket {
+                http,
+                endpoint: endpoint.trim_end_matches('/').to_string(),
+                region: region.clone(),
+                bucket: bucket.clone(),
+                prefix: prefix.clone(),
+                access_key_id: access_key_id.clone(),
+                secret_access_key: secret.ok_or(DestinationError::MissingSecret)?,
+                path_style: *path_style,
+            }),
+        })
+    }
+}
+
+impl Destination for Remote {
+    async fn upload(&self, file: &Path, name: &str) -> Result<(), DestinationError> {
+        match self {
+            Remote::Local(dir) => dir.upload(file, name).await,
+            Remote::Sftp(target) => target.upload(file, name).await,
+            Remote::S3(bucket) => bucket.upload(file, name).await,
+        }
+    }
+
+    async fn download(&self, name: &str, file: &Path) -> Result<(), DestinationError> {
+        match self {
+            Remote::Local(dir) => dir.download(name, file).await,
+            Remote::Sftp(target) => target.download(name, file).await,
+            Remote::S3(bucket) => bucket.download(name, file).await,
+        }
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), DestinationError> {
+        match self {
+            Remote::Local(dir) => dir.delete(name).await,
+            Remote::Sftp(target) => target.delete(name).await,
+            Remote::S3(bucket) => bucket.delete(name).await,
+        }
+    }
+}
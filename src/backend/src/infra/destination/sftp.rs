@@ -0,0 +1,200 @@
+use std::{
+    path::{Path, PathBuf},
+    process::Stdio,
+};
+
+use tokio::{io::AsyncWriteExt, process::Command};
+
+use super::{Destination, DestinationError};
+
+/// Keeps copies on an SSH host through the OpenSSH `sftp` client in batch mode,
+/// which only allows key authentication.
+#[derive(Debug, Clone)]
+pub struct SftpTarget {
+    pub host: String,
+    pub port: u16,
+    pub username: String,
+    pub identity_file: PathBuf,
+    pub remote_dir: String,
+    pub known_hosts_file: Option<PathBuf>,
+}
+
+impl SftpTarget {
+    fn remote_path(&self, name: &str) -> String {
+        format!("{}/{name}", self.remote_dir.trim_end_matches('/'))
+    }
+
+    /// Runs batch commands, a leading `-` makes `sftp` ignore that command
+    /// failing.
+    async fn batch(&self, commands: &[String]) -> Result<(), DestinationError> {
+        let mut command = Command::new("sftp");
+        command
+            .arg("-b")
+            .arg("-")
+            .arg("-P")
+            .arg(self.port.to_string())
+            .arg("-i")
+            .arg(&self.identity_file)
+            .args(["-o", "BatchMode=yes"])
+            .args(["-o", "ConnectTimeout=15"])
+            .args(["-o", "ServerAliveInterval=15"])
+            .args(["-o", "ServerAliveCountMax=4"]);
+        match &self.known_hosts_file {
+            Some(file) => {
+                command
+                    .arg("-o")
+                    .arg(format!("UserKnownHostsFile={}", file.display()))
+                    .args(["-o", "StrictHostKeyChecking=yes"]);
+            }
+            None => {
+                command.args(["-o", "StrictHostKeyChecking=accept-new"]);
+            }
+        }
+        command
+            .arg(format!("{}@{}", self.username, self.host))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true);
+
+        let mut child = command.spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(commands.join("\n").as_bytes()).await?;
+            stdin.write_all(b"\n").await?;
+        }
+
+        let output = child.wait_with_output().await?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let message = stderr
+            .lines()
+            .rev()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or("exited without output");
+        Err(DestinationError::Sftp(format!(
+            "{} ({})",
+            message.trim(),
+            output.status
+        )))
+    }
+}
+
+/// Quotes a batch argument, `sftp` reads backslash escapes inside quotes.
+fn quote(path: &str) -> String {
+    format!("\"{}\"", path.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+fn local(path: &Path) -> String {
+    quote(&path.to_string_lossy())
+}
+
+impl Destination for SftpTarget {
+    async fn upload(&self, file: &Path, name: &str) -> Result<(), DestinationError> {
+        let dest = self.remote_path(name);
+        let partial = format!("{dest}.part");
+        self.batch(&[
+            format!("-mkdir {}", quote(&self.remote_dir)),
+            format!("put {} {}", local(file), quote(&partial)),
+            // Renaming over an existing file fails on most servers
+            format!("-rm {}", quote(&dest)),
+            format!("rename {} {}", quote(&partial), quote(&dest)),
+        ])
+        .await
+    }
+
+    async fn download(&self, name: &str, file: &Path) -> Result<(), DestinationError> {
+        self.batch(&[format!(
+            "get {} {}",
+            quote(&self.remote_path(name)),
+            local(file)
+        )])
+        .await
+    }
+
+    async fn delete(&self, name: &str) -> Result<(), DestinationError> {
+        self.batch(&[format!("-rm {}", quote(&self.remote_path(name)))])
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn target(port: u16) -> SftpTarget {
+        SftpTarget {
+            host: "127.0.0.1".to_string(),
+            port,
+            username: "backup".to_string(),
+            identity_file: PathBuf::from("/nonexistent/id_ed25519"),
+            remote_dir: "/srv/backups/".to_string(),
+            known_hosts_file: None,
+        }
+    }
+
+    #[test]
+    fn remote_paths_join_once() {
+        assert_eq!(
+            target(22).remote_path("world.tar.zst"),
+            "/srv/backups/world.tar.zst"
+        );
+    }
+
+    #[test]
+    fn batch_arguments_are_quoted() {
+        assert_eq!(quote("/srv/my backups"), "\"/srv/my backups\"");
+        assert_eq!(quote(r#"a"b\c"#), r#""a\"b\\c""#);
+        assert_eq!(local(Path::new("/tmp/x y")), "\"/tmp/x y\"");
+    }
+
+    #[tokio::test]
+    async fn unreachable_hosts_fail_with_the_client_message() {
+        // Nothing listens on the discard port
+        let error = target(9).delete("world.tar.zst").await.unwrap_err();
+        match error {
+            DestinationError::Sftp(message) => assert!(!message.is_empty()),
+            other => panic!("expected an sftp error, got {other:?}"),
+        }
+    }
+
+    /// An SSH host reachable with a key, named by `RUSTYMINE_TEST_SFTP_*`
+    /// variables.
+    fn local_host() -> SftpTarget {
+        let var = |name: &str, default: &str| {
+            std::env::var(format!("RUSTYMINE_TEST_SFTP_{name}")).unwrap_or(default.to_string())
+        };
+        SftpTarget {
+            host: var("HOST", "127.0.0.1"),
+            port: var("PORT", "22").parse().unwrap(),
+            username: var("USERNAME", "backup"),
+            identity_file: var("IDENTITY_FILE", "id_ed25519").into(),
+            remote_dir: format!("{}/{}", var("DIR", "/tmp"), Uuid::new_v4()),
+            known_hosts_file: std::env::var("RUSTYMINE_TEST_SFTP_KNOWN_HOSTS")
+                .ok()
+                .map(PathBuf::from),
+        }
+    }
+
+    #[tokio::test]
+    #[ignore = "needs an SSH host"]
+    async fn local_host_round_trip() {
+        let target = local_host();
+        let scratch = tempfile::tempdir().unwrap();
+        let (file, back) = (scratch.path().join("world"), scratch.path().join("back"));
+        std::fs::write(&file, b"archive bytes").unwrap();
+
+        target.upload(&file, "world.tar.zst").await.unwrap();
+        // Replacing an existing copy works too
+        target.upload(&file, "world.tar.zst").await.unwrap();
+        target.download("world.tar.zst", &back).await.unwrap();
+        assert_eq!(std::fs::read(&back).unwrap(), b"archive bytes");
+
+        target.delete("world.tar.zst").await.unwrap();
+        target.delete("world.tar.zst").await.unwrap();
+        assert!(target.download("world.tar.zst", &back).await.is_err());
+    }
+}
@@ -0,0 +1,278 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+use uuid::Uuid;
+
+use crate::domain::players::LogEvent;
+
+lazy_static! {
+    /// Timestamp, optional thread/level and optional logger name in front of
+    /// the message, covering the vanilla, Paper, Fabric and Forge layouts:
+    /// `[12:00:00] [Server thread/INFO]: `, `[12:00:00 INFO]: `,
+    /// `[12:00:00] [Server thread/INFO] (Minecraft) `,
+    /// `[12:00:00] [Server thread/INFO] [minecraft/MinecraftServer]: `.
+    static ref PREFIX: Regex = Regex::new(
+        r"^\[[^\]]*\d{2}:\d{2}:\d{2}[^\]]*\](?: \[[^\]]+/[A-Z]+\])?(?: \[[^\]]*\]| \([^)]*\))?:? "
+    )
+    .unwrap();
+    static ref UUID_OF: Regex =
+        Regex::new(r"^UUID of player (\w{1,16}) is ([0-9a-fA-F-]{32,36})$").unwrap();
+    static ref LOGGED_IN: Regex =
+        Regex::new(r"^(\w{1,16})\[/(\[[^\]]+\][^\]]*|[^\]]+)\] logged in with entity id")
+            .unwrap();
+    static ref JOINED: Regex =
+        Regex::new(r"^(\w{1,16})(?: \(formerly known as \w{1,16}\))? joined the game$").unwrap();
+    static ref LEFT: Regex = Regex::new(r"^(\w{1,16}) left the game$").unwrap();
+    static ref CHAT: Regex = Regex::new(r"^(?:\[Not Secure\] )?<(\w{1,16})> (.*)$").unwrap();
+    static ref ADVANCEMENT: Regex = Regex::new(
+        r"^(\w{1,16}) has (?:made the advancement|completed the challenge|reached the goal) \[(.+)\]$"
+    )
+    .unwrap();
+}
+
+/// How vanilla death messages continue after the player name.
+const DEATH_PHRASES: &[&str] = &[
+    "was slain by",
+    "was shot by",
+    "was killed",
+    "was blown up",
+    "was fireballed by",
+    "was pummeled by",
+    "was impaled",
+    "was skewered",
+    "was squashed",
+    "was squished",
+    "was pricked to death",
+    "was poked to death",
+    "was stung to death",
+    "was struck by lightning",
+    "was roasted",
+    "was frozen to death",
+    "was obliterated",
+    "was doomed to fall",
+    "was burnt to a crisp",
+    "was speared",
+    "was smashed",
+    "was stomped",
+    "drowned",
+    "died",
+    "fell ",
+    "hit the ground too hard",
+    "experienced kinetic energy",
+    "blew up",
+    "burned to death",
+    "went up in flames",
+    "walked into",
+    "tried to swim in lava",
+    "discovered the floor was lava",
+    "suffocated in a wall",
+    "was squeezed too much",
+    "starved to death",
+    "froze to death",
+    "withered away",
+    "left the confines of this world",
+    "didn't want to live",
+];
+
+/// Strips the logger prefix, returns the line unchanged when it has none.
+pub fn message(line: &str) -> &str {
+    match PREFIX.find(line) {
+        Some(prefix) => &line[prefix.end()..],
+        None => line,
+    }
+}
+
+/// Parses a server log line into a player event. Death messages have no
+/// marker of their own, they are only recognised for names in `online`.
+pub fn parse_line<'a>(line: &str, online: impl IntoIterator<Item = &'a str>) -> Option<LogEvent> {
+    let message = message(line);
+
+    if let Some(caps) = CHAT.captures(message) {
+        return Some(LogEvent::Chat {
+            name: caps[1].to_string(),
+            message: caps[2].to_string(),
+        });
+    }
+    if let Some(caps) = UUID_OF.captures(message) {
+        return Uuid::parse_str(&caps[2])
+            .ok()
+            .map(|uuid| LogEvent::Identified {
+                name: caps[1].to_string(),
+                uuid,
+            });
+    }
+    if let Some(caps) = LOGGED_IN.captures(message) {
+        return Some(LogEvent::LoggedIn {
+            name: caps[1].to_string(),
+            ip: strip_port(&caps[2]).to_string(),
+        });
+    }
+    if let Some(caps) = JOINED.captures(message) {
+        return Some(LogEvent::Joined {
+            name: caps[1].to_string(),
+        });
+    }
+    if let Some(caps) = LEFT.captures(message) {
+        return Some(LogEvent::Left {
+            name: caps[1].to_string(),
+        });
+    }
+    if let Some(caps) = ADVANCEMENT.captures(message) {
+        return Some(LogEvent::Advancement {
+            name: caps[1].to_string(),
+            advancement: caps[2].to_string(),
+        });
+    }
+
+    online.into_iter().find_map(|name| {
+        let rest = message.strip_prefix(name)?.strip_prefix(' ')?;
+        DEATH_PHRASES
+            .iter()
+            .any(|phrase| rest.starts_with(phrase))
+            .then(|| LogEvent::Death {
+                name: name.to_string(),
+                message: message.to_string(),
+            })
+    })
+}
+
+/// `127.0.0.1:54321` or `[::1]:54321` without the port.
+fn strip_port(addr: &str) -> &str {
+    if let Some(v6) = addr.strip_prefix('[') {
+        return v6.split(']').next().unwrap_or(v6);
+    }
+    match addr.rsplit_once(':') {
+        Some((host, port)) if !host.contains(':') && port.chars().all(|c| c.is_ascii_digit()) => {
+            host
+        }
+        _ => addr,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn joined(name: &str) -> Option<LogEvent> {
+        Some(LogEvent::Joined {
+            name: name.to_string(),
+        })
+    }
+
+    #[test]
+    fn prefixes_of_every_server_flavour_are_stripped() {
+        for line in [
+            "[12:00:00] [Server thread/INFO]: Steve joined the game",
+            "[12:00:00 INFO]: Steve joined the game",
+            "[12:00:00] [Server thread/INFO] (Minecraft) Steve joined the game",
+            "[12:00:00] [Server thread/INFO] [minecraft/MinecraftServer]: Steve joined the game",
+            "[01Jan2024 12:00:00.000] [Server thread/INFO] [net.minecraft.server.MinecraftServer/]: Steve joined the game",
+        ] {
+            assert_eq!(message(line), "Steve joined the game", "{line}");
+            assert_eq!(parse_line(line, []), joined("Steve"), "{line}");
+        }
+        assert_eq!(message("Steve joined the game"), "Steve joined the game");
+    }
+
+    #[test]
+    fn chat_is_not_mistaken_for_other_events() {
+        let line = "[12:00:00] [Server thread/INFO]: [Not Secure] <Steve> Alex joined the game";
+        assert_eq!(
+            parse_line(line, ["Alex"]),
+            Some(LogEvent::Chat {
+                name: "Steve".to_string(),
+                message: "Alex joined the game".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn login_lines_carry_the_address_without_port() {
+        let uuid = "069a79f4-44e9-4726-a5be-fca90e38aaf5";
+        assert_eq!(
+            parse_line(
+                &format!("[12:00:00] [User Authenticator #1/INFO]: UUID of player Notch is {uuid}"),
+                []
+            ),
+            Some(LogEvent::Identified {
+                name: "Notch".to_string(),
+                uuid: Uuid::parse_str(uuid).unwrap(),
+            })
+        );
+        for (address, ip) in [
+            ("/127.0.0.1:54321", "127.0.0.1"),
+            ("/[::1]:54321", "::1"),
+            ("/[2001:db8::7]:25565", "2001:db8::7"),
+        ] {
+            let line = format!(
+                "[12:00:00] [Server thread/INFO]: Notch[{address}] logged in with entity id 42 at (0.5, 64.0, 0.5)"
+            );
+            let expected = Some(LogEvent::LoggedIn {
+                name: "Notch".to_string(),
+                ip: ip.to_string(),
+            });
+            assert_eq!(parse_line(&line, []), expected, "{address}");
+        }
+    }
+
+    #[test]
+    fn strip_port_leaves_bare_addresses_alone() {
+        assert_eq!(strip_port("10.0.0.2:25565"), "10.0.0.2");
+        assert_eq!(strip_port("[fe80::1]:25565"), "fe80::1");
+        assert_eq!(strip_port("fe80::1"), "fe80::1");
+        assert_eq!(strip_port("10.0.0.2"), "10.0.0.2");
+    }
+
+    #[test]
+    fn renamed_players_leaving_and_advancements() {
+        assert_eq!(
+            parse_line(
+                "[12:00:00 INFO]: Alex (formerly known as Steve) joined the game",
+                []
+            ),
+            joined("Alex")
+        );
+        assert_eq!(
+            parse_line("[12:00:00 INFO]: Alex left the game", []),
+            Some(LogEvent::Left {
+                name: "Alex".to_string(),
+            })
+        );
+        assert_eq!(
+            parse_line(
+                "[12:00:00 INFO]: Alex has made the advancement [Stone Age]",
+                []
+            ),
+            Some(LogEvent::Advancement {
+                name: "Alex".to_string(),
+                advancement: "Stone Age".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn deaths_only_match_online_players() {
+        let line = "[12:00:00] [Server thread/INFO]: Steve was slain by Zombie";
+        assert_eq!(
+            parse_line(line, ["Alex", "Steve"]),
+            Some(LogEvent::Death {
+                name: "Steve".to_string(),
+                message: "Steve was slain by Zombie".to_string(),
+            })
+        );
+        assert_eq!(parse_line(line, ["Alex"]), None);
+        // A longer name that starts with an online one is someone else
+        assert_eq!(
+            parse_line("[12:00:00 INFO]: Steve2 drowned", ["Steve"]),
+            None
+        );
+        // Plugins log all sorts of lines starting with a player name
+        assert_eq!(
+            parse_line(
+                "[12:00:00 INFO]: Steve issued server command: /home",
+                ["Steve"]
+            ),
+            None
+        );
+        assert!(parse_line("[12:00:00 INFO]: Steve fell from a high place", ["Steve"]).is_some());
+    }
+}
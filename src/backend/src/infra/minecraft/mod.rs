@@ -0,0 +1,7 @@
+pub mod log;
+pub mod mojang;
+pub mod ping;
+pub mod player_lists;
+pub mod properties;
+pub mod query;
+pub mod rcon;
@@ -0,0 +1,35 @@
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::{domain::player_lists::PlayerProfile, prelude::*};
+
+const PROFILE_URL: &str = "https://api.mojang.com/users/profiles/minecraft";
+
+#[derive(Debug, Deserialize)]
+struct MojangProfile {
+    /// UUID without dashes.
+    id: String,
+    name: String,
+}
+
+/// Resolves an online mode profile by name, `None` when no account has it.
+pub async fn profile_by_name(http: &Client, name: &str) -> anyhow::Result<Option<PlayerProfile>> {
+    debug!(player = name, "mojang profile lookup started");
+    let response = http.get(format!("{PROFILE_URL}/{name}")).send().await?;
+    if matches!(
+        response.status(),
+        StatusCode::NOT_FOUND | StatusCode::NO_CONTENT
+    ) {
+        return Ok(None);
+    }
+
+    let profile: MojangProfile = response.error_for_status()?.json().await?;
+    let uuid = Uuid::parse_str(&profile.id)?;
+
+    debug!(player = name, "mojang profile lookup completed");
+    Ok(Some(PlayerProfile {
+        uuid: uuid.hyphenated().to_string(),
+        name: profile.name,
+    }))
+}
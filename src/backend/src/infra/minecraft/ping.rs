@@ -0,0 +1,432 @@
+use std::time::{Duration, Instant};
+
+use serde::Deserialize;
+use serde_json::Value;
+use thiserror::Error;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::{self, timeout_at},
+};
+
+use crate::domain::status::{PingStatus, PlayerSample};
+
+/// Protocol number sent in the handshake, servers answer status for any value.
+const HANDSHAKE_PROTOCOL: i32 = -1;
+const MAX_RESPONSE_LEN: i32 = 1 << 21;
+
+#[derive(Debug, Error)]
+pub enum PingError {
+    #[error("ping io failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("ping timed out")]
+    Timeout,
+    #[error("ping protocol violation: {0}")]
+    Protocol(String),
+    #[error("ping response is not valid json: {0}")]
+    Json(#[from] serde_json::Error),
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusResponse {
+    version: Option<StatusVersion>,
+    players: Option<StatusPlayers>,
+    description: Option<Value>,
+    favicon: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusVersion {
+    name: String,
+    protocol: i32,
+}
+
+#[derive(Debug, Deserialize)]
+struct StatusPlayers {
+    max: i64,
+    online: i64,
+    #[serde(default)]
+    sample: Vec<PlayerSample>,
+}
+
+/// Runs the modern Server List Ping handshake (1.7+), falling back to the
+/// legacy 1.6 format when the server does not speak it. Both attempts share
+/// `limit`.
+pub async fn ping(host: &str, port: u16, limit: Duration) -> Result<PingStatus, PingError> {
+    let deadline = time::Instant::now() + limit;
+    match timeout_at(deadline, ping_modern(host, port)).await {
+        Ok(Ok(status)) => Ok(status),
+        // Nothing listening, a legacy attempt would fail the same way
+        Ok(Err(PingError::Io(e))) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+            Err(PingError::Io(e))
+        }
+        // A timed out modern attempt leaves nothing for the legacy one
+        Err(_) => Err(PingError::Timeout),
+        Ok(Err(_)) => timeout_at(deadline, ping_legacy(host, port))
+            .await
+            .map_err(|_| PingError::Timeout)?,
+    }
+}
+
+pub async fn ping_modern(host: &str, port: u16) -> Result<PingStatus, PingError> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+
+    let mut handshake = Vec::new();
+    write_varint(&mut handshake, 0x00);
+    write_varint(&mut handshake, HANDSHAKE_PROTOCOL);
+    write_string(&mut handshake, host);
+    handshake.extend_from_slice(&port.to_be_bytes());
+    write_varint(&mut handshake, 1);
+    send_packet(&mut stream, &handshake).await?;
+    send_packet(&mut stream, &[0x00]).await?;
+
+    let body = read_packet(&mut stream).await?;
+    let mut cursor = body.as_slice();
+    let id = read_varint(&mut cursor).await?;
+    if id != 0x00 {
+        return Err(PingError::Protocol(format!("unexpected packet id {id:#x}")));
+    }
+    let len = read_varint(&mut cursor).await?;
+    if len < 0 || len as usize > cursor.len() {
+        return Err(PingError::Protocol(
+            "status string length out of range".into(),
+        ));
+    }
+    let response: StatusResponse = serde_json::from_slice(&cursor[..len as usize])?;
+
+    // Ping/pong for latency, some proxies close the socket instead of answering
+    let started = Instant::now();
+    let mut ping = vec![0x01];
+    ping.extend_from_slice(&0i64.to_be_bytes());
+    let latency_ms = match send_packet(&mut stream, &ping).await {
+        Ok(()) => read_packet(&mut stream)
+            .await
+            .ok()
+            .map(|_| started.elapsed().as_millis() as u64),
+        Err(_) => None,
+    };
+
+    let (version, protocol) = response
+        .version
+        .map(|v| (v.name, v.protocol))
+        .unwrap_or_default();
+    let (online, max, sample) = response
+        .players
+        .map(|p| (p.online, p.max, p.sample))
+        .unwrap_or_default();
+
+    Ok(PingStatus {
+        version,
+        protocol,
+        motd: response
+            .description
+            .as_ref()
+            .map(chat_to_text)
+            .unwrap_or_default(),
+        players_online: online,
+        players_max: max,
+        sample,
+        favicon: response.favicon,
+        latency_ms,
+        legacy: false,
+    })
+}
+
+/// 1.6 style ping, answered with `§1\0protocol\0version\0motd\0online\0max`
+/// encoded as UTF-16BE.
+pub async fn ping_legacy(host: &str, port: u16) -> Result<PingStatus, PingError> {
+    let mut stream = TcpStream::connect((host, port)).await?;
+
+    let channel: Vec<u16> = "MC|PingHost".encode_utf16().collect();
+    let host16: Vec<u16> = host.encode_utf16().collect();
+
+    let mut req = vec![0xFE, 0x01, 0xFA];
+    req.extend_from_slice(&(channel.len() as u16).to_be_bytes());
+    channel
+        .iter()
+        .for_each(|c| req.extend_from_slice(&c.to_be_bytes()));
+    req.extend_from_slice(&((7 + host16.len() * 2) as u16).to_be_bytes());
+    req.push(74);
+    req.extend_from_slice(&(host16.len() as u16).to_be_bytes());
+    host16
+        .iter()
+        .for_each(|c| req.extend_from_slice(&c.to_be_bytes()));
+    req.extend_from_slice(&(port as i32).to_be_bytes());
+    stream.write_all(&req).await?;
+
+    let kick = stream.read_u8().await?;
+    if kick != 0xFF {
+        return Err(PingError::Protocol(format!(
+            "unexpected legacy packet {kick:#x}"
+        )));
+    }
+    let len = stream.read_u16().await? as usize;
+    let mut raw = vec![0u8; len * 2];
+    stream.read_exact(&mut raw).await?;
+    let units: Vec<u16> = raw
+        .chunks_exact(2)
+        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+        .collect();
+    let text = String::from_utf16_lossy(&units);
+
+    let fields: Vec<&str> = text.split('\0').collect();
+    if fields.len() < 6 || fields[0] != "§1" {
+        return Err(PingError::Protocol("malformed legacy response".into()));
+    }
+
+    Ok(PingStatus {
+        version: fields[2].to_string(),
+        protocol: fields[1].parse().unwrap_or_default(),
+        motd: fields[3].to_string(),
+        players_online: fields[4].parse().unwrap_or_default(),
+        players_max: fields[5].parse().unwrap_or_default(),
+        sample: Vec::new(),
+        favicon: None,
+        latency_ms: None,
+        legacy: true,
+    })
+}
+
+/// Flattens a chat component (or plain string) description into its text.
+fn chat_to_text(value: &Value) -> String {
+    match value {
+        Value::String(text) => text.clone(),
+        Value::Array(parts) => parts.iter().map(chat_to_text).collect(),
+        Value::Object(component) => {
+            let mut text = component
+                .get("text")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string();
+            if let Some(Value::Array(extra)) = component.get("extra") {
+                extra
+                    .iter()
+                    .for_each(|part| text.push_str(&chat_to_text(part)));
+            }
+            text
+        }
+        _ => String::new(),
+    }
+}
+
+async fn send_packet(stream: &mut TcpStream, body: &[u8]) -> Result<(), PingError> {
+    let mut packet = Vec::with_capacity(body.len() + 5);
+    write_varint(&mut packet, body.len() as i32);
+    packet.extend_from_slice(body);
+    stream.write_all(&packet).await?;
+    Ok(())
+}
+
+async fn read_packet(stream: &mut TcpStream) -> Result<Vec<u8>, PingError> {
+    let len = read_varint(stream).await?;
+    if !(1..=MAX_RESPONSE_LEN).contains(&len) {
+        return Err(PingError::Protocol(format!("invalid packet length {len}")));
+    }
+    let mut body = vec![0u8; len as usize];
+    stream.read_exact(&mut body).await?;
+    Ok(body)
+}
+
+fn write_varint(buf: &mut Vec<u8>, value: i32) {
+    let mut value = value as u32;
+    loop {
+        if value & !0x7F == 0 {
+            buf.push(value as u8);
+            return;
+        }
+        buf.push((value & 0x7F) as u8 | 0x80);
+        value >>= 7;
+    }
+}
+
+fn write_string(buf: &mut Vec<u8>, value: &str) {
+    write_varint(buf, value.len() as i32);
+    buf.extend_from_slice(value.as_bytes());
+}
+
+async fn read_varint<R: AsyncRead + Unpin>(reader: &mut R) -> Result<i32, PingError> {
+    let mut value: u32 = 0;
+    for position in 0..5 {
+        let byte = reader.read_u8().await?;
+        value |= ((byte & 0x7F) as u32) << (7 * position);
+        if byte & 0x80 == 0 {
+            return Ok(value as i32);
+        }
+    }
+    Err(PingError::Protocol("varint too long".into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use tokio::net::TcpListener;
+
+    const LIMIT: Duration = Duration::from_secs(5);
+
+    async fn varint_round_trip(value: i32) -> (usize, i32) {
+        let mut buf = Vec::new();
+        write_varint(&mut buf, value);
+        let len = buf.len();
+        (len, read_varint(&mut buf.as_slice()).await.unwrap())
+    }
+
+    fn legacy_kick(text: &str) -> Vec<u8> {
+        let units: Vec<u16> = text.encode_utf16().collect();
+        let mut kick = vec![0xFF];
+        kick.extend_from_slice(&(units.len() as u16).to_be_bytes());
+        units
+            .iter()
+            .for_each(|c| kick.extend_from_slice(&c.to_be_bytes()));
+        kick
+    }
+
+    #[tokio::test]
+    async fn varint_matches_protocol_examples() {
+        let mut buf = Vec::new();
+        write_varint(&mut buf, 300);
+        assert_eq!(buf, [0xAC, 0x02]);
+
+        let mut buf = Vec::new();
+        write_varint(&mut buf, -1);
+        assert_eq!(buf, [0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+
+        assert_eq!(varint_round_trip(0).await, (1, 0));
+        assert_eq!(varint_round_trip(127).await, (1, 127));
+        assert_eq!(varint_round_trip(128).await, (2, 128));
+        assert_eq!(varint_round_trip(i32::MAX).await, (5, i32::MAX));
+        assert_eq!(varint_round_trip(i32::MIN).await, (5, i32::MIN));
+    }
+
+    #[tokio::test]
+    async fn varint_longer_than_five_bytes_is_rejected() {
+        let mut reader: &[u8] = &[0x80, 0x80, 0x80, 0x80, 0x80, 0x01];
+        assert!(matches!(
+            read_varint(&mut reader).await,
+            Err(PingError::Protocol(_))
+        ));
+    }
+
+    #[test]
+    fn chat_components_flatten_to_text() {
+        assert_eq!(
+            chat_to_text(&json!("A Minecraft Server")),
+            "A Minecraft Server"
+        );
+        let component = json!({
+            "text": "Hello ",
+            "extra": [{"text": "big", "bold": true}, " world", [{"text": "!"}]]
+        });
+        assert_eq!(chat_to_text(&component), "Hello big world!");
+        assert_eq!(chat_to_text(&json!({"translate": "menu.server"})), "");
+    }
+
+    #[tokio::test]
+    async fn modern_ping_reads_status_json() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let handshake = read_packet(&mut stream).await.unwrap();
+            let mut cursor = handshake.as_slice();
+            assert_eq!(read_varint(&mut cursor).await.unwrap(), 0x00);
+            assert_eq!(read_varint(&mut cursor).await.unwrap(), HANDSHAKE_PROTOCOL);
+            assert_eq!(read_packet(&mut stream).await.unwrap(), [0x00]);
+
+            let status = json!({
+                "version": {"name": "1.21.1", "protocol": 767},
+                "players": {
+                    "max": 20,
+                    "online": 1,
+                    "sample": [{"name": "Steve", "id": "8667ba71-b85a-4004-af54-457a9734eed7"}]
+                },
+                "description": {"text": "hi ", "extra": ["there"]}
+            })
+            .to_string();
+            let mut body = Vec::new();
+            write_varint(&mut body, 0x00);
+            write_string(&mut body, &status);
+            send_packet(&mut stream, &body).await.unwrap();
+
+            let ping = read_packet(&mut stream).await.unwrap();
+            assert_eq!(ping[0], 0x01);
+            send_packet(&mut stream, &ping).await.unwrap();
+        });
+
+        let status = ping("127.0.0.1", port, LIMIT).await.unwrap();
+        assert_eq!(status.version, "1.21.1");
+        assert_eq!(status.protocol, 767);
+        assert_eq!(status.motd, "hi there");
+        assert_eq!(status.players_online, 1);
+        assert_eq!(status.players_max, 20);
+        assert_eq!(status.sample.len(), 1);
+        assert!(status.latency_ms.is_some());
+        assert!(!status.legacy);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn ping_falls_back_to_legacy() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            // A pre 1.7 server drops the modern handshake
+            let (stream, _) = listener.accept().await.unwrap();
+            drop(stream);
+
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut magic = [0u8; 3];
+            stream.read_exact(&mut magic).await.unwrap();
+            assert_eq!(magic, [0xFE, 0x01, 0xFA]);
+            let kick = legacy_kick(&["§1", "78", "1.6.4", "A Legacy server", "3", "20"].join("\0"));
+            stream.write_all(&kick).await.unwrap();
+        });
+
+        let status = ping("127.0.0.1", port, LIMIT).await.unwrap();
+        assert!(status.legacy);
+        assert_eq!(status.protocol, 78);
+        assert_eq!(status.version, "1.6.4");
+        assert_eq!(status.motd, "A Legacy server");
+        assert_eq!(status.players_online, 3);
+        assert_eq!(status.players_max, 20);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn silent_server_times_out_once() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (_stream, _) = listener.accept().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(2)).await;
+        });
+
+        let started = Instant::now();
+        let result = ping("127.0.0.1", port, Duration::from_millis(200)).await;
+        assert!(matches!(result, Err(PingError::Timeout)));
+        assert!(started.elapsed() < Duration::from_millis(400));
+        server.abort();
+    }
+
+    #[tokio::test]
+    async fn malformed_legacy_response_is_rejected() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut magic = [0u8; 3];
+            stream.read_exact(&mut magic).await.unwrap();
+            // Beta 1.8 format, without the §1 header
+            stream
+                .write_all(&legacy_kick("A server§3§20"))
+                .await
+                .unwrap();
+        });
+
+        assert!(matches!(
+            ping_legacy("127.0.0.1", port).await,
+            Err(PingError::Protocol(_))
+        ));
+        server.await.unwrap();
+    }
+}
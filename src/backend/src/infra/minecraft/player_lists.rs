@@ -0,0 +1,55 @@
+use std::{io::ErrorKind, path::Path};
+
+use md5::{Digest, Md5};
+use serde::{Serialize, de::DeserializeOwned};
+use tokio::fs;
+use uuid::Uuid;
+
+use crate::{domain::player_lists::PlayerProfile, prelude::*};
+
+pub const WHITELIST: &str = "whitelist.json";
+pub const OPS: &str = "ops.json";
+pub const BANNED_PLAYERS: &str = "banned-players.json";
+pub const BANNED_IPS: &str = "banned-ips.json";
+const USER_CACHE: &str = "usercache.json";
+
+/// Reads one of the JSON list files, a missing file reads as empty.
+pub async fn load<T: DeserializeOwned>(dir: &Path, file: &str) -> anyhow::Result<Vec<T>> {
+    match fs::read(dir.join(file)).await {
+        Ok(bytes) if bytes.iter().all(u8::is_ascii_whitespace) => Ok(Vec::new()),
+        Ok(bytes) => Ok(serde_json::from_slice(&bytes)?),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Writes a list file through a temporary file and a rename, so the server
+/// never reads a half written list.
+pub async fn save<T: Serialize>(dir: &Path, file: &str, entries: &[T]) -> anyhow::Result<()> {
+    let path = dir.join(file);
+    let tmp = dir.join(format!(".{file}.tmp"));
+    fs::write(&tmp, serde_json::to_vec_pretty(entries)?).await?;
+    fs::rename(&tmp, &path).await?;
+    debug!(path = %path.display(), entries = entries.len(), "player list written");
+    Ok(())
+}
+
+/// Looks a name up in the profiles the server has already seen.
+pub async fn cached_profile(dir: &Path, name: &str) -> Option<PlayerProfile> {
+    let profiles: Vec<PlayerProfile> = load(dir, USER_CACHE).await.ok()?;
+    profiles
+        .into_iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name))
+}
+
+/// UUID an offline mode server assigns to a name.
+pub fn offline_profile(name: &str) -> PlayerProfile {
+    let mut bytes: [u8; 16] = Md5::digest(format!("OfflinePlayer:{name}")).into();
+    bytes[6] = (bytes[6] & 0x0f) | 0x30;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+
+    PlayerProfile {
+        uuid: Uuid::from_bytes(bytes).hyphenated().to_string(),
+        name: name.to_string(),
+    }
+}
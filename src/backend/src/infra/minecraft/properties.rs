@@ -0,0 +1,344 @@
+use std::{io::ErrorKind, path::Path};
+
+use tokio::fs;
+
+use crate::{infra::sandbox, prelude::*};
+
+pub const FILE_NAME: &str = "server.properties";
+
+#[derive(Debug, Clone)]
+enum Line {
+    /// Comment, blank line or anything else kept verbatim.
+    Raw(String),
+    Entry {
+        key: String,
+        value: String,
+        /// Original text, dropped once the value is changed.
+        raw: Option<String>,
+    },
+}
+
+/// Java properties file that keeps comments, blank lines and key order, and
+/// rewrites only the entries that were changed.
+#[derive(Debug, Clone, Default)]
+pub struct Properties {
+    lines: Vec<Line>,
+}
+
+impl Properties {
+    pub fn parse(text: &str) -> Self {
+        let mut lines = Vec::new();
+        let mut physical = text.lines();
+
+        while let Some(first) = physical.next() {
+            let trimmed = first.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with('!') {
+                lines.push(Line::Raw(first.to_string()));
+                continue;
+            }
+
+            // An odd number of trailing backslashes continues the entry
+            let mut raw = first.to_string();
+            let mut logical = trimmed.to_string();
+            while ends_with_continuation(&logical) {
+                logical.pop();
+                let Some(next) = physical.next() else { break };
+                raw.push('\n');
+                raw.push_str(next);
+                logical.push_str(next.trim_start());
+            }
+
+            let (key, value) = split_entry(&logical);
+            lines.push(Line::Entry {
+                key,
+                value,
+                raw: Some(raw),
+            });
+        }
+
+        Self { lines }
+    }
+
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.entries().find(|(k, _)| *k == key).map(|(_, v)| v)
+    }
+
+    /// Entries in file order, a duplicated key is listed once with its last value
+    /// like the server reads it.
+    pub fn entries(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| match line {
+                Line::Entry { key, value, .. }
+                    if !self.lines[i + 1..].iter().any(|l| l.is_key(key)) =>
+                {
+                    Some((key.as_str(), value.as_str()))
+                }
+                _ => None,
+            })
+    }
+
+    /// Sets a value in place or appends it, returns whether anything changed.
+    pub fn set(&mut self, key: &str, value: &str) -> bool {
+        let existing = self.lines.iter_mut().rev().find(|line| line.is_key(key));
+        match existing {
+            Some(Line::Entry {
+                value: current,
+                raw,
+                ..
+            }) => {
+                if current == value {
+                    return false;
+                }
+                *current = value.to_string();
+                *raw = None;
+            }
+            _ => self.lines.push(Line::Entry {
+                key: key.to_string(),
+                value: value.to_string(),
+                raw: None,
+            }),
+        }
+        true
+    }
+
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for line in &self.lines {
+            match line {
+                Line::Raw(text)
+                | Line::Entry {
+                    raw: Some(text), ..
+                } => out.push_str(text),
+                Line::Entry {
+                    key,
+                    value,
+                    raw: None,
+                } => {
+                    out.push_str(&escape(key, true));
+                    out.push('=');
+                    out.push_str(&escape(value, false));
+                }
+            }
+            out.push('\n');
+        }
+        out
+    }
+}
+
+impl Line {
+    fn is_key(&self, wanted: &str) -> bool {
+        matches!(self, Line::Entry { key, .. } if key == wanted)
+    }
+}
+
+/// Reads `server.properties` from a server directory, a missing file reads as empty.
+pub async fn load(dir: &Path) -> std::io::Result<Properties> {
+    match fs::read(dir.join(FILE_NAME)).await {
+        Ok(bytes) => Ok(Properties::parse(&decode(bytes))),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Properties::default()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Writes `server.properties` through a temporary file so a crash never
+/// leaves the server with half a config.
+pub async fn save(dir: &Path, properties: &Properties) -> std::io::Result<()> {
+    let path = dir.join(FILE_NAME);
+    sandbox::write_atomic(&path, properties.render().as_bytes()).await?;
+    debug!(path = %path.display(), "server properties written");
+    Ok(())
+}
+
+/// Current servers write UTF-8, older ones ISO-8859-1.
+fn decode(bytes: Vec<u8>) -> String {
+    String::from_utf8(bytes)
+        .unwrap_or_else(|e| e.into_bytes().into_iter().map(char::from).collect())
+}
+
+fn ends_with_continuation(line: &str) -> bool {
+    line.chars().rev().take_while(|c| *c == '\\').count() % 2 == 1
+}
+
+fn split_entry(line: &str) -> (String, String) {
+    let mut chars = line.char_indices().peekable();
+    let mut key_end = line.len();
+    while let Some((i, c)) = chars.next() {
+        match c {
+            '\\' => {
+                chars.next();
+            }
+            '=' | ':' | ' ' | '\t' | '\x0c' => {
+                key_end = i;
+                break;
+            }
+            _ => {}
+        }
+    }
+
+    let rest = line[key_end..].trim_start_matches([' ', '\t', '\x0c']);
+    let rest = rest
+        .strip_prefix(['=', ':'])
+        .map(|r| r.trim_start_matches([' ', '\t', '\x0c']))
+        .unwrap_or(rest);
+
+    (unescape(&line[..key_end]), unescape(rest))
+}
+
+fn unescape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('t') => out.push('\t'),
+            Some('n') => out.push('\n'),
+            Some('r') => out.push('\r'),
+            Some('f') => out.push('\x0c'),
+            Some('u') => {
+                let hex: String = chars.by_ref().take(4).collect();
+                match u32::from_str_radix(&hex, 16).ok().and_then(char::from_u32) {
+                    Some(decoded) => out.push(decoded),
+                    None => out.push_str(&hex),
+                }
+            }
+            Some(other) => out.push(other),
+            None => {}
+        }
+    }
+    out
+}
+
+/// Escapes like `java.util.Properties#store`, spaces only matter in keys and
+/// at the start of a value.
+fn escape(text: &str, is_key: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for (i, c) in text.chars().enumerate() {
+        match c {
+            ' ' if is_key || i == 0 => out.push_str("\\ "),
+            '\\' => out.push_str("\\\\"),
+            '\t' => out.push_str("\\t"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\x0c' => out.push_str("\\f"),
+            '=' | ':' | '#' | '!' => {
+                out.push('\\');
+                out.push(c);
+            }
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::PermissionsExt;
+
+    const SAMPLE: &str = "\
+#Minecraft server properties
+#Mon Jan 01 00:00:00 UTC 2024
+
+motd=A \\u00A7aMinecraft Server
+level-name = world
+server-port:25565
+spawn-protection 16
+key\\ with\\ spaces=value
+resource-pack=https\\://example.com/pack.zip
+long-line=first \\
+    second
+    ! not a comment
+generator-settings=
+motd=Override
+";
+
+    #[test]
+    fn parse_reads_separators_escapes_and_continuations() {
+        let properties = Properties::parse(SAMPLE);
+        assert_eq!(properties.get("level-name"), Some("world"));
+        assert_eq!(properties.get("server-port"), Some("25565"));
+        assert_eq!(properties.get("spawn-protection"), Some("16"));
+        assert_eq!(properties.get("key with spaces"), Some("value"));
+        assert_eq!(
+            properties.get("resource-pack"),
+            Some("https://example.com/pack.zip")
+        );
+        assert_eq!(properties.get("long-line"), Some("first second"));
+        assert_eq!(properties.get("generator-settings"), Some(""));
+        // The last duplicate wins, like the server reads it
+        assert_eq!(properties.get("motd"), Some("Override"));
+        assert_eq!(properties.get("!"), None);
+    }
+
+    #[test]
+    fn untouched_file_renders_verbatim() {
+        assert_eq!(Properties::parse(SAMPLE).render(), SAMPLE);
+    }
+
+    #[test]
+    fn set_rewrites_only_changed_entries() {
+        let mut properties = Properties::parse(SAMPLE);
+        assert!(!properties.set("level-name", "world"));
+        assert!(properties.set("motd", "§aHi: #1 "));
+        assert!(properties.set("new key", " padded"));
+
+        let rendered = properties.render();
+        assert!(rendered.starts_with("#Minecraft server properties\n"));
+        assert!(rendered.contains("level-name = world\n"));
+        // The earlier duplicate stays as it was, the effective one changes
+        assert!(rendered.contains("motd=A \\u00A7aMinecraft Server\n"));
+        assert!(rendered.contains("motd=§aHi\\: \\#1 \n"));
+        assert!(rendered.ends_with("new\\ key=\\ padded\n"));
+
+        let reparsed = Properties::parse(&rendered);
+        assert_eq!(reparsed.get("motd"), Some("§aHi: #1 "));
+        assert_eq!(reparsed.get("new key"), Some(" padded"));
+    }
+
+    #[test]
+    fn escapes_round_trip() {
+        let mut properties = Properties::default();
+        let value = "tab\there\nnew line \\ back = colon: !bang";
+        properties.set("key", value);
+        assert_eq!(
+            Properties::parse(&properties.render()).get("key"),
+            Some(value)
+        );
+    }
+
+    #[test]
+    fn latin1_files_decode() {
+        assert_eq!(decode(b"motd=caf\xe9".to_vec()), "motd=café");
+        assert_eq!(decode("motd=café".as_bytes().to_vec()), "motd=café");
+    }
+
+    #[tokio::test]
+    async fn save_keeps_file_mode() {
+        let scratch = tempfile::tempdir().unwrap();
+        let dir = scratch.path();
+        let path = dir.join(FILE_NAME);
+        fs::write(&path, SAMPLE).await.unwrap();
+        fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))
+            .await
+            .unwrap();
+
+        let mut properties = load(dir).await.unwrap();
+        properties.set("server-port", "25566");
+        save(dir, &properties).await.unwrap();
+
+        let mode = fs::metadata(&path).await.unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert_eq!(load(dir).await.unwrap().get("server-port"), Some("25566"));
+        let mut entries = fs::read_dir(dir).await.unwrap();
+        let mut names = Vec::new();
+        while let Some(entry) = entries.next_entry().await.unwrap() {
+            names.push(entry.file_name());
+        }
+        assert_eq!(names, [FILE_NAME]);
+    }
+}
@@ -0,0 +1,361 @@
+use std::time::Duration;
+
+use rand_core::{OsRng, RngCore};
+use thiserror::Error;
+use tokio::{
+    net::{ToSocketAddrs, UdpSocket},
+    time::timeout,
+};
+
+use crate::domain::status::{QueryBasic, QueryStatus};
+
+const MAGIC: [u8; 2] = [0xFE, 0xFD];
+const TYPE_HANDSHAKE: u8 = 0x09;
+const TYPE_STAT: u8 = 0x00;
+/// The server only looks at the low nibble of every session id byte.
+const SESSION_MASK: i32 = 0x0F0F0F0F;
+/// Constant `splitnum\0\x80\0` block in front of the full stat key/values.
+const FULL_STAT_PADDING: usize = 11;
+/// Constant `\x01player_\0\0` block in front of the player list.
+const PLAYER_PADDING: usize = 10;
+const MAX_DATAGRAM: usize = 65_507;
+
+#[derive(Debug, Error)]
+pub enum QueryError {
+    #[error("query io failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("query request timed out")]
+    Timeout,
+    #[error("query protocol violation: {0}")]
+    Protocol(String),
+}
+
+/// GameSpy4 query client as implemented by the Minecraft server
+/// (`enable-query=true`).
+pub struct QueryClient {
+    socket: UdpSocket,
+    session_id: i32,
+    token: i32,
+    io_timeout: Duration,
+}
+
+impl QueryClient {
+    /// Binds a socket to `addr` and fetches a challenge token.
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        io_timeout: Duration,
+    ) -> Result<Self, QueryError> {
+        let socket = UdpSocket::bind("0.0.0.0:0").await?;
+        socket.connect(addr).await?;
+
+        let mut client = Self {
+            socket,
+            session_id: OsRng.next_u32() as i32 & SESSION_MASK,
+            token: 0,
+            io_timeout,
+        };
+        client.handshake().await?;
+        Ok(client)
+    }
+
+    /// Requests a new challenge token, the server rotates them every 30 seconds.
+    pub async fn handshake(&mut self) -> Result<(), QueryError> {
+        let response = self.request(TYPE_HANDSHAKE, &[]).await?;
+        let text = read_cstr(&mut response.as_slice())?;
+        self.token = text
+            .parse()
+            .map_err(|_| QueryError::Protocol(format!("invalid challenge token {text:?}")))?;
+        Ok(())
+    }
+
+    pub async fn basic_stat(&mut self) -> Result<QueryBasic, QueryError> {
+        let token = self.token.to_be_bytes();
+        let response = self.request(TYPE_STAT, &token).await?;
+        let mut cursor = response.as_slice();
+
+        let motd = read_cstr(&mut cursor)?;
+        let game_type = read_cstr(&mut cursor)?;
+        let map = read_cstr(&mut cursor)?;
+        let players_online = parse_num(&read_cstr(&mut cursor)?)?;
+        let players_max = parse_num(&read_cstr(&mut cursor)?)?;
+        if cursor.len() < 2 {
+            return Err(QueryError::Protocol("basic stat truncated".into()));
+        }
+        // The only little endian value in the protocol
+        let host_port = u16::from_le_bytes([cursor[0], cursor[1]]);
+        cursor = &cursor[2..];
+        let host_ip = read_cstr(&mut cursor)?;
+
+        Ok(QueryBasic {
+            motd,
+            game_type,
+            map,
+            players_online,
+            players_max,
+            host_port,
+            host_ip,
+        })
+    }
+
+    pub async fn full_stat(&mut self) -> Result<QueryStatus, QueryError> {
+        let mut payload = self.token.to_be_bytes().to_vec();
+        payload.extend_from_slice(&[0, 0, 0, 0]);
+        let response = self.request(TYPE_STAT, &payload).await?;
+        if response.len() < FULL_STAT_PADDING {
+            return Err(QueryError::Protocol("full stat truncated".into()));
+        }
+        let mut cursor = &response[FULL_STAT_PADDING..];
+
+        let mut status = QueryStatus::default();
+        loop {
+            let key = read_cstr(&mut cursor)?;
+            if key.is_empty() {
+                break;
+            }
+            let value = read_cstr(&mut cursor)?;
+            match key.as_str() {
+                "hostname" => status.motd = value,
+                "gametype" => status.game_type = value,
+                "game_id" => status.game_id = value,
+                "version" => status.version = value,
+                "plugins" => (status.server_mod, status.plugins) = parse_plugins(&value),
+                "map" => status.map = value,
+                "numplayers" => status.players_online = parse_num(&value)?,
+                "maxplayers" => status.players_max = parse_num(&value)?,
+                "hostport" => status.host_port = value.parse().unwrap_or_default(),
+                "hostip" => status.host_ip = value,
+                _ => {}
+            }
+        }
+
+        if cursor.len() < PLAYER_PADDING {
+            return Err(QueryError::Protocol("player section truncated".into()));
+        }
+        cursor = &cursor[PLAYER_PADDING..];
+        loop {
+            let name = read_cstr(&mut cursor)?;
+            if name.is_empty() {
+                break;
+            }
+            status.players.push(name);
+        }
+
+        Ok(status)
+    }
+
+    /// Sends a request and returns the response body past the type and session id.
+    async fn request(&mut self, kind: u8, payload: &[u8]) -> Result<Vec<u8>, QueryError> {
+        let mut packet = Vec::with_capacity(7 + payload.len());
+        packet.extend_from_slice(&MAGIC);
+        packet.push(kind);
+        packet.extend_from_slice(&self.session_id.to_be_bytes());
+        packet.extend_from_slice(payload);
+
+        timeout(self.io_timeout, self.socket.send(&packet))
+            .await
+            .map_err(|_| QueryError::Timeout)??;
+
+        let mut buf = vec![0u8; MAX_DATAGRAM];
+        loop {
+            let len = timeout(self.io_timeout, self.socket.recv(&mut buf))
+                .await
+                .map_err(|_| QueryError::Timeout)??;
+            if len < 5 {
+                return Err(QueryError::Protocol(format!("datagram of {len} bytes")));
+            }
+            let session = i32::from_be_bytes([buf[1], buf[2], buf[3], buf[4]]);
+            // Late answers to an earlier request on this socket
+            if buf[0] != kind || session != self.session_id {
+                continue;
+            }
+            return Ok(buf[5..len].to_vec());
+        }
+    }
+}
+
+/// Fetches the full stat of the server at `host:port` with a fresh session.
+pub async fn query(host: &str, port: u16, io_timeout: Duration) -> Result<QueryStatus, QueryError> {
+    let mut client = QueryClient::connect((host, port), io_timeout).await?;
+    client.full_stat().await
+}
+
+/// Splits `CraftBukkit on Bukkit 1.20: WorldEdit 7.2; Vault 1.7` into the
+/// server software and its plugins. Vanilla sends an empty string.
+fn parse_plugins(value: &str) -> (Option<String>, Vec<String>) {
+    let value = value.trim();
+    if value.is_empty() {
+        return (None, Vec::new());
+    }
+    match value.split_once(':') {
+        Some((server_mod, plugins)) => (
+            Some(server_mod.trim().to_string()),
+            plugins
+                .split(';')
+                .map(str::trim)
+                .filter(|plugin| !plugin.is_empty())
+                .map(String::from)
+                .collect(),
+        ),
+        None => (Some(value.to_string()), Vec::new()),
+    }
+}
+
+fn parse_num(value: &str) -> Result<i64, QueryError> {
+    value
+        .parse()
+        .map_err(|_| QueryError::Protocol(format!("invalid number {value:?}")))
+}
+
+fn read_cstr(cursor: &mut &[u8]) -> Result<String, QueryError> {
+    let end = cursor
+        .iter()
+        .position(|b| *b == 0)
+        .ok_or_else(|| QueryError::Protocol("unterminated string".into()))?;
+    // Strings are ISO-8859-1, every byte maps to the same code point
+    let text = cursor[..end].iter().map(|b| *b as char).collect();
+    *cursor = &cursor[end + 1..];
+    Ok(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+    const TOKEN: i32 = 9_513_307;
+
+    fn cstr(buf: &mut Vec<u8>, text: &str) {
+        buf.extend_from_slice(text.as_bytes());
+        buf.push(0);
+    }
+
+    /// Answers a handshake and one stat request the way a 1.20 server does.
+    async fn fake_server(full: bool) -> (u16, tokio::task::JoinHandle<()>) {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let task = tokio::spawn(async move {
+            let mut buf = [0u8; 1024];
+            let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(&buf[..3], &[0xFE, 0xFD, TYPE_HANDSHAKE]);
+            assert_eq!(len, 7);
+            let session = &buf[3..7];
+            assert_eq!(
+                i32::from_be_bytes(session.try_into().unwrap()) & !SESSION_MASK,
+                0
+            );
+            let mut reply = vec![TYPE_HANDSHAKE];
+            reply.extend_from_slice(session);
+            cstr(&mut reply, &TOKEN.to_string());
+            socket.send_to(&reply, peer).await.unwrap();
+
+            let (len, peer) = socket.recv_from(&mut buf).await.unwrap();
+            assert_eq!(buf[2], TYPE_STAT);
+            assert_eq!(&buf[7..11], &TOKEN.to_be_bytes());
+            assert_eq!(len, if full { 15 } else { 11 });
+            let mut reply = vec![TYPE_STAT];
+            reply.extend_from_slice(&buf[3..7]);
+            if full {
+                reply.extend_from_slice(b"splitnum\0\x80\0");
+                for (key, value) in [
+                    ("hostname", "A Minecraft Server"),
+                    ("gametype", "SMP"),
+                    ("game_id", "MINECRAFT"),
+                    ("version", "1.20.4"),
+                    ("plugins", "Paper on 1.20.4: WorldEdit 7.3.0; Vault 1.7.3"),
+                    ("map", "world"),
+                    ("numplayers", "2"),
+                    ("maxplayers", "20"),
+                    ("hostport", "25565"),
+                    ("hostip", "127.0.0.1"),
+                ] {
+                    cstr(&mut reply, key);
+                    cstr(&mut reply, value);
+                }
+                reply.push(0);
+                reply.extend_from_slice(b"\x01player_\0\0");
+                cstr(&mut reply, "Steve");
+                cstr(&mut reply, "Alex");
+                reply.push(0);
+            } else {
+                cstr(&mut reply, "A Minecraft Server");
+                cstr(&mut reply, "SMP");
+                cstr(&mut reply, "world");
+                cstr(&mut reply, "2");
+                cstr(&mut reply, "20");
+                reply.extend_from_slice(&25565u16.to_le_bytes());
+                cstr(&mut reply, "127.0.0.1");
+            }
+            socket.send_to(&reply, peer).await.unwrap();
+        });
+        (port, task)
+    }
+
+    #[test]
+    fn plugins_split_into_server_mod_and_list() {
+        assert_eq!(parse_plugins(""), (None, Vec::new()));
+        assert_eq!(
+            parse_plugins("CraftBukkit on Bukkit 1.20: WorldEdit 7.2; Vault 1.7;"),
+            (
+                Some("CraftBukkit on Bukkit 1.20".to_string()),
+                vec!["WorldEdit 7.2".to_string(), "Vault 1.7".to_string()]
+            )
+        );
+        assert_eq!(
+            parse_plugins("Fabric"),
+            (Some("Fabric".to_string()), Vec::new())
+        );
+    }
+
+    #[test]
+    fn cstr_reads_latin1_and_requires_terminator() {
+        let mut cursor: &[u8] = b"caf\xe9\0rest";
+        assert_eq!(read_cstr(&mut cursor).unwrap(), "café");
+        assert_eq!(cursor, b"rest");
+        assert!(matches!(
+            read_cstr(&mut cursor),
+            Err(QueryError::Protocol(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn basic_stat_reads_little_endian_port() {
+        let (port, server) = fake_server(false).await;
+        let mut client = QueryClient::connect(("127.0.0.1", port), TIMEOUT)
+            .await
+            .unwrap();
+        assert_eq!(client.token, TOKEN);
+
+        let basic = client.basic_stat().await.unwrap();
+        assert_eq!(basic.motd, "A Minecraft Server");
+        assert_eq!(basic.game_type, "SMP");
+        assert_eq!(basic.map, "world");
+        assert_eq!(basic.players_online, 2);
+        assert_eq!(basic.players_max, 20);
+        assert_eq!(basic.host_port, 25565);
+        assert_eq!(basic.host_ip, "127.0.0.1");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn full_stat_reads_keys_and_players() {
+        let (port, server) = fake_server(true).await;
+        let status = query("127.0.0.1", port, TIMEOUT).await.unwrap();
+        assert_eq!(status.motd, "A Minecraft Server");
+        assert_eq!(status.game_id, "MINECRAFT");
+        assert_eq!(status.version, "1.20.4");
+        assert_eq!(status.server_mod.as_deref(), Some("Paper on 1.20.4"));
+        assert_eq!(status.plugins, ["WorldEdit 7.3.0", "Vault 1.7.3"]);
+        assert_eq!(status.players_online, 2);
+        assert_eq!(status.host_port, 25565);
+        assert_eq!(status.players, ["Steve", "Alex"]);
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn unanswered_handshake_times_out() {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let result = QueryClient::connect(("127.0.0.1", port), Duration::from_millis(100)).await;
+        assert!(matches!(result, Err(QueryError::Timeout)));
+    }
+}
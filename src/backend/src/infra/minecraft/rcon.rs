@@ -0,0 +1,293 @@
+use std::time::Duration;
+
+use thiserror::Error;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpStream, ToSocketAddrs},
+    time::timeout,
+};
+
+use crate::prelude::*;
+
+const TYPE_RESPONSE: i32 = 0;
+const TYPE_COMMAND: i32 = 2;
+const TYPE_AUTH: i32 = 3;
+const TYPE_AUTH_RESPONSE: i32 = 2;
+
+/// Longest command body the vanilla server accepts.
+const MAX_COMMAND_LEN: usize = 1446;
+/// Upper bound for a single incoming packet, the server splits at 4096 bytes.
+const MAX_PACKET_LEN: i32 = 4096 + 10;
+
+#[derive(Debug, Error)]
+pub enum RconError {
+    #[error("rcon io failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("rcon request timed out")]
+    Timeout,
+    #[error("rcon authentication rejected")]
+    AuthFailed,
+    #[error("rcon command longer than {MAX_COMMAND_LEN} bytes")]
+    CommandTooLong,
+    #[error("rcon protocol violation: {0}")]
+    Protocol(String),
+}
+
+struct Packet {
+    id: i32,
+    kind: i32,
+    body: Vec<u8>,
+}
+
+/// Minimal Source RCON client as implemented by the Minecraft server.
+pub struct RconClient {
+    stream: TcpStream,
+    next_id: i32,
+    io_timeout: Duration,
+}
+
+impl RconClient {
+    pub async fn connect(
+        addr: impl ToSocketAddrs,
+        password: &str,
+        io_timeout: Duration,
+    ) -> Result<Self, RconError> {
+        let stream = timeout(io_timeout, TcpStream::connect(addr))
+            .await
+            .map_err(|_| RconError::Timeout)??;
+
+        let mut client = Self {
+            stream,
+            next_id: 1,
+            io_timeout,
+        };
+        client.authenticate(password).await?;
+        Ok(client)
+    }
+
+    async fn authenticate(&mut self, password: &str) -> Result<(), RconError> {
+        let id = self.next_id();
+        self.send(id, TYPE_AUTH, password.as_bytes()).await?;
+
+        loop {
+            let packet = self.recv().await?;
+            // Some servers send an empty response value before the auth result
+            if packet.kind == TYPE_RESPONSE {
+                continue;
+            }
+            if packet.kind != TYPE_AUTH_RESPONSE {
+                return Err(RconError::Protocol(format!(
+                    "unexpected packet type {} during auth",
+                    packet.kind
+                )));
+            }
+            return match packet.id {
+                -1 => Err(RconError::AuthFailed),
+                got if got == id => Ok(()),
+                got => Err(RconError::Protocol(format!(
+                    "auth response id {got} does not match {id}"
+                ))),
+            };
+        }
+    }
+
+    /// Runs a command and returns its full output. Long outputs arrive split
+    /// over several packets, a trailing marker request tells where they end.
+    pub async fn cmd(&mut self, command: &str) -> Result<String, RconError> {
+        if command.len() > MAX_COMMAND_LEN {
+            return Err(RconError::CommandTooLong);
+        }
+
+        let id = self.next_id();
+        let marker = self.next_id();
+        self.send(id, TYPE_COMMAND, command.as_bytes()).await?;
+        // Unknown request types are answered after the command output
+        self.send(marker, TYPE_RESPONSE, b"").await?;
+
+        let mut output = Vec::new();
+        loop {
+            let packet = self.recv().await?;
+            match packet.id {
+                got if got == id => output.extend_from_slice(&packet.body),
+                got if got == marker => break,
+                -1 => return Err(RconError::AuthFailed),
+                got => debug!(packet_id = got, "ignore stray rcon packet"),
+            }
+        }
+
+        Ok(String::from_utf8_lossy(&output).into_owned())
+    }
+
+    fn next_id(&mut self) -> i32 {
+        let id = self.next_id;
+        self.next_id = self.next_id.checked_add(1).unwrap_or(1);
+        id
+    }
+
+    async fn send(&mut self, id: i32, kind: i32, body: &[u8]) -> Result<(), RconError> {
+        let buf = encode(id, kind, body);
+        timeout(self.io_timeout, self.stream.write_all(&buf))
+            .await
+            .map_err(|_| RconError::Timeout)??;
+        Ok(())
+    }
+
+    async fn recv(&mut self) -> Result<Packet, RconError> {
+        timeout(self.io_timeout, self.read_packet())
+            .await
+            .map_err(|_| RconError::Timeout)?
+    }
+
+    async fn read_packet(&mut self) -> Result<Packet, RconError> {
+        let len = self.stream.read_i32_le().await?;
+        if !(10..=MAX_PACKET_LEN).contains(&len) {
+            return Err(RconError::Protocol(format!("invalid packet length {len}")));
+        }
+
+        let mut buf = vec![0u8; len as usize];
+        self.stream.read_exact(&mut buf).await?;
+        Ok(decode(&buf))
+    }
+}
+
+/// Frames a packet, length prefixed and little endian.
+fn encode(id: i32, kind: i32, body: &[u8]) -> Vec<u8> {
+    // id + type + body + two null terminators
+    let len = (4 + 4 + body.len() + 2) as i32;
+    let mut buf = Vec::with_capacity(len as usize + 4);
+    buf.extend_from_slice(&len.to_le_bytes());
+    buf.extend_from_slice(&id.to_le_bytes());
+    buf.extend_from_slice(&kind.to_le_bytes());
+    buf.extend_from_slice(body);
+    buf.extend_from_slice(&[0, 0]);
+    buf
+}
+
+/// Parses a packet without its length prefix, at least 10 bytes long.
+fn decode(buf: &[u8]) -> Packet {
+    let id = i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]);
+    let kind = i32::from_le_bytes([buf[4], buf[5], buf[6], buf[7]]);
+    let body = buf[8..buf.len() - 2].to_vec();
+    Packet { id, kind, body }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::net::TcpListener;
+
+    const TIMEOUT: Duration = Duration::from_secs(5);
+
+    async fn read_request(stream: &mut TcpStream) -> Packet {
+        let len = stream.read_i32_le().await.unwrap();
+        let mut buf = vec![0u8; len as usize];
+        stream.read_exact(&mut buf).await.unwrap();
+        decode(&buf)
+    }
+
+    async fn reply(stream: &mut TcpStream, id: i32, kind: i32, body: &[u8]) {
+        stream.write_all(&encode(id, kind, body)).await.unwrap();
+    }
+
+    #[test]
+    fn encode_frames_little_endian_with_terminators() {
+        let buf = encode(7, TYPE_COMMAND, b"list");
+        assert_eq!(&buf[..4], &14i32.to_le_bytes());
+        assert_eq!(&buf[4..8], &7i32.to_le_bytes());
+        assert_eq!(&buf[8..12], &TYPE_COMMAND.to_le_bytes());
+        assert_eq!(&buf[12..16], b"list");
+        assert_eq!(&buf[16..], &[0, 0]);
+    }
+
+    #[test]
+    fn decode_reverses_encode() {
+        let buf = encode(-1, TYPE_AUTH_RESPONSE, b"");
+        let packet = decode(&buf[4..]);
+        assert_eq!(packet.id, -1);
+        assert_eq!(packet.kind, TYPE_AUTH_RESPONSE);
+        assert!(packet.body.is_empty());
+
+        let buf = encode(3, TYPE_RESPONSE, "§aThere are 0 players".as_bytes());
+        let packet = decode(&buf[4..]);
+        assert_eq!(packet.id, 3);
+        assert_eq!(packet.body, "§aThere are 0 players".as_bytes());
+    }
+
+    #[tokio::test]
+    async fn cmd_joins_split_output_until_marker() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let auth = read_request(&mut stream).await;
+            assert_eq!(auth.kind, TYPE_AUTH);
+            assert_eq!(auth.body, b"secret");
+            reply(&mut stream, auth.id, TYPE_RESPONSE, b"").await;
+            reply(&mut stream, auth.id, TYPE_AUTH_RESPONSE, b"").await;
+
+            let command = read_request(&mut stream).await;
+            assert_eq!(command.kind, TYPE_COMMAND);
+            assert_eq!(command.body, b"help");
+            let marker = read_request(&mut stream).await;
+            reply(&mut stream, command.id, TYPE_RESPONSE, b"first ").await;
+            reply(&mut stream, command.id, TYPE_RESPONSE, b"second").await;
+            reply(&mut stream, marker.id, TYPE_RESPONSE, b"Unknown request 0").await;
+        });
+
+        let mut client = RconClient::connect(addr, "secret", TIMEOUT).await.unwrap();
+        assert_eq!(client.cmd("help").await.unwrap(), "first second");
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn connect_reports_rejected_password() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            reply(&mut stream, -1, TYPE_AUTH_RESPONSE, b"").await;
+        });
+
+        let result = RconClient::connect(addr, "wrong", TIMEOUT).await;
+        assert!(matches!(result, Err(RconError::AuthFailed)));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn oversized_packet_length_is_a_protocol_error() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            read_request(&mut stream).await;
+            stream
+                .write_all(&(MAX_PACKET_LEN + 1).to_le_bytes())
+                .await
+                .unwrap();
+        });
+
+        let result = RconClient::connect(addr, "secret", TIMEOUT).await;
+        assert!(matches!(result, Err(RconError::Protocol(_))));
+        server.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn cmd_refuses_overlong_commands() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let auth = read_request(&mut stream).await;
+            reply(&mut stream, auth.id, TYPE_AUTH_RESPONSE, b"").await;
+        });
+
+        let mut client = RconClient::connect(addr, "secret", TIMEOUT).await.unwrap();
+        let command = "say ".to_string() + &"a".repeat(MAX_COMMAND_LEN);
+        assert!(matches!(
+            client.cmd(&command).await,
+            Err(RconError::CommandTooLong)
+        ));
+        server.await.unwrap();
+    }
+}
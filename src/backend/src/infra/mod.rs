@@ -1 +1,7 @@
+pub mod archive;
+pub mod crypto;
 pub mod db;
+pub mod dedup;
+pub mod destination;
+pub mod minecraft;
+pub mod sandbox;
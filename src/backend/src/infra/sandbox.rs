@@ -0,0 +1,302 @@
+use std::{
+    io::{self, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+use thiserror::Error;
+use tokio::fs;
+use uuid::Uuid;
+
+#[derive(Debug, Error)]
+pub enum SandboxError {
+    #[error("path resolves outside the server directory")]
+    Escape,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A server directory file operations are confined to. Paths are resolved
+/// against the canonical root with every symlink followed, anything ending up
+/// outside of it, or inside an excluded directory, is refused.
+#[derive(Debug, Clone)]
+pub struct Sandbox {
+    root: PathBuf,
+    excluded: Vec<PathBuf>,
+}
+
+impl Sandbox {
+    /// `excluded` are directories the daemon keeps inside server directories
+    /// for itself, such as a nested backup directory.
+    pub async fn open(root: &Path, excluded: &[&Path]) -> Result<Self, SandboxError> {
+        let root = fs::canonicalize(root).await?;
+        let mut hidden = Vec::new();
+        for dir in excluded {
+            if let Ok(dir) = fs::canonicalize(dir).await
+                && dir.starts_with(&root)
+                && dir != root
+            {
+                hidden.push(dir);
+            }
+        }
+        Ok(Self {
+            root,
+            excluded: hidden,
+        })
+    }
+
+    /// Where the cleaned relative `path` lives. Existing parents are resolved
+    /// one by one and must stay inside, the last component is left as is so
+    /// a symlink can be renamed or removed rather than its target.
+    pub async fn locate(&self, path: &Path) -> Result<PathBuf, SandboxError> {
+        let Some(name) = path.file_name() else {
+            return Ok(self.root.clone());
+        };
+
+        let mut dir = self.root.clone();
+        let mut missing = PathBuf::new();
+        for component in path.parent().into_iter().flat_map(Path::components) {
+            if !missing.as_os_str().is_empty() {
+                missing.push(component);
+                continue;
+            }
+            match fs::canonicalize(dir.join(component)).await {
+                Ok(resolved) => {
+                    self.check(&resolved)?;
+                    dir = resolved;
+                }
+                Err(e) if e.kind() == ErrorKind::NotFound => missing.push(component),
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let located = dir.join(missing).join(name);
+        self.check(&located)?;
+        Ok(located)
+    }
+
+    /// `located` with its last component resolved too, for reading and
+    /// writing through a symlink. A dangling symlink is refused since writing
+    /// through it would create its target wherever it points.
+    pub async fn follow(&self, located: &Path) -> Result<PathBuf, SandboxError> {
+        match fs::canonicalize(located).await {
+            Ok(resolved) => {
+                self.check(&resolved)?;
+                Ok(resolved)
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                match fs::symlink_metadata(located).await {
+                    Ok(meta) if meta.file_type().is_symlink() => Err(SandboxError::Escape),
+                    _ => Ok(located.to_path_buf()),
+                }
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// `path` inside the sandbox relative to its root.
+    pub fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root)
+            .map(Path::to_path_buf)
+            .unwrap_or_default()
+    }
+
+    /// Whether directory listings should leave `path` out.
+    pub fn is_excluded(&self, path: &Path) -> bool {
+        self.excluded.iter().any(|dir| path.starts_with(dir))
+    }
+
+    fn check(&self, path: &Path) -> Result<(), SandboxError> {
+        if path.starts_with(&self.root) && !self.is_excluded(path) {
+            Ok(())
+        } else {
+            Err(SandboxError::Escape)
+        }
+    }
+}
+
+/// Text is valid UTF-8 without NUL bytes. `partial` allows the sample to end
+/// in the middle of a character.
+pub fn is_text(sample: &[u8], partial: bool) -> bool {
+    if sample.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        Err(e) => partial && e.error_len().is_none(),
+    }
+}
+
+/// Replaces `path` through a temporary sibling so readers, the server
+/// included, never see a half written file. Permissions of the file being
+/// replaced are kept.
+pub async fn write_atomic(path: &Path, data: &[u8]) -> io::Result<()> {
+    let temp = temp_sibling(path);
+    let written = async {
+        fs::write(&temp, data).await?;
+        if let Ok(meta) = fs::metadata(path).await {
+            fs::set_permissions(&temp, meta.permissions()).await?;
+        }
+        fs::rename(&temp, path).await
+    }
+    .await;
+
+    if written.is_err() {
+        let _ = fs::remove_file(&temp).await;
+    }
+    written
+}
+
+/// Moves `source` to `target`, copying through a temporary sibling when they
+/// are on different filesystems.
+pub async fn move_file(source: &Path, target: &Path) -> io::Result<()> {
+    match fs::rename(source, target).await {
+        Err(e) if e.kind() == ErrorKind::CrossesDevices => {}
+        result => return result,
+    }
+
+    let temp = temp_sibling(target);
+    let copied = async {
+        fs::copy(source, &temp).await?;
+        fs::rename(&temp, target).await
+    }
+    .await;
+
+    if copied.is_err() {
+        let _ = fs::remove_file(&temp).await;
+        return copied;
+    }
+    fs::remove_file(source).await
+}
+
+/// Removes a file, a symlink (never its target) or a directory, which has to
+/// be empty unless `recursive`.
+pub async fn remove(path: &Path, recursive: bool) -> io::Result<()> {
+    let meta = fs::symlink_metadata(path).await?;
+    if !meta.is_dir() {
+        fs::remove_file(path).await
+    } else if recursive {
+        fs::remove_dir_all(path).await
+    } else {
+        fs::remove_dir(path).await
+    }
+}
+
+fn temp_sibling(path: &Path) -> PathBuf {
+    let name = path.file_name().unwrap_or_default().to_string_lossy();
+    path.with_file_name(format!(".{name}.{}.tmp", Uuid::new_v4().simple()))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::symlink;
+
+    use super::*;
+
+    /// The directory as the sandbox sees it, with symlinks resolved.
+    fn canonical(dir: &tempfile::TempDir) -> PathBuf {
+        std::fs::canonicalize(dir.path()).unwrap()
+    }
+
+    fn escaped<T: std::fmt::Debug>(result: Result<T, SandboxError>) -> bool {
+        matches!(result, Err(SandboxError::Escape))
+    }
+
+    #[tokio::test]
+    async fn locate_keeps_paths_inside_the_root() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("world")).unwrap();
+        let sandbox = Sandbox::open(dir.path(), &[]).await.unwrap();
+        let root = canonical(&dir);
+
+        let located = sandbox.locate(Path::new("world/level.dat")).await.unwrap();
+        assert_eq!(located, root.join("world/level.dat"));
+        assert_eq!(sandbox.relative(&located), Path::new("world/level.dat"));
+
+        let missing = sandbox.locate(Path::new("plugins/new/config.yml")).await;
+        assert_eq!(missing.unwrap(), root.join("plugins/new/config.yml"));
+        assert_eq!(sandbox.locate(Path::new("")).await.unwrap(), root);
+    }
+
+    #[tokio::test]
+    async fn symlinked_parent_may_not_leave_the_root() {
+        let outside = tempfile::tempdir().unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        symlink(outside.path(), dir.path().join("escape")).unwrap();
+        std::fs::create_dir(dir.path().join("world")).unwrap();
+        symlink(dir.path().join("world"), dir.path().join("inside")).unwrap();
+        let sandbox = Sandbox::open(dir.path(), &[]).await.unwrap();
+        let root = canonical(&dir);
+
+        assert!(escaped(sandbox.locate(Path::new("escape/secret")).await));
+        assert!(escaped(sandbox.locate(Path::new("escape/a/b")).await));
+        assert_eq!(
+            sandbox.locate(Path::new("inside/level.dat")).await.unwrap(),
+            root.join("world/level.dat")
+        );
+        // The link itself can still be handled, just not followed.
+        assert_eq!(
+            sandbox.locate(Path::new("escape")).await.unwrap(),
+            root.join("escape")
+        );
+    }
+
+    #[tokio::test]
+    async fn follow_refuses_escaping_and_dangling_links() {
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(outside.path().join("secret"), "").unwrap();
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("server.properties"), "").unwrap();
+        symlink(outside.path().join("secret"), dir.path().join("secret")).unwrap();
+        symlink(
+            dir.path().join("server.properties"),
+            dir.path().join("props"),
+        )
+        .unwrap();
+        symlink(dir.path().join("nowhere"), dir.path().join("dangling")).unwrap();
+        let sandbox = Sandbox::open(dir.path(), &[]).await.unwrap();
+        let root = canonical(&dir);
+
+        assert!(escaped(sandbox.follow(&root.join("secret")).await));
+        assert!(escaped(sandbox.follow(&root.join("dangling")).await));
+        assert_eq!(
+            sandbox.follow(&root.join("props")).await.unwrap(),
+            root.join("server.properties")
+        );
+        assert_eq!(
+            sandbox.follow(&root.join("new.txt")).await.unwrap(),
+            root.join("new.txt")
+        );
+    }
+
+    #[tokio::test]
+    async fn excluded_directories_are_refused() {
+        let dir = tempfile::tempdir().unwrap();
+        let backups = dir.path().join("backups");
+        std::fs::create_dir(&backups).unwrap();
+        symlink(&backups, dir.path().join("saves")).unwrap();
+        let sandbox = Sandbox::open(dir.path(), &[&backups, dir.path()])
+            .await
+            .unwrap();
+        let root = canonical(&dir);
+
+        assert!(sandbox.is_excluded(&root.join("backups/a.tar.gz")));
+        assert!(!sandbox.is_excluded(&root));
+        assert!(escaped(sandbox.locate(Path::new("backups")).await));
+        assert!(escaped(sandbox.locate(Path::new("backups/a.tar.gz")).await));
+        assert!(escaped(sandbox.locate(Path::new("saves/a.tar.gz")).await));
+        assert!(escaped(sandbox.follow(&root.join("saves")).await));
+    }
+
+    #[test]
+    fn text_allows_a_split_character_only_when_partial() {
+        let split = &"é".as_bytes()[..1];
+        assert!(is_text(b"motd=hello", false));
+        assert!(!is_text(b"level\0dat", true));
+        assert!(is_text(split, true));
+        assert!(!is_text(split, false));
+    }
+}
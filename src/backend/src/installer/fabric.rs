@@ -0,0 +1,83 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{
+    config::InstallerCfg,
+    domain::install::{SoftwareBuild, SoftwareVersion},
+};
+
+use super::{Artifact, InstallError, get_json};
+
+#[derive(Debug, Deserialize)]
+struct Component {
+    version: String,
+    stable: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct LoaderEntry {
+    loader: Component,
+}
+
+pub async fn versions(
+    http: &Client,
+    cfg: &InstallerCfg,
+) -> Result<Vec<SoftwareVersion>, InstallError> {
+    let url = format!("{}/v2/versions/game", cfg.fabric_url);
+    let games: Vec<Component> = get_json(http, &url, "fabric game versions").await?;
+    Ok(games
+        .into_iter()
+        .map(|game| SoftwareVersion {
+            version: game.version,
+            stable: game.stable,
+        })
+        .collect())
+}
+
+/// Loader versions usable with a game version.
+pub async fn builds(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, InstallError> {
+    let url = format!("{}/v2/versions/loader/{version}", cfg.fabric_url);
+    let loaders: Vec<LoaderEntry> = get_json(http, &url, format!("fabric {version}")).await?;
+    Ok(loaders
+        .into_iter()
+        .map(|entry| SoftwareBuild {
+            build: entry.loader.version,
+            stable: entry.loader.stable,
+        })
+        .collect())
+}
+
+/// The server launcher jar for a loader, built with the newest stable
+/// installer. It fetches the vanilla jar and libraries on its first start.
+/// Fabric publishes no checksum for it.
+pub async fn resolve(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+    loader: &str,
+) -> Result<Artifact, InstallError> {
+    let url = format!("{}/v2/versions/installer", cfg.fabric_url);
+    let installers: Vec<Component> = get_json(http, &url, "fabric installer").await?;
+    let installer = installers
+        .iter()
+        .find(|installer| installer.stable)
+        .or(installers.first())
+        .ok_or_else(|| InstallError::NotFound("fabric installer".to_string()))?;
+
+    Ok(Artifact {
+        url: format!(
+            "{}/v2/versions/loader/{version}/{loader}/{}/server/jar",
+            cfg.fabric_url, installer.version
+        ),
+        file_name: format!(
+            "fabric-server-mc.{version}-loader.{loader}-launcher.{}.jar",
+            installer.version
+        ),
+        checksum: None,
+        installer: false,
+    })
+}
@@ -0,0 +1,197 @@
+use std::path::Path;
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use reqwest::Client;
+use tokio::fs;
+
+use crate::{
+    config::InstallerCfg,
+    domain::install::{SoftwareBuild, SoftwareVersion},
+};
+
+use super::{Artifact, Checksum, InstallError, get_text, is_release};
+
+lazy_static! {
+    static ref MAVEN_VERSION: Regex = Regex::new(r"<version>\s*([^<\s]+)\s*</version>").unwrap();
+}
+
+/// Forge and NeoForge share the maven layout and the installer, they differ
+/// in where they publish and how they name versions.
+#[derive(Debug, Clone, Copy)]
+pub enum Flavor {
+    /// Versions are the game version and the Forge version, `1.20.1-47.3.0`.
+    Forge,
+    /// Versions drop the leading `1.` of the game version, `21.1.77` is for
+    /// 1.21.1 and `21.0.x` for 1.21.
+    NeoForge,
+}
+
+impl Flavor {
+    fn name(&self) -> &'static str {
+        match self {
+            Flavor::Forge => "forge",
+            Flavor::NeoForge => "neoforge",
+        }
+    }
+
+    /// Maven directory of the artifact.
+    fn artifact_url(&self, cfg: &InstallerCfg) -> String {
+        match self {
+            Flavor::Forge => format!("{}/net/minecraftforge/forge", cfg.forge_maven_url),
+            Flavor::NeoForge => format!("{}/net/neoforged/neoforge", cfg.neoforge_maven_url),
+        }
+    }
+
+    /// Where the installer puts the libraries of a build, relative to the
+    /// server directory.
+    fn library_dir(&self, build: &str) -> String {
+        match self {
+            Flavor::Forge => format!("libraries/net/minecraftforge/forge/{build}"),
+            Flavor::NeoForge => format!("libraries/net/neoforged/neoforge/{build}"),
+        }
+    }
+
+    /// The game version a build is for.
+    fn game_version(&self, build: &str) -> Option<String> {
+        match self {
+            Flavor::Forge => build.split_once('-').map(|(game, _)| game.to_string()),
+            Flavor::NeoForge => {
+                let mut parts = build.split(['.', '-']);
+                let major = parts.next()?;
+                let minor = parts.next()?;
+                match minor {
+                    "0" => Some(format!("1.{major}")),
+                    _ => Some(format!("1.{major}.{minor}")),
+                }
+            }
+        }
+    }
+
+    fn is_stable(&self, build: &str) -> bool {
+        !["alpha", "beta", "pre", "rc"]
+            .iter()
+            .any(|marker| build.contains(marker))
+    }
+}
+
+/// Every published build, newest first.
+async fn all_builds(
+    http: &Client,
+    cfg: &InstallerCfg,
+    flavor: Flavor,
+) -> Result<Vec<String>, InstallError> {
+    let url = format!("{}/maven-metadata.xml", flavor.artifact_url(cfg));
+    let metadata = get_text(http, &url, format!("{} maven metadata", flavor.name())).await?;
+    let mut builds: Vec<String> = MAVEN_VERSION
+        .captures_iter(&metadata)
+        .map(|captures| captures[1].to_string())
+        .collect();
+    if builds.is_empty() {
+        return Err(InstallError::Metadata(format!(
+            "no versions in {} maven metadata",
+            flavor.name()
+        )));
+    }
+    // Maven lists them oldest first
+    builds.reverse();
+    Ok(builds)
+}
+
+pub async fn versions(
+    http: &Client,
+    cfg: &InstallerCfg,
+    flavor: Flavor,
+) -> Result<Vec<SoftwareVersion>, InstallError> {
+    let mut versions: Vec<SoftwareVersion> = Vec::new();
+    for build in all_builds(http, cfg, flavor).await? {
+        let Some(version) = flavor.game_version(&build) else {
+            continue;
+        };
+        if versions.iter().any(|known| known.version == version) {
+            continue;
+        }
+        versions.push(SoftwareVersion {
+            stable: is_release(&version),
+            version,
+        });
+    }
+    Ok(versions)
+}
+
+pub async fn builds(
+    http: &Client,
+    cfg: &InstallerCfg,
+    flavor: Flavor,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, InstallError> {
+    Ok(all_builds(http, cfg, flavor)
+        .await?
+        .into_iter()
+        .filter(|build| flavor.game_version(build).as_deref() == Some(version))
+        .map(|build| SoftwareBuild {
+            stable: flavor.is_stable(&build),
+            build,
+        })
+        .collect())
+}
+
+/// The installer jar of a build, checked against the SHA-1 maven publishes
+/// next to it.
+pub async fn resolve(
+    http: &Client,
+    cfg: &InstallerCfg,
+    flavor: Flavor,
+    version: &str,
+    build: &str,
+) -> Result<Artifact, InstallError> {
+    if flavor.game_version(build).as_deref() != Some(version) {
+        return Err(InstallError::NotFound(format!(
+            "{} {build} for {version}",
+            flavor.name()
+        )));
+    }
+
+    let file_name = format!("{}-{build}-installer.jar", flavor.name());
+    let url = format!("{}/{build}/{file_name}", flavor.artifact_url(cfg));
+    let sha1 = get_text(
+        http,
+        &format!("{url}.sha1"),
+        format!("{} {build} installer", flavor.name()),
+    )
+    .await?;
+    // Some sidecars carry the file name after the digest
+    let sha1 = sha1
+        .split_whitespace()
+        .next()
+        .unwrap_or_default()
+        .to_string();
+
+    Ok(Artifact {
+        url,
+        file_name,
+        checksum: Some(Checksum::Sha1(sha1)),
+        installer: true,
+    })
+}
+
+/// What to launch after the installer ran: a jar for older builds, or the
+/// `@` argument file newer ones expect on the java command line.
+pub async fn launch_target(root: &Path, flavor: Flavor, build: &str) -> Option<String> {
+    let name = flavor.name();
+    let candidates = [
+        format!("{name}-{build}-shim.jar"),
+        format!("{}/unix_args.txt", flavor.library_dir(build)),
+        format!("{name}-{build}.jar"),
+        format!("{name}-{build}-universal.jar"),
+    ];
+    for candidate in candidates {
+        if fs::metadata(root.join(&candidate)).await.is_ok() {
+            if candidate.ends_with(".txt") {
+                return Some(format!("@{candidate}"));
+            }
+            return Some(candidate);
+        }
+    }
+    None
+}
@@ -0,0 +1,980 @@
+mod fabric;
+mod forge;
+mod paper;
+mod purpur;
+mod vanilla;
+
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::Mutex,
+};
+
+use md5::Md5;
+use reqwest::{Client, StatusCode};
+use serde::de::DeserializeOwned;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::{
+    fs::{self, File},
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    process::Command,
+    task::spawn_blocking,
+    time::timeout,
+};
+use uuid::Uuid;
+
+use crate::{
+    config::InstallerCfg,
+    domain::{
+        install::{InstallOutcome, InstallRequest, Software, SoftwareBuild, SoftwareVersion},
+        server::Server,
+    },
+    jobs::JobHandle,
+    prelude::*,
+    state::AppState,
+};
+
+const PARTIAL_SUFFIX: &str = "part";
+
+#[derive(Debug, Error)]
+pub enum InstallError {
+    #[error("{0} not found")]
+    NotFound(String),
+    #[error("unexpected metadata: {0}")]
+    Metadata(String),
+    #[error("{0} does not match its published checksum")]
+    ChecksumMismatch(String),
+    #[error("installer failed: {0}")]
+    Installer(String),
+    #[error(transparent)]
+    Http(#[from] reqwest::Error),
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+/// A file to download, as the metadata of some software describes it.
+#[derive(Debug, Clone)]
+pub struct Artifact {
+    pub url: String,
+    pub file_name: String,
+    /// Absent where the source publishes none, like Fabric's launcher jars.
+    pub checksum: Option<Checksum>,
+    /// An installer jar to run rather than the server jar itself.
+    pub installer: bool,
+}
+
+/// Hex digest published next to an artifact.
+#[derive(Debug, Clone)]
+pub enum Checksum {
+    Sha1(String),
+    Sha256(String),
+    Md5(String),
+}
+
+/// Servers with an install in progress, at most one each.
+#[derive(Default)]
+pub struct Installer {
+    running: Mutex<HashSet<Uuid>>,
+}
+
+impl Installer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Reserves the server for an install, `false` if one is already running.
+    pub fn claim(&self, server_uuid: Uuid) -> bool {
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(server_uuid)
+    }
+
+    pub fn release(&self, server_uuid: Uuid) {
+        self.running
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&server_uuid);
+    }
+}
+
+pub async fn init(state: &AppState) {
+    if let Err(e) = fs::create_dir_all(&state.config.installer.cache_dir).await {
+        error!(
+            error = %e,
+            dir = %state.config.installer.cache_dir.display(),
+            "create installer cache directory failed"
+        );
+    }
+}
+
+/// Game versions `software` is available for, newest first.
+pub async fn versions(
+    state: &AppState,
+    software: Software,
+) -> Result<Vec<SoftwareVersion>, InstallError> {
+    let (http, cfg) = (&state.http, &state.config.installer);
+    match software {
+        Software::Vanilla => vanilla::versions(http, cfg).await,
+        Software::Paper => paper::versions(http, cfg).await,
+        Software::Purpur => purpur::versions(http, cfg).await,
+        Software::Fabric => fabric::versions(http, cfg).await,
+        Software::Forge => forge::versions(http, cfg, forge::Flavor::Forge).await,
+        Software::NeoForge => forge::versions(http, cfg, forge::Flavor::NeoForge).await,
+    }
+}
+
+/// Builds of `software` for a game version, newest first.
+pub async fn builds(
+    state: &AppState,
+    software: Software,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, InstallError> {
+    let (http, cfg) = (&state.http, &state.config.installer);
+    let builds = match software {
+        Software::Vanilla => vanilla::builds(http, cfg, version).await,
+        Software::Paper => paper::builds(http, cfg, version).await,
+        Software::Purpur => purpur::builds(http, cfg, version).await,
+        Software::Fabric => fabric::builds(http, cfg, version).await,
+        Software::Forge => forge::builds(http, cfg, forge::Flavor::Forge, version).await,
+        Software::NeoForge => forge::builds(http, cfg, forge::Flavor::NeoForge, version).await,
+    }?;
+    if builds.is_empty() {
+        return Err(InstallError::NotFound(format!(
+            "{} builds for {version}",
+            software.as_str()
+        )));
+    }
+    Ok(builds)
+}
+
+/// Downloads, or takes from the cache, what the request asks for and sets it
+/// up in the directory of `server`. Returns what the server should launch,
+/// the server record itself is left to the caller.
+pub async fn install(
+    state: &AppState,
+    server: &Server,
+    request: &InstallRequest,
+    job: &JobHandle,
+) -> Result<InstallOutcome, InstallError> {
+    let software = request.software;
+    job.step("resolving version");
+    let version = match &request.version {
+        Some(version) => version.clone(),
+        None => latest(versions(state, software).await?, |version| {
+            (version.version, version.stable)
+        })?,
+    };
+    let build = match &request.build {
+        Some(build) => build.clone(),
+        None => latest(builds(state, software, &version).await?, |build| {
+            (build.build, build.stable)
+        })?,
+    };
+    job.log(format!("{} {version} build {build}", software.as_str()));
+
+    let (http, cfg) = (&state.http, &state.config.installer);
+    let artifact = match software {
+        Software::Vanilla => vanilla::resolve(http, cfg, &version).await,
+        Software::Paper => paper::resolve(http, cfg, &version, &build).await,
+        Software::Purpur => purpur::resolve(http, cfg, &version, &build).await,
+        Software::Fabric => fabric::resolve(http, cfg, &version, &build).await,
+        Software::Forge => forge::resolve(http, cfg, forge::Flavor::Forge, &version, &build).await,
+        Software::NeoForge => {
+            forge::resolve(http, cfg, forge::Flavor::NeoForge, &version, &build).await
+        }
+    }?;
+
+    let cached_path = cfg
+        .cache_dir
+        .join(software.as_str())
+        .join(&version)
+        .join(&build)
+        .join(&artifact.file_name);
+    let cached = fetch(http, &artifact, &cached_path, job).await?;
+
+    let root = PathBuf::from(&server.working_dir);
+    fs::create_dir_all(&root).await?;
+    let jar_file = if artifact.installer {
+        job.step("running installer");
+        run_installer(cfg, &server.java_path, &cached_path, &root, job).await?;
+        match software {
+            Software::NeoForge => forge::launch_target(&root, forge::Flavor::NeoForge, &build),
+            _ => forge::launch_target(&root, forge::Flavor::Forge, &build),
+        }
+        .await
+        .ok_or_else(|| InstallError::Installer("no server jar or arguments file".to_string()))?
+    } else {
+        job.step("copying server jar");
+        let target = root.join(&artifact.file_name);
+        let partial = partial_path(&target);
+        fs::copy(&cached_path, &partial).await?;
+        fs::rename(&partial, &target).await?;
+        artifact.file_name
+    };
+
+    Ok(InstallOutcome {
+        software,
+        version,
+        build,
+        jar_file,
+        cached,
+    })
+}
+
+/// The first stable entry of a newest first listing, or the first one if none
+/// is stable.
+fn latest<T>(
+    entries: Vec<T>,
+    fields: impl Fn(T) -> (String, bool),
+) -> Result<String, InstallError> {
+    let mut entries = entries.into_iter().map(fields).peekable();
+    let first = entries
+        .peek()
+        .map(|(name, _)| name.clone())
+        .ok_or_else(|| InstallError::NotFound("version".to_string()))?;
+    Ok(entries
+        .find(|(_, stable)| *stable)
+        .map(|(name, _)| name)
+        .unwrap_or(first))
+}
+
+/// Puts `artifact` at `path` unless a verified copy is already there, returns
+/// whether it was. Downloads go to a sibling first and are only moved into
+/// place once their checksum matched.
+async fn fetch(
+    http: &Client,
+    artifact: &Artifact,
+    path: &Path,
+    job: &JobHandle,
+) -> Result<bool, InstallError> {
+    if fs::metadata(path).await.is_ok() {
+        let matches = match &artifact.checksum {
+            Some(checksum) => verify_file(path, checksum).await?,
+            None => true,
+        };
+        if matches {
+            job.log("using cached copy");
+            return Ok(true);
+        }
+        warn!(path = %path.display(), "cached artifact does not match its checksum");
+    }
+
+    job.step("downloading");
+    debug!(url = artifact.url, "artifact download started");
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).await?;
+    }
+    let partial = partial_path(path);
+    let result = download(http, artifact, &partial, job).await;
+    if let Err(e) = result {
+        let _ = fs::remove_file(&partial).await;
+        return Err(e);
+    }
+    fs::rename(&partial, path).await?;
+
+    debug!(url = artifact.url, "artifact download completed");
+    Ok(false)
+}
+
+async fn download(
+    http: &Client,
+    artifact: &Artifact,
+    file: &Path,
+    job: &JobHandle,
+) -> Result<(), InstallError> {
+    let response = http.get(&artifact.url).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(InstallError::NotFound(artifact.url.clone()));
+    }
+    let mut response = response.error_for_status()?;
+    if let Some(length) = response.content_length() {
+        job.set_total(length);
+    }
+
+    let mut hasher = artifact.checksum.as_ref().map(Hasher::new);
+    let mut out = File::create(file).await?;
+    while let Some(chunk) = response.chunk().await? {
+        job.check_cancelled()?;
+        if let Some(hasher) = &mut hasher {
+            hasher.update(&chunk);
+        }
+        out.write_all(&chunk).await?;
+        job.advance(chunk.len() as u64);
+    }
+    out.sync_all().await?;
+
+    if let (Some(checksum), Some(hasher)) = (&artifact.checksum, hasher)
+        && !checksum.matches(&hasher.finish())
+    {
+        return Err(InstallError::ChecksumMismatch(artifact.file_name.clone()));
+    }
+    Ok(())
+}
+
+async fn verify_file(path: &Path, checksum: &Checksum) -> io::Result<bool> {
+    let path = path.to_path_buf();
+    let checksum = checksum.clone();
+    spawn_blocking(move || {
+        let mut hasher = Hasher::new(&checksum);
+        let mut file = std::fs::File::open(path)?;
+        io::copy(&mut file, &mut hasher)?;
+        Ok(checksum.matches(&hasher.finish()))
+    })
+    .await
+    .map_err(io::Error::other)?
+}
+
+/// Runs a Forge style installer jar in server mode inside `root`, its output
+/// goes to the job log.
+async fn run_installer(
+    cfg: &InstallerCfg,
+    java_path: &str,
+    installer: &Path,
+    root: &Path,
+    job: &JobHandle,
+) -> Result<(), InstallError> {
+    let installer = fs::canonicalize(installer).await?;
+    let mut child = Command::new(java_path)
+        .arg("-jar")
+        .arg(&installer)
+        .arg("--installServer")
+        .arg(root)
+        .current_dir(root)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()
+        .map_err(|e| InstallError::Installer(format!("start {java_path}: {e}")))?;
+
+    let stdout = child.stdout.take();
+    let stderr = child.stderr.take();
+    let run = async {
+        tokio::join!(log_lines(stdout, job), log_lines(stderr, job));
+        child.wait().await
+    };
+    let status = tokio::select! {
+        status = timeout(cfg.installer_timeout, run) => match status {
+            Ok(status) => status?,
+            Err(_) => return Err(InstallError::Installer("timed out".to_string())),
+        },
+        _ = job.cancelled() => return Err(io::Error::other("cancelled").into()),
+    };
+    if !status.success() {
+        return Err(InstallError::Installer(format!("exited with {status}")));
+    }
+    Ok(())
+}
+
+async fn log_lines(output: Option<impl AsyncRead + Unpin>, job: &JobHandle) {
+    let Some(output) = output else {
+        return;
+    };
+    let mut lines = BufReader::new(output).lines();
+    while let Ok(Some(line)) = lines.next_line().await {
+        job.log(line);
+    }
+}
+
+/// Fetches and parses a JSON document, a 404 becomes [`InstallError::NotFound`]
+/// naming `what`.
+async fn get_json<T: DeserializeOwned>(
+    http: &Client,
+    url: &str,
+    what: impl Into<String>,
+) -> Result<T, InstallError> {
+    debug!(url, "fetch installer metadata started");
+    let response = http.get(url).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(InstallError::NotFound(what.into()));
+    }
+    let body = response.error_for_status()?.json().await?;
+    debug!(url, "fetch installer metadata completed");
+    Ok(body)
+}
+
+/// Same as [`get_json`] for plain text, such as maven metadata.
+async fn get_text(
+    http: &Client,
+    url: &str,
+    what: impl Into<String>,
+) -> Result<String, InstallError> {
+    debug!(url, "fetch installer metadata started");
+    let response = http.get(url).send().await?;
+    if response.status() == StatusCode::NOT_FOUND {
+        return Err(InstallError::NotFound(what.into()));
+    }
+    let body = response.error_for_status()?.text().await?;
+    debug!(url, "fetch installer metadata completed");
+    Ok(body)
+}
+
+/// Plain release numbers like `1.21.1`, as opposed to snapshots and
+/// pre-releases.
+fn is_release(version: &str) -> bool {
+    version
+        .split('.')
+        .all(|part| !part.is_empty() && part.bytes().all(|b| b.is_ascii_digit()))
+}
+
+fn partial_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{PARTIAL_SUFFIX}"));
+    path.with_file_name(name)
+}
+
+impl Checksum {
+    fn matches(&self, digest: &str) -> bool {
+        let expected = match self {
+            Checksum::Sha1(hex) | Checksum::Sha256(hex) | Checksum::Md5(hex) => hex,
+        };
+        expected.trim().eq_ignore_ascii_case(digest)
+    }
+}
+
+enum Hasher {
+    Sha1(Sha1),
+    Sha256(Sha256),
+    Md5(Md5),
+}
+
+impl Hasher {
+    fn new(checksum: &Checksum) -> Self {
+        match checksum {
+            Checksum::Sha1(_) => Hasher::Sha1(Sha1::new()),
+            Checksum::Sha256(_) => Hasher::Sha256(Sha256::new()),
+            Checksum::Md5(_) => Hasher::Md5(Md5::new()),
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha1(hasher) => hasher.update(data),
+            Hasher::Sha256(hasher) => hasher.update(data),
+            Hasher::Md5(hasher) => hasher.update(data),
+        }
+    }
+
+    fn finish(self) -> String {
+        let digest = match self {
+            Hasher::Sha1(hasher) => hasher.finalize().to_vec(),
+            Hasher::Sha256(hasher) => hasher.finalize().to_vec(),
+            Hasher::Md5(hasher) => hasher.finalize().to_vec(),
+        };
+        digest.iter().map(|b| format!("{b:02x}")).collect()
+    }
+}
+
+impl io::Write for Hasher {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.update(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, sync::Arc};
+
+    use axum::{Router, http::Uri, response::IntoResponse};
+    use tempfile::TempDir;
+    use tokio::net::TcpListener;
+
+    use crate::domain::job::JobKind;
+
+    use super::*;
+
+    const VANILLA_JAR: &[u8] = b"vanilla server jar";
+    const FORGE_INSTALLER: &[u8] = b"forge installer jar";
+
+    /// Canned metadata and jars of every source on a loopback port, with the
+    /// paths it was asked for.
+    struct Fixture {
+        base: String,
+        hits: Arc<Mutex<Vec<String>>>,
+        cache: TempDir,
+    }
+
+    impl Fixture {
+        async fn start() -> Self {
+            let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let base = format!("http://{}", listener.local_addr().unwrap());
+            let routes = Arc::new(routes(&base));
+            let hits = Arc::new(Mutex::new(Vec::new()));
+
+            let log = hits.clone();
+            let app = Router::new().fallback(move |uri: Uri| {
+                let (routes, log) = (routes.clone(), log.clone());
+                async move {
+                    log.lock().unwrap().push(uri.path().to_string());
+                    match routes.get(uri.path()) {
+                        Some(body) => body.clone().into_response(),
+                        None => axum::http::StatusCode::NOT_FOUND.into_response(),
+                    }
+                }
+            });
+            tokio::spawn(async move { axum::serve(listener, app).await });
+
+            let cache = tempfile::tempdir().unwrap();
+            Self { base, hits, cache }
+        }
+
+        fn cfg(&self) -> InstallerCfg {
+            InstallerCfg {
+                cache_dir: self.cache.path().to_path_buf(),
+                vanilla_manifest_url: format!("{}/mc/version_manifest_v2.json", self.base),
+                paper_url: self.base.clone(),
+                purpur_url: self.base.clone(),
+                fabric_url: self.base.clone(),
+                forge_maven_url: format!("{}/forge", self.base),
+                neoforge_maven_url: format!("{}/neo", self.base),
+                ..InstallerCfg::default()
+            }
+        }
+
+        fn hits(&self, path: &str) -> usize {
+            self.hits
+                .lock()
+                .unwrap()
+                .iter()
+                .filter(|hit| *hit == path)
+                .count()
+        }
+    }
+
+    fn routes(base: &str) -> HashMap<String, Vec<u8>> {
+        let vanilla_sha1 = format!("{:x}", Sha1::digest(VANILLA_JAR));
+        let forge_sha1 = format!("{:x}", Sha1::digest(FORGE_INSTALLER));
+        let maven = |versions: &[&str]| {
+            let versions: String = versions
+                .iter()
+                .map(|v| format!("<version>{v}</version>"))
+                .collect();
+            format!("<metadata><versioning><versions>{versions}</versions></versioning></metadata>")
+        };
+        [
+            (
+                "/mc/version_manifest_v2.json".to_string(),
+                format!(
+                    r#"{{"latest":{{}},"versions":[
+                        {{"id":"24w14a","type":"snapshot","url":"{base}/mc/24w14a.json"}},
+                        {{"id":"1.21.1","type":"release","url":"{base}/mc/1.21.1.json"}},
+                        {{"id":"b1.7.3","type":"old_beta","url":"{base}/mc/b1.7.3.json"}}]}}"#
+                ),
+            ),
+            (
+                "/mc/1.21.1.json".to_string(),
+                format!(
+                    r#"{{"downloads":{{"server":{{"sha1":"{vanilla_sha1}","url":"{base}/jars/vanilla.jar"}}}}}}"#
+                ),
+            ),
+            (
+                "/mc/b1.7.3.json".to_string(),
+                r#"{"downloads":{"client":{"sha1":"00","url":"x"}}}"#.to_string(),
+            ),
+            ("/jars/vanilla.jar".to_string(), String::from_utf8(VANILLA_JAR.to_vec()).unwrap()),
+            (
+                "/jars/tampered.jar".to_string(),
+                "not what was published".to_string(),
+            ),
+            (
+                "/v2/projects/paper".to_string(),
+                r#"{"versions":["1.20.6","1.21","1.21.1-rc1"]}"#.to_string(),
+            ),
+            (
+                "/v2/projects/paper/versions/1.21/builds".to_string(),
+                r#"{"builds":[
+                    {"build":100,"channel":"default","downloads":{"application":{"name":"paper-1.21-100.jar","sha256":"aa"}}},
+                    {"build":130,"channel":"experimental","downloads":{"application":{"name":"paper-1.21-130.jar","sha256":"bb"}}}]}"#
+                    .to_string(),
+            ),
+            (
+                "/v2/projects/paper/versions/1.21/builds/130".to_string(),
+                r#"{"build":130,"channel":"experimental","downloads":{"application":{"name":"paper-1.21-130.jar","sha256":"BB"}}}"#
+                    .to_string(),
+            ),
+            (
+                "/v2/purpur".to_string(),
+                r#"{"project":"purpur","versions":["1.20.4","1.21.1"]}"#.to_string(),
+            ),
+            (
+                "/v2/purpur/1.21.1".to_string(),
+                r#"{"builds":{"latest":"2300","all":["2299","2300"]}}"#.to_string(),
+            ),
+            (
+                "/v2/purpur/1.21.1/2300".to_string(),
+                r#"{"md5":"cc","result":"SUCCESS"}"#.to_string(),
+            ),
+            (
+                "/v2/purpur/1.21.1/2299".to_string(),
+                r#"{"md5":"dd","result":"FAILURE"}"#.to_string(),
+            ),
+            (
+                "/v2/versions/game".to_string(),
+                r#"[{"version":"24w14a","stable":false},{"version":"1.21.1","stable":true}]"#
+                    .to_string(),
+            ),
+            (
+                "/v2/versions/loader/1.21.1".to_string(),
+                r#"[{"loader":{"version":"0.16.1","stable":false}},{"loader":{"version":"0.16.0","stable":true}}]"#
+                    .to_string(),
+            ),
+            (
+                "/v2/versions/installer".to_string(),
+                r#"[{"version":"1.1.0","stable":false},{"version":"1.0.1","stable":true}]"#
+                    .to_string(),
+            ),
+            (
+                "/forge/net/minecraftforge/forge/maven-metadata.xml".to_string(),
+                maven(&["1.20.1-47.2.0", "1.20.1-47.3.0", "1.21.1-52.0.1-beta"]),
+            ),
+            (
+                "/forge/net/minecraftforge/forge/1.20.1-47.3.0/forge-1.20.1-47.3.0-installer.jar"
+                    .to_string(),
+                String::from_utf8(FORGE_INSTALLER.to_vec()).unwrap(),
+            ),
+            (
+                "/forge/net/minecraftforge/forge/1.20.1-47.3.0/forge-1.20.1-47.3.0-installer.jar.sha1"
+                    .to_string(),
+                format!("{forge_sha1}  forge-1.20.1-47.3.0-installer.jar\n"),
+            ),
+            (
+                "/neo/net/neoforged/neoforge/maven-metadata.xml".to_string(),
+                maven(&["21.0.167", "21.1.1-beta", "21.1.77"]),
+            ),
+        ]
+        .into_iter()
+        .map(|(path, body)| (path, body.into_bytes()))
+        .collect()
+    }
+
+    fn versions_of(versions: Vec<SoftwareVersion>) -> Vec<(String, bool)> {
+        versions
+            .into_iter()
+            .map(|v| (v.version, v.stable))
+            .collect()
+    }
+
+    fn builds_of(builds: Vec<SoftwareBuild>) -> Vec<(String, bool)> {
+        builds.into_iter().map(|b| (b.build, b.stable)).collect()
+    }
+
+    fn owned(entries: &[(&str, bool)]) -> Vec<(String, bool)> {
+        entries
+            .iter()
+            .map(|(name, stable)| (name.to_string(), *stable))
+            .collect()
+    }
+
+    fn sha1_of(artifact: &Artifact) -> Option<&str> {
+        match &artifact.checksum {
+            Some(Checksum::Sha1(hex)) => Some(hex),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn vanilla_reads_the_version_manifest() {
+        let fixture = Fixture::start().await;
+        let (http, cfg) = (Client::new(), fixture.cfg());
+
+        let versions = vanilla::versions(&http, &cfg).await.unwrap();
+        assert_eq!(
+            versions_of(versions),
+            owned(&[("24w14a", false), ("1.21.1", true), ("b1.7.3", false)])
+        );
+        let builds = vanilla::builds(&http, &cfg, "1.21.1").await.unwrap();
+        assert_eq!(builds_of(builds), owned(&[("1.21.1", true)]));
+
+        let artifact = vanilla::resolve(&http, &cfg, "1.21.1").await.unwrap();
+        assert_eq!(artifact.url, format!("{}/jars/vanilla.jar", fixture.base));
+        assert_eq!(artifact.file_name, "minecraft_server.1.21.1.jar");
+        assert_eq!(
+            sha1_of(&artifact),
+            Some(format!("{:x}", Sha1::digest(VANILLA_JAR)).as_str())
+        );
+        assert!(!artifact.installer);
+
+        let unknown = vanilla::resolve(&http, &cfg, "1.99").await;
+        assert!(matches!(unknown, Err(InstallError::NotFound(_))));
+        // The oldest versions had no server jar
+        let no_server = vanilla::resolve(&http, &cfg, "b1.7.3").await;
+        assert!(matches!(no_server, Err(InstallError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn paper_lists_newest_first_and_marks_channels() {
+        let fixture = Fixture::start().await;
+        let (http, cfg) = (Client::new(), fixture.cfg());
+
+        let versions = paper::versions(&http, &cfg).await.unwrap();
+        assert_eq!(
+            versions_of(versions),
+            owned(&[("1.21.1-rc1", false), ("1.21", true), ("1.20.6", true)])
+        );
+        let builds = paper::builds(&http, &cfg, "1.21").await.unwrap();
+        assert_eq!(builds_of(builds), owned(&[("130", false), ("100", true)]));
+
+        let artifact = paper::resolve(&http, &cfg, "1.21", "130").await.unwrap();
+        assert_eq!(
+            artifact.url,
+            format!(
+                "{}/v2/projects/paper/versions/1.21/builds/130/downloads/paper-1.21-130.jar",
+                fixture.base
+            )
+        );
+        assert_eq!(artifact.file_name, "paper-1.21-130.jar");
+        assert!(matches!(&artifact.checksum, Some(Checksum::Sha256(hex)) if hex == "BB"));
+
+        let missing = paper::builds(&http, &cfg, "1.8.8").await;
+        assert!(matches!(missing, Err(InstallError::NotFound(what)) if what == "paper 1.8.8"));
+    }
+
+    #[tokio::test]
+    async fn purpur_only_resolves_successful_builds() {
+        let fixture = Fixture::start().await;
+        let (http, cfg) = (Client::new(), fixture.cfg());
+
+        let versions = purpur::versions(&http, &cfg).await.unwrap();
+        assert_eq!(
+            versions_of(versions),
+            owned(&[("1.21.1", true), ("1.20.4", true)])
+        );
+        let builds = purpur::builds(&http, &cfg, "1.21.1").await.unwrap();
+        assert_eq!(builds_of(builds), owned(&[("2300", true), ("2299", true)]));
+
+        let artifact = purpur::resolve(&http, &cfg, "1.21.1", "2300")
+            .await
+            .unwrap();
+        assert_eq!(
+            artifact.url,
+            format!("{}/v2/purpur/1.21.1/2300/download", fixture.base)
+        );
+        assert_eq!(artifact.file_name, "purpur-1.21.1-2300.jar");
+        assert!(matches!(&artifact.checksum, Some(Checksum::Md5(hex)) if hex == "cc"));
+
+        let failed = purpur::resolve(&http, &cfg, "1.21.1", "2299").await;
+        assert!(matches!(failed, Err(InstallError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn fabric_uses_the_newest_stable_installer() {
+        let fixture = Fixture::start().await;
+        let (http, cfg) = (Client::new(), fixture.cfg());
+
+        let versions = fabric::versions(&http, &cfg).await.unwrap();
+        assert_eq!(
+            versions_of(versions),
+            owned(&[("24w14a", false), ("1.21.1", true)])
+        );
+        let builds = fabric::builds(&http, &cfg, "1.21.1").await.unwrap();
+        assert_eq!(
+            builds_of(builds),
+            owned(&[("0.16.1", false), ("0.16.0", true)])
+        );
+
+        let artifact = fabric::resolve(&http, &cfg, "1.21.1", "0.16.0")
+            .await
+            .unwrap();
+        assert_eq!(
+            artifact.url,
+            format!(
+                "{}/v2/versions/loader/1.21.1/0.16.0/1.0.1/server/jar",
+                fixture.base
+            )
+        );
+        assert_eq!(
+            artifact.file_name,
+            "fabric-server-mc.1.21.1-loader.0.16.0-launcher.1.0.1.jar"
+        );
+        assert!(artifact.checksum.is_none());
+    }
+
+    #[tokio::test]
+    async fn forge_groups_maven_builds_by_game_version() {
+        let fixture = Fixture::start().await;
+        let (http, cfg) = (Client::new(), fixture.cfg());
+        let forge = forge::Flavor::Forge;
+
+        let versions = forge::versions(&http, &cfg, forge).await.unwrap();
+        assert_eq!(
+            versions_of(versions),
+            owned(&[("1.21.1", true), ("1.20.1", true)])
+        );
+        let builds = forge::builds(&http, &cfg, forge, "1.20.1").await.unwrap();
+        assert_eq!(
+            builds_of(builds),
+            owned(&[("1.20.1-47.3.0", true), ("1.20.1-47.2.0", true)])
+        );
+        let beta = forge::builds(&http, &cfg, forge, "1.21.1").await.unwrap();
+        assert_eq!(builds_of(beta), owned(&[("1.21.1-52.0.1-beta", false)]));
+
+        let artifact = forge::resolve(&http, &cfg, forge, "1.20.1", "1.20.1-47.3.0")
+            .await
+            .unwrap();
+        assert_eq!(
+            artifact.url,
+            format!(
+                "{}/forge/net/minecraftforge/forge/1.20.1-47.3.0/forge-1.20.1-47.3.0-installer.jar",
+                fixture.base
+            )
+        );
+        // The sidecar names the file after the digest
+        assert_eq!(
+            sha1_of(&artifact),
+            Some(format!("{:x}", Sha1::digest(FORGE_INSTALLER)).as_str())
+        );
+        assert!(artifact.installer);
+
+        let mismatched = forge::resolve(&http, &cfg, forge, "1.21.1", "1.20.1-47.3.0").await;
+        assert!(matches!(mismatched, Err(InstallError::NotFound(_))));
+    }
+
+    #[tokio::test]
+    async fn neoforge_maps_builds_to_game_versions() {
+        let fixture = Fixture::start().await;
+        let (http, cfg) = (Client::new(), fixture.cfg());
+        let neoforge = forge::Flavor::NeoForge;
+
+        let versions = forge::versions(&http, &cfg, neoforge).await.unwrap();
+        assert_eq!(
+            versions_of(versions),
+            owned(&[("1.21.1", true), ("1.21", true)])
+        );
+        let builds = forge::builds(&http, &cfg, neoforge, "1.21.1")
+            .await
+            .unwrap();
+        assert_eq!(
+            builds_of(builds),
+            owned(&[("21.1.77", true), ("21.1.1-beta", false)])
+        );
+        let builds = forge::builds(&http, &cfg, neoforge, "1.21").await.unwrap();
+        assert_eq!(builds_of(builds), owned(&[("21.0.167", true)]));
+
+        // No sidecar published for it
+        let missing = forge::resolve(&http, &cfg, neoforge, "1.21.1", "21.1.77").await;
+        assert!(matches!(missing, Err(InstallError::NotFound(_))));
+    }
+
+    #[test]
+    fn latest_prefers_the_newest_stable_entry() {
+        let pick = |entries: &[(&str, bool)]| latest(owned(entries), |entry| entry);
+
+        assert_eq!(
+            pick(&[("24w14a", false), ("1.21.1", true), ("1.21", true)]).unwrap(),
+            "1.21.1"
+        );
+        assert_eq!(pick(&[("b", false), ("a", false)]).unwrap(), "b");
+        assert!(matches!(pick(&[]), Err(InstallError::NotFound(_))));
+    }
+
+    fn jar(fixture: &Fixture, path: &str, sha1: &str) -> Artifact {
+        Artifact {
+            url: format!("{}{path}", fixture.base),
+            file_name: "server.jar".to_string(),
+            checksum: Some(Checksum::Sha1(sha1.to_string())),
+            installer: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn downloads_are_verified_and_cached() {
+        let fixture = Fixture::start().await;
+        let (http, job) = (Client::new(), JobHandle::detached(JobKind::Install));
+        let sha1 = format!("{:x}", Sha1::digest(VANILLA_JAR));
+        let artifact = jar(&fixture, "/jars/vanilla.jar", &sha1.to_uppercase());
+        let path = fixture
+            .cache
+            .path()
+            .join("vanilla/1.21.1/1.21.1/server.jar");
+
+        assert!(!fetch(&http, &artifact, &path, &job).await.unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), VANILLA_JAR);
+        assert!(!partial_path(&path).exists());
+
+        // A verified copy is not downloaded again
+        assert!(fetch(&http, &artifact, &path, &job).await.unwrap());
+        assert_eq!(fixture.hits("/jars/vanilla.jar"), 1);
+
+        // A damaged one is
+        std::fs::write(&path, b"damaged").unwrap();
+        assert!(!fetch(&http, &artifact, &path, &job).await.unwrap());
+        assert_eq!(std::fs::read(&path).unwrap(), VANILLA_JAR);
+        assert_eq!(fixture.hits("/jars/vanilla.jar"), 2);
+    }
+
+    #[tokio::test]
+    async fn checksum_mismatches_leave_nothing_behind() {
+        let fixture = Fixture::start().await;
+        let (http, job) = (Client::new(), JobHandle::detached(JobKind::Install));
+        let sha1 = format!("{:x}", Sha1::digest(VANILLA_JAR));
+        let artifact = jar(&fixture, "/jars/tampered.jar", &sha1);
+        let path = fixture
+            .cache
+            .path()
+            .join("vanilla/1.21.1/1.21.1/server.jar");
+
+        let result = fetch(&http, &artifact, &path, &job).await;
+        assert!(
+            matches!(result, Err(InstallError::ChecksumMismatch(name)) if name == "server.jar")
+        );
+        assert!(!path.exists());
+        assert!(!partial_path(&path).exists());
+
+        let missing = jar(&fixture, "/jars/gone.jar", &sha1);
+        let result = fetch(&http, &missing, &path, &job).await;
+        assert!(matches!(result, Err(InstallError::NotFound(_))));
+        assert!(!partial_path(&path).exists());
+    }
+
+    #[test]
+    fn checksums_compare_without_case_or_whitespace() {
+        let digest = |checksum: &Checksum, data: &[u8]| {
+            let mut hasher = Hasher::new(checksum);
+            hasher.update(data);
+            checksum.matches(&hasher.finish())
+        };
+        let sha256 = format!("{:x}", Sha256::digest(b"jar"));
+        let md5 = format!("{:x}", Md5::digest(b"jar"));
+
+        assert!(digest(
+            &Checksum::Sha256(format!(" {}\n", sha256.to_uppercase())),
+            b"jar"
+        ));
+        assert!(digest(&Checksum::Md5(md5.clone()), b"jar"));
+        assert!(!digest(&Checksum::Md5(md5), b"other"));
+    }
+
+    #[tokio::test]
+    async fn forge_launch_targets_prefer_argument_files() {
+        let scratch = tempfile::tempdir().unwrap();
+        let root = scratch.path();
+        let args = root.join("libraries/net/minecraftforge/forge/1.20.1-47.3.0");
+        std::fs::create_dir_all(&args).unwrap();
+        std::fs::write(root.join("forge-1.20.1-47.3.0.jar"), b"").unwrap();
+
+        let forge = forge::Flavor::Forge;
+        assert_eq!(
+            forge::launch_target(root, forge, "1.20.1-47.3.0").await,
+            Some("forge-1.20.1-47.3.0.jar".to_string())
+        );
+        std::fs::write(args.join("unix_args.txt"), b"").unwrap();
+        assert_eq!(
+            forge::launch_target(root, forge, "1.20.1-47.3.0").await,
+            Some("@libraries/net/minecraftforge/forge/1.20.1-47.3.0/unix_args.txt".to_string())
+        );
+        assert_eq!(
+            forge::launch_target(root, forge::Flavor::NeoForge, "21.1.77").await,
+            None
+        );
+    }
+}
@@ -0,0 +1,99 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{
+    config::InstallerCfg,
+    domain::install::{SoftwareBuild, SoftwareVersion},
+};
+
+use super::{Artifact, Checksum, InstallError, get_json, is_release};
+
+const PROJECT: &str = "paper";
+/// Builds outside this channel are experimental.
+const STABLE_CHANNEL: &str = "default";
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    /// Oldest first.
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Builds {
+    /// Oldest first.
+    builds: Vec<Build>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Build {
+    build: u32,
+    channel: String,
+    downloads: BuildDownloads,
+}
+
+#[derive(Debug, Deserialize)]
+struct BuildDownloads {
+    application: Download,
+}
+
+#[derive(Debug, Deserialize)]
+struct Download {
+    name: String,
+    sha256: String,
+}
+
+fn project_url(cfg: &InstallerCfg) -> String {
+    format!("{}/v2/projects/{PROJECT}", cfg.paper_url)
+}
+
+pub async fn versions(
+    http: &Client,
+    cfg: &InstallerCfg,
+) -> Result<Vec<SoftwareVersion>, InstallError> {
+    let project: Project = get_json(http, &project_url(cfg), "paper").await?;
+    Ok(project
+        .versions
+        .into_iter()
+        .rev()
+        .map(|version| SoftwareVersion {
+            stable: is_release(&version),
+            version,
+        })
+        .collect())
+}
+
+pub async fn builds(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, InstallError> {
+    let url = format!("{}/versions/{version}/builds", project_url(cfg));
+    let builds: Builds = get_json(http, &url, format!("paper {version}")).await?;
+    Ok(builds
+        .builds
+        .into_iter()
+        .rev()
+        .map(|build| SoftwareBuild {
+            build: build.build.to_string(),
+            stable: build.channel == STABLE_CHANNEL,
+        })
+        .collect())
+}
+
+pub async fn resolve(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+    build: &str,
+) -> Result<Artifact, InstallError> {
+    let url = format!("{}/versions/{version}/builds/{build}", project_url(cfg));
+    let meta: Build = get_json(http, &url, format!("paper {version} build {build}")).await?;
+    let download = meta.downloads.application;
+
+    Ok(Artifact {
+        url: format!("{url}/downloads/{}", download.name),
+        file_name: download.name,
+        checksum: Some(Checksum::Sha256(download.sha256)),
+        installer: false,
+    })
+}
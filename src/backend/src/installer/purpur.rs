@@ -0,0 +1,94 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{
+    config::InstallerCfg,
+    domain::install::{SoftwareBuild, SoftwareVersion},
+};
+
+use super::{Artifact, Checksum, InstallError, get_json, is_release};
+
+const SUCCESS: &str = "SUCCESS";
+
+#[derive(Debug, Deserialize)]
+struct Project {
+    /// Oldest first.
+    versions: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Version {
+    builds: VersionBuilds,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionBuilds {
+    /// Oldest first.
+    all: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Build {
+    md5: String,
+    /// `SUCCESS` for builds that produced a jar.
+    result: String,
+}
+
+pub async fn versions(
+    http: &Client,
+    cfg: &InstallerCfg,
+) -> Result<Vec<SoftwareVersion>, InstallError> {
+    let url = format!("{}/v2/purpur", cfg.purpur_url);
+    let project: Project = get_json(http, &url, "purpur").await?;
+    Ok(project
+        .versions
+        .into_iter()
+        .rev()
+        .map(|version| SoftwareVersion {
+            stable: is_release(&version),
+            version,
+        })
+        .collect())
+}
+
+/// Purpur does not mark builds, every listed one counts as stable.
+pub async fn builds(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, InstallError> {
+    let url = format!("{}/v2/purpur/{version}", cfg.purpur_url);
+    let meta: Version = get_json(http, &url, format!("purpur {version}")).await?;
+    Ok(meta
+        .builds
+        .all
+        .into_iter()
+        .rev()
+        .map(|build| SoftwareBuild {
+            build,
+            stable: true,
+        })
+        .collect())
+}
+
+pub async fn resolve(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+    build: &str,
+) -> Result<Artifact, InstallError> {
+    let url = format!("{}/v2/purpur/{version}/{build}", cfg.purpur_url);
+    let meta: Build = get_json(http, &url, format!("purpur {version} build {build}")).await?;
+    if meta.result != SUCCESS {
+        return Err(InstallError::NotFound(format!(
+            "successful purpur {version} build {build}"
+        )));
+    }
+
+    Ok(Artifact {
+        url: format!("{url}/download"),
+        file_name: format!("purpur-{version}-{build}.jar"),
+        checksum: Some(Checksum::Md5(meta.md5)),
+        installer: false,
+    })
+}
@@ -0,0 +1,104 @@
+use reqwest::Client;
+use serde::Deserialize;
+
+use crate::{
+    config::InstallerCfg,
+    domain::install::{SoftwareBuild, SoftwareVersion},
+};
+
+use super::{Artifact, Checksum, InstallError, get_json};
+
+const RELEASE: &str = "release";
+
+#[derive(Debug, Deserialize)]
+struct Manifest {
+    versions: Vec<ManifestVersion>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ManifestVersion {
+    id: String,
+    #[serde(rename = "type")]
+    kind: String,
+    /// The version's own metadata document.
+    url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct VersionMeta {
+    downloads: Downloads,
+}
+
+#[derive(Debug, Deserialize)]
+struct Downloads {
+    /// Missing for the oldest versions, which had no server jar.
+    server: Option<Download>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Download {
+    sha1: String,
+    url: String,
+}
+
+async fn manifest(http: &Client, cfg: &InstallerCfg) -> Result<Manifest, InstallError> {
+    get_json(http, &cfg.vanilla_manifest_url, "version manifest").await
+}
+
+pub async fn versions(
+    http: &Client,
+    cfg: &InstallerCfg,
+) -> Result<Vec<SoftwareVersion>, InstallError> {
+    Ok(manifest(http, cfg)
+        .await?
+        .versions
+        .into_iter()
+        .map(|version| SoftwareVersion {
+            stable: version.kind == RELEASE,
+            version: version.id,
+        })
+        .collect())
+}
+
+/// Vanilla has a single build per version, named like it.
+pub async fn builds(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+) -> Result<Vec<SoftwareBuild>, InstallError> {
+    Ok(manifest(http, cfg)
+        .await?
+        .versions
+        .into_iter()
+        .filter(|entry| entry.id == version)
+        .map(|entry| SoftwareBuild {
+            build: entry.id,
+            stable: entry.kind == RELEASE,
+        })
+        .collect())
+}
+
+pub async fn resolve(
+    http: &Client,
+    cfg: &InstallerCfg,
+    version: &str,
+) -> Result<Artifact, InstallError> {
+    let entry = manifest(http, cfg)
+        .await?
+        .versions
+        .into_iter()
+        .find(|entry| entry.id == version)
+        .ok_or_else(|| InstallError::NotFound(format!("vanilla {version}")))?;
+    let meta: VersionMeta = get_json(http, &entry.url, format!("vanilla {version}")).await?;
+    let server = meta
+        .downloads
+        .server
+        .ok_or_else(|| InstallError::NotFound(format!("vanilla {version} server jar")))?;
+
+    Ok(Artifact {
+        url: server.url,
+        file_name: format!("minecraft_server.{version}.jar"),
+        checksum: Some(Checksum::Sha1(server.sha1)),
+        installer: false,
+    })
+}
@@ -0,0 +1,360 @@
+use std::{
+    collections::HashSet,
+    io,
+    path::{Path, PathBuf},
+    process::Stdio,
+    sync::RwLock,
+    time::Duration,
+};
+
+use lazy_static::lazy_static;
+use regex::Regex;
+use thiserror::Error;
+use tokio::{fs, process::Command, time::timeout};
+
+use crate::{config::JavaCfg, domain::java::JavaRuntime, prelude::*, state::AppState};
+
+lazy_static! {
+    static ref VERSION: Regex = Regex::new(r#"version "([^"]+)""#).unwrap();
+    static ref IMPLEMENTOR: Regex = Regex::new(r#"(?m)^IMPLEMENTOR="([^"]*)""#).unwrap();
+}
+
+/// `java -version` starts the JVM, which can be slow on a busy host.
+const PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where distributions, installers and vendors put JDKs.
+const COMMON_DIRS: [&str; 7] = [
+    "/usr/lib/jvm",
+    "/usr/lib64/jvm",
+    "/usr/java",
+    "/usr/local/java",
+    "/opt/java",
+    "/opt/jdk",
+    "/Library/Java/JavaVirtualMachines",
+];
+
+/// The same for tools installing per user, relative to `HOME`.
+const HOME_DIRS: [&str; 2] = [".sdkman/candidates/java", ".jdks"];
+
+#[derive(Debug, Error)]
+pub enum JavaError {
+    #[error("run {0}: {1}")]
+    Run(String, #[source] io::Error),
+    #[error("{0} did not answer in time")]
+    Timeout(String),
+    #[error("{0} did not report a version")]
+    Version(String),
+}
+
+/// Runtimes found by the last scan, strongest first.
+#[derive(Default)]
+pub struct JavaRuntimes {
+    runtimes: RwLock<Vec<JavaRuntime>>,
+}
+
+impl JavaRuntimes {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn list(&self) -> Vec<JavaRuntime> {
+        self.runtimes
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn replace(&self, runtimes: Vec<JavaRuntime>) {
+        *self.runtimes.write().unwrap_or_else(|e| e.into_inner()) = runtimes;
+    }
+}
+
+pub async fn init(state: &AppState) {
+    scan(state).await;
+}
+
+/// Looks for Java installations again and replaces the known ones.
+pub async fn scan(state: &AppState) -> Vec<JavaRuntime> {
+    debug!("scan java runtimes started");
+    let mut runtimes: Vec<JavaRuntime> = Vec::new();
+    for candidate in candidates(&state.config.java).await {
+        let candidate = candidate.to_string_lossy();
+        match probe(&candidate).await {
+            Ok(runtime) => {
+                if !runtimes.iter().any(|known| known.path == runtime.path) {
+                    runtimes.push(runtime);
+                }
+            }
+            Err(e) => debug!(error = %e, "skipping java candidate"),
+        }
+    }
+    runtimes.sort_by(|a, b| b.major.cmp(&a.major).then_with(|| a.path.cmp(&b.path)));
+
+    for runtime in &runtimes {
+        info!(
+            path = runtime.path,
+            version = runtime.version,
+            "java runtime found"
+        );
+    }
+    if runtimes.is_empty() {
+        warn!("no java runtime found");
+    }
+    state.java.replace(runtimes.clone());
+    runtimes
+}
+
+/// Runs `java_path -version` and describes the runtime behind it. A bare
+/// command name is looked up in the `PATH` like the server launch would.
+pub async fn probe(java_path: &str) -> Result<JavaRuntime, JavaError> {
+    let output = Command::new(java_path)
+        .arg("-version")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .kill_on_drop(true)
+        .output();
+    let output = timeout(PROBE_TIMEOUT, output)
+        .await
+        .map_err(|_| JavaError::Timeout(java_path.to_string()))?
+        .map_err(|e| JavaError::Run(java_path.to_string(), e))?;
+
+    // The version goes to stderr, some wrappers print it to stdout
+    let text = format!(
+        "{}{}",
+        String::from_utf8_lossy(&output.stderr),
+        String::from_utf8_lossy(&output.stdout)
+    );
+    let version = VERSION
+        .captures(&text)
+        .map(|captures| captures[1].to_string())
+        .ok_or_else(|| JavaError::Version(java_path.to_string()))?;
+    let major = major(&version).ok_or_else(|| JavaError::Version(java_path.to_string()))?;
+
+    let path = resolve(java_path)
+        .await
+        .unwrap_or_else(|| PathBuf::from(java_path));
+    let home = path
+        .parent()
+        .and_then(Path::parent)
+        .map(Path::to_path_buf)
+        .unwrap_or_default();
+    let vendor = fs::read_to_string(home.join("release"))
+        .await
+        .ok()
+        .and_then(|release| {
+            IMPLEMENTOR
+                .captures(&release)
+                .map(|captures| captures[1].to_string())
+        })
+        .filter(|vendor| !vendor.is_empty());
+
+    Ok(JavaRuntime {
+        path: path.to_string_lossy().into_owned(),
+        home: home.to_string_lossy().into_owned(),
+        version,
+        major,
+        vendor,
+    })
+}
+
+/// Feature release of a version string, the second number for the `1.x`
+/// scheme Java 8 and older use.
+fn major(version: &str) -> Option<u32> {
+    let mut numbers = version
+        .split(['.', '_', '-', '+'])
+        .map(|part| part.parse::<u32>().ok());
+    match numbers.next()?? {
+        1 => numbers.next()?,
+        major => Some(major),
+    }
+}
+
+/// The binary `java_path` runs, with symlinks resolved.
+async fn resolve(java_path: &str) -> Option<PathBuf> {
+    if java_path.contains('/') {
+        return fs::canonicalize(java_path).await.ok();
+    }
+    for dir in std::env::split_paths(&std::env::var_os("PATH")?) {
+        if let Ok(path) = fs::canonicalize(dir.join(java_path)).await {
+            return Some(path);
+        }
+    }
+    None
+}
+
+/// `java` binaries worth probing, duplicates removed.
+async fn candidates(cfg: &JavaCfg) -> Vec<PathBuf> {
+    let mut roots: Vec<PathBuf> = cfg.search_dirs.clone();
+    if let Some(java_home) = std::env::var_os("JAVA_HOME") {
+        roots.push(PathBuf::from(java_home));
+    }
+    roots.extend(COMMON_DIRS.iter().map(PathBuf::from));
+    if let Some(home) = std::env::var_os("HOME") {
+        roots.extend(HOME_DIRS.iter().map(|dir| Path::new(&home).join(dir)));
+    }
+
+    let mut binaries: Vec<PathBuf> = Vec::new();
+    if let Some(path) = resolve("java").await {
+        binaries.push(path);
+    }
+    for root in roots {
+        binaries.extend(installations(&root).await);
+    }
+
+    let mut seen = HashSet::new();
+    binaries.retain(|binary| seen.insert(binary.clone()));
+    binaries
+}
+
+/// The `java` binary of `root` if it is an installation, otherwise those of
+/// the installations directly inside it.
+async fn installations(root: &Path) -> Vec<PathBuf> {
+    if let Some(binary) = java_binary(root).await {
+        return vec![binary];
+    }
+
+    let mut binaries = Vec::new();
+    let Ok(mut entries) = fs::read_dir(root).await else {
+        return binaries;
+    };
+    while let Ok(Some(entry)) = entries.next_entry().await {
+        let dir = entry.path();
+        // macOS bundles keep the installation under Contents/Home
+        for home in [dir.clone(), dir.join("Contents/Home")] {
+            if let Some(binary) = java_binary(&home).await {
+                binaries.push(binary);
+                break;
+            }
+        }
+    }
+    binaries
+}
+
+async fn java_binary(home: &Path) -> Option<PathBuf> {
+    let binary = fs::canonicalize(home.join("bin/java")).await.ok()?;
+    fs::metadata(&binary)
+        .await
+        .is_ok_and(|metadata| metadata.is_file())
+        .then_some(binary)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::os::unix::fs::PermissionsExt;
+
+    use tempfile::TempDir;
+
+    use super::*;
+
+    /// An installation at `home` below `dir` whose `java -version` prints
+    /// `output`, to stderr like the real one unless `stdout`.
+    fn jdk(dir: &TempDir, home: &str, output: &str, stdout: bool) -> PathBuf {
+        let home = dir.path().join(home);
+        std::fs::create_dir_all(home.join("bin")).unwrap();
+        let binary = home.join("bin/java");
+        let redirect = if stdout { "" } else { " >&2" };
+        std::fs::write(
+            &binary,
+            format!("#!/bin/sh\ncat{redirect} <<'EOF'\n{output}\nEOF\n"),
+        )
+        .unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+        binary
+    }
+
+    const OPENJDK_21: &str = r#"openjdk version "21.0.2" 2024-01-16 LTS
+OpenJDK Runtime Environment Temurin-21.0.2+13 (build 21.0.2+13-LTS)
+OpenJDK 64-Bit Server VM Temurin-21.0.2+13 (build 21.0.2+13-LTS, mixed mode, sharing)"#;
+
+    const JAVA_8: &str = r#"openjdk version "1.8.0_392"
+OpenJDK Runtime Environment (Temurin)(build 1.8.0_392-b08)
+OpenJDK 64-Bit Server VM (Temurin)(build 25.392-b08, mixed mode)"#;
+
+    #[test]
+    fn major_reads_both_version_schemes() {
+        assert_eq!(major("21.0.2"), Some(21));
+        assert_eq!(major("17"), Some(17));
+        assert_eq!(major("22-ea"), Some(22));
+        assert_eq!(major("25+36"), Some(25));
+        assert_eq!(major("1.8.0_392"), Some(8));
+        assert_eq!(major("1.7.0_80"), Some(7));
+        assert_eq!(major("1"), None);
+        assert_eq!(major("unknown"), None);
+        assert_eq!(major(""), None);
+    }
+
+    #[tokio::test]
+    async fn probe_describes_the_runtime() {
+        let scratch = tempfile::tempdir().unwrap();
+        let binary = jdk(&scratch, "jdk-21.0.2", OPENJDK_21, false);
+        std::fs::write(
+            scratch.path().join("jdk-21.0.2/release"),
+            "JAVA_VERSION=\"21.0.2\"\nIMPLEMENTOR=\"Eclipse Adoptium\"\n",
+        )
+        .unwrap();
+
+        let runtime = probe(&binary.to_string_lossy()).await.unwrap();
+        assert_eq!(runtime.version, "21.0.2");
+        assert_eq!(runtime.major, 21);
+        assert_eq!(runtime.vendor.as_deref(), Some("Eclipse Adoptium"));
+        assert_eq!(
+            runtime.home,
+            std::fs::canonicalize(scratch.path().join("jdk-21.0.2"))
+                .unwrap()
+                .to_string_lossy()
+        );
+    }
+
+    #[tokio::test]
+    async fn probe_reads_legacy_versions_and_wrappers_printing_to_stdout() {
+        let scratch = tempfile::tempdir().unwrap();
+        let binary = jdk(&scratch, "jdk8u392", JAVA_8, true);
+
+        let runtime = probe(&binary.to_string_lossy()).await.unwrap();
+        assert_eq!(runtime.version, "1.8.0_392");
+        assert_eq!(runtime.major, 8);
+        assert_eq!(runtime.vendor, None);
+    }
+
+    #[tokio::test]
+    async fn probe_fails_without_a_version() {
+        let scratch = tempfile::tempdir().unwrap();
+        let binary = jdk(
+            &scratch,
+            "broken",
+            "Error: could not find libjava.so",
+            false,
+        );
+        let result = probe(&binary.to_string_lossy()).await;
+        assert!(matches!(result, Err(JavaError::Version(_))));
+
+        let missing = scratch.path().join("missing/bin/java");
+        let result = probe(&missing.to_string_lossy()).await;
+        assert!(matches!(result, Err(JavaError::Run(..))));
+    }
+
+    #[tokio::test]
+    async fn installations_are_found_directly_or_one_level_down() {
+        let scratch = tempfile::tempdir().unwrap();
+        let jdk21 = jdk(&scratch, "jvm/jdk-21.0.2", OPENJDK_21, false);
+        let jdk8 = jdk(&scratch, "jvm/jdk8u392.jdk/Contents/Home", JAVA_8, false);
+        std::fs::create_dir_all(scratch.path().join("jvm/not-a-jdk/lib")).unwrap();
+
+        let jdk21 = std::fs::canonicalize(jdk21).unwrap();
+        let jdk8 = std::fs::canonicalize(jdk8).unwrap();
+        let mut found = installations(&scratch.path().join("jvm")).await;
+        found.sort();
+        let mut expected = vec![jdk21.clone(), jdk8];
+        expected.sort();
+        assert_eq!(found, expected);
+
+        let direct = installations(&scratch.path().join("jvm/jdk-21.0.2")).await;
+        assert_eq!(direct, vec![jdk21]);
+        assert!(
+            installations(&scratch.path().join("nowhere"))
+                .await
+                .is_empty()
+        );
+    }
+}
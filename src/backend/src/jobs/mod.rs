@@ -0,0 +1,382 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicBool, AtomicI64, Ordering},
+    },
+};
+
+use anyhow::Result;
+use chrono::Utc;
+use serde_json::Value;
+use sqlx::types::Json;
+use tokio::{
+    sync::{Semaphore, broadcast, watch},
+    task::JoinHandle,
+    time::{MissedTickBehavior, interval},
+};
+use uuid::Uuid;
+
+use crate::{
+    config::JobsCfg,
+    domain::job::{Job, JobKind, JobStatus, NewJob},
+    infra::db,
+    prelude::*,
+    state::AppState,
+};
+
+/// What the work of a job ends with: a result to record, or why it failed.
+pub type JobOutcome = std::result::Result<Option<Value>, String>;
+
+const CANCELLED: &str = "cancelled";
+
+/// Queues jobs per kind, tracks the ones not finished yet and streams their
+/// updates.
+pub struct JobManager {
+    cfg: JobsCfg,
+    slots: Mutex<HashMap<JobKind, Arc<Semaphore>>>,
+    active: Mutex<HashMap<Uuid, Arc<JobHandle>>>,
+    events: broadcast::Sender<Job>,
+}
+
+/// The running side of a job. Work reports progress and log lines through
+/// it and checks it for cancellation, blocking code included.
+pub struct JobHandle {
+    job: Mutex<Job>,
+    log_lines: usize,
+    /// Negative while unknown.
+    total: AtomicI64,
+    processed: AtomicI64,
+    changed: AtomicBool,
+    cancelled: watch::Sender<bool>,
+}
+
+impl JobManager {
+    pub fn new(cfg: JobsCfg) -> Self {
+        Self {
+            cfg,
+            slots: Mutex::new(HashMap::new()),
+            active: Mutex::new(HashMap::new()),
+            events: broadcast::channel(256).0,
+        }
+    }
+
+    /// Jobs not finished yet and every update from now on.
+    pub fn subscribe(&self) -> (Vec<Job>, broadcast::Receiver<Job>) {
+        let rx = self.events.subscribe();
+        let active = self.active.lock().unwrap_or_else(|e| e.into_inner());
+        let mut jobs: Vec<Job> = active.values().map(|handle| handle.snapshot()).collect();
+        jobs.sort_by_key(|job| job.created_at);
+        (jobs, rx)
+    }
+
+    /// Live state of a job not finished yet.
+    pub fn get(&self, job_uuid: Uuid) -> Option<Job> {
+        self.handle(job_uuid).map(|handle| handle.snapshot())
+    }
+
+    /// Asks a job to stop. A queued job never starts, a running one stops
+    /// once its work notices. `None` if the job is not active.
+    pub fn cancel(&self, job_uuid: Uuid) -> Option<Job> {
+        let handle = self.handle(job_uuid)?;
+        if !handle.cancelled.send_replace(true) {
+            handle.log("cancellation requested");
+        }
+        Some(handle.snapshot())
+    }
+
+    fn handle(&self, job_uuid: Uuid) -> Option<Arc<JobHandle>> {
+        self.active
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&job_uuid)
+            .cloned()
+    }
+
+    fn slots(&self, kind: JobKind) -> Arc<Semaphore> {
+        self.slots
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .entry(kind)
+            .or_insert_with(|| Arc::new(Semaphore::new(self.cfg.limit(kind))))
+            .clone()
+    }
+}
+
+impl JobHandle {
+    fn new(job: Job, log_lines: usize) -> Self {
+        Self {
+            job: Mutex::new(job),
+            log_lines: log_lines.max(1),
+            total: AtomicI64::new(-1),
+            processed: AtomicI64::new(0),
+            changed: AtomicBool::new(false),
+            cancelled: watch::channel(false).0,
+        }
+    }
+
+    /// A handle no manager knows about, for running job work in tests.
+    #[cfg(test)]
+    pub fn detached(kind: JobKind) -> Self {
+        Self::new(
+            Job {
+                uuid: Uuid::new_v4(),
+                kind,
+                server_uuid: None,
+                status: JobStatus::Running,
+                description: String::new(),
+                step: None,
+                total: None,
+                processed: 0,
+                log: Vec::new(),
+                result: None,
+                error: None,
+                cancellable: true,
+                created_by: None,
+                created_at: Utc::now(),
+                started_at: None,
+                finished_at: None,
+            },
+            1,
+        )
+    }
+
+    pub fn uuid(&self) -> Uuid {
+        self.lock().uuid
+    }
+
+    pub fn set_total(&self, total: u64) {
+        self.total.store(total as i64, Ordering::Relaxed);
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn advance(&self, done: u64) {
+        self.processed.fetch_add(done as i64, Ordering::Relaxed);
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    /// Moves on to `step`, which is logged as well.
+    pub fn step(&self, step: impl Into<String>) {
+        let step = step.into();
+        self.lock().step = Some(step.clone());
+        self.log(step);
+    }
+
+    pub fn log(&self, line: impl Into<String>) {
+        let mut job = self.lock();
+        let mut log = VecDeque::from(std::mem::take(&mut job.log));
+        log.push_back(line.into());
+        while log.len() > self.log_lines {
+            log.pop_front();
+        }
+        job.log = log.into();
+        self.changed.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        *self.cancelled.borrow()
+    }
+
+    /// Resolves once the job is cancelled, for async work to select on.
+    pub async fn cancelled(&self) {
+        let _ = self
+            .cancelled
+            .subscribe()
+            .wait_for(|cancelled| *cancelled)
+            .await;
+    }
+
+    /// Fails once the job was cancelled, for blocking work to bail out with.
+    pub fn check_cancelled(&self) -> io::Result<()> {
+        if self.is_cancelled() {
+            return Err(io::Error::other(CANCELLED));
+        }
+        Ok(())
+    }
+
+    fn snapshot(&self) -> Job {
+        let total = self.total.load(Ordering::Relaxed);
+        Job {
+            total: (total >= 0).then_some(total),
+            processed: self.processed.load(Ordering::Relaxed),
+            ..self.lock().clone()
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Job> {
+        self.job.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Records a job and runs `work` in the background once a slot of its kind
+/// is free. The handle resolves to the finished job, callers that do not care
+/// may drop it.
+pub async fn start<F, Fut>(
+    state: &Arc<AppState>,
+    new_job: NewJob,
+    work: F,
+) -> Result<(Job, JoinHandle<Job>)>
+where
+    F: FnOnce(Arc<JobHandle>) -> Fut + Send + 'static,
+    Fut: Future<Output = JobOutcome> + Send + 'static,
+{
+    let job = Job {
+        uuid: Uuid::new_v4(),
+        kind: new_job.kind,
+        server_uuid: new_job.server_uuid,
+        status: JobStatus::Queued,
+        description: new_job.description,
+        step: None,
+        total: None,
+        processed: 0,
+        log: Vec::new(),
+        result: None,
+        error: None,
+        cancellable: new_job.cancellable,
+        created_by: new_job.created_by,
+        created_at: Utc::now(),
+        started_at: None,
+        finished_at: None,
+    };
+    let job = db::job::create(&state.db_pool, &job).await?;
+
+    let handle = Arc::new(JobHandle::new(job.clone(), state.config.jobs.log_lines));
+    state
+        .jobs
+        .active
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(job.uuid, handle.clone());
+    let _ = state.jobs.events.send(job.clone());
+
+    info!(
+        job_uuid = %job.uuid,
+        kind = job.kind.as_str(),
+        description = job.description,
+        "job queued"
+    );
+    let task = tokio::spawn(run(state.clone(), handle, work));
+    Ok((job, task))
+}
+
+/// Marks the jobs a previous run of the daemon left unfinished as interrupted
+/// and drops old finished ones. What their work left behind is reconciled by
+/// the subsystem that ran it, backups and restores in [`crate::backup::init`].
+pub async fn init(state: &AppState) {
+    match db::job::interrupt_unfinished(&state.db_pool, "daemon stopped before the job finished")
+        .await
+    {
+        Ok(jobs) => {
+            for job in jobs {
+                warn!(job_uuid = %job.uuid, kind = job.kind.as_str(), "job interrupted by daemon shutdown");
+            }
+        }
+        Err(e) => error!(error = %e, "interrupt unfinished jobs failed"),
+    }
+    prune(state).await;
+}
+
+async fn run<F, Fut>(state: Arc<AppState>, handle: Arc<JobHandle>, work: F) -> Job
+where
+    F: FnOnce(Arc<JobHandle>) -> Fut + Send + 'static,
+    Fut: Future<Output = JobOutcome> + Send + 'static,
+{
+    let job_uuid = handle.uuid();
+    let kind = handle.lock().kind;
+    let slots = state.jobs.slots(kind);
+    let mut cancelled = handle.cancelled.subscribe();
+
+    let permit = tokio::select! {
+        permit = slots.acquire_owned() => permit.ok(),
+        _ = cancelled.wait_for(|cancelled| *cancelled) => None,
+    };
+    let outcome = match permit {
+        Some(permit) => {
+            {
+                let mut job = handle.lock();
+                job.status = JobStatus::Running;
+                job.started_at = Some(Utc::now());
+            }
+            save(&state, &handle).await;
+            debug!(job_uuid = %job_uuid, "job started");
+
+            // A task of its own so a panic fails the job instead of leaving
+            // it running forever
+            let mut work = tokio::spawn(work(handle.clone()));
+            let mut ticks = interval(state.config.jobs.progress_interval);
+            ticks.set_missed_tick_behavior(MissedTickBehavior::Skip);
+            let outcome = loop {
+                tokio::select! {
+                    outcome = &mut work => {
+                        break outcome.unwrap_or_else(|e| Err(format!("job task failed: {e}")));
+                    }
+                    _ = ticks.tick() => {
+                        if handle.changed.swap(false, Ordering::Relaxed) {
+                            save(&state, &handle).await;
+                        }
+                    }
+                }
+            };
+            drop(permit);
+            outcome
+        }
+        None => Err(CANCELLED.to_string()),
+    };
+
+    {
+        let mut job = handle.lock();
+        job.finished_at = Some(Utc::now());
+        match outcome {
+            Ok(result) => {
+                job.status = JobStatus::Completed;
+                job.result = result.map(Json);
+            }
+            Err(_) if handle.is_cancelled() => job.status = JobStatus::Cancelled,
+            Err(e) => {
+                job.status = JobStatus::Failed;
+                job.error = Some(e);
+            }
+        }
+    }
+    let job = save(&state, &handle).await;
+    state
+        .jobs
+        .active
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&job_uuid);
+
+    match job.status {
+        JobStatus::Completed => info!(job_uuid = %job_uuid, kind = kind.as_str(), "job completed"),
+        JobStatus::Cancelled => info!(job_uuid = %job_uuid, kind = kind.as_str(), "job cancelled"),
+        _ => warn!(
+            job_uuid = %job_uuid,
+            kind = kind.as_str(),
+            error = job.error.as_deref().unwrap_or_default(),
+            "job failed"
+        ),
+    }
+    prune(&state).await;
+    job
+}
+
+/// Persists and streams the current state of a job.
+async fn save(state: &AppState, handle: &JobHandle) -> Job {
+    let job = handle.snapshot();
+    if let Err(e) = db::job::update(&state.db_pool, &job).await {
+        error!(error = %e, job_uuid = %job.uuid, "save job failed");
+    }
+    let _ = state.jobs.events.send(job.clone());
+    job
+}
+
+async fn prune(state: &AppState) {
+    let cutoff =
+        Utc::now() - chrono::Duration::from_std(state.config.jobs.retention).unwrap_or_default();
+    match db::job::delete_finished_before(&state.db_pool, cutoff).await {
+        Ok(0) => {}
+        Ok(count) => debug!(job_count = count, "old jobs deleted"),
+        Err(e) => error!(error = %e, "delete old jobs failed"),
+    }
+}
@@ -1,8 +1,19 @@
 pub mod auth;
+pub mod backup;
 pub mod config;
 pub mod core;
+pub mod countdown;
+pub mod crash;
 pub mod domain;
+pub mod files;
 pub mod infra;
+pub mod installer;
+pub mod java;
+pub mod jobs;
+pub mod players;
 pub mod prelude;
 pub mod router;
+pub mod scheduler;
 pub mod state;
+pub mod status;
+pub mod supervisor;
@@ -0,0 +1,282 @@
+use std::{
+    collections::{HashMap, VecDeque, hash_map::Entry},
+    sync::{Arc, Mutex},
+};
+
+use chrono::Utc;
+use tokio::{
+    sync::{broadcast, watch},
+    task::JoinHandle,
+};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        console::{ConsoleLine, ConsoleStream},
+        players::{
+            LogEvent, OnlinePlayer, PlayerActivity, PlayerEvent, PlayerSession, ServerPlayers,
+        },
+        server::ProcessState,
+    },
+    infra::{db, minecraft::log},
+    prelude::*,
+    state::AppState,
+};
+
+const RECENT_EVENTS: usize = 100;
+
+#[derive(Default)]
+struct Roster {
+    online: HashMap<String, OnlinePlayer>,
+    /// UUID and address logged ahead of the join line.
+    pending: HashMap<String, (Option<Uuid>, Option<String>)>,
+    recent: VecDeque<PlayerEvent>,
+}
+
+impl Roster {
+    fn record(&mut self, activity: PlayerActivity) {
+        if self.recent.len() >= RECENT_EVENTS {
+            self.recent.pop_front();
+        }
+        self.recent.push_back(PlayerEvent {
+            activity,
+            at: Utc::now(),
+        });
+    }
+}
+
+/// Who is online on every server, rebuilt from the console output.
+#[derive(Default)]
+pub struct PlayerTracker {
+    rosters: Mutex<HashMap<Uuid, Roster>>,
+    tasks: Mutex<HashMap<Uuid, JoinHandle<()>>>,
+}
+
+impl PlayerTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn players(&self, uuid: Uuid) -> ServerPlayers {
+        let rosters = self.rosters.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(roster) = rosters.get(&uuid) else {
+            return ServerPlayers {
+                online: Vec::new(),
+                recent: Vec::new(),
+            };
+        };
+
+        let mut online: Vec<OnlinePlayer> = roster.online.values().cloned().collect();
+        online.sort_by_key(|player| player.joined_at);
+        ServerPlayers {
+            online,
+            recent: roster.recent.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Closes sessions left open by a previous daemon run and starts following
+/// every known server. Must run before any server is started.
+pub async fn init(state: Arc<AppState>) {
+    match db::player::close_open_sessions(&state.db_pool, None).await {
+        Ok(0) => {}
+        Ok(closed) => info!(closed, "closed player sessions left open by last run"),
+        Err(e) => error!(error = %e, "close stale player sessions failed"),
+    }
+
+    match db::server::get_all(&state.db_pool).await {
+        Ok(servers) => {
+            for server in servers {
+                watch(state.clone(), server.uuid).await;
+            }
+        }
+        Err(e) => error!(error = %e, "fetch servers for player tracking failed"),
+    }
+}
+
+/// Starts following the console of a server, once.
+pub async fn watch(state: Arc<AppState>, uuid: Uuid) {
+    if state
+        .players
+        .tasks
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .contains_key(&uuid)
+    {
+        return;
+    }
+
+    // Subscribe before spawning so no line logged in between is missed
+    let (_, lines) = state.supervisor.subscribe(uuid).await;
+    let process = state.supervisor.watch(uuid).await;
+    let task = tokio::spawn(track(state.clone(), uuid, lines, process));
+
+    let mut tasks = state
+        .players
+        .tasks
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    match tasks.entry(uuid) {
+        Entry::Occupied(_) => task.abort(),
+        Entry::Vacant(slot) => {
+            slot.insert(task);
+        }
+    }
+}
+
+/// Stops following a deleted server.
+pub fn forget(state: &AppState, uuid: Uuid) {
+    if let Some(task) = state
+        .players
+        .tasks
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&uuid)
+    {
+        task.abort();
+    }
+    state
+        .players
+        .rosters
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(&uuid);
+}
+
+async fn track(
+    state: Arc<AppState>,
+    uuid: Uuid,
+    mut lines: broadcast::Receiver<ConsoleLine>,
+    mut process: watch::Receiver<ProcessState>,
+) {
+    loop {
+        tokio::select! {
+            line = lines.recv() => match line {
+                Ok(line) if line.stream == ConsoleStream::Stdout => {
+                    handle_line(&state, uuid, &line.line).await;
+                }
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(server_uuid = %uuid, skipped, "player tracking fell behind the console");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            },
+            changed = process.changed() => {
+                if changed.is_err() {
+                    break;
+                }
+                if !process.borrow_and_update().is_alive() {
+                    close_all(&state, uuid).await;
+                }
+            }
+        }
+    }
+}
+
+async fn handle_line(state: &AppState, uuid: Uuid, line: &str) {
+    let event = {
+        let rosters = state
+            .players
+            .rosters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let online = rosters
+            .get(&uuid)
+            .map(|roster| roster.online.keys().map(String::as_str).collect::<Vec<_>>())
+            .unwrap_or_default();
+        log::parse_line(line, online)
+    };
+    let Some(event) = event else { return };
+
+    // Database work happens after the roster lock is released
+    let mut opened = None;
+    let mut closed = None;
+    {
+        let mut rosters = state
+            .players
+            .rosters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        let roster = rosters.entry(uuid).or_default();
+
+        match event {
+            LogEvent::Identified { name, uuid: player } => {
+                roster.pending.entry(name).or_default().0 = Some(player);
+            }
+            LogEvent::LoggedIn { name, ip } => {
+                roster.pending.entry(name).or_default().1 = Some(ip);
+            }
+            LogEvent::Joined { name } => {
+                let (player_uuid, ip) = roster.pending.remove(&name).unwrap_or_default();
+                let player = OnlinePlayer {
+                    name: name.clone(),
+                    uuid: player_uuid,
+                    ip,
+                    joined_at: Utc::now(),
+                    session: Uuid::new_v4(),
+                };
+                opened = Some(PlayerSession {
+                    uuid: player.session,
+                    server_uuid: uuid,
+                    player_uuid: player.uuid,
+                    player_name: player.name.clone(),
+                    ip: player.ip.clone(),
+                    joined_at: player.joined_at,
+                    left_at: None,
+                });
+                // A rejoin without a leave line replaces the stale session
+                closed = roster
+                    .online
+                    .insert(name.clone(), player)
+                    .map(|p| p.session);
+                roster.record(PlayerActivity::Join { name });
+            }
+            LogEvent::Left { name } => {
+                closed = roster.online.remove(&name).map(|p| p.session);
+                roster.record(PlayerActivity::Leave { name });
+            }
+            LogEvent::Chat { name, message } => {
+                roster.record(PlayerActivity::Chat { name, message });
+            }
+            LogEvent::Death { name, message } => {
+                roster.record(PlayerActivity::Death { name, message });
+            }
+            LogEvent::Advancement { name, advancement } => {
+                roster.record(PlayerActivity::Advancement { name, advancement });
+            }
+        }
+    }
+
+    if let Some(session) = closed
+        && let Err(e) = db::player::close_session(&state.db_pool, session, Utc::now()).await
+    {
+        error!(error = %e, server_uuid = %uuid, "close player session failed");
+    }
+    if let Some(session) = opened {
+        info!(server_uuid = %uuid, player = session.player_name, "player joined");
+        if let Err(e) = db::player::create_session(&state.db_pool, &session).await {
+            error!(error = %e, server_uuid = %uuid, "create player session failed");
+        }
+    }
+}
+
+/// Everyone is gone once the process exits, whether or not it logged it.
+async fn close_all(state: &AppState, uuid: Uuid) {
+    {
+        let mut rosters = state
+            .players
+            .rosters
+            .lock()
+            .unwrap_or_else(|e| e.into_inner());
+        if let Some(roster) = rosters.get_mut(&uuid) {
+            roster.online.clear();
+            roster.pending.clear();
+        }
+    }
+
+    match db::player::close_open_sessions(&state.db_pool, Some(uuid)).await {
+        Ok(0) => {}
+        Ok(closed) => debug!(server_uuid = %uuid, closed, "closed player sessions on exit"),
+        Err(e) => error!(error = %e, server_uuid = %uuid, "close player sessions failed"),
+    }
+}
@@ -0,0 +1,130 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core::backup_routines,
+    domain::{
+        backup::{Backup, BackupVerification, NewBackup, RestoreRequest, RetentionPolicy},
+        job::Job,
+    },
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Request, State},
+    http::{HeaderValue, StatusCode, header::CONTENT_DISPOSITION},
+    response::{IntoResponse, Response},
+};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use uuid::Uuid;
+
+pub async fn get_backups(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<Backup>>, StatusCode> {
+    debug!(server_uuid = %uuid, "list backups route started");
+    let backups = backup_routines::list(state, uuid).await?;
+    debug!(backup_count = backups.len(), "list backups route completed");
+    Ok(Json(backups))
+}
+
+pub async fn create_backup(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    request: Option<Json<NewBackup>>,
+) -> Result<(StatusCode, Json<Backup>), StatusCode> {
+    debug!(server_uuid = %uuid, "create backup route started");
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let (backup, _) = backup_routines::create(state, uuid, Some(user.uuid), request).await?;
+    info!("create backup route completed");
+    Ok((StatusCode::ACCEPTED, Json(backup)))
+}
+
+pub async fn get_backup(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, backup_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Backup>, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "get backup route started");
+    let backup = backup_routines::get(state, uuid, backup_uuid).await?;
+    debug!("get backup route completed");
+    Ok(Json(backup))
+}
+
+pub async fn restore_backup(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, backup_uuid)): Path<(Uuid, Uuid)>,
+    request: Option<Json<RestoreRequest>>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "restore backup route started");
+    let request = request.map(|Json(request)| request).unwrap_or_default();
+    let job = backup_routines::restore(state, &user, uuid, backup_uuid, request).await?;
+    info!(job_uuid = %job.uuid, "restore backup route completed");
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn verify_backup(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, backup_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<BackupVerification>, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "verify backup route started");
+    let verification = backup_routines::verify(state, uuid, backup_uuid).await?;
+    debug!(ok = verification.ok, "verify backup route completed");
+    Ok(Json(verification))
+}
+
+pub async fn delete_backup(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, backup_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "delete backup route started");
+    backup_routines::delete(state, uuid, backup_uuid).await?;
+    info!("delete backup route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn download_backup(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, backup_uuid)): Path<(Uuid, Uuid)>,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "download backup route started");
+    let (path, file_name) = backup_routines::download(state, uuid, backup_uuid).await?;
+
+    // ServeFile takes care of ranges so large archives can be resumed
+    let Ok(response) = ServeFile::new(path).oneshot(request).await;
+    let mut response = response.into_response();
+    if response.status().is_success() {
+        let disposition = HeaderValue::from_str(&format!("attachment; filename=\"{file_name}\""))
+            .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+        response
+            .headers_mut()
+            .insert(CONTENT_DISPOSITION, disposition);
+    }
+
+    debug!("download backup route completed");
+    Ok(response)
+}
+
+pub async fn get_retention(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<RetentionPolicy>, StatusCode> {
+    debug!(server_uuid = %uuid, "get backup retention route started");
+    let policy = backup_routines::get_retention(state, uuid).await?;
+    debug!("get backup retention route completed");
+    Ok(Json(policy))
+}
+
+pub async fn set_retention(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Json(policy): Json<RetentionPolicy>,
+) -> Result<Json<RetentionPolicy>, StatusCode> {
+    debug!(server_uuid = %uuid, "set backup retention route started");
+    let policy = backup_routines::set_retention(state, uuid, policy).await?;
+    info!("set backup retention route completed");
+    Ok(Json(policy))
+}
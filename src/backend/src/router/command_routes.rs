@@ -0,0 +1,76 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core,
+    domain::command::{
+        CommandEntry, CommandOutcome, CommandRequest, CommandRule, CommandSource, HistoryQuery,
+        NewCommandRule,
+    },
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn execute(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<CommandRequest>,
+) -> Result<Json<CommandOutcome>, StatusCode> {
+    debug!(server_uuid = %uuid, "execute command route started");
+    let outcome =
+        core::command_routines::execute(state, &user, uuid, request, CommandSource::Api).await?;
+    info!("execute command route completed");
+    Ok(Json(outcome))
+}
+
+pub async fn history(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<HistoryQuery>,
+) -> Result<Json<Vec<CommandEntry>>, StatusCode> {
+    debug!(server_uuid = %uuid, "command history route started");
+    let entries = core::command_routines::history(state, uuid, query.limit).await?;
+    debug!(
+        entry_count = entries.len(),
+        "command history route completed"
+    );
+    Ok(Json(entries))
+}
+
+pub async fn get_rules(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<CommandRule>>, StatusCode> {
+    debug!("list command rules route started");
+    let rules = core::command_routines::get_rules(state).await?;
+    debug!(
+        rule_count = rules.len(),
+        "list command rules route completed"
+    );
+    Ok(Json(rules))
+}
+
+pub async fn create_rule(
+    State(state): State<Arc<AppState>>,
+    Json(new_rule): Json<NewCommandRule>,
+) -> Result<Json<CommandRule>, StatusCode> {
+    debug!("create command rule route started");
+    let rule = core::command_routines::create_rule(state, new_rule).await?;
+    info!("create command rule route completed");
+    Ok(Json(rule))
+}
+
+pub async fn delete_rule(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(rule_uuid = %uuid, "delete command rule route started");
+    core::command_routines::delete_rule(state, uuid).await?;
+    info!("delete command rule route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
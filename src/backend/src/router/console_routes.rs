@@ -0,0 +1,134 @@
+use crate::{
+    domain::{
+        command::{CommandRequest, CommandSource},
+        console::{ConsoleEvent, ConsoleInput, ConsoleLine},
+        user::InternalUser,
+    },
+    prelude::*,
+};
+use std::sync::Arc;
+
+use crate::{core, state::AppState};
+use axum::{
+    Extension,
+    extract::{
+        Path, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::Response,
+};
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+pub async fn console(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    ws: WebSocketUpgrade,
+) -> Result<Response, StatusCode> {
+    debug!(server_uuid = %uuid, "console route started");
+    let (scrollback, rx) = core::console_routines::subscribe(state.clone(), uuid).await?;
+    Ok(ws.on_upgrade(move |socket| stream(state, user, uuid, socket, scrollback, rx)))
+}
+
+async fn stream(
+    state: Arc<AppState>,
+    user: InternalUser,
+    uuid: Uuid,
+    socket: WebSocket,
+    scrollback: Vec<ConsoleLine>,
+    mut rx: broadcast::Receiver<ConsoleLine>,
+) {
+    debug!(server_uuid = %uuid, username = user.username, "console client connected");
+    let (mut sender, mut receiver) = socket.split();
+
+    for line in scrollback {
+        if send(&mut sender, ConsoleEvent::Line(line)).await.is_err() {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            line = rx.recv() => {
+                let event = match line {
+                    Ok(line) => ConsoleEvent::Line(line),
+                    Err(RecvError::Lagged(skipped)) => ConsoleEvent::Lagged { skipped },
+                    Err(RecvError::Closed) => break,
+                };
+                if send(&mut sender, event).await.is_err() {
+                    break;
+                }
+            }
+            msg = receiver.next() => {
+                let text = match msg {
+                    Some(Ok(Message::Text(text))) => text,
+                    Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                    Some(Ok(_)) => continue,
+                };
+                let event = handle_input(state.clone(), &user, uuid, &text).await;
+                if send(&mut sender, event).await.is_err() {
+                    break;
+                }
+            }
+        }
+    }
+
+    debug!(server_uuid = %uuid, username = user.username, "console client disconnected");
+}
+
+async fn handle_input(
+    state: Arc<AppState>,
+    user: &InternalUser,
+    uuid: Uuid,
+    text: &str,
+) -> ConsoleEvent {
+    let input = match serde_json::from_str::<ConsoleInput>(text) {
+        Ok(input) => input,
+        Err(e) => {
+            return ConsoleEvent::Error {
+                status: StatusCode::BAD_REQUEST.as_u16(),
+                message: e.to_string(),
+            };
+        }
+    };
+
+    match input {
+        ConsoleInput::Command { command } => {
+            let request = CommandRequest { command };
+            match core::command_routines::execute(
+                state,
+                user,
+                uuid,
+                request,
+                CommandSource::Console,
+            )
+            .await
+            {
+                Ok(outcome) => ConsoleEvent::Command(outcome),
+                Err(status) => ConsoleEvent::Error {
+                    status: status.as_u16(),
+                    message: status
+                        .canonical_reason()
+                        .unwrap_or("command failed")
+                        .to_string(),
+                },
+            }
+        }
+    }
+}
+
+async fn send<S>(sender: &mut S, event: ConsoleEvent) -> Result<(), ()>
+where
+    S: SinkExt<Message> + Unpin,
+{
+    let text = serde_json::to_string(&event).map_err(|e| {
+        error!(error = %e, "serialize console event failed");
+    })?;
+    sender
+        .send(Message::Text(text.into()))
+        .await
+        .map_err(|_| ())
+}
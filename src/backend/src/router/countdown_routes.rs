@@ -0,0 +1,45 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    core::countdown_routines,
+    domain::countdown::{Countdown, CountdownRequest},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn begin(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<CountdownRequest>,
+) -> Result<Json<Countdown>, StatusCode> {
+    debug!(server_uuid = %uuid, "begin countdown route started");
+    let (countdown, _) = countdown_routines::begin(state, uuid, request).await?;
+    info!("begin countdown route completed");
+    Ok(Json(countdown))
+}
+
+pub async fn get_countdown(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Countdown>, StatusCode> {
+    debug!(server_uuid = %uuid, "get countdown route started");
+    let countdown = countdown_routines::get(state, uuid).await?;
+    debug!("get countdown route completed");
+    Ok(Json(countdown))
+}
+
+pub async fn cancel(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Countdown>, StatusCode> {
+    debug!(server_uuid = %uuid, "cancel countdown route started");
+    let countdown = countdown_routines::cancel(state, uuid).await?;
+    info!("cancel countdown route completed");
+    Ok(Json(countdown))
+}
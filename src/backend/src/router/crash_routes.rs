@@ -0,0 +1,35 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    core::crash_routines,
+    domain::crash::{CrashQuery, CrashRecord, CrashSummary},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_crashes(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<CrashQuery>,
+) -> Result<Json<Vec<CrashSummary>>, StatusCode> {
+    debug!(server_uuid = %uuid, "get crashes route started");
+    let crashes = crash_routines::list(state, uuid, query.limit).await?;
+    debug!(crash_count = crashes.len(), "get crashes route completed");
+    Ok(Json(crashes))
+}
+
+pub async fn get_crash(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, crash_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<CrashRecord>, StatusCode> {
+    debug!(server_uuid = %uuid, crash_uuid = %crash_uuid, "get crash route started");
+    let crash = crash_routines::get(state, uuid, crash_uuid).await?;
+    debug!(crash_uuid = %crash.uuid, "get crash route completed");
+    Ok(Json(crash))
+}
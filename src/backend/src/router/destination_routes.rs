@@ -0,0 +1,82 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    core::destination_routines,
+    domain::destination::{BackupCopy, BackupDestination, NewDestination, UpdateDestination},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_destinations(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<BackupDestination>>, StatusCode> {
+    debug!(server_uuid = %uuid, "list backup destinations route started");
+    let destinations = destination_routines::list(state, uuid).await?;
+    debug!(
+        destination_count = destinations.len(),
+        "list backup destinations route completed"
+    );
+    Ok(Json(destinations))
+}
+
+pub async fn create_destination(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Json(new_destination): Json<NewDestination>,
+) -> Result<Json<BackupDestination>, StatusCode> {
+    debug!(server_uuid = %uuid, "create backup destination route started");
+    let destination = destination_routines::create(state, uuid, new_destination).await?;
+    info!("create backup destination route completed");
+    Ok(Json(destination))
+}
+
+pub async fn get_destination(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, destination_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<BackupDestination>, StatusCode> {
+    debug!(destination_uuid = %destination_uuid, "get backup destination route started");
+    let destination = destination_routines::get(state, uuid, destination_uuid).await?;
+    debug!("get backup destination route completed");
+    Ok(Json(destination))
+}
+
+pub async fn update_destination(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, destination_uuid)): Path<(Uuid, Uuid)>,
+    Json(update): Json<UpdateDestination>,
+) -> Result<Json<BackupDestination>, StatusCode> {
+    debug!(destination_uuid = %destination_uuid, "update backup destination route started");
+    let destination = destination_routines::update(state, uuid, destination_uuid, update).await?;
+    info!("update backup destination route completed");
+    Ok(Json(destination))
+}
+
+pub async fn delete_destination(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, destination_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(destination_uuid = %destination_uuid, "delete backup destination route started");
+    destination_routines::delete(state, uuid, destination_uuid).await?;
+    info!("delete backup destination route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_copies(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, backup_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<Vec<BackupCopy>>, StatusCode> {
+    debug!(backup_uuid = %backup_uuid, "list backup copies route started");
+    let copies = destination_routines::copies(state, uuid, backup_uuid).await?;
+    debug!(
+        copy_count = copies.len(),
+        "list backup copies route completed"
+    );
+    Ok(Json(copies))
+}
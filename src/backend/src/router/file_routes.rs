@@ -0,0 +1,267 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core::file_routines,
+    domain::{
+        file::{
+            CompressRequest, DeleteQuery, DirListing, ExtractRequest, FileContent, FileRule,
+            NewDirectory, NewFileRule, NewUpload, PathQuery, RenameRequest, UploadQuery,
+            UploadStatus, UploadedFile, WriteFile,
+        },
+        job::Job,
+    },
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    body::Body,
+    extract::{Multipart, Path, Query, Request, State},
+    http::{HeaderMap, HeaderValue, StatusCode, header::CONTENT_DISPOSITION},
+    response::{IntoResponse, Response},
+};
+use tower::ServiceExt;
+use tower_http::services::ServeFile;
+use uuid::Uuid;
+
+/// Offset a resumable upload chunk starts at.
+pub const UPLOAD_OFFSET: &str = "upload-offset";
+
+pub async fn list_files(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<DirListing>, StatusCode> {
+    debug!(server_uuid = %uuid, "list files route started");
+    let listing = file_routines::list(state, &user, uuid, &query.path).await?;
+    debug!(
+        entry_count = listing.entries.len(),
+        "list files route completed"
+    );
+    Ok(Json(listing))
+}
+
+pub async fn delete_file(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<DeleteQuery>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(server_uuid = %uuid, "delete file route started");
+    file_routines::delete(state, &user, uuid, query).await?;
+    info!("delete file route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn read_file(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<PathQuery>,
+) -> Result<Json<FileContent>, StatusCode> {
+    debug!(server_uuid = %uuid, "read file route started");
+    let content = file_routines::read(state, &user, uuid, &query.path).await?;
+    debug!(binary = content.binary, "read file route completed");
+    Ok(Json(content))
+}
+
+pub async fn write_file(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<WriteFile>,
+) -> Result<Json<FileContent>, StatusCode> {
+    debug!(server_uuid = %uuid, "write file route started");
+    let content = file_routines::write(state, &user, uuid, request).await?;
+    info!("write file route completed");
+    Ok(Json(content))
+}
+
+pub async fn create_dir(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<NewDirectory>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(server_uuid = %uuid, "create directory route started");
+    file_routines::create_dir(state, &user, uuid, request).await?;
+    info!("create directory route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn rename_file(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<RenameRequest>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(server_uuid = %uuid, "rename file route started");
+    file_routines::rename(state, &user, uuid, request).await?;
+    info!("rename file route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn download_file(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<PathQuery>,
+    request: Request,
+) -> Result<Response, StatusCode> {
+    debug!(server_uuid = %uuid, "download file route started");
+    let (path, file_name) = file_routines::download(state, &user, uuid, &query.path).await?;
+
+    let Ok(response) = ServeFile::new(path).oneshot(request).await;
+    let mut response = response.into_response();
+    if response.status().is_success() {
+        response
+            .headers_mut()
+            .insert(CONTENT_DISPOSITION, attachment(&file_name));
+    }
+
+    debug!("download file route completed");
+    Ok(response)
+}
+
+pub async fn upload_files(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Query(query): Query<UploadQuery>,
+    multipart: Multipart,
+) -> Result<Json<Vec<UploadedFile>>, StatusCode> {
+    debug!(server_uuid = %uuid, "upload files route started");
+    let uploaded = file_routines::upload(state, &user, uuid, query, multipart).await?;
+    info!(file_count = uploaded.len(), "upload files route completed");
+    Ok(Json(uploaded))
+}
+
+pub async fn start_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<NewUpload>,
+) -> Result<Json<UploadStatus>, StatusCode> {
+    debug!(server_uuid = %uuid, "start file upload route started");
+    let status = file_routines::start_upload(state, &user, uuid, request).await?;
+    info!(upload_uuid = %status.upload.uuid, "start file upload route completed");
+    Ok(Json(status))
+}
+
+pub async fn get_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, upload_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<UploadStatus>, StatusCode> {
+    debug!(upload_uuid = %upload_uuid, "get file upload route started");
+    let status = file_routines::upload_status(state, &user, uuid, upload_uuid).await?;
+    debug!(
+        received = status.received,
+        "get file upload route completed"
+    );
+    Ok(Json(status))
+}
+
+pub async fn append_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, upload_uuid)): Path<(Uuid, Uuid)>,
+    headers: HeaderMap,
+    body: Body,
+) -> Result<Json<UploadStatus>, StatusCode> {
+    debug!(upload_uuid = %upload_uuid, "append file upload route started");
+    let offset = headers
+        .get(UPLOAD_OFFSET)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .ok_or(StatusCode::BAD_REQUEST)?;
+    let status = file_routines::append(state, &user, uuid, upload_uuid, offset, body).await?;
+    debug!(
+        received = status.received,
+        completed = status.completed,
+        "append file upload route completed"
+    );
+    Ok(Json(status))
+}
+
+pub async fn cancel_upload(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, upload_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(upload_uuid = %upload_uuid, "cancel file upload route started");
+    file_routines::cancel_upload(state, &user, uuid, upload_uuid).await?;
+    info!("cancel file upload route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn compress_files(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<CompressRequest>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    debug!(server_uuid = %uuid, "compress files route started");
+    let job = file_routines::compress(state, &user, uuid, request).await?;
+    info!(job_uuid = %job.uuid, "compress files route completed");
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn extract_archive(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<ExtractRequest>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    debug!(server_uuid = %uuid, "extract archive route started");
+    let job = file_routines::extract(state, &user, uuid, request).await?;
+    info!(job_uuid = %job.uuid, "extract archive route completed");
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
+
+pub async fn get_rules(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<FileRule>>, StatusCode> {
+    debug!(server_uuid = %uuid, "list file rules route started");
+    let rules = file_routines::get_rules(state, uuid).await?;
+    debug!(rule_count = rules.len(), "list file rules route completed");
+    Ok(Json(rules))
+}
+
+pub async fn create_rule(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Json(new_rule): Json<NewFileRule>,
+) -> Result<Json<FileRule>, StatusCode> {
+    debug!(server_uuid = %uuid, "create file rule route started");
+    let rule = file_routines::create_rule(state, uuid, new_rule).await?;
+    info!(rule_uuid = %rule.uuid, "create file rule route completed");
+    Ok(Json(rule))
+}
+
+pub async fn delete_rule(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, rule_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(rule_uuid = %rule_uuid, "delete file rule route started");
+    file_routines::delete_rule(state, uuid, rule_uuid).await?;
+    info!("delete file rule route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Header values are limited to visible ASCII, other characters in the name
+/// are replaced.
+fn attachment(file_name: &str) -> HeaderValue {
+    let name: String = file_name
+        .chars()
+        .map(|c| match c {
+            '"' | '\\' => '_',
+            c if c.is_ascii_graphic() || c == ' ' => c,
+            _ => '_',
+        })
+        .collect();
+    HeaderValue::from_str(&format!("attachment; filename=\"{name}\""))
+        .unwrap_or_else(|_| HeaderValue::from_static("attachment"))
+}
@@ -0,0 +1,389 @@
+use crate::{config::FrontendSource, prelude::*};
+
+use axum::{
+    Router,
+    extract::Request,
+    handler::HandlerWithoutStateExt,
+    http::{
+        HeaderValue, StatusCode,
+        header::{CACHE_CONTROL, CONTENT_TYPE},
+    },
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+};
+use tower::ServiceExt;
+use tower_http::services::{ServeDir, ServeFile};
+
+const IMMUTABLE: &str = "public, max-age=31536000, immutable";
+const REVALIDATE: &str = "no-cache";
+const SHORT_LIVED: &str = "public, max-age=3600";
+
+/// Builds the service answering every request no API route matched, or `None`
+/// when the daemon is not configured to serve the web UI.
+pub fn service(source: &FrontendSource) -> Option<Router> {
+    let router = match source {
+        FrontendSource::Disabled => {
+            debug!("frontend serving disabled");
+            return None;
+        }
+        FrontendSource::Directory(dir) => {
+            info!(dir = %dir.display(), "serving frontend from directory");
+            let index = ServeFile::new(dir.join("index.html"))
+                .precompressed_br()
+                .precompressed_gzip();
+            let fallback = move |req: Request| async move {
+                if !is_client_route(req.uri().path()) {
+                    return StatusCode::NOT_FOUND.into_response();
+                }
+                index.oneshot(req).await.into_response()
+            };
+            let serve_dir = ServeDir::new(dir)
+                .precompressed_br()
+                .precompressed_gzip()
+                .fallback(fallback.into_service());
+            Router::new().fallback_service(serve_dir)
+        }
+        #[cfg(feature = "embed-frontend")]
+        FrontendSource::Embedded => {
+            info!("serving embedded frontend");
+            Router::new().fallback(embedded::serve)
+        }
+    };
+
+    Some(router.layer(middleware::from_fn(headers)))
+}
+
+/// Whether a path no file matched is a client side route to answer with
+/// index.html. Missing assets, e.g. from a build replaced by a redeploy, get
+/// a 404 instead of the page served as a script or stylesheet.
+fn is_client_route(path: &str) -> bool {
+    !path.starts_with("/assets/")
+        && !path
+            .rsplit('/')
+            .next()
+            .is_some_and(|name| name.contains('.'))
+}
+
+async fn headers(req: Request, next: Next) -> Response {
+    let path = req.uri().path().to_string();
+
+    // Unmatched API calls must not be answered with the SPA shell
+    if path == "/api" || path.starts_with("/api/") {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+
+    let mut res = next.run(req).await;
+    if !res.status().is_success() {
+        return res;
+    }
+
+    let is_html = res
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("text/html"));
+
+    // Vite fingerprints everything under /assets, the rest may change on redeploy
+    let policy = if is_html {
+        REVALIDATE
+    } else if path.starts_with("/assets/") {
+        IMMUTABLE
+    } else {
+        SHORT_LIVED
+    };
+
+    res.headers_mut()
+        .insert(CACHE_CONTROL, HeaderValue::from_static(policy));
+    res
+}
+
+/// A precompressed sibling the frontend build emits next to each asset.
+#[cfg(any(feature = "embed-frontend", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Encoding {
+    name: &'static str,
+    extension: &'static str,
+    etag_suffix: &'static str,
+}
+
+#[cfg(any(feature = "embed-frontend", test))]
+const BROTLI: Encoding = Encoding {
+    name: "br",
+    extension: ".br",
+    etag_suffix: "-br",
+};
+#[cfg(any(feature = "embed-frontend", test))]
+const GZIP: Encoding = Encoding {
+    name: "gzip",
+    extension: ".gz",
+    etag_suffix: "-gz",
+};
+
+/// The encodings `accept_encoding` allows, most preferred first. An encoding
+/// listed with `q=0`, or only covered by a `*;q=0`, is refused.
+#[cfg(any(feature = "embed-frontend", test))]
+fn preferred_encodings(accept_encoding: &str) -> Vec<Encoding> {
+    let mut explicit = Vec::new();
+    let mut wildcard = None;
+    for item in accept_encoding.split(',') {
+        let mut parts = item.split(';');
+        let name = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        let quality = parts
+            .filter_map(|param| param.trim().strip_prefix("q="))
+            .find_map(|q| q.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if name == "*" {
+            wildcard = Some(quality);
+        } else if !name.is_empty() {
+            explicit.push((name, quality));
+        }
+    }
+
+    let mut accepted: Vec<(Encoding, f32)> = [BROTLI, GZIP]
+        .into_iter()
+        .filter_map(|encoding| {
+            let quality = explicit
+                .iter()
+                .find(|(name, _)| name == encoding.name)
+                .map(|(_, quality)| *quality)
+                .or(wildcard)?;
+            (quality > 0.0).then_some((encoding, quality))
+        })
+        .collect();
+    // Stable, brotli stays ahead of gzip at the same quality
+    accepted.sort_by(|a, b| b.1.total_cmp(&a.1));
+    accepted.into_iter().map(|(encoding, _)| encoding).collect()
+}
+
+/// Whether an `If-None-Match` header names `etag`, weak or strong.
+#[cfg(any(feature = "embed-frontend", test))]
+fn etag_matches(if_none_match: Option<&HeaderValue>, etag: &str) -> bool {
+    if_none_match
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| {
+            value.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == etag
+            })
+        })
+}
+
+#[cfg(feature = "embed-frontend")]
+mod embedded {
+    use axum::{
+        body::Body,
+        http::{
+            HeaderMap, HeaderValue, StatusCode, Uri,
+            header::{ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE, ETAG, IF_NONE_MATCH, VARY},
+        },
+        response::{IntoResponse, Response},
+    };
+    use rust_embed::RustEmbed;
+
+    #[derive(RustEmbed)]
+    #[folder = "../frontend/build/client"]
+    struct Assets;
+
+    pub async fn serve(uri: Uri, headers: HeaderMap) -> Response {
+        let path = uri.path().trim_start_matches('/');
+        let path = if path.is_empty() { "index.html" } else { path };
+
+        match lookup(path, &headers) {
+            Some(res) => res,
+            None if super::is_client_route(uri.path()) => lookup("index.html", &headers)
+                .unwrap_or_else(|| StatusCode::NOT_FOUND.into_response()),
+            None => StatusCode::NOT_FOUND.into_response(),
+        }
+    }
+
+    fn lookup(path: &str, headers: &HeaderMap) -> Option<Response> {
+        let file = Assets::get(path)?;
+        let mime = file.metadata.mimetype().to_string();
+
+        let accepted = headers
+            .get(ACCEPT_ENCODING)
+            .and_then(|value| value.to_str().ok())
+            .unwrap_or_default();
+
+        // Prefer the precompressed siblings emitted by the frontend build
+        let (data, encoding) = super::preferred_encodings(accepted)
+            .into_iter()
+            .find_map(|encoding| {
+                Assets::get(&format!("{path}{}", encoding.extension))
+                    .map(|f| (f.data, Some(encoding)))
+            })
+            .unwrap_or((file.data, None));
+
+        // Each encoding is a different representation and needs its own tag
+        let hash: String = file
+            .metadata
+            .sha256_hash()
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect();
+        let etag = match encoding {
+            Some(encoding) => format!("\"{hash}{}\"", encoding.etag_suffix),
+            None => format!("\"{hash}\""),
+        };
+
+        let mut res = if super::etag_matches(headers.get(IF_NONE_MATCH), &etag) {
+            StatusCode::NOT_MODIFIED.into_response()
+        } else {
+            let mut res = Response::new(Body::from(data.into_owned()));
+            if let Ok(value) = HeaderValue::from_str(&mime) {
+                res.headers_mut().insert(CONTENT_TYPE, value);
+            }
+            if let Some(encoding) = encoding {
+                res.headers_mut()
+                    .insert(CONTENT_ENCODING, HeaderValue::from_static(encoding.name));
+            }
+            res
+        };
+        let res_headers = res.headers_mut();
+        if let Ok(value) = HeaderValue::from_str(&etag) {
+            res_headers.insert(ETAG, value);
+        }
+        res_headers.insert(VARY, HeaderValue::from_static("accept-encoding"));
+
+        Some(res)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::fs;
+
+    use axum::{
+        body::Body,
+        http::header::{ACCEPT_ENCODING, CONTENT_ENCODING},
+    };
+
+    use super::*;
+
+    fn site() -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("assets")).unwrap();
+        fs::write(dir.path().join("index.html"), "<html>app</html>").unwrap();
+        fs::write(dir.path().join("assets/app-1a2b.js"), "console.log(1)").unwrap();
+        fs::write(dir.path().join("favicon.ico"), [0u8; 4]).unwrap();
+        dir
+    }
+
+    async fn get(dir: &tempfile::TempDir, path: &str, accept_encoding: &str) -> Response {
+        let router = service(&FrontendSource::Directory(dir.path().to_path_buf())).unwrap();
+        let req = Request::get(path)
+            .header(ACCEPT_ENCODING, accept_encoding)
+            .body(Body::empty())
+            .unwrap();
+        router.oneshot(req).await.unwrap()
+    }
+
+    fn header<'a>(res: &'a Response, name: &str) -> Option<&'a str> {
+        res.headers()
+            .get(name)
+            .and_then(|value| value.to_str().ok())
+    }
+
+    async fn body(res: Response) -> String {
+        let bytes = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        String::from_utf8(bytes.to_vec()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn cache_policy_follows_the_kind_of_file() {
+        let dir = site();
+
+        let index = get(&dir, "/", "").await;
+        assert_eq!(index.status(), StatusCode::OK);
+        assert_eq!(header(&index, "cache-control"), Some(REVALIDATE));
+
+        let asset = get(&dir, "/assets/app-1a2b.js", "").await;
+        assert_eq!(asset.status(), StatusCode::OK);
+        assert_eq!(header(&asset, "cache-control"), Some(IMMUTABLE));
+
+        let icon = get(&dir, "/favicon.ico", "").await;
+        assert_eq!(icon.status(), StatusCode::OK);
+        assert_eq!(header(&icon, "cache-control"), Some(SHORT_LIVED));
+    }
+
+    #[tokio::test]
+    async fn client_routes_fall_back_to_the_page() {
+        let dir = site();
+        for path in ["/servers", "/servers/3f2a/console", "/settings/"] {
+            let res = get(&dir, path, "").await;
+            assert_eq!(res.status(), StatusCode::OK, "{path}");
+            assert_eq!(header(&res, "cache-control"), Some(REVALIDATE));
+            assert_eq!(body(res).await, "<html>app</html>");
+        }
+    }
+
+    #[tokio::test]
+    async fn missing_files_and_api_calls_are_not_found() {
+        let dir = site();
+        for path in [
+            "/assets/app-0000.js",
+            "/assets/style",
+            "/missing.css",
+            "/api",
+            "/api/servers/unknown",
+        ] {
+            let res = get(&dir, path, "").await;
+            assert_eq!(res.status(), StatusCode::NOT_FOUND, "{path}");
+            assert_eq!(header(&res, "cache-control"), None);
+        }
+    }
+
+    #[tokio::test]
+    async fn precompressed_siblings_are_served_when_accepted() {
+        let dir = site();
+        fs::write(dir.path().join("assets/app-1a2b.js.gz"), "gzipped").unwrap();
+
+        let res = get(&dir, "/assets/app-1a2b.js", "gzip, deflate").await;
+        assert_eq!(header(&res, CONTENT_ENCODING.as_str()), Some("gzip"));
+        assert_eq!(body(res).await, "gzipped");
+
+        let res = get(&dir, "/assets/app-1a2b.js", "gzip;q=0").await;
+        assert_eq!(header(&res, CONTENT_ENCODING.as_str()), None);
+        assert_eq!(body(res).await, "console.log(1)");
+    }
+
+    #[test]
+    fn client_routes_have_no_file_extension() {
+        assert!(is_client_route("/"));
+        assert!(is_client_route("/servers/3f2a"));
+        assert!(is_client_route("/servers/"));
+        assert!(!is_client_route("/assets/chunk"));
+        assert!(!is_client_route("/assets/app-1a2b.js"));
+        assert!(!is_client_route("/robots.txt"));
+    }
+
+    #[test]
+    fn encodings_follow_their_quality() {
+        let names = |header: &str| {
+            preferred_encodings(header)
+                .into_iter()
+                .map(|encoding| encoding.name)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(names("gzip, deflate, br"), ["br", "gzip"]);
+        assert_eq!(names("br;q=0, gzip"), ["gzip"]);
+        assert_eq!(names("BR ; q=0.0,gzip;q=0.8"), ["gzip"]);
+        assert_eq!(names("gzip;q=1.0, br;q=0.5"), ["gzip", "br"]);
+        assert_eq!(names("*"), ["br", "gzip"]);
+        assert_eq!(names("gzip, *;q=0"), ["gzip"]);
+        assert_eq!(names("identity"), Vec::<&str>::new());
+        assert_eq!(names(""), Vec::<&str>::new());
+    }
+
+    #[test]
+    fn etags_match_weak_and_listed_tags() {
+        let value = |s: &str| HeaderValue::from_str(s).unwrap();
+        assert!(etag_matches(Some(&value("\"abc-br\"")), "\"abc-br\""));
+        assert!(etag_matches(Some(&value("W/\"abc\", \"def\"")), "\"abc\""));
+        assert!(etag_matches(Some(&value("*")), "\"abc\""));
+        assert!(!etag_matches(Some(&value("\"abc\"")), "\"abc-gz\""));
+        assert!(!etag_matches(None, "\"abc\""));
+    }
+}
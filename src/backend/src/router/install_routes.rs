@@ -0,0 +1,61 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core::install_routines,
+    domain::{
+        install::{InstallRequest, Software, SoftwareBuild, SoftwareVersion},
+        job::Job,
+    },
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_versions(
+    State(state): State<Arc<AppState>>,
+    Path(software): Path<Software>,
+) -> Result<Json<Vec<SoftwareVersion>>, StatusCode> {
+    debug!(
+        software = software.as_str(),
+        "get software versions route started"
+    );
+    let versions = install_routines::versions(state, software).await?;
+    debug!(
+        version_count = versions.len(),
+        "get software versions route completed"
+    );
+    Ok(Json(versions))
+}
+
+pub async fn get_builds(
+    State(state): State<Arc<AppState>>,
+    Path((software, version)): Path<(Software, String)>,
+) -> Result<Json<Vec<SoftwareBuild>>, StatusCode> {
+    debug!(
+        software = software.as_str(),
+        version, "get software builds route started"
+    );
+    let builds = install_routines::builds(state, software, &version).await?;
+    debug!(
+        build_count = builds.len(),
+        "get software builds route completed"
+    );
+    Ok(Json(builds))
+}
+
+pub async fn install(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(request): Json<InstallRequest>,
+) -> Result<(StatusCode, Json<Job>), StatusCode> {
+    debug!(server_uuid = %uuid, "install software route started");
+    let job = install_routines::install(state, &user, uuid, request).await?;
+    info!(job_uuid = %job.uuid, "install software route completed");
+    Ok((StatusCode::ACCEPTED, Json(job)))
+}
@@ -0,0 +1,64 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    core::java_routines,
+    domain::java::{JavaRecommendation, JavaRecommendationQuery, JavaRuntime, ServerJava},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_runtimes(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<JavaRuntime>>, StatusCode> {
+    debug!("get java runtimes route started");
+    let runtimes = java_routines::list(state);
+    debug!(
+        runtime_count = runtimes.len(),
+        "get java runtimes route completed"
+    );
+    Ok(Json(runtimes))
+}
+
+pub async fn scan_runtimes(
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<Vec<JavaRuntime>>, StatusCode> {
+    debug!("scan java runtimes route started");
+    let runtimes = java_routines::scan(state).await;
+    info!(
+        runtime_count = runtimes.len(),
+        "scan java runtimes route completed"
+    );
+    Ok(Json(runtimes))
+}
+
+pub async fn get_recommendation(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<JavaRecommendationQuery>,
+) -> Result<Json<JavaRecommendation>, StatusCode> {
+    debug!("get java recommendation route started");
+    let recommendation = java_routines::recommend(state, query)?;
+    debug!(
+        recommended_major = recommendation.requirement.recommended_major,
+        "get java recommendation route completed"
+    );
+    Ok(Json(recommendation))
+}
+
+pub async fn get_server_java(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerJava>, StatusCode> {
+    debug!(server_uuid = %uuid, "get server java route started");
+    let server_java = java_routines::server_java(state, uuid).await?;
+    debug!(
+        compatible = server_java.compatible,
+        "get server java route completed"
+    );
+    Ok(Json(server_java))
+}
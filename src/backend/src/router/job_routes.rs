@@ -0,0 +1,121 @@
+use crate::{
+    domain::{
+        job::{Job, JobEvent, JobQuery},
+        user::InternalUser,
+    },
+    prelude::*,
+};
+use std::sync::Arc;
+
+use crate::{core::job_routines, state::AppState};
+use axum::{
+    Extension, Json,
+    extract::{
+        Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::Response,
+};
+use futures_util::{SinkExt, StreamExt};
+use tokio::sync::broadcast::{self, error::RecvError};
+use uuid::Uuid;
+
+pub async fn get_jobs(
+    State(state): State<Arc<AppState>>,
+    Query(query): Query<JobQuery>,
+) -> Result<Json<Vec<Job>>, StatusCode> {
+    debug!("get jobs route started");
+    let jobs = job_routines::list(state, query).await?;
+    debug!(job_count = jobs.len(), "get jobs route completed");
+    Ok(Json(jobs))
+}
+
+pub async fn get_job(
+    State(state): State<Arc<AppState>>,
+    Path(job_uuid): Path<Uuid>,
+) -> Result<Json<Job>, StatusCode> {
+    debug!(job_uuid = %job_uuid, "get job route started");
+    let job = job_routines::get(state, job_uuid).await?;
+    debug!(status = job.status.as_str(), "get job route completed");
+    Ok(Json(job))
+}
+
+pub async fn cancel_job(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(job_uuid): Path<Uuid>,
+) -> Result<Json<Job>, StatusCode> {
+    debug!(job_uuid = %job_uuid, "cancel job route started");
+    let job = job_routines::cancel(state, &user, job_uuid).await?;
+    info!(job_uuid = %job.uuid, "cancel job route completed");
+    Ok(Json(job))
+}
+
+/// Streams jobs matching the query filters: the unfinished ones first, then
+/// every change to any of them.
+pub async fn live(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Query(query): Query<JobQuery>,
+    ws: WebSocketUpgrade,
+) -> Response {
+    debug!("job stream route started");
+    let (jobs, rx) = job_routines::subscribe(&state, &query);
+    ws.on_upgrade(move |socket| stream(user, query, socket, jobs, rx))
+}
+
+async fn stream(
+    user: InternalUser,
+    query: JobQuery,
+    socket: WebSocket,
+    jobs: Vec<Job>,
+    mut rx: broadcast::Receiver<Job>,
+) {
+    debug!(username = user.username, "job stream client connected");
+    let (mut sender, mut receiver) = socket.split();
+
+    for job in jobs {
+        if send(&mut sender, JobEvent::Job(Box::new(job)))
+            .await
+            .is_err()
+        {
+            return;
+        }
+    }
+
+    loop {
+        tokio::select! {
+            job = rx.recv() => {
+                let event = match job {
+                    Ok(job) if query.matches(&job) => JobEvent::Job(Box::new(job)),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(skipped)) => JobEvent::Lagged { skipped },
+                    Err(RecvError::Closed) => break,
+                };
+                if send(&mut sender, event).await.is_err() {
+                    break;
+                }
+            }
+            msg = receiver.next() => match msg {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => break,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+
+    debug!(username = user.username, "job stream client disconnected");
+}
+
+async fn send<S>(sender: &mut S, event: JobEvent) -> Result<(), ()>
+where
+    S: SinkExt<Message> + Unpin,
+{
+    let text = serde_json::to_string(&event).map_err(|e| {
+        error!(error = %e, "serialize job event failed");
+    })?;
+    sender
+        .send(Message::Text(text.into()))
+        .await
+        .map_err(|_| ())
+}
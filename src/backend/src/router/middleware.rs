@@ -5,18 +5,21 @@ use axum::{
     Extension,
     extract::{MatchedPath, Request, State},
     http::{
-        self, HeaderValue, Method, StatusCode,
-        header::{AUTHORIZATION, CONTENT_TYPE},
+        self, HeaderName, HeaderValue, Method, StatusCode,
+        header::{AUTHORIZATION, CONTENT_TYPE, UPGRADE},
     },
     middleware::Next,
     response::Response,
 };
 
 use axum_extra::extract::CookieJar;
-use tower_http::cors::{Any, CorsLayer};
+use tower_http::cors::CorsLayer;
 use tracing::debug;
 
-use crate::{auth::verify_jwt, state::AppState};
+use crate::{
+    auth::{AUTH_COOKIE, CSRF_HEADER, verify_csrf, verify_jwt},
+    state::AppState,
+};
 
 pub async fn auth(
     State(state): State<Arc<AppState>>,
@@ -30,9 +33,8 @@ pub async fn auth(
     debug!(?method, path, "authenticate request started");
 
     // 1) Try JWT from cookie first
-    let token_from_cookie = jar
-        .get("auth_token")
-        .map(|cookie| cookie.value().to_owned());
+    let token_from_cookie = jar.get(AUTH_COOKIE).map(|cookie| cookie.value().to_owned());
+    let from_cookie = token_from_cookie.is_some();
 
     // 2) If no cookie, fall back to Authorization: Bearer ...
     let token = match token_from_cookie {
@@ -73,7 +75,36 @@ pub async fn auth(
     })?;
     let username = &token_data.claims.username;
 
-    // 4) Load user from DB
+    // 4) Cookie sessions must echo the CSRF token on state changing requests,
+    //    bearer tokens are never sent implicitly by the browser so they are exempt.
+    //    Browsers cannot set headers on WebSocket upgrades, those may use ?csrf=
+    let is_upgrade = req
+        .headers()
+        .get(UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+
+    if from_cookie && (!is_safe_method(&method) || is_upgrade) {
+        let provided = req
+            .headers()
+            .get(CSRF_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .or_else(|| {
+                is_upgrade
+                    .then(|| query_param(req.uri().query(), "csrf"))
+                    .flatten()
+            });
+
+        match provided {
+            Some(value) if verify_csrf(value, &token_data.claims.csrf) => {}
+            _ => {
+                warn!(?method, path, username, "csrf token missing or invalid");
+                return Err(StatusCode::FORBIDDEN);
+            }
+        }
+    }
+
+    // 5) Load user from DB
     let current_user = user_routines::get_by_username(state, username)
         .await
         .map_err(|e| {
@@ -94,19 +125,43 @@ pub async fn auth(
             StatusCode::INTERNAL_SERVER_ERROR
         })?;
 
-    // 5) Attach user to request extensions
+    // 6) Attach user to request extensions
     req.extensions_mut().insert(current_user);
 
-    // 6) Continue the chain
+    // 7) Continue the chain
     Ok(next.run(req).await)
 }
 
+fn is_safe_method(method: &Method) -> bool {
+    matches!(*method, Method::GET | Method::HEAD | Method::OPTIONS)
+}
+
+fn query_param<'a>(query: Option<&'a str>, name: &str) -> Option<&'a str> {
+    query?
+        .split('&')
+        .filter_map(|pair| pair.split_once('='))
+        .find(|(key, _)| *key == name)
+        .map(|(_, value)| value)
+}
+
 pub fn cors() -> CorsLayer {
     debug!("build cors layer");
     CorsLayer::new()
         .allow_origin(HeaderValue::from_static("http://localhost:5173"))
-        .allow_methods([Method::GET, Method::POST, Method::OPTIONS])
-        .allow_headers([AUTHORIZATION, CONTENT_TYPE])
+        .allow_methods([
+            Method::GET,
+            Method::POST,
+            Method::PUT,
+            Method::PATCH,
+            Method::DELETE,
+            Method::OPTIONS,
+        ])
+        .allow_headers([
+            AUTHORIZATION,
+            CONTENT_TYPE,
+            HeaderName::from_static(CSRF_HEADER),
+        ])
+        .expose_headers([HeaderName::from_static(CSRF_HEADER)])
         .allow_credentials(true)
 }
 
@@ -1,10 +1,28 @@
+pub mod backup_routes;
+pub mod command_routes;
+pub mod console_routes;
+pub mod countdown_routes;
+pub mod crash_routes;
+pub mod destination_routes;
+pub mod file_routes;
+pub mod frontend;
+pub mod install_routes;
+pub mod java_routes;
+pub mod job_routes;
 pub mod middleware;
+pub mod player_list_routes;
+pub mod player_routes;
+pub mod properties_routes;
+pub mod schedule_routes;
+pub mod server_routes;
+pub mod status_routes;
 pub mod user_routes;
 
 use axum::{
     Json, Router,
+    extract::DefaultBodyLimit,
     http::StatusCode,
-    routing::{get, post},
+    routing::{delete, get, post},
 };
 use serde_json::{Value, json};
 use std::sync::Arc;
@@ -51,6 +69,409 @@ pub async fn init_router(app_state: Arc<AppState>) -> Router {
                 .layer(middleware!(cors_auth_perms, app_state.clone()))
                 .with_state(app_state.clone()),
         )
+        .route(
+            "/api/servers",
+            get(server_routes::get_all)
+                .post(server_routes::create)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}",
+            get(server_routes::get_uuid)
+                .patch(server_routes::update)
+                .delete(server_routes::delete)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/launch",
+            get(server_routes::get_launch)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/start",
+            post(server_routes::start)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/stop",
+            post(server_routes::stop)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/restart",
+            post(server_routes::restart)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/countdown",
+            get(countdown_routes::get_countdown)
+                .post(countdown_routes::begin)
+                .delete(countdown_routes::cancel)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/kill",
+            post(server_routes::kill)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/status",
+            get(status_routes::get_status)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/properties",
+            get(properties_routes::get_properties)
+                .patch(properties_routes::update_properties)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/players",
+            get(player_routes::get_players)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/players/{name}/sessions",
+            get(player_routes::get_sessions)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/whitelist",
+            get(player_list_routes::get_whitelist)
+                .post(player_list_routes::add_whitelist)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/whitelist/{name}",
+            delete(player_list_routes::remove_whitelist)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/ops",
+            get(player_list_routes::get_ops)
+                .post(player_list_routes::add_op)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/ops/{name}",
+            delete(player_list_routes::remove_op)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/bans",
+            get(player_list_routes::get_bans)
+                .post(player_list_routes::add_ban)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/bans/{name}",
+            delete(player_list_routes::remove_ban)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/ip-bans",
+            get(player_list_routes::get_ip_bans)
+                .post(player_list_routes::add_ip_ban)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/ip-bans/{ip}",
+            delete(player_list_routes::remove_ip_ban)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/crashes",
+            get(crash_routes::get_crashes)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/crashes/{crash_uuid}",
+            get(crash_routes::get_crash)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups",
+            get(backup_routes::get_backups)
+                .post(backup_routes::create_backup)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/retention",
+            get(backup_routes::get_retention)
+                .put(backup_routes::set_retention)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/destinations",
+            get(destination_routes::get_destinations)
+                .post(destination_routes::create_destination)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/destinations/{destination_uuid}",
+            get(destination_routes::get_destination)
+                .patch(destination_routes::update_destination)
+                .delete(destination_routes::delete_destination)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/{backup_uuid}",
+            get(backup_routes::get_backup)
+                .delete(backup_routes::delete_backup)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/{backup_uuid}/download",
+            get(backup_routes::download_backup)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/{backup_uuid}/restore",
+            post(backup_routes::restore_backup)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/{backup_uuid}/verify",
+            post(backup_routes::verify_backup)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/backups/{backup_uuid}/copies",
+            get(destination_routes::get_copies)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files",
+            get(file_routes::list_files)
+                .delete(file_routes::delete_file)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/content",
+            get(file_routes::read_file)
+                .put(file_routes::write_file)
+                // Escaping in JSON can make a file up to six times larger
+                .layer(DefaultBodyLimit::max(
+                    app_state.config.files.max_edit_bytes as usize * 6 + 4096,
+                ))
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/download",
+            get(file_routes::download_file)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/rename",
+            post(file_routes::rename_file)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/directories",
+            post(file_routes::create_dir)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/upload",
+            post(file_routes::upload_files)
+                // Uploads are streamed to disk and limited there
+                .layer(DefaultBodyLimit::disable())
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/uploads",
+            post(file_routes::start_upload)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/uploads/{upload_uuid}",
+            get(file_routes::get_upload)
+                .patch(file_routes::append_upload)
+                .delete(file_routes::cancel_upload)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/compress",
+            post(file_routes::compress_files)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/extract",
+            post(file_routes::extract_archive)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/installer/{software}/versions",
+            get(install_routes::get_versions)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/installer/{software}/versions/{version}/builds",
+            get(install_routes::get_builds)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/install",
+            post(install_routes::install)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/java",
+            get(java_routes::get_runtimes)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/java/scan",
+            post(java_routes::scan_runtimes)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/java/recommendation",
+            get(java_routes::get_recommendation)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/java",
+            get(java_routes::get_server_java)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/jobs",
+            get(job_routes::get_jobs)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/jobs/live",
+            get(job_routes::live)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/jobs/{job_uuid}",
+            get(job_routes::get_job)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/jobs/{job_uuid}/cancel",
+            post(job_routes::cancel_job)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/rules",
+            get(file_routes::get_rules)
+                .post(file_routes::create_rule)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/files/rules/{rule_uuid}",
+            delete(file_routes::delete_rule)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/schedules",
+            get(schedule_routes::get_tasks)
+                .post(schedule_routes::create_task)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/schedules/{task_uuid}",
+            get(schedule_routes::get_task)
+                .patch(schedule_routes::update_task)
+                .delete(schedule_routes::delete_task)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/schedules/{task_uuid}/runs",
+            get(schedule_routes::get_runs)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/console",
+            get(console_routes::console)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/command",
+            post(command_routes::execute)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/servers/{uuid}/commands",
+            get(command_routes::history)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/command-rules",
+            get(command_routes::get_rules)
+                .post(command_routes::create_rule)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
+        .route(
+            "/api/command-rules/{uuid}",
+            delete(command_routes::delete_rule)
+                .layer(middleware!(cors_auth_perms, app_state.clone()))
+                .with_state(app_state.clone()),
+        )
         .route(
             "/api/login",
             post(user_routes::login)
@@ -70,6 +491,11 @@ pub async fn init_router(app_state: Arc<AppState>) -> Router {
                 .with_state(app_state.clone()),
         );
 
+    let router = match frontend::service(&app_state.config.frontend) {
+        Some(spa) => router.fallback_service(spa),
+        None => router,
+    };
+
     info!("router initialization completed");
     router
 }
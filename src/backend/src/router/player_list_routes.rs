@@ -0,0 +1,149 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core::player_list_routines,
+    domain::player_lists::{
+        BannedIpEntry, BannedPlayerEntry, ListChange, NewBan, NewIpBan, NewOp, NewWhitelistEntry,
+        OpEntry, WhitelistEntry,
+    },
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_whitelist(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<WhitelistEntry>>, StatusCode> {
+    debug!(server_uuid = %uuid, "get whitelist route started");
+    let entries = player_list_routines::whitelist(state, uuid).await?;
+    debug!(entry_count = entries.len(), "get whitelist route completed");
+    Ok(Json(entries))
+}
+
+pub async fn add_whitelist(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(entry): Json<NewWhitelistEntry>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "add whitelist route started");
+    let change = player_list_routines::add_whitelist(state, &user, uuid, entry).await?;
+    info!("add whitelist route completed");
+    Ok(Json(change))
+}
+
+pub async fn remove_whitelist(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, name)): Path<(Uuid, String)>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "remove whitelist route started");
+    let change = player_list_routines::remove_whitelist(state, &user, uuid, name).await?;
+    info!("remove whitelist route completed");
+    Ok(Json(change))
+}
+
+pub async fn get_ops(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<OpEntry>>, StatusCode> {
+    debug!(server_uuid = %uuid, "get ops route started");
+    let entries = player_list_routines::ops(state, uuid).await?;
+    debug!(entry_count = entries.len(), "get ops route completed");
+    Ok(Json(entries))
+}
+
+pub async fn add_op(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(op): Json<NewOp>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "add op route started");
+    let change = player_list_routines::add_op(state, &user, uuid, op).await?;
+    info!("add op route completed");
+    Ok(Json(change))
+}
+
+pub async fn remove_op(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, name)): Path<(Uuid, String)>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "remove op route started");
+    let change = player_list_routines::remove_op(state, &user, uuid, name).await?;
+    info!("remove op route completed");
+    Ok(Json(change))
+}
+
+pub async fn get_bans(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<BannedPlayerEntry>>, StatusCode> {
+    debug!(server_uuid = %uuid, "get bans route started");
+    let entries = player_list_routines::bans(state, uuid).await?;
+    debug!(entry_count = entries.len(), "get bans route completed");
+    Ok(Json(entries))
+}
+
+pub async fn add_ban(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(ban): Json<NewBan>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "add ban route started");
+    let change = player_list_routines::add_ban(state, &user, uuid, ban).await?;
+    info!("add ban route completed");
+    Ok(Json(change))
+}
+
+pub async fn remove_ban(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, name)): Path<(Uuid, String)>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "remove ban route started");
+    let change = player_list_routines::remove_ban(state, &user, uuid, name).await?;
+    info!("remove ban route completed");
+    Ok(Json(change))
+}
+
+pub async fn get_ip_bans(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<BannedIpEntry>>, StatusCode> {
+    debug!(server_uuid = %uuid, "get ip bans route started");
+    let entries = player_list_routines::ip_bans(state, uuid).await?;
+    debug!(entry_count = entries.len(), "get ip bans route completed");
+    Ok(Json(entries))
+}
+
+pub async fn add_ip_ban(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(ban): Json<NewIpBan>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "add ip ban route started");
+    let change = player_list_routines::add_ip_ban(state, &user, uuid, ban).await?;
+    info!("add ip ban route completed");
+    Ok(Json(change))
+}
+
+pub async fn remove_ip_ban(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, ip)): Path<(Uuid, String)>,
+) -> Result<Json<ListChange>, StatusCode> {
+    debug!(server_uuid = %uuid, "remove ip ban route started");
+    let change = player_list_routines::remove_ip_ban(state, &user, uuid, ip).await?;
+    info!("remove ip ban route completed");
+    Ok(Json(change))
+}
@@ -0,0 +1,38 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    core::player_routines,
+    domain::players::{PlayerSession, ServerPlayers, SessionQuery},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_players(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerPlayers>, StatusCode> {
+    debug!(server_uuid = %uuid, "get players route started");
+    let players = player_routines::online(state, uuid).await?;
+    debug!(online = players.online.len(), "get players route completed");
+    Ok(Json(players))
+}
+
+pub async fn get_sessions(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, name)): Path<(Uuid, String)>,
+    Query(query): Query<SessionQuery>,
+) -> Result<Json<Vec<PlayerSession>>, StatusCode> {
+    debug!(server_uuid = %uuid, "get player sessions route started");
+    let sessions = player_routines::sessions(state, uuid, name, query.limit).await?;
+    debug!(
+        session_count = sessions.len(),
+        "get player sessions route completed"
+    );
+    Ok(Json(sessions))
+}
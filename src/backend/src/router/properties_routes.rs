@@ -0,0 +1,38 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{
+    core,
+    domain::properties::{PropertiesUpdate, PropertyChanges, ServerProperties},
+    state::AppState,
+};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_properties(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerProperties>, StatusCode> {
+    debug!(server_uuid = %uuid, "get server properties route started");
+    let properties = core::properties_routines::get(state, uuid).await?;
+    debug!("get server properties route completed");
+    Ok(Json(properties))
+}
+
+pub async fn update_properties(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Json(changes): Json<PropertyChanges>,
+) -> Result<Json<PropertiesUpdate>, StatusCode> {
+    debug!(server_uuid = %uuid, "update server properties route started");
+    let update = core::properties_routines::update(state, uuid, changes).await?;
+    info!(
+        restart_required = update.restart_required,
+        "update server properties route completed"
+    );
+    Ok(Json(update))
+}
@@ -0,0 +1,82 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core::schedule_routines,
+    domain::schedule::{NewScheduledTask, RunQuery, ScheduledTask, TaskRun, UpdateScheduledTask},
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_tasks(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Vec<ScheduledTask>>, StatusCode> {
+    debug!(server_uuid = %uuid, "list scheduled tasks route started");
+    let tasks = schedule_routines::list(state, uuid).await?;
+    debug!(
+        task_count = tasks.len(),
+        "list scheduled tasks route completed"
+    );
+    Ok(Json(tasks))
+}
+
+pub async fn create_task(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path(uuid): Path<Uuid>,
+    Json(new_task): Json<NewScheduledTask>,
+) -> Result<Json<ScheduledTask>, StatusCode> {
+    debug!(server_uuid = %uuid, "create scheduled task route started");
+    let task = schedule_routines::create(state, &user, uuid, new_task).await?;
+    info!("create scheduled task route completed");
+    Ok(Json(task))
+}
+
+pub async fn get_task(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, task_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<Json<ScheduledTask>, StatusCode> {
+    debug!(task_uuid = %task_uuid, "get scheduled task route started");
+    let task = schedule_routines::get(state, uuid, task_uuid).await?;
+    debug!("get scheduled task route completed");
+    Ok(Json(task))
+}
+
+pub async fn update_task(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Path((uuid, task_uuid)): Path<(Uuid, Uuid)>,
+    Json(update): Json<UpdateScheduledTask>,
+) -> Result<Json<ScheduledTask>, StatusCode> {
+    debug!(task_uuid = %task_uuid, "update scheduled task route started");
+    let task = schedule_routines::update(state, &user, uuid, task_uuid, update).await?;
+    info!("update scheduled task route completed");
+    Ok(Json(task))
+}
+
+pub async fn delete_task(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, task_uuid)): Path<(Uuid, Uuid)>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(task_uuid = %task_uuid, "delete scheduled task route started");
+    schedule_routines::delete(state, uuid, task_uuid).await?;
+    info!("delete scheduled task route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn get_runs(
+    State(state): State<Arc<AppState>>,
+    Path((uuid, task_uuid)): Path<(Uuid, Uuid)>,
+    Query(query): Query<RunQuery>,
+) -> Result<Json<Vec<TaskRun>>, StatusCode> {
+    debug!(task_uuid = %task_uuid, "list task runs route started");
+    let runs = schedule_routines::runs(state, uuid, task_uuid, query.limit).await?;
+    debug!(run_count = runs.len(), "list task runs route completed");
+    Ok(Json(runs))
+}
@@ -0,0 +1,116 @@
+use crate::{domain::user::InternalUser, prelude::*};
+use std::sync::Arc;
+
+use crate::{
+    core,
+    domain::{
+        launch::LaunchPlan,
+        server::{NewServer, Server, ServerProcess, UpdateServer},
+    },
+    state::AppState,
+};
+use axum::{
+    Extension, Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn create(
+    State(state): State<Arc<AppState>>,
+    Extension(user): Extension<InternalUser>,
+    Json(new_server): Json<NewServer>,
+) -> Result<Json<Server>, StatusCode> {
+    debug!("create server route started");
+    let server = core::server_routines::create(state, user.uuid, new_server).await?;
+    info!("create server route completed");
+    Ok(Json(server))
+}
+
+pub async fn get_all(State(state): State<Arc<AppState>>) -> Result<Json<Vec<Server>>, StatusCode> {
+    debug!("list servers route started");
+    let servers = core::server_routines::get_all(state).await?;
+    debug!(server_count = servers.len(), "list servers route completed");
+    Ok(Json(servers))
+}
+
+pub async fn get_uuid(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<Server>, StatusCode> {
+    debug!(server_uuid = %uuid, "get server by uuid route started");
+    let server = core::server_routines::get_by_uuid(state, uuid).await?;
+    debug!("get server by uuid route completed");
+    Ok(Json(server))
+}
+
+pub async fn get_launch(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<LaunchPlan>, StatusCode> {
+    debug!(server_uuid = %uuid, "get server launch route started");
+    let plan = core::server_routines::launch_plan(state, uuid).await?;
+    debug!(source = ?plan.source, "get server launch route completed");
+    Ok(Json(plan))
+}
+
+pub async fn update(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+    Json(update): Json<UpdateServer>,
+) -> Result<Json<Server>, StatusCode> {
+    debug!(server_uuid = %uuid, "update server route started");
+    let server = core::server_routines::update(state, uuid, update).await?;
+    info!("update server route completed");
+    Ok(Json(server))
+}
+
+pub async fn delete(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<StatusCode, StatusCode> {
+    debug!(server_uuid = %uuid, "delete server route started");
+    core::server_routines::delete(state, uuid).await?;
+    info!("delete server route completed");
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn start(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerProcess>, StatusCode> {
+    debug!(server_uuid = %uuid, "start server route started");
+    let process = core::server_routines::start(state, uuid).await?;
+    info!("start server route completed");
+    Ok(Json(process))
+}
+
+pub async fn stop(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerProcess>, StatusCode> {
+    debug!(server_uuid = %uuid, "stop server route started");
+    let process = core::server_routines::stop(state, uuid).await?;
+    info!("stop server route completed");
+    Ok(Json(process))
+}
+
+pub async fn restart(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<(StatusCode, Json<ServerProcess>), StatusCode> {
+    debug!(server_uuid = %uuid, "restart server route started");
+    let process = core::server_routines::restart(state, uuid).await?;
+    info!("restart server route completed");
+    Ok((StatusCode::ACCEPTED, Json(process)))
+}
+
+pub async fn kill(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerProcess>, StatusCode> {
+    debug!(server_uuid = %uuid, "kill server route started");
+    let process = core::server_routines::kill(state, uuid).await?;
+    info!("kill server route completed");
+    Ok(Json(process))
+}
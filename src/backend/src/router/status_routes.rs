@@ -0,0 +1,20 @@
+use crate::prelude::*;
+use std::sync::Arc;
+
+use crate::{core, domain::status::ServerStatus, state::AppState};
+use axum::{
+    Json,
+    extract::{Path, State},
+    http::StatusCode,
+};
+use uuid::Uuid;
+
+pub async fn get_status(
+    State(state): State<Arc<AppState>>,
+    Path(uuid): Path<Uuid>,
+) -> Result<Json<ServerStatus>, StatusCode> {
+    debug!(server_uuid = %uuid, "get server status route started");
+    let status = core::status_routines::get(state, uuid).await?;
+    debug!("get server status route completed");
+    Ok(Json(status))
+}
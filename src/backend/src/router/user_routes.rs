@@ -1,4 +1,5 @@
 use crate::{
+    auth::{AUTH_COOKIE, CSRF_COOKIE, CSRF_HEADER},
     domain::{api::LoginData, user::InternalUser},
     prelude::*,
 };
@@ -13,7 +14,7 @@ use anyhow::Result;
 use axum::{
     Extension, Json,
     extract::{Path, State},
-    http::StatusCode,
+    http::{HeaderMap, HeaderValue, StatusCode},
 };
 use axum_extra::extract::{
     CookieJar,
@@ -52,28 +53,44 @@ pub async fn login(
     State(state): State<Arc<AppState>>,
     jar: CookieJar,
     Json(login_data): Json<LoginData>,
-) -> Result<(CookieJar, Json<User>), StatusCode> {
+) -> Result<(CookieJar, HeaderMap, Json<User>), StatusCode> {
     debug!("login endpoint called");
-    let (jwt, user) = core::user_routines::login(state, login_data).await?;
+    let (tokens, user) = core::user_routines::login(state, login_data).await?;
 
-    let cookie = Cookie::build(("auth_token", jwt))
+    let cookie = Cookie::build((AUTH_COOKIE, tokens.jwt))
         .http_only(true)
         .secure(false)
         .same_site(SameSite::None)
         .path("/")
         .build();
 
-    let jar = jar.add(cookie);
+    // Readable by scripts on the same origin, cross origin clients use the header instead
+    let csrf_cookie = Cookie::build((CSRF_COOKIE, tokens.csrf.clone()))
+        .http_only(false)
+        .secure(false)
+        .same_site(SameSite::None)
+        .path("/")
+        .build();
+
+    let jar = jar.add(cookie).add(csrf_cookie);
+
+    let mut headers = HeaderMap::new();
+    let csrf_value = HeaderValue::from_str(&tokens.csrf).map_err(|e| {
+        error!(error = %e, "build csrf header failed");
+        StatusCode::INTERNAL_SERVER_ERROR
+    })?;
+    headers.insert(CSRF_HEADER, csrf_value);
 
-    Ok((jar, Json(user)))
+    Ok((jar, headers, Json(user)))
 }
 
 pub async fn logout(jar: CookieJar) -> Result<CookieJar, StatusCode> {
-    let cookie = Cookie::build(("auth_token", ""))
+    let cookie = Cookie::build((AUTH_COOKIE, ""))
         .path("/")
         .http_only(true)
         .build();
-    let jar = jar.remove(cookie);
+    let csrf_cookie = Cookie::build((CSRF_COOKIE, "")).path("/").build();
+    let jar = jar.remove(cookie).remove(csrf_cookie);
 
     Ok(jar)
 }
@@ -0,0 +1,365 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use tokio::{sync::Notify, time::sleep};
+use uuid::Uuid;
+
+use crate::{
+    core::{backup_routines, command_routines, countdown_routines, server_routines},
+    domain::{
+        backup::NewBackup,
+        countdown::{CountdownEnd, CountdownRequest, ShutdownMode},
+        job::JobStatus,
+        schedule::{CatchUp, RunOutcome, ScheduledTask, TaskAction, TaskRun},
+    },
+    infra::db,
+    prelude::*,
+    state::AppState,
+};
+
+/// Longest the loop sleeps, so a wall clock change is noticed in time.
+const MAX_IDLE: Duration = Duration::from_secs(60);
+/// Missed runs counted per task when the daemon comes back.
+const MAX_MISSED_COUNTED: usize = 10_000;
+
+/// Enabled scheduled tasks and when each is due next, driven by [`run`].
+#[derive(Default)]
+pub struct Scheduler {
+    tasks: Mutex<HashMap<Uuid, ScheduledTask>>,
+    wake: Notify,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tracks a created or edited task, a disabled one stops being tracked.
+    pub fn upsert(&self, task: ScheduledTask) {
+        {
+            let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+            if task.enabled && task.next_run_at.is_some() {
+                tasks.insert(task.uuid, task);
+            } else {
+                tasks.remove(&task.uuid);
+            }
+        }
+        self.wake.notify_one();
+    }
+
+    pub fn remove(&self, uuid: Uuid) {
+        self.tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&uuid);
+    }
+
+    /// Drops the tasks of a deleted server, the database cascades the rows.
+    pub fn forget_server(&self, server_uuid: Uuid) {
+        self.tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .retain(|_, task| task.server_uuid != server_uuid);
+    }
+
+    /// Takes every run due at `now` together with the occurrence it belongs
+    /// to, and moves each task on to its next occurrence.
+    fn take_due(&self, now: DateTime<Utc>) -> Vec<(ScheduledTask, DateTime<Utc>)> {
+        let mut tasks = self.tasks.lock().unwrap_or_else(|e| e.into_inner());
+        let mut due = Vec::new();
+
+        tasks.retain(|_, task| {
+            let Some(scheduled_for) = task.next_run_at.filter(|at| *at <= now) else {
+                return true;
+            };
+            task.next_run_at = task.next_after(now);
+            due.push((task.clone(), scheduled_for));
+            task.next_run_at.is_some()
+        });
+        due
+    }
+
+    fn next_due(&self) -> Option<DateTime<Utc>> {
+        self.tasks
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .values()
+            .filter_map(|task| task.next_run_at)
+            .min()
+    }
+}
+
+/// Applies the catch-up policies for the time the daemon was down, then fires
+/// tasks as they come due forever.
+pub async fn run(state: Arc<AppState>) {
+    catch_up(&state).await;
+
+    loop {
+        for (task, scheduled_for) in state.scheduler.take_due(Utc::now()) {
+            tokio::spawn(fire(state.clone(), task, scheduled_for));
+        }
+
+        let idle = state
+            .scheduler
+            .next_due()
+            .map(|at| (at - Utc::now()).to_std().unwrap_or(Duration::ZERO))
+            .unwrap_or(MAX_IDLE)
+            .min(MAX_IDLE);
+
+        tokio::select! {
+            _ = sleep(idle) => {}
+            _ = state.scheduler.wake.notified() => {}
+        }
+    }
+}
+
+async fn catch_up(state: &Arc<AppState>) {
+    let tasks = match db::schedule::get_all_tasks(&state.db_pool).await {
+        Ok(tasks) => tasks,
+        Err(e) => {
+            error!(error = %e, "fetch scheduled tasks failed");
+            return;
+        }
+    };
+
+    let now = Utc::now();
+    for mut task in tasks.into_iter().filter(|task| task.enabled) {
+        let missed_since = task.next_run_at.filter(|at| *at <= now);
+        task.next_run_at = task.next_after(now);
+
+        if let Err(e) =
+            db::schedule::set_run_times(&state.db_pool, task.uuid, None, task.next_run_at).await
+        {
+            error!(error = %e, task_uuid = %task.uuid, "store next task run failed");
+        }
+
+        if let Some(due) = missed_since {
+            let missed = 1 + task
+                .occurrences_after(due)
+                .take_while(|at| *at <= now)
+                .take(MAX_MISSED_COUNTED)
+                .count();
+            warn!(
+                task_uuid = %task.uuid,
+                missed,
+                catch_up = task.catch_up.as_str(),
+                "scheduled task missed runs while the daemon was down"
+            );
+
+            match task.catch_up {
+                CatchUp::Skip => {
+                    let message = format!("daemon was down, {missed} run(s) skipped");
+                    record(
+                        state,
+                        &task,
+                        due,
+                        now,
+                        now,
+                        RunOutcome::Missed,
+                        Some(message),
+                    )
+                    .await;
+                }
+                CatchUp::RunOnce => {
+                    tokio::spawn(fire(state.clone(), task.clone(), due));
+                }
+            }
+        }
+
+        state.scheduler.upsert(task);
+    }
+}
+
+async fn fire(state: Arc<AppState>, task: ScheduledTask, scheduled_for: DateTime<Utc>) {
+    let started_at = Utc::now();
+    if let Err(e) = db::schedule::set_run_times(
+        &state.db_pool,
+        task.uuid,
+        Some(started_at),
+        task.next_run_at,
+    )
+    .await
+    {
+        error!(error = %e, task_uuid = %task.uuid, "store task run times failed");
+    }
+
+    let (outcome, message) = match execute(&state, &task).await {
+        Ok(response) => {
+            info!(task_uuid = %task.uuid, action = task.action.as_str(), "scheduled task ran");
+            (RunOutcome::Success, response)
+        }
+        Err(reason) => {
+            warn!(task_uuid = %task.uuid, action = task.action.as_str(), reason, "scheduled task failed");
+            (RunOutcome::Failed, Some(reason))
+        }
+    };
+
+    record(
+        &state,
+        &task,
+        scheduled_for,
+        started_at,
+        Utc::now(),
+        outcome,
+        message,
+    )
+    .await;
+}
+
+/// Runs the task action, returning command output or why it failed.
+async fn execute(state: &Arc<AppState>, task: &ScheduledTask) -> Result<Option<String>, String> {
+    let uuid = task.server_uuid;
+    let result = match task.action {
+        TaskAction::Start => server_routines::start(state.clone(), uuid)
+            .await
+            .map(|_| None),
+        TaskAction::Stop | TaskAction::Restart
+            if task.countdown_secs.is_some() && state.supervisor.state(uuid).await.is_alive() =>
+        {
+            return count_down(state, task).await;
+        }
+        TaskAction::Stop => server_routines::stop(state.clone(), uuid)
+            .await
+            .map(|_| None),
+        TaskAction::Restart => server_routines::restart(state.clone(), uuid)
+            .await
+            .map(|_| None),
+        TaskAction::Command => {
+            let command = task.command.as_deref().unwrap_or_default();
+            match server_routines::get_by_uuid(state.clone(), uuid).await {
+                Ok(server) => command_routines::dispatch(state, &server, command).await,
+                Err(status) => Err(status),
+            }
+        }
+        TaskAction::Backup => return back_up(state, task).await,
+    };
+
+    result.map_err(|status| format!("{} failed with {status}", task.action.as_str()))
+}
+
+/// Takes a full backup and waits for it to finish.
+async fn back_up(state: &Arc<AppState>, task: &ScheduledTask) -> Result<Option<String>, String> {
+    let (backup, handle) = backup_routines::create(
+        state.clone(),
+        task.server_uuid,
+        task.created_by,
+        NewBackup::default(),
+    )
+    .await
+    .map_err(|status| format!("backup failed with {status}"))?;
+
+    match handle.await {
+        Ok(job) if job.status == JobStatus::Completed => {
+            Ok(Some(format!("backup {} written", backup.uuid)))
+        }
+        Ok(job) => Err(job.error.unwrap_or_else(|| "backup failed".to_string())),
+        Err(e) => Err(format!("backup aborted: {e}")),
+    }
+}
+
+/// Stops or restarts through a countdown with the configured warnings and
+/// waits for it to end.
+async fn count_down(state: &Arc<AppState>, task: &ScheduledTask) -> Result<Option<String>, String> {
+    let request = CountdownRequest {
+        mode: match task.action {
+            TaskAction::Restart => ShutdownMode::Restart,
+            _ => ShutdownMode::Stop,
+        },
+        delay_secs: task.countdown_secs.unwrap_or_default().max(0) as u32,
+        announce_at: None,
+        message: None,
+        style: None,
+    };
+
+    let (_, handle) = countdown_routines::begin(state.clone(), task.server_uuid, request)
+        .await
+        .map_err(|status| format!("countdown failed with {status}"))?;
+
+    match handle.await {
+        Ok(CountdownEnd::Completed) => Ok(None),
+        Ok(CountdownEnd::Cancelled) => Err("countdown cancelled".to_string()),
+        Ok(CountdownEnd::Failed(reason)) => Err(reason),
+        Err(e) => Err(format!("countdown aborted: {e}")),
+    }
+}
+
+async fn record(
+    state: &AppState,
+    task: &ScheduledTask,
+    scheduled_for: DateTime<Utc>,
+    started_at: DateTime<Utc>,
+    finished_at: DateTime<Utc>,
+    outcome: RunOutcome,
+    message: Option<String>,
+) {
+    let run = TaskRun {
+        uuid: Uuid::new_v4(),
+        task_uuid: task.uuid,
+        scheduled_for,
+        started_at,
+        finished_at,
+        outcome,
+        message,
+    };
+    if let Err(e) = db::schedule::create_run(&state.db_pool, &run).await {
+        error!(error = %e, task_uuid = %task.uuid, "record task run failed");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::schedule::NewScheduledTask;
+    use chrono::TimeZone;
+
+    fn hourly(enabled: bool, next_run_at: Option<DateTime<Utc>>) -> ScheduledTask {
+        let new = NewScheduledTask {
+            name: "hourly".to_string(),
+            cron: "0 * * * *".to_string(),
+            timezone: None,
+            action: TaskAction::Backup,
+            command: None,
+            countdown_secs: None,
+            catch_up: CatchUp::Skip,
+            enabled: Some(enabled),
+        };
+        let mut task = ScheduledTask::new(new, Uuid::new_v4(), Uuid::new_v4());
+        task.next_run_at = next_run_at;
+        task
+    }
+
+    #[test]
+    fn due_tasks_move_on_to_their_next_occurrence() {
+        let at = |h, m| Utc.with_ymd_and_hms(2024, 5, 1, h, m, 0).unwrap();
+        let scheduler = Scheduler::new();
+        let due = hourly(true, Some(at(10, 0)));
+        let later = hourly(true, Some(at(11, 0)));
+        scheduler.upsert(due.clone());
+        scheduler.upsert(later.clone());
+        scheduler.upsert(hourly(false, Some(at(9, 0))));
+        assert_eq!(scheduler.next_due(), Some(at(10, 0)));
+
+        // Fired late, the next run is still on the hour after now
+        let fired = scheduler.take_due(at(10, 20));
+        assert_eq!(fired.len(), 1);
+        assert_eq!(fired[0].0.uuid, due.uuid);
+        assert_eq!(fired[0].1, at(10, 0));
+        assert_eq!(scheduler.next_due(), Some(at(11, 0)));
+
+        let fired = scheduler.take_due(at(11, 0));
+        let mut uuids: Vec<_> = fired.iter().map(|(task, _)| task.uuid).collect();
+        uuids.sort();
+        let mut expected = vec![due.uuid, later.uuid];
+        expected.sort();
+        assert_eq!(uuids, expected);
+        assert_eq!(scheduler.next_due(), Some(at(12, 0)));
+
+        scheduler.forget_server(due.server_uuid);
+        scheduler.remove(later.uuid);
+        assert_eq!(scheduler.next_due(), None);
+    }
+}
@@ -1,14 +1,42 @@
-use std::{process::exit, sync::Arc};
+use std::{process::exit, sync::Arc, time::Duration};
 
 use crate::{core, domain::user::NewUser, prelude::*};
 
 use sqlx::PgPool;
 
-use crate::{config::AppCfg, infra::db};
+use crate::{
+    backup::BackupEngine,
+    config::AppCfg,
+    countdown::Countdowns,
+    crash::CrashGuard,
+    files::FileManager,
+    infra::{crypto::SecretBox, db},
+    installer::Installer,
+    java::JavaRuntimes,
+    jobs::JobManager,
+    players::PlayerTracker,
+    scheduler::Scheduler,
+    status::StatusTracker,
+    supervisor::Supervisor,
+};
 
 pub struct AppState {
     pub db_pool: PgPool,
     pub config: AppCfg,
+    pub supervisor: Supervisor,
+    pub secrets: SecretBox,
+    pub status: StatusTracker,
+    pub players: PlayerTracker,
+    pub crashes: CrashGuard,
+    pub countdowns: Countdowns,
+    pub backups: BackupEngine,
+    pub files: FileManager,
+    pub jobs: JobManager,
+    pub installer: Installer,
+    pub java: JavaRuntimes,
+    pub scheduler: Scheduler,
+    /// Shared client for outbound requests (profile lookups, downloads).
+    pub http: reqwest::Client,
 }
 
 impl AppState {
@@ -33,7 +61,36 @@ impl AppState {
             .unwrap();
         info!("database ready after connect and migrate");
 
-        Self { db_pool, config }
+        let supervisor = Supervisor::new(config.supervisor.clone());
+        let secrets = SecretBox::from_passphrase(&config.secret_key);
+        let jobs = JobManager::new(config.jobs.clone());
+        let http = reqwest::Client::builder()
+            .user_agent(concat!("rustymine/", env!("CARGO_PKG_VERSION")))
+            .connect_timeout(Duration::from_secs(10))
+            .build()
+            .map_err(|e| {
+                error!(error = %e, "build http client failed");
+                exit(24);
+            })
+            .unwrap();
+
+        Self {
+            db_pool,
+            config,
+            supervisor,
+            secrets,
+            status: StatusTracker::new(),
+            players: PlayerTracker::new(),
+            crashes: CrashGuard::new(),
+            countdowns: Countdowns::new(),
+            backups: BackupEngine::new(),
+            files: FileManager::new(),
+            jobs,
+            installer: Installer::new(),
+            java: JavaRuntimes::new(),
+            scheduler: Scheduler::new(),
+            http,
+        }
     }
 }
 
@@ -0,0 +1,135 @@
+use std::{collections::HashMap, sync::Arc, time::Instant};
+
+use chrono::Utc;
+use futures_util::future::join_all;
+use tokio::{sync::RwLock, time::interval};
+use uuid::Uuid;
+
+use crate::{
+    domain::{
+        server::Server,
+        status::{PollResult, QueryStatus},
+    },
+    infra::{
+        db,
+        minecraft::{ping, query},
+    },
+    prelude::*,
+    state::AppState,
+};
+
+struct CachedQuery {
+    fetched_at: Instant,
+    status: Option<QueryStatus>,
+}
+
+/// Latest reachability of every managed server, refreshed by [`run`], and
+/// the query answers fetched on demand.
+#[derive(Default)]
+pub struct StatusTracker {
+    results: RwLock<HashMap<Uuid, PollResult>>,
+    queries: RwLock<HashMap<Uuid, CachedQuery>>,
+}
+
+impl StatusTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub async fn get(&self, uuid: Uuid) -> Option<PollResult> {
+        self.results.read().await.get(&uuid).cloned()
+    }
+
+    async fn set(&self, uuid: Uuid, result: PollResult) {
+        self.results.write().await.insert(uuid, result);
+    }
+}
+
+/// Drops the poll result and cached query answer of a deleted server.
+pub async fn forget(state: &AppState, uuid: Uuid) {
+    state.status.results.write().await.remove(&uuid);
+    state.status.queries.write().await.remove(&uuid);
+}
+
+/// Full stat of a server with a query port, served from cache while younger
+/// than the configured TTL. Failures are cached too so a server with query
+/// disabled is not asked on every request.
+pub async fn query_status(state: &AppState, server: &Server) -> Option<QueryStatus> {
+    let port = server.query_port? as u16;
+
+    if let Some(cached) = state.status.queries.read().await.get(&server.uuid)
+        && cached.fetched_at.elapsed() < state.config.status.query_ttl
+    {
+        return cached.status.clone();
+    }
+
+    let status = match query::query("127.0.0.1", port, state.config.status.ping_timeout).await {
+        Ok(status) => Some(status),
+        Err(e) => {
+            debug!(server_uuid = %server.uuid, error = %e, "query server failed");
+            None
+        }
+    };
+
+    state.status.queries.write().await.insert(
+        server.uuid,
+        CachedQuery {
+            fetched_at: Instant::now(),
+            status: status.clone(),
+        },
+    );
+    status
+}
+
+/// Polls every server with a Server List Ping forever. The first successful
+/// ping of a starting server is what marks it running.
+pub async fn run(state: Arc<AppState>) {
+    let mut ticker = interval(state.config.status.poll_interval);
+    info!(
+        interval_secs = state.config.status.poll_interval.as_secs(),
+        "status polling started"
+    );
+
+    loop {
+        ticker.tick().await;
+
+        let servers = match db::server::get_all(&state.db_pool).await {
+            Ok(servers) => servers,
+            Err(e) => {
+                error!(error = %e, "fetch servers for status polling failed");
+                continue;
+            }
+        };
+
+        join_all(servers.iter().map(|server| poll(&state, server))).await;
+    }
+}
+
+async fn poll(state: &AppState, server: &Server) {
+    let result = match ping::ping(
+        "127.0.0.1",
+        server.server_port as u16,
+        state.config.status.ping_timeout,
+    )
+    .await
+    {
+        Ok(status) => PollResult {
+            reachable: true,
+            checked_at: Utc::now(),
+            ping: Some(status),
+            error: None,
+        },
+        Err(e) => PollResult {
+            reachable: false,
+            checked_at: Utc::now(),
+            ping: None,
+            error: Some(e.to_string()),
+        },
+    };
+
+    if result.reachable && state.supervisor.mark_ready(server.uuid).await {
+        info!(server_uuid = %server.uuid, "server answered ping, marked running");
+    }
+
+    state.status.set(server.uuid, result).await;
+}
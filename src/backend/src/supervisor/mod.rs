@@ -0,0 +1,537 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    os::unix::process::ExitStatusExt,
+    process::Stdio,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+
+use chrono::{DateTime, Utc};
+use nix::{
+    sys::signal::{self, Signal},
+    unistd::Pid,
+};
+use thiserror::Error;
+use tokio::{
+    io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader},
+    process::{Child, ChildStdin, Command},
+    sync::{Mutex, RwLock, broadcast, watch},
+    time::timeout,
+};
+use uuid::Uuid;
+
+use crate::{
+    config::SupervisorCfg,
+    domain::{
+        console::{ConsoleLine, ConsoleStream},
+        crash::{ExitKind, ProcessExit},
+        java::JavaRequirement,
+        server::{ProcessState, Server},
+    },
+    java,
+    prelude::*,
+};
+
+#[derive(Debug, Error)]
+pub enum SupervisorError {
+    #[error("server process is already running")]
+    AlreadyRunning,
+    #[error("server process is not running")]
+    NotRunning,
+    #[error("server launch command is empty")]
+    EmptyCommand,
+    #[error("server files are being replaced")]
+    Maintenance,
+    #[error("java {found} cannot run minecraft {version}, it needs {required}")]
+    IncompatibleJava {
+        found: u32,
+        version: String,
+        required: JavaRequirement,
+    },
+    #[error("spawn server process failed: {0}")]
+    Spawn(#[source] std::io::Error),
+    #[error("write to server stdin failed: {0}")]
+    Stdin(#[source] std::io::Error),
+    #[error("signal server process failed: {0}")]
+    Signal(#[from] nix::errno::Errno),
+}
+
+/// Runtime handle of one managed server, kept for the lifetime of the daemon.
+struct Instance {
+    state: watch::Sender<ProcessState>,
+    stdin: Mutex<Option<ChildStdin>>,
+    pid: std::sync::Mutex<Option<u32>>,
+    console: broadcast::Sender<ConsoleLine>,
+    scrollback: std::sync::Mutex<VecDeque<ConsoleLine>>,
+    scrollback_lines: usize,
+    started_at: std::sync::Mutex<DateTime<Utc>>,
+    /// Set when the current run logged an `OutOfMemoryError`.
+    out_of_memory: AtomicBool,
+    /// Held by a [`MaintenanceGuard`], starts are refused meanwhile.
+    maintenance: AtomicBool,
+    last_exit: std::sync::Mutex<Option<ProcessExit>>,
+}
+
+impl Instance {
+    fn new(scrollback_lines: usize) -> Self {
+        Self {
+            state: watch::Sender::new(ProcessState::Stopped),
+            stdin: Mutex::new(None),
+            pid: std::sync::Mutex::new(None),
+            console: broadcast::channel(scrollback_lines.max(16)).0,
+            scrollback: std::sync::Mutex::new(VecDeque::with_capacity(scrollback_lines)),
+            scrollback_lines,
+            started_at: std::sync::Mutex::new(Utc::now()),
+            out_of_memory: AtomicBool::new(false),
+            maintenance: AtomicBool::new(false),
+            last_exit: std::sync::Mutex::new(None),
+        }
+    }
+
+    fn push_line(&self, line: ConsoleLine) {
+        // Publish under the scrollback lock so subscribers never miss or repeat a line
+        let mut scrollback = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+        if scrollback.len() >= self.scrollback_lines {
+            scrollback.pop_front();
+        }
+        scrollback.push_back(line.clone());
+        let _ = self.console.send(line);
+    }
+
+    fn subscribe(&self) -> (Vec<ConsoleLine>, broadcast::Receiver<ConsoleLine>) {
+        let scrollback = self.scrollback.lock().unwrap_or_else(|e| e.into_inner());
+        (
+            scrollback.iter().cloned().collect(),
+            self.console.subscribe(),
+        )
+    }
+
+    fn state(&self) -> ProcessState {
+        *self.state.borrow()
+    }
+
+    async fn write_line(&self, line: &str) -> Result<(), SupervisorError> {
+        let mut stdin = self.stdin.lock().await;
+        let stdin = stdin.as_mut().ok_or(SupervisorError::NotRunning)?;
+        stdin
+            .write_all(format!("{line}\n").as_bytes())
+            .await
+            .map_err(SupervisorError::Stdin)?;
+        stdin.flush().await.map_err(SupervisorError::Stdin)
+    }
+
+    fn signal(&self, sig: Signal) -> Result<(), SupervisorError> {
+        let pid = self
+            .pid
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .ok_or(SupervisorError::NotRunning)?;
+        signal::kill(Pid::from_raw(pid as i32), sig)?;
+        Ok(())
+    }
+
+    fn mark_ready(&self) -> bool {
+        self.state.send_if_modified(|state| {
+            if *state == ProcessState::Starting {
+                *state = ProcessState::Running;
+                true
+            } else {
+                false
+            }
+        })
+    }
+
+    /// Waits until the process has exited, returns `false` on timeout.
+    async fn wait_exit(&self, limit: Duration) -> bool {
+        let mut rx = self.state.subscribe();
+        timeout(limit, rx.wait_for(|state| !state.is_alive()))
+            .await
+            .is_ok()
+    }
+}
+
+/// Keeps a server from starting until dropped.
+pub struct MaintenanceGuard {
+    instance: Arc<Instance>,
+}
+
+impl Drop for MaintenanceGuard {
+    fn drop(&mut self) {
+        self.instance.maintenance.store(false, Ordering::SeqCst);
+    }
+}
+
+pub struct Supervisor {
+    cfg: SupervisorCfg,
+    instances: RwLock<HashMap<Uuid, Arc<Instance>>>,
+}
+
+impl Supervisor {
+    pub fn new(cfg: SupervisorCfg) -> Self {
+        Self {
+            cfg,
+            instances: RwLock::new(HashMap::new()),
+        }
+    }
+
+    async fn instance(&self, uuid: Uuid) -> Arc<Instance> {
+        if let Some(instance) = self.instances.read().await.get(&uuid) {
+            return instance.clone();
+        }
+
+        self.instances
+            .write()
+            .await
+            .entry(uuid)
+            .or_insert_with(|| Arc::new(Instance::new(self.cfg.scrollback_lines)))
+            .clone()
+    }
+
+    async fn existing(&self, uuid: Uuid) -> Option<Arc<Instance>> {
+        self.instances.read().await.get(&uuid).cloned()
+    }
+
+    pub async fn state(&self, uuid: Uuid) -> ProcessState {
+        match self.existing(uuid).await {
+            Some(instance) => instance.state(),
+            None => ProcessState::Stopped,
+        }
+    }
+
+    /// Moves a starting server to running, returns whether it changed state.
+    pub async fn mark_ready(&self, uuid: Uuid) -> bool {
+        match self.existing(uuid).await {
+            Some(instance) => instance.mark_ready(),
+            None => false,
+        }
+    }
+
+    /// How the previous run of a server ended, if it ran since the daemon started.
+    pub async fn last_exit(&self, uuid: Uuid) -> Option<ProcessExit> {
+        let instance = self.existing(uuid).await?;
+        let last_exit = instance.last_exit.lock().unwrap_or_else(|e| e.into_inner());
+        last_exit.clone()
+    }
+
+    /// Console lines currently buffered for a server, oldest first.
+    pub async fn scrollback(&self, uuid: Uuid) -> Vec<ConsoleLine> {
+        match self.existing(uuid).await {
+            Some(instance) => instance.subscribe().0,
+            None => Vec::new(),
+        }
+    }
+
+    /// Follows the process state of a server.
+    pub async fn watch(&self, uuid: Uuid) -> watch::Receiver<ProcessState> {
+        self.instance(uuid).await.state.subscribe()
+    }
+
+    /// Returns the buffered scrollback and a receiver for every line after it.
+    pub async fn subscribe(
+        &self,
+        uuid: Uuid,
+    ) -> (Vec<ConsoleLine>, broadcast::Receiver<ConsoleLine>) {
+        self.instance(uuid).await.subscribe()
+    }
+
+    /// Refuses every start of the server until the guard is dropped, `None`
+    /// if someone else holds it. A server already running keeps running, the
+    /// holder checks the state after taking the guard.
+    pub async fn maintenance(&self, uuid: Uuid) -> Option<MaintenanceGuard> {
+        let instance = self.instance(uuid).await;
+        instance
+            .maintenance
+            .compare_exchange(false, true, Ordering::SeqCst, Ordering::SeqCst)
+            .ok()
+            .map(|_| MaintenanceGuard { instance })
+    }
+
+    pub async fn start(&self, server: &Server) -> Result<(), SupervisorError> {
+        let uuid = server.uuid;
+        let instance = self.instance(uuid).await;
+
+        // Claim the instance atomically so two concurrent starts cannot both spawn
+        let mut claimed = false;
+        let mut maintenance = false;
+        instance.state.send_if_modified(|state| {
+            if state.is_alive() {
+                return false;
+            }
+            if instance.maintenance.load(Ordering::SeqCst) {
+                maintenance = true;
+                return false;
+            }
+            *state = ProcessState::Starting;
+            claimed = true;
+            true
+        });
+        if maintenance {
+            return Err(SupervisorError::Maintenance);
+        }
+        if !claimed {
+            return Err(SupervisorError::AlreadyRunning);
+        }
+
+        if let Err(e) = check_java(server).await {
+            instance.state.send_replace(ProcessState::Stopped);
+            return Err(e);
+        }
+
+        let argv = server.launch_args();
+        let Some((program, args)) = argv.split_first() else {
+            instance.state.send_replace(ProcessState::Stopped);
+            return Err(SupervisorError::EmptyCommand);
+        };
+
+        debug!(server_uuid = %uuid, program, ?args, "spawn server process started");
+        let spawned = Command::new(program)
+            .args(args)
+            .current_dir(&server.working_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn();
+
+        let mut child = match spawned {
+            Ok(child) => child,
+            Err(e) => {
+                instance.state.send_replace(ProcessState::Stopped);
+                return Err(SupervisorError::Spawn(e));
+            }
+        };
+
+        let pid = child.id();
+        *instance.pid.lock().unwrap_or_else(|e| e.into_inner()) = pid;
+        *instance
+            .started_at
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Utc::now();
+        instance.out_of_memory.store(false, Ordering::Relaxed);
+        *instance.stdin.lock().await = child.stdin.take();
+
+        if let Some(stdout) = child.stdout.take() {
+            tokio::spawn(read_output(instance.clone(), ConsoleStream::Stdout, stdout));
+        }
+        if let Some(stderr) = child.stderr.take() {
+            tokio::spawn(read_output(instance.clone(), ConsoleStream::Stderr, stderr));
+        }
+        tokio::spawn(monitor(uuid, instance.clone(), child));
+
+        info!(server_uuid = %uuid, ?pid, "server process spawned");
+        Ok(())
+    }
+
+    /// Asks the server to shut down through its console, escalating to SIGTERM
+    /// and then SIGKILL in the background if it does not exit in time.
+    pub async fn stop(&self, uuid: Uuid) -> Result<(), SupervisorError> {
+        let instance = self
+            .existing(uuid)
+            .await
+            .ok_or(SupervisorError::NotRunning)?;
+
+        let mut claimed = false;
+        instance.state.send_if_modified(|state| match state {
+            ProcessState::Starting | ProcessState::Running => {
+                *state = ProcessState::Stopping;
+                claimed = true;
+                true
+            }
+            _ => false,
+        });
+        if !claimed {
+            return match instance.state() {
+                ProcessState::Stopping => Ok(()),
+                _ => Err(SupervisorError::NotRunning),
+            };
+        }
+
+        if let Err(e) = instance.write_line("stop").await {
+            warn!(error = %e, server_uuid = %uuid, "send stop command failed, sending SIGTERM");
+            instance.signal(Signal::SIGTERM)?;
+        }
+
+        tokio::spawn(escalate(uuid, instance, self.cfg.clone()));
+        Ok(())
+    }
+
+    /// Writes a single command line to the server's stdin.
+    pub async fn send_command(&self, uuid: Uuid, command: &str) -> Result<(), SupervisorError> {
+        let instance = self
+            .existing(uuid)
+            .await
+            .ok_or(SupervisorError::NotRunning)?;
+
+        match instance.state() {
+            ProcessState::Starting | ProcessState::Running => instance.write_line(command).await,
+            _ => Err(SupervisorError::NotRunning),
+        }
+    }
+
+    pub async fn kill(&self, uuid: Uuid) -> Result<(), SupervisorError> {
+        let instance = self
+            .existing(uuid)
+            .await
+            .ok_or(SupervisorError::NotRunning)?;
+
+        if !instance.state().is_alive() {
+            return Err(SupervisorError::NotRunning);
+        }
+
+        // Mark as stopping so the exit is not reported as a crash
+        instance.state.send_replace(ProcessState::Stopping);
+        instance.signal(Signal::SIGKILL)?;
+        warn!(server_uuid = %uuid, "server process killed");
+        Ok(())
+    }
+
+    /// Stops the server if it is alive, waits for it to exit and starts it again.
+    pub async fn restart(&self, server: &Server) -> Result<(), SupervisorError> {
+        let uuid = server.uuid;
+
+        if self.state(uuid).await.is_alive() {
+            self.stop(uuid).await?;
+            self.wait_stopped(uuid).await;
+        }
+
+        self.start(server).await
+    }
+
+    /// Waits for the server to exit, including the full stop escalation.
+    pub async fn wait_stopped(&self, uuid: Uuid) -> bool {
+        let Some(instance) = self.existing(uuid).await else {
+            return true;
+        };
+
+        // Escalation ends with SIGKILL, leave it a moment to be reaped
+        let limit = self.cfg.stop_timeout + self.cfg.term_timeout + Duration::from_secs(5);
+        instance.wait_exit(limit).await
+    }
+}
+
+/// Refuses a runtime the game version of the server is known not to run on.
+/// A custom launch command picks its own java, and a runtime that cannot be
+/// probed is left for the launch itself to fail on.
+async fn check_java(server: &Server) -> Result<(), SupervisorError> {
+    if server.launch_command.is_some() {
+        return Ok(());
+    }
+    let Some(version) = server.minecraft_version.as_deref() else {
+        return Ok(());
+    };
+    let Some(required) = JavaRequirement::for_minecraft(version) else {
+        return Ok(());
+    };
+
+    match java::probe(&server.java_path).await {
+        Ok(runtime) if !required.allows(runtime.major) => Err(SupervisorError::IncompatibleJava {
+            found: runtime.major,
+            version: version.to_string(),
+            required,
+        }),
+        Ok(_) => Ok(()),
+        Err(e) => {
+            warn!(error = %e, server_uuid = %server.uuid, "probe java runtime failed");
+            Ok(())
+        }
+    }
+}
+
+async fn escalate(uuid: Uuid, instance: Arc<Instance>, cfg: SupervisorCfg) {
+    if instance.wait_exit(cfg.stop_timeout).await {
+        return;
+    }
+
+    warn!(server_uuid = %uuid, "server did not stop in time, sending SIGTERM");
+    if let Err(e) = instance.signal(Signal::SIGTERM) {
+        debug!(error = %e, server_uuid = %uuid, "send SIGTERM failed");
+    }
+    if instance.wait_exit(cfg.term_timeout).await {
+        return;
+    }
+
+    warn!(server_uuid = %uuid, "server ignored SIGTERM, sending SIGKILL");
+    if let Err(e) = instance.signal(Signal::SIGKILL) {
+        debug!(error = %e, server_uuid = %uuid, "send SIGKILL failed");
+    }
+}
+
+async fn read_output<R>(instance: Arc<Instance>, kind: ConsoleStream, stream: R)
+where
+    R: AsyncRead + Unpin,
+{
+    let mut reader = BufReader::new(stream);
+    let mut buf = Vec::new();
+
+    loop {
+        buf.clear();
+        match reader.read_until(b'\n', &mut buf).await {
+            Ok(0) | Err(_) => break,
+            Ok(_) => {}
+        }
+
+        let line = String::from_utf8_lossy(&buf);
+        let line = line.trim_end_matches(['\r', '\n']);
+
+        // Vanilla and its forks all log this once the world is loaded
+        if line.contains("]: Done (") {
+            instance.mark_ready();
+        }
+        if line.contains("java.lang.OutOfMemoryError") {
+            instance.out_of_memory.store(true, Ordering::Relaxed);
+        }
+
+        instance.push_line(ConsoleLine::new(kind, line));
+    }
+}
+
+async fn monitor(uuid: Uuid, instance: Arc<Instance>, mut child: Child) {
+    let status = child.wait().await;
+
+    *instance.pid.lock().unwrap_or_else(|e| e.into_inner()) = None;
+    *instance.stdin.lock().await = None;
+
+    let requested = instance.state() == ProcessState::Stopping;
+    let (code, signal) = match &status {
+        Ok(status) => {
+            info!(server_uuid = %uuid, code = ?status.code(), signal = ?status.signal(), requested, "server process exited");
+            (status.code(), status.signal())
+        }
+        Err(e) => {
+            error!(error = %e, server_uuid = %uuid, "wait for server process failed");
+            (None, None)
+        }
+    };
+
+    let kind = if requested || status.as_ref().is_ok_and(|s| s.success()) {
+        ExitKind::Clean
+    } else if instance.out_of_memory.load(Ordering::Relaxed)
+        || signal == Some(Signal::SIGKILL as i32)
+        // A shell wrapper reports its SIGKILLed child as 128 + 9
+        || code == Some(137)
+    {
+        ExitKind::OutOfMemory
+    } else {
+        ExitKind::Crash
+    };
+
+    let started_at = *instance
+        .started_at
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+    *instance.last_exit.lock().unwrap_or_else(|e| e.into_inner()) = Some(ProcessExit {
+        kind,
+        code,
+        signal,
+        started_at,
+        exited_at: Utc::now(),
+    });
+
+    let next = match kind {
+        ExitKind::Clean => ProcessState::Stopped,
+        _ => ProcessState::Crashed,
+    };
+    instance.state.send_replace(next);
+}
@@ -0,0 +1,2 @@
+// The dev server runs on its own port, a production build is served by the daemon itself
+export const API_BASE = import.meta.env.DEV ? "http://127.0.0.1:3000" : "";
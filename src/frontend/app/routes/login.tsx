@@ -3,6 +3,7 @@ import { PageShell } from "~/components/PageShell";
 import { Field, FieldGroup, FieldLabel } from "~/components/ui/field";
 import { Input } from "~/components/ui/input";
 import { Button } from "~/components/ui/button";
+import { API_BASE } from "~/lib/api";
 import {
   Card,
   CardContent,
@@ -22,7 +23,7 @@ export default function Login() {
     debugger;
 
     try {
-      const res = await fetch("http://127.0.0.1:3000/api/login", {
+      const res = await fetch(`${API_BASE}/api/login`, {
         method: "POST",
         headers: { "Content-Type": "application/json" },
         credentials: "include",
@@ -0,0 +1,28 @@
+// Writes .br and .gz siblings next to the built client assets so the
+// daemon can serve them precompressed.
+import { readdir, readFile, writeFile } from "node:fs/promises";
+import { join } from "node:path";
+import { brotliCompressSync, constants, gzipSync } from "node:zlib";
+
+const root = new URL("../build/client/", import.meta.url).pathname;
+const compressible = /\.(html|js|mjs|css|json|svg|txt|xml|webmanifest)$/;
+
+async function walk(dir) {
+  for (const entry of await readdir(dir, { withFileTypes: true })) {
+    const path = join(dir, entry.name);
+    if (entry.isDirectory()) {
+      await walk(path);
+    } else if (compressible.test(entry.name)) {
+      const data = await readFile(path);
+      await writeFile(
+        `${path}.br`,
+        brotliCompressSync(data, {
+          params: { [constants.BROTLI_PARAM_QUALITY]: constants.BROTLI_MAX_QUALITY },
+        }),
+      );
+      await writeFile(`${path}.gz`, gzipSync(data, { level: 9 }));
+    }
+  }
+}
+
+await walk(root);